@@ -0,0 +1,33 @@
+error = Fehler: { $message }
+warning = Warnung: { $message }
+invalid-input = ungültige Eingabe
+not-a-png = { $path } ist keine gültige PNG-Datei
+invalid-chunk-type = `{ $type }` ist kein gültiger Chunk-Typ: { $reason }
+no-chunk = kein { $type }-Chunk gefunden
+check-ok = { $path }: in Ordnung
+warnings-found = { $count } Warnung(en) gefunden
+
+## Help text, by command path
+
+help = Daten in PNG-Chunks verstecken und untersuchen
+help--registry = TOML-Datei mit projektspezifischen Chunk-Typen
+help--no-swap = Den Prozess im Speicher sperren, damit Passwörter und Nutzdaten nie ausgelagert werden
+help--hardened = Streng parsen und jeden Fehler mit derselben knappen Meldung melden
+help--lang = Sprache für Meldungen und Hilfe, z. B. de; sonst aus LC_ALL, LC_MESSAGES oder LANG
+
+help-encode = Eine Nachricht in einer Datei verstecken
+help-encode--file = Das Bild, in dem die Nachricht versteckt wird
+help-encode--message = Die Nachricht; wird im Terminal abgefragt, wenn sie fehlt
+help-encode--chunk-type = Chunk-Typ, in dem die Nachricht gespeichert wird (Standard: ruSt)
+help-encode--method = Wie die Nachricht gespeichert wird
+help-encode--password = Passwort für die Spread-Methode
+help-encode--output = Das Ergebnis hierhin schreiben, statt die Eingabe zu überschreiben
+
+help-decode = Eine versteckte Nachricht aus einer Datei lesen
+help-decode--chunk-type = Chunk-Typ, in dem die Nachricht gespeichert ist
+help-decode--password = Passwort für die Spread-Methode
+help-decode--passphrase = Eine verschlüsselte Nachricht mit dieser Passphrase entschlüsseln
+
+help-print = Die Chunks einer Datei auflisten
+help-check = Eine Datei prüfen und Probleme melden
+help-strip = Alle Zusatz-Chunks der angegebenen Kategorien entfernen
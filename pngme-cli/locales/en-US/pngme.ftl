@@ -0,0 +1,11 @@
+# Messages printed at run time. Help text is written in English as doc comments in
+# args.rs; see locales/de for how a catalog translates it.
+
+error = error: { $message }
+warning = warning: { $message }
+invalid-input = invalid input
+not-a-png = { $path } is not a valid PNG file
+invalid-chunk-type = `{ $type }` is not a valid chunk type: { $reason }
+no-chunk = no { $type } chunk found
+check-ok = { $path }: ok
+warnings-found = { $count } warning(s) found
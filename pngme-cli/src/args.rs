@@ -0,0 +1,1119 @@
+use std::path::PathBuf;
+
+use clap::{builder::FalseyValueParser, Args, Parser, Subcommand, ValueEnum};
+use pngme_core::convert::Target;
+use pngme_core::envelope::Date;
+use pngme_core::filter::Filter;
+use pngme_core::geometry::{Border, Flip, Region, Rotation};
+use pngme_core::hash::Algorithm;
+use pngme_core::image::Color;
+use pngme_core::seal::DEFAULT_SEGMENT_SIZE;
+use pngme_core::select::Selector;
+use pngme_core::strip::Category;
+
+#[derive(Parser)]
+#[command(name = "pngme", version, about = "Hide and inspect data in PNG chunks")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// TOML file describing project-specific chunk types
+    /// [default: $XDG_CONFIG_HOME/pngme/chunks.toml]
+    #[arg(long, global = true, env = "PNGME_REGISTRY", value_name = "PATH")]
+    pub registry: Option<PathBuf>,
+    /// Lock the process in memory so passwords and payloads never reach swap
+    #[arg(long, global = true)]
+    pub no_swap: bool,
+    /// Parse strictly and report every failure as the same terse error
+    #[arg(long, global = true, env = "PNGME_HARDENED", value_parser = FalseyValueParser::new())]
+    pub hardened: bool,
+    /// Reject files that break any rule of the specification, even ones decoders tolerate
+    #[arg(long, global = true, conflicts_with = "permissive")]
+    pub strict: bool,
+    /// Accept chunks with the wrong CRC and a cut-short last chunk, warning about each
+    #[arg(long, global = true)]
+    pub permissive: bool,
+    /// Language for messages and help, e.g. de [default: from LC_ALL, LC_MESSAGES or LANG]
+    #[arg(long, global = true, env = "PNGME_LANG", value_name = "LANG")]
+    pub lang: Option<String>,
+    /// Never colour the output, as when NO_COLOR is set
+    #[arg(long, global = true)]
+    pub no_color: bool,
+    /// Print long output straight to the terminal instead of through $PAGER
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+    /// Decode images this many rows at a time, keeping memory bounded on huge images; used
+    /// by spread encode and decode and by compare
+    #[arg(long, global = true, env = "PNGME_BAND_HEIGHT", value_name = "ROWS",
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub band_height: Option<u32>,
+    /// Threads for unfiltering and compressing image data, and for decode --wordlist
+    /// [default: one per core]
+    #[arg(long, global = true, env = "PNGME_JOBS", value_name = "N",
+          value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: Option<u32>,
+    /// Add each command's running time to a local statistics file, shown by `pngme stats --self`
+    #[arg(long, global = true, env = "PNGME_STATS", value_parser = FalseyValueParser::new())]
+    pub stats: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Hide a message in a file
+    Encode(EncodeArgs),
+    /// Recover a hidden message from a file
+    Decode(DecodeArgs),
+    /// Rewrite the payload of a chunk, in $EDITOR or from the command line
+    Edit(EditArgs),
+    /// Read or write one field of a registered chunk type
+    Field {
+        #[command(subcommand)]
+        command: FieldCommand,
+    },
+    /// Copy selected chunks from one file into another
+    Copy(CopyArgs),
+    /// Overwrite bytes at an absolute offset, optionally fixing the CRCs this breaks
+    Patch(PatchArgs),
+    /// List the chunks whose CRC doesn't match, optionally putting right single-bit errors
+    Repair(RepairArgs),
+    /// Write each chunk's payload to its own file in DIRECTORY, with a manifest
+    Explode {
+        file: PathBuf,
+        directory: PathBuf,
+        /// Only write the chunks matching this selection; repeatable
+        #[arg(long)]
+        select: Vec<Selector>,
+    },
+    /// Rebuild a file from a directory written by `pngme explode`
+    Implode { directory: PathBuf, output: PathBuf },
+    /// Separate PNG files laid end to end, and whatever follows them, into DIRECTORY
+    SplitConcat { file: PathBuf, directory: PathBuf },
+    /// Write a copy of IMAGE that is also a ZIP archive holding PAYLOAD's files
+    Polyglot {
+        image: PathBuf,
+        payload: PathBuf,
+        output: PathBuf,
+    },
+    /// Remove every ancillary chunk in the given categories
+    Strip(StripArgs),
+    /// Rewrite a file cleanly, dropping anything after IEND
+    Normalize(NormalizeArgs),
+    /// Cut the image down to a region, keeping the ancillary chunks that still hold
+    Crop(CropArgs),
+    /// Add a border around the image, keeping the ancillary chunks that still hold
+    Pad(PadArgs),
+    /// Turn the image clockwise, keeping the ancillary chunks that still hold
+    Rotate(RotateArgs),
+    /// Mirror the image, keeping the ancillary chunks that still hold
+    Flip(FlipArgs),
+    /// Rewrite the image in another colour type and bit depth
+    Convert(ConvertArgs),
+    /// Filter PNG files between standard input and output for `.gitattributes`
+    GitFilter(GitFilterArgs),
+    /// List the ancillary chunks that files share byte for byte, and the space they take
+    Shared(SharedArgs),
+    /// Check staged PNG files against a policy before each commit
+    Hook {
+        #[command(subcommand)]
+        command: HookCommand,
+    },
+    /// Run the operations listed in a TOML plan against each file, in order
+    Apply(ApplyArgs),
+    /// Run a Rhai script against a file, with its chunks in the variable `png`
+    #[cfg(feature = "scripting")]
+    Script(ScriptArgs),
+    /// Store or remove passwords in the OS keyring
+    Keyring {
+        #[command(subcommand)]
+        command: KeyringCommand,
+    },
+    /// Record or show build provenance metadata
+    Stamp(StampArgs),
+    /// Sum up a file on one screen: the image, its chunks, metadata, trailers and any sign
+    /// of hidden data
+    Inspect(FileArgs),
+    /// Break a file's size down by what the bytes are for, and what stripping or optimizing
+    /// would save
+    Weigh(FileArgs),
+    /// List the chunks in a file
+    Print(PrintArgs),
+    /// Cache where each chunk sits in `<file>.pngidx`, so print and decode skip rescanning
+    Index {
+        #[command(subcommand)]
+        command: IndexCommand,
+    },
+    /// Validate a file and report problems
+    Check(CheckArgs),
+    /// Work with C2PA content credentials
+    C2pa {
+        #[command(subcommand)]
+        command: C2paCommand,
+    },
+    /// Show, set or remove the background colour (bKGD)
+    Background {
+        #[command(subcommand)]
+        command: BackgroundCommand,
+    },
+    /// Show, set or remove single-colour transparency (tRNS)
+    Transparency {
+        #[command(subcommand)]
+        command: TransparencyCommand,
+    },
+    /// List, add or remove suggested palettes (sPLT)
+    Splt {
+        #[command(subcommand)]
+        command: SpltCommand,
+    },
+    /// Assign a colour profile
+    Icc {
+        #[command(subcommand)]
+        command: IccCommand,
+    },
+    /// Read, replace or remove XMP metadata
+    Xmp {
+        #[command(subcommand)]
+        command: XmpCommand,
+    },
+    /// Extract or strip thumbnails embedded in EXIF or XMP metadata
+    Thumb {
+        #[command(subcommand)]
+        command: ThumbCommand,
+    },
+    /// Compare the pixels of two images
+    Compare(CompareArgs),
+    /// Estimate the likelihood that pixel LSBs carry a hidden message
+    Analyze(FileArgs),
+    /// Split an image into one greyscale image per channel, or merge them back
+    Channels {
+        #[command(subcommand)]
+        command: ChannelsCommand,
+    },
+    /// Write a self-contained HTML report on a file's structure and contents
+    Report(ReportArgs),
+    /// Dump a whole file in hex, with each chunk's length, type, payload and CRC marked
+    Hexview(HexviewArgs),
+    /// Write a file's structure as a Kaitai Struct spec or an 010 Editor template, for other
+    /// reverse-engineering tools
+    Template(TemplateArgs),
+    /// Draw a file's layout as a Graphviz or Mermaid diagram: the signature, its chunks to
+    /// scale by size, and any trailing data
+    Graph(GraphArgs),
+    /// Print digests of whole files and of each chunk's payload
+    Hash(HashArgs),
+    /// Check files against a manifest written by `pngme hash --manifest`
+    VerifyManifest { manifest: PathBuf },
+    /// Store a hash tree of the image data, so later changes can be located
+    Seal(SealArgs),
+    /// Check the image data against the hash tree stored by `pngme seal`
+    Verify(VerifyArgs),
+    /// List the versions of a message kept by `pngme encode --append-history`
+    History(HistoryArgs),
+    /// Split a secret into shares hidden in several images, some number of which recover it
+    Share(ShareArgs),
+    /// Recover a secret from images holding enough of the shares written by `pngme share`
+    Reconstruct(ReconstructArgs),
+    /// Agree on a session key with someone by exchanging images that carry public keys
+    Handshake {
+        #[command(subcommand)]
+        command: HandshakeCommand,
+    },
+    /// Stamp text on an image, visibly or as an invisible mark, or check for the mark
+    Watermark {
+        #[command(subcommand)]
+        command: WatermarkCommand,
+    },
+    /// Create a pseudo-random carrier image, the same every time for the same seed and
+    /// settings
+    New(NewArgs),
+    /// Check this build against a corpus of edge-case files, or write the corpus out
+    Selftest(SelftestArgs),
+    /// Decode files with both pngme and the `png` crate and report where the pixels differ
+    #[cfg(feature = "conformance")]
+    Conformance(ConformanceArgs),
+    /// Serve encode, decode and inspect requests to another process
+    Api(ApiArgs),
+    /// Replace this binary with the latest signed release from GitHub
+    #[cfg(feature = "self-update")]
+    SelfUpdate(SelfUpdateArgs),
+    /// Show or reset the usage statistics recorded with --stats
+    Stats {
+        /// This installation's own statistics, the only ones there are
+        #[arg(long = "self", required = true)]
+        own: bool,
+        /// Delete them instead
+        #[arg(long)]
+        reset: bool,
+    },
+    /// List what this build supports: optional features, ciphers, methods and chunk types
+    Features {
+        /// Print a JSON object of lists instead
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    /// Store the message in its own ancillary chunk
+    Chunk,
+    /// Scatter the message over pixel LSBs chosen by a password-seeded PRNG
+    Spread,
+    /// As spread, but in the channels and number of low bits given by --channels and --bits,
+    /// which are recorded in the image for decode
+    Lsb,
+    /// Keep the message in an extended attribute beside the file, leaving the PNG untouched
+    /// (Linux and macOS)
+    Xattr,
+}
+
+#[derive(Args)]
+pub struct EncodeArgs {
+    /// The image to hide the message in, or with --image-from-clipboard where to save it
+    pub file: PathBuf,
+    /// Asked for on the terminal if left out
+    pub message: Option<String>,
+    /// Take the message from the clipboard instead of the command line
+    #[arg(long, conflicts_with = "message")]
+    pub from_clipboard: bool,
+    /// Hide the message in the image on the clipboard, writing the result to FILE
+    #[arg(long, conflicts_with = "output")]
+    pub image_from_clipboard: bool,
+    /// Chunk type to store the message in, or to name the attribute after (chunk and xattr
+    /// methods); ruSt unless given, or asked for along with the message
+    #[arg(short = 't', long)]
+    pub chunk_type: Option<String>,
+    #[arg(long, visible_alias = "via", value_enum, default_value_t = Method::Chunk)]
+    pub method: Method,
+    /// Password that seeds the spread and lsb methods' bit positions and mask; asked for on
+    /// the terminal if neither it nor --use-keyring is given
+    #[arg(long)]
+    pub password: Option<String>,
+    /// Read the spread or lsb password from the OS keyring entry saved by `pngme keyring set`
+    #[arg(long, value_name = "NAME", conflicts_with = "password")]
+    pub use_keyring: Option<String>,
+    /// Channels the lsb method hides the message in, e.g. `b,a` or `blue,alpha`; the colour
+    /// channels unless given
+    #[arg(long, value_name = "CHANNELS", value_delimiter = ',')]
+    pub channels: Vec<String>,
+    /// Low bits of each sample the lsb method uses, from 1 to 4: more hold more, and are
+    /// easier to detect [default: 1]
+    #[arg(long)]
+    pub bits: Option<u8>,
+    /// Keep the lsb method's message out of flat areas, using only the more textured half of
+    /// the pixels
+    #[arg(long)]
+    pub adaptive: bool,
+    /// Encrypt the message to this age public key first; may be repeated
+    #[arg(long = "recipient", value_name = "AGE_PUBLIC_KEY")]
+    pub recipients: Vec<String>,
+    /// Encrypt the message so that this passphrase can read it; may be repeated, and combined
+    /// with --recipient
+    #[arg(long = "passphrase", value_name = "PASSPHRASE")]
+    pub passphrases: Vec<String>,
+    /// Encrypt the message with the session key saved by `pngme handshake`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["recipients", "passphrases"])]
+    pub session: Option<PathBuf>,
+    /// Mark the message as expiring at the end of this UTC date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub expires: Option<Date>,
+    /// Declare the message's MIME type, or with `auto` the type it looks like, for decode
+    /// to report and name saved payloads by
+    #[arg(long, value_name = "TYPE", value_parser = parse_content_type)]
+    pub content_type: Option<String>,
+    /// Keep the message this replaces as an earlier version, instead of adding a second chunk
+    /// (chunk method only)
+    #[arg(long)]
+    pub append_history: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct DecodeArgs {
+    pub file: PathBuf,
+    /// Try every ancillary chunk and list likely messages, most printable first
+    #[arg(long, conflicts_with_all = ["chunk_type", "version", "method", "pipe"])]
+    pub guess: bool,
+    /// Chunk type the message is stored in, or the attribute is named after (chunk and xattr
+    /// methods)
+    #[arg(short = 't', long, default_value = "ruSt")]
+    pub chunk_type: String,
+    /// Read this earlier version of the message, as numbered by `pngme history` (chunk method only)
+    #[arg(long, value_name = "N")]
+    pub version: Option<u32>,
+    #[arg(long, visible_alias = "via", value_enum, default_value_t = Method::Chunk)]
+    pub method: Method,
+    /// Password for the spread and lsb methods
+    #[arg(long)]
+    pub password: Option<String>,
+    /// Read the spread password from the OS keyring entry saved by `pngme keyring set`
+    #[arg(long, value_name = "NAME", conflicts_with = "password")]
+    pub use_keyring: Option<String>,
+    /// Decrypt an age-encrypted message with the identities in this file
+    #[arg(long, value_name = "PATH")]
+    pub identity: Option<PathBuf>,
+    /// Decrypt an encrypted message with this passphrase
+    #[arg(long, conflicts_with = "identity")]
+    pub passphrase: Option<String>,
+    /// Decrypt with the session key saved by `pngme handshake`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["identity", "passphrase"])]
+    pub session: Option<PathBuf>,
+    /// Recover a forgotten spread or lsb password, and then the passphrase of an encrypted
+    /// message, by trying each line of this file
+    #[arg(long, value_name = "PATH")]
+    pub wordlist: Option<PathBuf>,
+    /// Transforms to apply to the payload in order, e.g. `base64d|gunzip`:
+    /// rot13, xor:<key> (or xor:0x<hex>), base64d, gunzip
+    #[arg(long, value_name = "FILTERS", value_delimiter = '|')]
+    pub pipe: Vec<Filter>,
+    /// Skip over corrupt chunks instead of failing on them
+    #[arg(long)]
+    pub lossy: bool,
+    /// Print a message even if it has expired, with a warning
+    #[arg(long)]
+    pub ignore_expiry: bool,
+    /// Put the message on the clipboard instead of printing it
+    #[arg(long, conflicts_with = "guess")]
+    pub to_clipboard: bool,
+    /// Text encoding to print the message in, like latin1 or shift_jis [default: UTF-8 if
+    /// valid, otherwise a guess]
+    #[arg(long, value_name = "ENCODING")]
+    pub encoding: Option<String>,
+    /// Save the message to this new file instead of printing it; `auto` names it after the
+    /// image, with an extension for the message's type
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["guess", "to_clipboard"])]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct EditArgs {
+    pub file: PathBuf,
+    pub chunk_type: String,
+    /// Which chunk of that type to edit, counting from 0
+    #[arg(long, default_value_t = 0)]
+    pub index: usize,
+    /// New payload as hexadecimal bytes; whitespace is ignored
+    #[arg(long, value_name = "HEX", conflicts_with = "set_file")]
+    pub set_hex: Option<String>,
+    /// Read the new payload from a file
+    #[arg(long, value_name = "PATH")]
+    pub set_file: Option<PathBuf>,
+    /// Edit in the temporary directory when $XDG_RUNTIME_DIR isn't memory-backed, even
+    /// though the payload could then reach disk
+    #[arg(long, conflicts_with_all = ["set_hex", "set_file"])]
+    pub allow_disk: bool,
+}
+
+#[derive(Subcommand)]
+pub enum KeyringCommand {
+    /// Save a password under NAME, reading it from standard input
+    Set { name: String },
+    /// Delete the password saved under NAME
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum FieldCommand {
+    /// Print a field's value
+    Get(FieldGetArgs),
+    /// Replace a field's value, re-encoding the rest of the payload unchanged
+    Set(FieldSetArgs),
+}
+
+#[derive(Args)]
+pub struct FieldGetArgs {
+    pub file: PathBuf,
+    pub chunk_type: String,
+    pub field: String,
+    /// Which chunk of that type to read, counting from 0
+    #[arg(long, default_value_t = 0)]
+    pub index: usize,
+}
+
+#[derive(Args)]
+pub struct FieldSetArgs {
+    pub file: PathBuf,
+    pub chunk_type: String,
+    pub field: String,
+    /// Decimal for integers, text for strz, hex for bytes[n]
+    pub value: String,
+    /// Which chunk of that type to change, counting from 0
+    #[arg(long, default_value_t = 0)]
+    pub index: usize,
+}
+
+#[derive(Args)]
+pub struct CopyArgs {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// Comma-separated chunk types to copy, e.g. `iCCP,tEXt`
+    #[arg(long, value_delimiter = ',', required_unless_present = "select")]
+    pub types: Vec<String>,
+    /// Copy the ancillary chunks matching this selection instead, e.g. `text,!after-idat`
+    #[arg(long, conflicts_with = "types")]
+    pub select: Vec<Selector>,
+    /// Copy chunks even if they are not marked safe to copy
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// remove, insert, set-text and optimize operations, as `[[operation]]` tables
+    pub plan: PathBuf,
+    /// Files, or directories to search for `.png` files
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct SharedArgs {
+    /// Files, or directories to search for `.png` files
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Only list chunks found in at least this many files
+    #[arg(long, default_value_t = 2)]
+    pub min_files: usize,
+    /// Skip CRC verification, roughly halving the time a large sweep takes
+    #[arg(long)]
+    pub no_crc: bool,
+}
+
+#[cfg(feature = "scripting")]
+#[derive(Args)]
+pub struct ScriptArgs {
+    pub script: PathBuf,
+    pub file: PathBuf,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Set up with `git config filter.pngme.clean 'pngme git-filter --clean %f'`, the same for
+/// `smudge`, and `*.png filter=pngme` in `.gitattributes`.
+#[derive(Args)]
+pub struct GitFilterArgs {
+    /// Remove timestamps, volatile text and editor chunks, keeping a local copy of them
+    #[arg(long, required_unless_present = "smudge", conflicts_with = "smudge")]
+    pub clean: bool,
+    /// Put back the chunks the last clean of this path removed
+    #[arg(long)]
+    pub smudge: bool,
+    /// The file's path in the repository (git's %f); without it nothing is kept or restored
+    pub path: Option<PathBuf>,
+    /// Where the removed chunks are kept, one file per path
+    #[arg(long, default_value = ".git/pngme")]
+    pub store: PathBuf,
+}
+
+#[derive(Subcommand)]
+pub enum HookCommand {
+    /// Add a pre-commit hook to the current repository that runs `pngme hook run`
+    Install {
+        /// Replace an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the staged PNG files, failing if any breaks the policy
+    Run {
+        /// allow-gps, forbidden-text-keys, max-file-size and max-chunk-size; if the file
+        /// doesn't exist, GPS locations and Author text are refused
+        #[arg(long, default_value = ".pngme-policy.toml")]
+        policy: PathBuf,
+    },
+}
+
+#[derive(Args)]
+pub struct NormalizeArgs {
+    pub file: PathBuf,
+    /// Drop ancillary chunks that repeat an earlier chunk's type and payload
+    #[arg(long)]
+    pub dedupe: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CropArgs {
+    pub file: PathBuf,
+    /// WIDTHxHEIGHT+X+Y, e.g. `640x480+10+20`; without an offset, from the top left
+    pub region: Region,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PadArgs {
+    pub file: PathBuf,
+    /// Border widths in pixels: N, VERTICAL,HORIZONTAL or TOP,RIGHT,BOTTOM,LEFT
+    pub border: Border,
+    /// Fill colour, as `N` or `R,G,B` samples; defaults to the bKGD colour, else
+    /// transparent black
+    #[arg(long)]
+    pub color: Option<Color>,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RotateArgs {
+    pub file: PathBuf,
+    /// Degrees clockwise: 90, 180 or 270
+    pub angle: Rotation,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct FlipArgs {
+    pub file: PathBuf,
+    /// `h` to mirror left to right, `v` to mirror top to bottom
+    pub axis: Flip,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    pub file: PathBuf,
+    /// Colour type and bit depth: gray, graya, rgb, rgba or indexed, then the depth, e.g.
+    /// `rgba8` or `indexed4`; indexed targets get a palette by median cut if needed
+    #[arg(long)]
+    pub to: Target,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct StripArgs {
+    pub file: PathBuf,
+    /// legacy (gIFg, gIFx, gIFt), text, metadata (eXIf, tIME) or private; repeatable
+    #[arg(long, required_unless_present = "select")]
+    pub category: Vec<Category>,
+    /// Also remove the ancillary chunks matching this selection, e.g. `size>1000,after-idat`
+    #[arg(long)]
+    pub select: Vec<Selector>,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    /// Report PSNR and SSIM instead of just whether the pixels match
+    #[arg(long)]
+    pub metrics: bool,
+}
+
+#[derive(Args)]
+pub struct PrintArgs {
+    /// The file to list; with --ndjson, any number of files or directories to search for
+    /// `.png` files
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// Also show each payload, decoded by field for registered chunk types
+    #[arg(long)]
+    pub detailed: bool,
+    /// Skip over corrupt chunks instead of failing on them
+    #[arg(long)]
+    pub lossy: bool,
+    /// Only list the chunks matching this selection, e.g. `ancillary,size>1000`; repeatable
+    #[arg(long)]
+    pub select: Vec<Selector>,
+    /// Print a JSON object per line for each chunk as it is read, and one with an `error`
+    /// for each file that can't be read to the end; short for `--format ndjson`
+    #[arg(long, conflicts_with_all = ["detailed", "lossy", "select", "format"])]
+    pub ndjson: bool,
+    #[arg(long, value_enum, default_value_t = PrintFormat::Table)]
+    pub format: PrintFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrintFormat {
+    /// Aligned columns
+    Table,
+    /// A row per chunk of file, index, offset, type, length, crc and flags, header first;
+    /// takes any number of files
+    Csv,
+    /// A JSON object per line, as for --ndjson
+    Ndjson,
+}
+
+#[derive(Args)]
+pub struct FileArgs {
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    pub file: PathBuf,
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One warning per line
+    Text,
+    /// A SARIF 2.1.0 log, for code-scanning dashboards
+    Sarif,
+}
+
+#[derive(Args)]
+pub struct HashArgs {
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// sha256, sha384 or sha512
+    #[arg(long, default_value = "sha256")]
+    pub algo: Algorithm,
+    /// Also write the digests to this JSON manifest
+    #[arg(long, value_name = "PATH")]
+    pub manifest: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct SealArgs {
+    pub file: PathBuf,
+    /// Bytes of image data covered by each leaf of the tree
+    #[arg(long, default_value_t = DEFAULT_SEGMENT_SIZE)]
+    pub segment_size: u32,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    pub file: PathBuf,
+    /// Check only these segments, e.g. `0,5,9`, instead of the whole image
+    #[arg(long, value_delimiter = ',')]
+    pub segments: Vec<usize>,
+    /// Also require the tree's root to be this hex digest, recorded when sealing
+    #[arg(long, value_name = "HEX")]
+    pub root: Option<String>,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    pub file: PathBuf,
+    /// Chunk type the message is stored in
+    #[arg(short = 't', long, default_value = "ruSt")]
+    pub chunk_type: String,
+}
+
+#[derive(Args)]
+pub struct ShareArgs {
+    /// File holding the secret
+    pub secret: PathBuf,
+    /// Images to hide one share each in; each is overwritten
+    #[arg(required = true)]
+    pub images: Vec<PathBuf>,
+    /// How many shares are needed to recover the secret
+    #[arg(long)]
+    pub threshold: u8,
+    /// How many shares to make; must match the number of images, which is the default
+    #[arg(long)]
+    pub shares: Option<u8>,
+}
+
+#[derive(Args)]
+pub struct ReconstructArgs {
+    #[arg(required = true)]
+    pub images: Vec<PathBuf>,
+    /// Write the secret to this new file instead of standard output
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum HandshakeCommand {
+    /// Hide a new public key in CARRIER and save its secret to the new file SECRET
+    Init {
+        carrier: PathBuf,
+        #[arg(long)]
+        secret: PathBuf,
+        /// Write the carrier here instead of overwriting it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Answer the image THEIRS: hide a public key in CARRIER and save the session key
+    Respond {
+        theirs: PathBuf,
+        carrier: PathBuf,
+        /// New file to save the session key to
+        #[arg(long)]
+        session: PathBuf,
+        /// Write the carrier here instead of overwriting it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Finish a handshake from `init` with the image THEIRS sent in response
+    Finish {
+        theirs: PathBuf,
+        /// The secret saved by `init`; deleted once the session key is saved
+        #[arg(long)]
+        secret: PathBuf,
+        /// New file to save the session key to
+        #[arg(long)]
+        session: PathBuf,
+    },
+}
+
+#[cfg(feature = "conformance")]
+#[derive(Args)]
+pub struct ConformanceArgs {
+    /// Files, or directories to search for `.png` files
+    #[arg(required = true)]
+    pub corpus: Vec<PathBuf>,
+}
+
+#[cfg(feature = "self-update")]
+#[derive(Args)]
+pub struct SelfUpdateArgs {
+    /// Only say whether there is a newer release
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Args)]
+pub struct SelftestArgs {
+    /// Write the corpus and an index, vectors.txt, to this directory instead
+    #[arg(long, value_name = "DIR")]
+    pub emit: Option<PathBuf>,
+}
+
+fn parse_content_type(mime: &str) -> Result<String, String> {
+    if mime != "auto" {
+        pngme_core::mime::validate(mime)?;
+    }
+    Ok(mime.to_string())
+}
+
+#[derive(Args)]
+pub struct NewArgs {
+    pub output: PathBuf,
+    /// Uniform noise over a flat colour
+    #[arg(long, conflicts_with_all = ["gradient", "perlin"])]
+    pub noise: bool,
+    /// A blend between two colours
+    #[arg(long, conflicts_with = "perlin")]
+    pub gradient: bool,
+    /// Perlin noise, like clouds; the default
+    #[arg(long)]
+    pub perlin: bool,
+    /// WIDTHxHEIGHT in pixels
+    #[arg(long, default_value = "512x512", value_parser = parse_size)]
+    pub size: (u32, u32),
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// How much uniform noise to mix in, from 0 for the pattern alone to 1 for noise alone
+    #[arg(long, default_value_t = 0.25)]
+    pub entropy: f64,
+    /// Greyscale rather than RGB
+    #[arg(long)]
+    pub grayscale: bool,
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("`{}` is not a size like 640x480", size);
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// A byte offset in decimal, or in hex with a `0x` prefix.
+fn parse_offset(offset: &str) -> Result<usize, String> {
+    let parsed = match offset
+        .strip_prefix("0x")
+        .or_else(|| offset.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => offset.parse(),
+    };
+    parsed.map_err(|_| format!("`{}` is not a byte offset", offset))
+}
+
+#[derive(Args)]
+pub struct PatchArgs {
+    pub file: PathBuf,
+    /// Offset of the first byte to overwrite, e.g. 26 or 0x1A
+    #[arg(long, value_parser = parse_offset)]
+    pub at: usize,
+    /// Bytes to write there, in hex; spaces are ignored
+    #[arg(long, value_name = "HEX")]
+    pub write_hex: String,
+    /// Recompute the CRC of each chunk the patch touches
+    #[arg(long)]
+    pub fix_crc: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RepairArgs {
+    pub file: PathBuf,
+    /// For each bad CRC, look for one flipped bit in the chunk or its CRC that explains it,
+    /// and flip it back; chunks over 64 MiB are left alone
+    #[arg(long)]
+    pub try_bitflips: bool,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    pub file: PathBuf,
+    /// Where to write the HTML report
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct HexviewArgs {
+    pub file: PathBuf,
+    /// Write the dump as a self-contained HTML page here instead
+    #[arg(long, value_name = "PATH")]
+    pub html: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct TemplateArgs {
+    pub file: PathBuf,
+    #[arg(long, value_enum, default_value_t = TemplateFormat::Kaitai)]
+    pub format: TemplateFormat,
+    /// Where to write it, instead of standard output
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TemplateFormat {
+    /// A Kaitai Struct `.ksy` spec
+    Kaitai,
+    /// An 010 Editor `.bt` binary template
+    #[value(name = "010")]
+    BinaryTemplate,
+}
+
+#[derive(Args)]
+pub struct GraphArgs {
+    pub file: PathBuf,
+    /// Graphviz DOT, for `dot -Tsvg`; the default
+    #[arg(long)]
+    pub dot: bool,
+    /// A Mermaid flowchart, for Markdown that renders Mermaid
+    #[arg(long, conflicts_with = "dot")]
+    pub mermaid: bool,
+}
+
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("transport").required(true)))]
+pub struct ApiArgs {
+    /// Read newline-delimited JSON requests from stdin and answer each on stdout
+    #[arg(long, group = "transport")]
+    pub stdio: bool,
+    /// Serve the gRPC interface in proto/pngme.proto on this address
+    #[cfg(feature = "grpc")]
+    #[arg(long, value_name = "ADDRESS", group = "transport")]
+    pub grpc: Option<std::net::SocketAddr>,
+    /// The largest request to accept, in bytes: a gRPC message or a line of JSON
+    /// [default: 64 MiB]
+    #[arg(long, value_name = "BYTES")]
+    pub max_request_size: Option<usize>,
+    /// Cancel a gRPC request still running after this many seconds [default: 60]
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+    /// gRPC requests each connection may have in flight at once [default: 8]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub concurrency: Option<u32>,
+}
+
+#[derive(Subcommand)]
+pub enum BackgroundCommand {
+    /// Print the background colour
+    Get { file: PathBuf },
+    /// Set the background colour: a palette index or grey level, or R,G,B, as sample values
+    Set { file: PathBuf, color: Color },
+    /// Remove the background colour
+    Remove { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum TransparencyCommand {
+    /// Print the tRNS chunk's contents
+    Get { file: PathBuf },
+    /// Make a palette entry, grey level or R,G,B colour fully transparent
+    Set { file: PathBuf, color: Color },
+    /// Remove the tRNS chunk
+    Remove { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum SpltCommand {
+    /// Print each suggested palette's name, size and sample depth
+    List { file: PathBuf },
+    /// Add a palette of the image's most common colours
+    Add(SpltAddArgs),
+    /// Remove the suggested palette called NAME
+    Remove { file: PathBuf, name: String },
+}
+
+#[derive(Args)]
+pub struct SpltAddArgs {
+    pub file: PathBuf,
+    pub name: String,
+    /// Bits per sample: 8 or 16
+    #[arg(long, default_value_t = 8)]
+    pub depth: u8,
+    /// Most colours to include
+    #[arg(long, default_value_t = 256)]
+    pub colors: usize,
+}
+
+#[derive(Subcommand)]
+pub enum IccCommand {
+    /// Replace the image's colour space chunks with a profile
+    Set(IccSetArgs),
+}
+
+#[derive(Args)]
+pub struct IccSetArgs {
+    /// `srgb`, `display-p3`, or an ICC profile file
+    pub profile: String,
+    pub file: PathBuf,
+    /// Embed the sRGB profile in iCCP rather than writing an sRGB chunk
+    #[arg(long)]
+    pub embed: bool,
+    /// Name to store with a profile file [default: the file's name]
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum C2paCommand {
+    /// Write the embedded JUMBF manifest store to a file
+    Extract { file: PathBuf, output: PathBuf },
+}
+
+#[derive(Args)]
+pub struct StampArgs {
+    pub file: PathBuf,
+    /// Provenance entry as `key=value`; may be repeated
+    #[arg(
+        long = "key",
+        value_name = "KEY=VALUE",
+        value_parser = parse_key_value,
+        required_unless_present = "show",
+        conflicts_with = "show"
+    )]
+    pub keys: Vec<(String, String)>,
+    /// Print the recorded provenance instead of writing it
+    #[arg(long)]
+    pub show: bool,
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{}`", arg)),
+    }
+}
+
+#[derive(Subcommand)]
+pub enum XmpCommand {
+    /// Print the XMP packet
+    Get { file: PathBuf },
+    /// Replace the XMP packet with the contents of an XML file
+    Set { file: PathBuf, xmp: PathBuf },
+    /// Remove the XMP packet
+    Remove { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum WatermarkCommand {
+    /// Draw TEXT in the bottom right corner, or with --invisible hide a mark keyed by it
+    Add(WatermarkAddArgs),
+    /// Check whether the image carries the invisible mark for TEXT
+    Detect {
+        file: PathBuf,
+        #[arg(long)]
+        text: String,
+        /// Look for the mark added with --robust
+        #[arg(long)]
+        robust: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct WatermarkAddArgs {
+    pub file: PathBuf,
+    #[arg(long)]
+    pub text: String,
+    /// Spread a mark over the pixels that `pngme watermark detect` finds but eyes don't
+    #[arg(long)]
+    pub invisible: bool,
+    /// Make the invisible mark survive JPEG re-encoding and mild scaling, by spreading it
+    /// over larger cells laid out relative to the image's size; it needs at least 128x128
+    #[arg(long, requires = "invisible")]
+    pub robust: bool,
+    /// How opaque the visible text is, from 0 to 1
+    #[arg(long, default_value_t = 0.6, conflicts_with_all = ["invisible", "strength"])]
+    pub opacity: f64,
+    /// How many levels of 255 the invisible mark shifts each block by; more survives more
+    /// editing but starts to show
+    #[arg(long, default_value_t = 2.0, requires = "invisible")]
+    pub strength: f64,
+    /// Write the result here instead of overwriting the input
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum ChannelsCommand {
+    /// Write each channel to DIRECTORY as a greyscale PNG: red.png, green.png, blue.png
+    /// and alpha.png, or gray.png and alpha.png
+    Split { file: PathBuf, directory: PathBuf },
+    /// Combine one to four greyscale PNGs, given in channel order, into OUTPUT: grey,
+    /// grey and alpha, RGB or RGBA
+    Merge {
+        output: PathBuf,
+        #[arg(required = true, num_args = 1..=4)]
+        channels: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IndexCommand {
+    /// Write or refresh the sidecar of each file
+    Build {
+        /// Files, or directories to search for `.png` files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Remove the sidecar of each file
+    Clear {
+        /// Files, or directories to search for `.png` files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ThumbCommand {
+    /// Write the embedded thumbnail to a file
+    Extract { file: PathBuf, output: PathBuf },
+    /// Remove embedded thumbnails
+    Strip { file: PathBuf },
+}
@@ -0,0 +1,68 @@
+//! The system clipboard, for `encode --from-clipboard` and `decode --to-clipboard`, so short
+//! secrets need not pass through a file or the shell's history.
+
+#[cfg(feature = "clipboard")]
+mod sys {
+    use arboard::Clipboard;
+    use pngme_core::image;
+    use pngme_core::png::Png;
+
+    fn clipboard() -> Result<Clipboard, String> {
+        Clipboard::new().map_err(|error| format!("cannot open the clipboard: {}", error))
+    }
+
+    pub fn text() -> Result<String, String> {
+        clipboard()?
+            .get_text()
+            .map_err(|error| format!("cannot read text from the clipboard: {}", error))
+    }
+
+    /// On X11 and Wayland the clipboard is served by the program that set it, so this waits
+    /// until something else takes it over.
+    pub fn set_text(text: &str) -> Result<(), String> {
+        let mut clipboard = clipboard()?;
+        #[cfg(target_os = "linux")]
+        let result = {
+            use arboard::SetExtLinux;
+            eprintln!("waiting for the clipboard to be pasted or replaced");
+            clipboard.set().wait().text(text)
+        };
+        #[cfg(not(target_os = "linux"))]
+        let result = clipboard.set_text(text);
+        result.map_err(|error| format!("cannot write to the clipboard: {}", error))
+    }
+
+    /// The clipboard's image as a new RGBA PNG, for any picture the OS can hand over as
+    /// pixels.
+    pub fn image() -> Result<Png, String> {
+        let image = clipboard()?
+            .get_image()
+            .map_err(|error| format!("cannot read an image from the clipboard: {}", error))?;
+        let width = u32::try_from(image.width).map_err(|_| "the clipboard image is too wide")?;
+        let height = u32::try_from(image.height).map_err(|_| "the clipboard image is too tall")?;
+        image::rgba8_png(width, height, &image.bytes)
+            .map_err(|()| "the clipboard image has an unexpected size".to_string())
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+mod sys {
+    use pngme_core::png::Png;
+
+    const UNSUPPORTED: &str = "this build of pngme has no clipboard support; rebuild it with \
+                               `--features clipboard`";
+
+    pub fn text() -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn set_text(_: &str) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn image() -> Result<Png, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+pub use sys::{image, set_text, text};
@@ -0,0 +1,3237 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use pngme_core::c2pa::{self, C2PA_CHUNK_TYPE};
+use pngme_core::cache::ChunkStatistics;
+use pngme_core::carrier::{Carrier, Pattern};
+use pngme_core::chunk::Chunk;
+use pngme_core::chunk_type::{ChunkType, KnownChunk};
+use pngme_core::convert::Target;
+use pngme_core::envelope::Date;
+use pngme_core::extensions::{ImageOffset, PhysicalScale, PixelCalibration};
+use pngme_core::graph;
+use pngme_core::handshake::{self, KeyPair, SessionKey, HANDSHAKE_CHUNK_TYPE};
+use pngme_core::hash::{Algorithm, FileDigest, Manifest};
+use pngme_core::hexview::{self, Field};
+use pngme_core::histogram::Histogram;
+use pngme_core::image::{Color, ColorType, ImageData, ImageHeader};
+use pngme_core::index::{self, IndexEntry};
+use pngme_core::inspect;
+use pngme_core::legacy::{ApplicationExtension, GraphicControl};
+use pngme_core::metrics::{self, Comparison};
+use pngme_core::parallel;
+use pngme_core::plan::{Plan, RecompressCache};
+use pngme_core::png::{
+    Conformance, ParseErrorKind, ParseOptions, Png, Recovered, UNIQUE_ANCILLARY,
+};
+use pngme_core::policy::Policy;
+use pngme_core::provenance::{Provenance, PROVENANCE_CHUNK_TYPE};
+use pngme_core::registry::Registry;
+use pngme_core::schema;
+use pngme_core::seal::{self, Seal, SEAL_CHUNK_TYPE};
+use pngme_core::select::Selector;
+use pngme_core::share::{self, Share, SHARE_CHUNK_TYPE};
+use pngme_core::significant_bits::SignificantBits;
+use pngme_core::steganalysis::{self, ChiSquare, RsAnalysis};
+use pngme_core::suggested_palette::{self, SuggestedPalette};
+use pngme_core::template::{self, Template};
+use pngme_core::tiles::Bands;
+use pngme_core::validate::Diagnostics;
+use pngme_core::weigh;
+use pngme_core::{
+    api, background, channels, charset, envelope, explode, filter, geometry, git_filter, guess,
+    hex, history, icc, lsb, mime, patch, polyglot, report, sarif, select, spread, strip, thumbnail,
+    transparency, validate, vectors, watermark, xmp,
+};
+use zeroize::Zeroizing;
+
+use crate::args::{
+    ApiArgs, ApplyArgs, BackgroundCommand, C2paCommand, ChannelsCommand, CheckArgs, Cli, Command,
+    CompareArgs, ConvertArgs, CopyArgs, CropArgs, DecodeArgs, EditArgs, EncodeArgs, FieldCommand,
+    FieldGetArgs, FieldSetArgs, FileArgs, FlipArgs, Format, GitFilterArgs, GraphArgs,
+    HandshakeCommand, HashArgs, HexviewArgs, HistoryArgs, HookCommand, IccCommand, IccSetArgs,
+    IndexCommand, KeyringCommand, Method, NewArgs, NormalizeArgs, PadArgs, PatchArgs, PrintArgs,
+    PrintFormat, ReconstructArgs, RepairArgs, ReportArgs, RotateArgs, SealArgs, SelftestArgs,
+    ShareArgs, SharedArgs, SpltAddArgs, SpltCommand, StampArgs, StripArgs, TemplateArgs,
+    TemplateFormat, ThumbCommand, TransparencyCommand, VerifyArgs, WatermarkAddArgs,
+    WatermarkCommand, XmpCommand,
+};
+use crate::clipboard;
+use crate::i18n;
+use crate::output::{self, Align, Style, Table};
+use crate::prompt;
+use crate::stats;
+use crate::xattr;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Set by `--hardened`, so that `read_png` parses strictly in every command.
+static HARDENED: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--strict` and `--permissive`, picking the `ParseOptions` preset `read_png` uses.
+static STRICT: AtomicBool = AtomicBool::new(false);
+static PERMISSIVE: AtomicBool = AtomicBool::new(false);
+
+fn parse_options() -> ParseOptions {
+    if STRICT.load(Ordering::Relaxed) {
+        ParseOptions::strict()
+    } else if PERMISSIVE.load(Ordering::Relaxed) {
+        ParseOptions::permissive()
+    } else {
+        ParseOptions::default()
+    }
+}
+
+/// Whether files are parsed as by default, so that a chunk can be read through an index
+/// without parsing the rest.
+fn plain_parsing() -> bool {
+    !HARDENED.load(Ordering::Relaxed) && parse_options() == ParseOptions::default()
+}
+
+/// Set by `--band-height`; 0 decodes images whole.
+static BAND_HEIGHT: AtomicU32 = AtomicU32::new(0);
+
+fn band_height() -> Option<u32> {
+    Some(BAND_HEIGHT.load(Ordering::Relaxed)).filter(|&rows| rows > 0)
+}
+
+const INTERLACED_BANDS: &str = "--band-height doesn't work on interlaced images";
+
+pub fn run(cli: Cli) -> Result<()> {
+    if cli.no_swap {
+        lock_memory()?;
+    }
+    if cli.hardened && cli.permissive {
+        return Err("--permissive is not available in hardened mode".into());
+    }
+    HARDENED.store(cli.hardened, Ordering::Relaxed);
+    STRICT.store(cli.strict, Ordering::Relaxed);
+    PERMISSIVE.store(cli.permissive, Ordering::Relaxed);
+    BAND_HEIGHT.store(cli.band_height.unwrap_or(0), Ordering::Relaxed);
+    parallel::set_jobs(cli.jobs.unwrap_or(0) as usize);
+    output::init(cli.no_color, cli.no_pager);
+    let result = dispatch(cli.command, cli.registry.as_deref());
+    if cli.hardened {
+        // Any detail in the message could help an attacker probe what was wrong.
+        return result.map_err(|_| i18n::tr("invalid-input", &[]).into());
+    }
+    result
+}
+
+fn dispatch(command: Command, registry: Option<&Path>) -> Result<()> {
+    match command {
+        Command::Encode(args) => encode(args),
+        Command::Decode(args) => decode(args),
+        Command::Edit(args) => edit(args, registry),
+        Command::Field { command } => match command {
+            FieldCommand::Get(args) => field_get(args, registry),
+            FieldCommand::Set(args) => field_set(args, registry),
+        },
+        Command::Keyring { command } => match command {
+            KeyringCommand::Set { name } => keyring_set(&name),
+            KeyringCommand::Delete { name } => keyring_delete(&name),
+        },
+        Command::Copy(args) => copy(args),
+        Command::Explode {
+            file,
+            directory,
+            select,
+        } => explode(&file, &directory, &select),
+        Command::Implode { directory, output } => implode(&directory, &output),
+        Command::SplitConcat { file, directory } => split_concat(&file, &directory),
+        Command::Polyglot {
+            image,
+            payload,
+            output,
+        } => make_polyglot(&image, &payload, &output),
+        Command::Patch(args) => patch(args),
+        Command::Repair(args) => repair(args),
+        Command::Apply(args) => apply(args),
+        #[cfg(feature = "scripting")]
+        Command::Script(args) => script(args),
+        Command::GitFilter(args) => git_filter(args),
+        Command::Hook { command } => match command {
+            HookCommand::Install { force } => hook_install(force),
+            HookCommand::Run { policy } => hook_run(&policy),
+        },
+        Command::Shared(args) => shared(args),
+        Command::Strip(args) => strip(args),
+        Command::Normalize(args) => normalize(args),
+        Command::Crop(args) => crop(args),
+        Command::Pad(args) => pad(args),
+        Command::Rotate(args) => rotate(args),
+        Command::Flip(args) => flip(args),
+        Command::Convert(args) => convert(args),
+        Command::Stamp(args) => stamp(args),
+        Command::Print(args) => print(args, registry),
+        Command::Check(args) => check(args),
+        Command::C2pa { command } => match command {
+            C2paCommand::Extract { file, output } => c2pa_extract(&file, &output),
+        },
+        Command::Background { command } => match command {
+            BackgroundCommand::Get { file } => background_get(&file),
+            BackgroundCommand::Set { file, color } => background_set(&file, color),
+            BackgroundCommand::Remove { file } => background_remove(&file),
+        },
+        Command::Transparency { command } => match command {
+            TransparencyCommand::Get { file } => transparency_get(&file),
+            TransparencyCommand::Set { file, color } => transparency_set(&file, color),
+            TransparencyCommand::Remove { file } => transparency_remove(&file),
+        },
+        Command::Splt { command } => match command {
+            SpltCommand::List { file } => splt_list(&file),
+            SpltCommand::Add(args) => splt_add(args),
+            SpltCommand::Remove { file, name } => splt_remove(&file, &name),
+        },
+        Command::Icc { command } => match command {
+            IccCommand::Set(args) => icc_set(args),
+        },
+        Command::Xmp { command } => match command {
+            XmpCommand::Get { file } => xmp_get(&file),
+            XmpCommand::Set { file, xmp } => xmp_set(&file, &xmp),
+            XmpCommand::Remove { file } => xmp_remove(&file),
+        },
+        Command::Thumb { command } => match command {
+            ThumbCommand::Extract { file, output } => thumb_extract(&file, &output),
+            ThumbCommand::Strip { file } => thumb_strip(&file),
+        },
+        Command::Compare(args) => compare(args),
+        Command::Analyze(args) => analyze(args),
+        Command::Channels { command } => match command {
+            ChannelsCommand::Split { file, directory } => channels_split(&file, &directory),
+            ChannelsCommand::Merge { output, channels } => channels_merge(&output, &channels),
+        },
+        Command::Index { command } => match command {
+            IndexCommand::Build { files } => index_build(&files),
+            IndexCommand::Clear { files } => index_clear(&files),
+        },
+        Command::Inspect(args) => inspect(&args.file),
+        Command::Weigh(args) => weigh(&args.file),
+        Command::Report(args) => report(args),
+        Command::Hexview(args) => hexview(args),
+        Command::Template(args) => template(args),
+        Command::Graph(args) => graph(args),
+        Command::Hash(args) => hash(args),
+        Command::VerifyManifest { manifest } => verify_manifest(&manifest),
+        Command::Seal(args) => seal(args),
+        Command::Verify(args) => verify(args),
+        Command::History(args) => history(args),
+        Command::Handshake { command } => match command {
+            HandshakeCommand::Init {
+                carrier,
+                secret,
+                output,
+            } => handshake_init(&carrier, &secret, output.as_deref()),
+            HandshakeCommand::Respond {
+                theirs,
+                carrier,
+                session,
+                output,
+            } => handshake_respond(&theirs, &carrier, &session, output.as_deref()),
+            HandshakeCommand::Finish {
+                theirs,
+                secret,
+                session,
+            } => handshake_finish(&theirs, &secret, &session),
+        },
+        Command::Watermark { command } => match command {
+            WatermarkCommand::Add(args) => watermark_add(args),
+            WatermarkCommand::Detect { file, text, robust } => {
+                watermark_detect(&file, &text, robust)
+            }
+        },
+        Command::New(args) => new(args),
+        Command::Selftest(args) => selftest(args),
+        #[cfg(feature = "conformance")]
+        Command::Conformance(args) => conformance(args),
+        Command::Share(args) => share(args),
+        Command::Reconstruct(args) => reconstruct(args),
+        Command::Api(args) => api(args),
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate(args) => self_update(args),
+        Command::Features { json } => features(json),
+        Command::Stats { own, reset } => usage_stats(own, reset),
+    }
+}
+
+fn default_registry_path() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("pngme").join("chunks.toml"))
+}
+
+/// Loads the chunk registry, which is optional unless a path was given explicitly.
+fn load_registry(path: Option<&Path>) -> Result<Registry> {
+    let (path, text) = match path {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+            (path.to_path_buf(), text)
+        }
+        None => match default_registry_path() {
+            Some(path) if path.exists() => {
+                let text = fs::read_to_string(&path)?;
+                (path, text)
+            }
+            _ => return Ok(Registry::default()),
+        },
+    };
+    Registry::from_toml(&text).map_err(|error| format!("{}: {}", path.display(), error).into())
+}
+
+fn read_png(path: &Path) -> Result<Png> {
+    parse_png(path, &fs::read(path)?)
+}
+
+fn parse_png(path: &Path, bytes: &[u8]) -> Result<Png> {
+    let not_a_png = || i18n::tr("not-a-png", &[("path", path.display().to_string())]);
+    if HARDENED.load(Ordering::Relaxed) {
+        return Png::from_bytes_hardened(bytes).map_err(|()| not_a_png().into());
+    }
+    let options = parse_options();
+    let mut diagnostics = Diagnostics::new();
+    let png = if options.conformance == Conformance::Permissive {
+        Png::parse_with_diagnostics(bytes, &options, &mut diagnostics)
+    } else {
+        Png::parse(bytes, &options)
+    };
+    let png = png.map_err(|error| match error.kind {
+        ParseErrorKind::OffSpec(_) => format!("{}: {}", path.display(), error),
+        _ => not_a_png(),
+    })?;
+    for finding in diagnostics.findings() {
+        eprintln!("warning: {}: {}", path.display(), finding);
+    }
+    Ok(png)
+}
+
+/// Reads whatever chunks survive in a damaged file, warning about each skipped byte range.
+fn read_png_lossy(path: &Path) -> Result<Png> {
+    if HARDENED.load(Ordering::Relaxed) {
+        return Err("--lossy is not available in hardened mode".into());
+    }
+    let Recovered { png, skipped, .. } = Png::from_bytes_lossy(&fs::read(path)?, &parse_options())
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    for range in skipped {
+        eprintln!(
+            "warning: skipped bytes {}..{} of {}",
+            range.start,
+            range.end,
+            path.display()
+        );
+    }
+    Ok(png)
+}
+
+fn read_png_with(path: &Path, lossy: bool) -> Result<Png> {
+    if lossy {
+        read_png_lossy(path)
+    } else {
+        read_png(path)
+    }
+}
+
+fn read_image(path: &Path) -> Result<ImageData> {
+    image_of(path, &read_png(path)?)
+}
+
+fn image_header(path: &Path, png: &Png) -> Result<ImageHeader> {
+    ImageHeader::from_png(png)
+        .map_err(|()| format!("{} has no valid IHDR chunk", path.display()).into())
+}
+
+fn image_of(path: &Path, png: &Png) -> Result<ImageData> {
+    ImageData::from_png(png)
+        .map_err(|()| format!("{} has no decodable image data", path.display()).into())
+}
+
+fn write_png(path: &Path, png: &Png) -> Result<()> {
+    if c2pa::manifest_store(png).is_some() {
+        eprintln!("warning: this change invalidates the file's C2PA manifest");
+    }
+    fs::write(path, png.as_bytes())?;
+    Ok(())
+}
+
+const KEYRING_SERVICE: &str = "pngme";
+
+/// Keeps every page of the process in RAM, so secrets are never swapped out, and turns
+/// off core dumps.
+#[cfg(unix)]
+fn lock_memory() -> Result<()> {
+    let no_core = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: both calls only change process-wide limits and take no pointers we keep.
+    unsafe {
+        if libc::setrlimit(libc::RLIMIT_CORE, &no_core) != 0 {
+            return Err(format!(
+                "cannot disable core dumps: {}",
+                std::io::Error::last_os_error()
+            )
+            .into());
+        }
+        if libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) != 0 {
+            return Err(format!(
+                "cannot lock memory: {}; try raising `ulimit -l`",
+                std::io::Error::last_os_error()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lock_memory() -> Result<()> {
+    Err("--no-swap is only supported on Unix".into())
+}
+
+fn keyring_entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .map_err(|error| format!("cannot open the OS keyring: {}", error).into())
+}
+
+/// The password given on the command line, or else the one saved in the keyring.
+fn secret(
+    password: Option<String>,
+    keyring_name: &Option<String>,
+) -> Result<Option<Zeroizing<String>>> {
+    match (password, keyring_name) {
+        (Some(password), _) => Ok(Some(Zeroizing::new(password))),
+        (None, Some(name)) => keyring_entry(name)?
+            .get_password()
+            .map(|password| Some(Zeroizing::new(password)))
+            .map_err(|error| format!("no keyring password for {}: {}", name, error).into()),
+        (None, None) => Ok(None),
+    }
+}
+
+/// As `secret`, but asking on the terminal when neither a password nor a keyring entry was
+/// given.
+fn secret_or_prompt(
+    password: Option<String>,
+    keyring_name: &Option<String>,
+    confirm: bool,
+) -> Result<Option<Zeroizing<String>>> {
+    match secret(password, keyring_name)? {
+        None if prompt::is_interactive() => Ok(Some(prompt::password("Password", confirm)?)),
+        found => Ok(found),
+    }
+}
+
+fn keyring_set(name: &str) -> Result<()> {
+    let mut password = Zeroizing::new(String::new());
+    std::io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err("no password given on standard input".into());
+    }
+    keyring_entry(name)?
+        .set_password(password)
+        .map_err(|error| format!("cannot save to the keyring: {}", error).into())
+}
+
+fn keyring_delete(name: &str) -> Result<()> {
+    keyring_entry(name)?
+        .delete_credential()
+        .map_err(|error| format!("cannot delete {} from the keyring: {}", name, error).into())
+}
+
+fn no_chunk(chunk_type: ChunkType) -> String {
+    i18n::tr("no-chunk", &[("type", chunk_type.to_string())])
+}
+
+fn parse_chunk_type(chunk_type: &str) -> Result<ChunkType> {
+    ChunkType::from_str(chunk_type).map_err(|reason| {
+        i18n::tr(
+            "invalid-chunk-type",
+            &[("type", chunk_type.to_string()), ("reason", reason)],
+        )
+        .into()
+    })
+}
+
+fn encode(args: EncodeArgs) -> Result<()> {
+    let mut png = if args.image_from_clipboard {
+        clipboard::image()?
+    } else {
+        read_png(&args.file)?
+    };
+    let mut chunk_type = args.chunk_type;
+    let message = match args.message {
+        Some(message) => message,
+        None if args.from_clipboard => clipboard::text()?,
+        None if prompt::is_interactive() => {
+            if chunk_type.is_none() && !matches!(args.method, Method::Spread | Method::Lsb) {
+                chunk_type = Some(prompt::chunk_type(DEFAULT_CHUNK_TYPE)?);
+            }
+            prompt::message()?
+        }
+        None => {
+            return Err("no message given, and not running in a terminal to ask for one".into())
+        }
+    };
+    let chunk_type = chunk_type.unwrap_or_else(|| DEFAULT_CHUNK_TYPE.to_string());
+    let plaintext = Zeroizing::new(message.into_bytes());
+    let declared = match args.content_type.as_deref() {
+        Some("auto") => Some(mime::sniff(&plaintext)),
+        declared => declared,
+    };
+    let plaintext = envelope::with_content_type(declared, &plaintext);
+    let encrypted =
+        args.session.is_some() || !args.recipients.is_empty() || !args.passphrases.is_empty();
+    // Sealed inside the encryption too, so the readable copy added below can't be changed.
+    let plaintext = match args.expires {
+        Some(expires) if encrypted => envelope::with_expiry(expires, &plaintext),
+        _ => plaintext,
+    };
+    let passphrases: Vec<Zeroizing<String>> =
+        args.passphrases.into_iter().map(Zeroizing::new).collect();
+    let message = if let Some(path) = &args.session {
+        Zeroizing::new(envelope::encrypt_with_key(
+            read_session(path)?.key(),
+            &plaintext,
+        )?)
+    } else if !encrypted {
+        plaintext
+    } else {
+        Zeroizing::new(envelope::encrypt(
+            &args.recipients,
+            &passphrases,
+            &plaintext,
+        )?)
+    };
+    let message = match args.expires {
+        Some(expires) => envelope::with_expiry(expires, &message),
+        None => message,
+    };
+    if args.method != Method::Lsb
+        && (!args.channels.is_empty() || args.bits.is_some() || args.adaptive)
+    {
+        return Err("--channels, --bits and --adaptive need the lsb method".into());
+    }
+    match args.method {
+        Method::Chunk => {
+            let chunk = Chunk::new(parse_chunk_type(&chunk_type)?, message.to_vec());
+            if args.append_history {
+                history::append(&mut png, chunk)
+                    .map_err(|()| "the file has a corrupt history chunk")?;
+            } else {
+                png.append_chunk(chunk);
+            }
+        }
+        Method::Spread | Method::Lsb | Method::Xattr if args.append_history => {
+            return Err("--append-history needs the chunk method".into())
+        }
+        Method::Xattr => {
+            let target = match &args.output {
+                Some(output) => {
+                    fs::copy(&args.file, output)?;
+                    output
+                }
+                None if args.image_from_clipboard => {
+                    write_png(&args.file, &png)?;
+                    &args.file
+                }
+                None => &args.file,
+            };
+            let name = attribute_name(&chunk_type)?;
+            return xattr::set(target, &name, &message).map_err(|error| {
+                format!("cannot set extended attribute {}: {}", name, error).into()
+            });
+        }
+        Method::Spread => {
+            let password = secret_or_prompt(args.password, &args.use_keyring, true)?
+                .ok_or("the spread method needs --password or --use-keyring")?;
+            let password = password.as_str();
+            if let Some(band_height) = band_height() {
+                let header = image_header(&args.file, &png)?;
+                if header.interlaced {
+                    return Err(INTERLACED_BANDS.into());
+                }
+                let capacity = spread::header_capacity(&header)
+                    .map_err(|()| "the spread method does not support palette images")?;
+                if message.len() > capacity {
+                    return Err(format!(
+                        "message is {} bytes but the image can hold only {}",
+                        message.len(),
+                        capacity
+                    )
+                    .into());
+                }
+                spread::embed_in_bands(&mut png, password, &message, band_height)
+                    .map_err(|()| format!("{} has no decodable image data", args.file.display()))?;
+                return write_png(args.output.as_deref().unwrap_or(&args.file), &png);
+            }
+            let mut image = image_of(&args.file, &png)?;
+            let capacity = spread::capacity(&image)
+                .map_err(|()| "the spread method does not support palette images")?;
+            spread::embed(&mut image, password, &message).map_err(|()| {
+                format!(
+                    "message is {} bytes but the image can hold only {}",
+                    message.len(),
+                    capacity
+                )
+            })?;
+            image
+                .write_to(&mut png)
+                .map_err(|()| "failed to write the image data")?;
+            warn_if_detectable(&image, "try a shorter message")?;
+        }
+        Method::Lsb => {
+            let password = secret_or_prompt(args.password, &args.use_keyring, true)?
+                .ok_or("the lsb method needs --password or --use-keyring")?;
+            let mut image = image_of(&args.file, &png)?;
+            let layout = lsb::Layout::parse(
+                image.header().color_type,
+                &args.channels,
+                args.bits.unwrap_or(1),
+            )?;
+            let layout = lsb::Layout {
+                adaptive: args.adaptive,
+                ..layout
+            };
+            let cover = image.to_srgb();
+            lsb::embed(&mut image, &password, layout, &message)?;
+            image
+                .write_to(&mut png)
+                .map_err(|()| "failed to write the image data")?;
+            // How visible the embedding is, as `compare --metrics` would put it.
+            let stego = image.to_srgb();
+            eprintln!("PSNR: {:.2} dB", metrics::psnr(&cover, &stego).unwrap());
+            eprintln!("SSIM: {:.6}", metrics::ssim(&cover, &stego).unwrap());
+            warn_if_detectable(&image, "try a shorter message, or fewer channels or bits")?;
+        }
+    }
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)
+}
+
+/// Warns if steganalysis would pick up the message just hidden in `image`, with `advice`.
+fn warn_if_detectable(image: &ImageData, advice: &str) -> Result<()> {
+    let (chi_square, rs) = steganalyze(image)?;
+    if steganalysis::is_likely(&chi_square, &rs) {
+        eprintln!(
+            "warning: the output is easily detectable by steganalysis \
+             (chi-square p = {:.4}, RS estimate {:.0}%); {}",
+            chi_square.probability,
+            rs.estimate * 100.0,
+            advice
+        );
+    }
+    Ok(())
+}
+
+/// The extended attribute `--method xattr` keeps a message for `chunk_type` in.
+fn attribute_name(chunk_type: &str) -> Result<String> {
+    Ok(format!(
+        "{}{}",
+        validate::PAYLOAD_ATTRIBUTE_PREFIX,
+        parse_chunk_type(chunk_type)?
+    ))
+}
+
+const DEFAULT_CHUNK_TYPE: &str = "ruSt";
+
+const GUESS_PREVIEW: usize = 60;
+
+fn history(args: HistoryArgs) -> Result<()> {
+    let png = read_png(&args.file)?;
+    let chunk_type = parse_chunk_type(&args.chunk_type)?;
+    let versions =
+        history::versions(&png, chunk_type).map_err(|()| "the file has a corrupt history chunk")?;
+    if versions.is_empty() {
+        return Err(no_chunk(chunk_type).into());
+    }
+    for version in versions.iter().rev() {
+        let (expires, data) = envelope::split_expiry(&version.data);
+        let contents = if envelope::is_envelope(data) {
+            String::from("(encrypted)")
+        } else {
+            let text = String::from_utf8_lossy(data);
+            text.chars()
+                .take(GUESS_PREVIEW)
+                .collect::<String>()
+                .escape_debug()
+                .to_string()
+        };
+        let expires = expires.map_or(String::new(), |date| format!("  expires {}", date));
+        println!(
+            "{:>4}  {:>6} bytes  {}{}",
+            version.sequence,
+            version.data.len(),
+            contents,
+            expires
+        );
+    }
+    Ok(())
+}
+
+fn guess(file: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    let candidates = guess::candidates(&png);
+    if candidates.is_empty() {
+        return Err("no ancillary chunks with a payload".into());
+    }
+    for candidate in candidates {
+        let preview: String = candidate.text.chars().take(GUESS_PREVIEW).collect();
+        println!(
+            "{:>5.1}%  {:>4}  {}  {:<14}  {}",
+            candidate.printability * 100.0,
+            candidate.chunk_index,
+            candidate.chunk_type,
+            candidate.decoding,
+            preview.escape_debug()
+        );
+    }
+    Ok(())
+}
+
+/// Whether a message extracted with a guessed password is the one. Wrong passwords almost
+/// always fail the length check, and the message must also be valid UTF-8 or, sealed with a
+/// passphrase still to find, an envelope.
+fn is_message(message: &[u8]) -> bool {
+    let (_, contents) = envelope::split_expiry(message);
+    std::str::from_utf8(message).is_ok() || envelope::is_envelope(contents)
+}
+
+/// Candidates tried between progress updates.
+const PROGRESS_INTERVAL: usize = 1000;
+
+/// Tries every password in `wordlist` across a pool of worker threads, stopping at the first
+/// that `extract` gets a message with.
+fn search_wordlist(
+    wordlist: &str,
+    extract: impl Fn(&str) -> Option<Zeroizing<Vec<u8>>> + Sync,
+) -> Result<(Zeroizing<String>, Zeroizing<Vec<u8>>)> {
+    let candidates: Vec<&str> = wordlist.lines().filter(|line| !line.is_empty()).collect();
+    let workers = parallel::jobs();
+    let next = AtomicUsize::new(0);
+    let tried = AtomicUsize::new(0);
+    let found = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if found.lock().unwrap().is_some() {
+                    return;
+                }
+                let Some(password) = candidates.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                    return;
+                };
+                if let Some(message) = extract(password) {
+                    *found.lock().unwrap() = Some((Zeroizing::new(password.to_string()), message));
+                }
+                let tried = tried.fetch_add(1, Ordering::Relaxed) + 1;
+                if tried.is_multiple_of(PROGRESS_INTERVAL) {
+                    eprint!("\rtried {}/{} passwords", tried, candidates.len());
+                }
+            });
+        }
+    });
+    if candidates.len() >= PROGRESS_INTERVAL {
+        eprintln!();
+    }
+    found
+        .into_inner()
+        .unwrap()
+        .ok_or_else(|| format!("none of the {} passwords matched", candidates.len()).into())
+}
+
+/// Refuses a message that expired before today, or with `ignore` only warns about it.
+fn check_expiry(expires: Date, ignore: bool) -> Result<()> {
+    if envelope::is_expired(expires, Date::today()) {
+        if !ignore {
+            return Err(format!(
+                "the message expired on {}; pass --ignore-expiry to read it anyway",
+                expires
+            )
+            .into());
+        }
+        eprintln!("warning: the message expired on {}", expires);
+    }
+    Ok(())
+}
+
+fn decode(args: DecodeArgs) -> Result<()> {
+    if args.guess {
+        return guess(&args.file);
+    }
+    let wordlist = args
+        .wordlist
+        .as_deref()
+        .map(|path| fs::read_to_string(path).map(Zeroizing::new))
+        .transpose()?;
+    let message = match args.method {
+        Method::Chunk if args.version.is_none() && !args.lossy && plain_parsing() => {
+            // Seeking through the chunk headers, or reading them from the sidecar, reads one
+            // chunk without parsing the file.
+            let chunk_type = parse_chunk_type(&args.chunk_type)?;
+            let index = indexed(&args.file)?;
+            let entry = index
+                .iter()
+                .find(|entry| entry.chunk_type == chunk_type)
+                .ok_or_else(|| no_chunk(chunk_type))?;
+            let chunk = entry.read(&mut fs::File::open(&args.file)?)?;
+            Zeroizing::new(chunk.data().to_vec())
+        }
+        Method::Chunk => {
+            let png = read_png_with(&args.file, args.lossy)?;
+            let chunk_type = parse_chunk_type(&args.chunk_type)?;
+            match args.version {
+                Some(version) => {
+                    let versions = history::versions(&png, chunk_type)
+                        .map_err(|()| "the file has a corrupt history chunk")?;
+                    let version = versions
+                        .into_iter()
+                        .find(|found| found.sequence == version)
+                        .ok_or_else(|| {
+                            format!("no version {} of the {} message", version, chunk_type)
+                        })?;
+                    Zeroizing::new(version.data)
+                }
+                None => {
+                    let chunk = png
+                        .chunk_by_type(chunk_type)
+                        .ok_or_else(|| no_chunk(chunk_type))?;
+                    Zeroizing::new(chunk.data().to_vec())
+                }
+            }
+        }
+        Method::Spread | Method::Lsb | Method::Xattr if args.version.is_some() => {
+            return Err("--version needs the chunk method".into())
+        }
+        Method::Xattr => {
+            read_png_with(&args.file, args.lossy)?;
+            let name = attribute_name(&args.chunk_type)?;
+            let value = xattr::get(&args.file, &name)
+                .map_err(|error| format!("cannot read extended attribute {}: {}", name, error))?
+                .ok_or_else(|| format!("no {} extended attribute found", name))?;
+            Zeroizing::new(value)
+        }
+        Method::Lsb => {
+            let png = read_png_with(&args.file, args.lossy)?;
+            let password = if args.wordlist.is_some() {
+                secret(args.password, &args.use_keyring)?
+            } else {
+                secret_or_prompt(args.password, &args.use_keyring, false)?
+            };
+            let image = image_of(&args.file, &png)?;
+            match (password, &wordlist) {
+                (Some(password), _) => {
+                    lsb::extract(&image, &password)
+                        .map_err(|()| "no message found; is the password right?")?
+                        .1
+                }
+                (None, Some(wordlist)) => {
+                    let (password, message) = search_wordlist(wordlist, |password| {
+                        let (_, message) = lsb::extract(&image, password).ok()?;
+                        is_message(&message).then_some(message)
+                    })?;
+                    eprintln!("password found: {}", *password);
+                    message
+                }
+                (None, None) => {
+                    return Err(
+                        "the lsb method needs --password, --use-keyring or --wordlist".into(),
+                    )
+                }
+            }
+        }
+        Method::Spread => {
+            let png = read_png_with(&args.file, args.lossy)?;
+            let password = if args.wordlist.is_some() {
+                secret(args.password, &args.use_keyring)?
+            } else {
+                secret_or_prompt(args.password, &args.use_keyring, false)?
+            };
+            if let Some(band_height) = band_height() {
+                let password = password.ok_or("--band-height needs --password or --use-keyring")?;
+                if image_header(&args.file, &png)?.interlaced {
+                    return Err(INTERLACED_BANDS.into());
+                }
+                spread::extract_from_bands(&png, &password, band_height)
+                    .map_err(|()| "no message found; is the password right?")?
+            } else {
+                let image = image_of(&args.file, &png)?;
+                match (password, &wordlist) {
+                    (Some(password), _) => spread::extract(&image, &password)
+                        .map_err(|()| "no message found; is the password right?")?,
+                    (None, Some(wordlist)) => {
+                        let (password, message) = search_wordlist(wordlist, |password| {
+                            let message = spread::extract(&image, password).ok()?;
+                            is_message(&message).then_some(message)
+                        })?;
+                        eprintln!("password found: {}", *password);
+                        message
+                    }
+                    (None, None) => {
+                        return Err(
+                            "the spread method needs --password, --use-keyring or --wordlist"
+                                .into(),
+                        )
+                    }
+                }
+            }
+        }
+    };
+    let (expires, message) = envelope::split_expiry(&message);
+    if let Some(expires) = expires {
+        check_expiry(expires, args.ignore_expiry)?;
+    }
+    let encrypted = envelope::is_envelope(message);
+    let message = Zeroizing::new(message.to_vec());
+    let passphrase = args.passphrase.map(Zeroizing::new);
+    let message = match (&args.identity, passphrase, &args.session) {
+        (Some(path), _, _) => {
+            let identity = fs::read_to_string(path)
+                .map(Zeroizing::new)
+                .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+            envelope::decrypt(&identity, &message)?
+        }
+        (None, Some(passphrase), _) => envelope::decrypt_with_passphrase(&passphrase, &message)?,
+        (None, None, Some(path)) => {
+            envelope::decrypt_with_key(read_session(path)?.key(), &message)?
+        }
+        (None, None, None) if envelope::is_envelope(&message) => match &wordlist {
+            // Only the right passphrase gets past the envelope's authentication tag.
+            Some(wordlist) => {
+                let (passphrase, message) = search_wordlist(wordlist, |passphrase| {
+                    envelope::decrypt_with_passphrase(passphrase, &message).ok()
+                })?;
+                eprintln!("passphrase found: {}", *passphrase);
+                message
+            }
+            None if prompt::is_interactive() => {
+                let passphrase = prompt::password("Passphrase", false)?;
+                envelope::decrypt_with_passphrase(&passphrase, &message)?
+            }
+            None => return Err(
+                "the message is encrypted; pass --identity, --passphrase, --session or --wordlist"
+                    .into(),
+            ),
+        },
+        (None, None, None) => message,
+    };
+    let message = if encrypted {
+        let (sealed, message) = envelope::sealed_expiry(expires, &message)?;
+        if let (Some(sealed), None) = (sealed, expires) {
+            check_expiry(sealed, args.ignore_expiry)?;
+        }
+        Zeroizing::new(message.to_vec())
+    } else {
+        message
+    };
+    let (declared, message) = envelope::split_content_type(&message);
+    let declared = declared.map(String::from);
+    let message = filter::apply_all(&args.pipe, message)
+        .map(Zeroizing::new)
+        .map_err(|index| {
+            format!(
+                "filter {} ({}) could not decode its input",
+                index + 1,
+                args.pipe[index]
+            )
+        })?;
+    // Filters change what the payload is, so the declared type only holds without them.
+    let content_type = match declared {
+        Some(declared) if args.pipe.is_empty() => declared,
+        _ => mime::sniff(&message).to_string(),
+    };
+    if let Some(path) = &args.output_file {
+        let path = if path.as_os_str() == "auto" {
+            let stem = args.file.file_stem().unwrap_or_default().to_string_lossy();
+            args.file.with_file_name(format!(
+                "{}-message.{}",
+                stem,
+                mime::extension(&content_type)
+            ))
+        } else {
+            path.clone()
+        };
+        private_file(&path)?.write_all(&message)?;
+        eprintln!(
+            "wrote {} bytes of {} to {}",
+            message.len(),
+            content_type,
+            path.display()
+        );
+        return Ok(());
+    }
+    let message =
+        if mime::is_text(&content_type) || content_type == mime::UNKNOWN || args.encoding.is_some()
+        {
+            let decoded = charset::decode(&message, args.encoding.as_deref())?;
+            if decoded.encoding != "UTF-8" && args.encoding.is_none() {
+                eprintln!(
+                    "note: the message isn't UTF-8; showing it as {}, or pass --encoding",
+                    decoded.encoding
+                );
+            }
+            decoded.text
+        } else {
+            eprintln!(
+                "note: the message is {}; pass --output-file auto to save it as is",
+                content_type
+            );
+            Zeroizing::new(String::from_utf8_lossy(&message).into_owned())
+        };
+    if args.to_clipboard {
+        return Ok(clipboard::set_text(&message)?);
+    }
+    println!("{}", *message);
+    Ok(())
+}
+
+/// Lets the user edit `text` in `$VISUAL` or `$EDITOR`, returning the saved result. The file
+/// the editor works on lives in `$XDG_RUNTIME_DIR` when that is memory-backed, and only with
+/// `allow_disk` in the temporary directory; either way it is overwritten before removal.
+fn edit_in_editor(text: &str, label: &str, allow_disk: bool) -> Result<Vec<u8>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let directory = match env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from) {
+        Some(directory) if is_memory_backed(&directory) => directory,
+        _ if allow_disk => env::temp_dir(),
+        _ => {
+            return Err(
+                "$XDG_RUNTIME_DIR is not set or not memory-backed, so the payload \
+                        could reach disk; pass --allow-disk to edit it in the temporary \
+                        directory anyway"
+                    .into(),
+            )
+        }
+    };
+    let path = directory.join(format!("pngme-{}-{}.txt", process::id(), label));
+    private_file(&path)?.write_all(text.as_bytes())?;
+    let mut words = editor.split_whitespace();
+    let status: Result<process::ExitStatus> = match words.next() {
+        Some(program) => process::Command::new(program)
+            .args(words)
+            .arg(&path)
+            .status()
+            .map_err(Into::into),
+        None => Err("$EDITOR is empty".into()),
+    };
+    let edited = fs::read(&path);
+    scrub_file(&path)?;
+    if !status?.success() {
+        return Err("editor exited with an error; chunk left unchanged".into());
+    }
+    Ok(edited?)
+}
+
+/// Whether `directory` is on a tmpfs, whose files never reach disk except through swap.
+#[cfg(target_os = "linux")]
+fn is_memory_backed(directory: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(directory.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: statfs only reads the path and fills in the struct it is given.
+    let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::statfs(path.as_ptr(), &mut stats) };
+    result == 0 && stats.f_type as libc::c_long == libc::TMPFS_MAGIC
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_memory_backed(_directory: &Path) -> bool {
+    false
+}
+
+/// Overwrites the file at `path` with zeros, then removes it.
+fn scrub_file(path: &Path) -> Result<()> {
+    let scrubbed = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|mut file| {
+            let length = file.metadata()?.len();
+            io::copy(&mut io::repeat(0).take(length), &mut file)?;
+            file.sync_all()
+        });
+    fs::remove_file(path)?;
+    Ok(scrubbed?)
+}
+
+/// Creates a new file that only the current user can read.
+fn private_file(path: &Path) -> Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    Ok(options.open(path)?)
+}
+
+/// Position in the file of the `nth` chunk of the given type.
+fn find_chunk(png: &Png, chunk_type: &ChunkType, nth: usize) -> Result<usize> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| chunk.chunk_type() == chunk_type)
+        .nth(nth)
+        .map(|(index, _)| index)
+        .ok_or_else(|| format!("no {} chunk at index {}", chunk_type, nth).into())
+}
+
+fn field_schema<'a>(registry: &'a Registry, chunk_type: &ChunkType) -> Result<&'a schema::Schema> {
+    registry
+        .descriptor(&chunk_type.to_string())
+        .map(|descriptor| &descriptor.schema)
+        .ok_or_else(|| format!("{} is not a registered chunk type", chunk_type).into())
+}
+
+fn field_get(args: FieldGetArgs, registry: Option<&Path>) -> Result<()> {
+    let png = read_png(&args.file)?;
+    let registry = load_registry(registry)?;
+    let chunk_type = parse_chunk_type(&args.chunk_type)?;
+    let schema = field_schema(&registry, &chunk_type)?;
+    let chunk = &png.chunks()[find_chunk(&png, &chunk_type, args.index)?];
+    println!("{}", schema.get(chunk.data(), &args.field)?);
+    Ok(())
+}
+
+fn field_set(args: FieldSetArgs, registry: Option<&Path>) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let registry = load_registry(registry)?;
+    let chunk_type = parse_chunk_type(&args.chunk_type)?;
+    let schema = field_schema(&registry, &chunk_type)?;
+    let index = find_chunk(&png, &chunk_type, args.index)?;
+    let data = schema.set(png.chunks()[index].data(), &args.field, &args.value)?;
+    png.replace_chunk(index, Chunk::new(chunk_type, data));
+    write_png(&args.file, &png)
+}
+
+fn edit(args: EditArgs, registry: Option<&Path>) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let registry = load_registry(registry)?;
+    let chunk_type = parse_chunk_type(&args.chunk_type)?;
+    let index = find_chunk(&png, &chunk_type, args.index)?;
+    let chunk = &png.chunks()[index];
+
+    let data = if let Some(hex) = &args.set_hex {
+        hex::decode(hex).map_err(|()| "--set-hex needs pairs of hex digits")?
+    } else if let Some(path) = &args.set_file {
+        fs::read(path)?
+    } else if let Some(descriptor) = registry.descriptor(&chunk_type.to_string()) {
+        let fields = descriptor.schema.decode(chunk.data()).map_err(|()| {
+            format!(
+                "payload does not match the {} layout; use --set-hex or --set-file",
+                descriptor.name
+            )
+        })?;
+        let edited = edit_in_editor(
+            &schema::format_fields(&fields),
+            &chunk_type.to_string(),
+            args.allow_disk,
+        )?;
+        let edited = String::from_utf8(edited).map_err(|_| "edited text is not UTF-8")?;
+        let fields = descriptor.schema.parse_fields(&edited)?;
+        descriptor
+            .schema
+            .encode(&fields)
+            .map_err(|()| format!("every {} field needs a value", descriptor.name))?
+    } else {
+        let text = std::str::from_utf8(chunk.data())
+            .map_err(|_| "payload is not text; use --set-hex or --set-file")?;
+        edit_in_editor(text, &chunk_type.to_string(), args.allow_disk)?
+    };
+    png.replace_chunk(index, Chunk::new(chunk_type, data));
+    write_png(&args.file, &png)
+}
+
+fn copy(args: CopyArgs) -> Result<()> {
+    let source = read_png(&args.source)?;
+    let mut destination = read_png(&args.destination)?;
+    let mut chunks: Vec<&Chunk> = Vec::new();
+    if args.select.is_empty() {
+        for chunk_type in &args.types {
+            let chunk_type = parse_chunk_type(chunk_type.trim())?;
+            if chunk_type.is_critical() {
+                return Err(
+                    format!("{} is a critical chunk and cannot be copied", chunk_type).into(),
+                );
+            }
+            let before = chunks.len();
+            chunks.extend(
+                source
+                    .chunks()
+                    .iter()
+                    .filter(|chunk| *chunk.chunk_type() == chunk_type),
+            );
+            if chunks.len() == before {
+                eprintln!(
+                    "warning: {} has no {} chunk",
+                    args.source.display(),
+                    chunk_type
+                );
+            }
+        }
+    } else {
+        let selected = select::selected(&source, &args.select);
+        chunks.extend(
+            source
+                .chunks()
+                .iter()
+                .zip(selected)
+                .filter(|(chunk, selected)| *selected && !chunk.chunk_type().is_critical())
+                .map(|(chunk, _)| chunk),
+        );
+    }
+    if let Some(chunk) = chunks
+        .iter()
+        .find(|chunk| !chunk.chunk_type().is_safe_to_copy())
+        .filter(|_| !args.force)
+    {
+        return Err(format!(
+            "{} is not safe to copy into a different image; use --force to copy it anyway",
+            chunk.chunk_type()
+        )
+        .into());
+    }
+    if chunks.is_empty() {
+        return Err("nothing to copy".into());
+    }
+    for chunk in &chunks {
+        if UNIQUE_ANCILLARY.contains(chunk.chunk_type()) {
+            destination.retain_chunks(|existing| existing.chunk_type() != chunk.chunk_type());
+        }
+    }
+    for chunk in &chunks {
+        destination.insert_ancillary(Chunk::new(*chunk.chunk_type(), chunk.data().to_vec()));
+    }
+    println!("copied {} chunk(s)", chunks.len());
+    write_png(&args.destination, &destination)
+}
+
+fn normalize(args: NormalizeArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    if args.dedupe {
+        let removed = validate::remove_duplicates(&mut png);
+        println!("removed {} duplicate chunk(s)", removed);
+    }
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)
+}
+
+fn reframed(
+    file: &Path,
+    output: Option<&Path>,
+    reframe: impl FnOnce(&mut Png) -> std::result::Result<Vec<String>, String>,
+) -> Result<()> {
+    let mut png = read_png(file)?;
+    for note in reframe(&mut png)? {
+        eprintln!("warning: {}", note);
+    }
+    write_png(output.unwrap_or(file), &png)
+}
+
+fn crop(args: CropArgs) -> Result<()> {
+    reframed(&args.file, args.output.as_deref(), |png| {
+        geometry::crop(png, args.region)
+    })
+}
+
+fn pad(args: PadArgs) -> Result<()> {
+    reframed(&args.file, args.output.as_deref(), |png| {
+        geometry::pad(png, args.border, args.color)
+    })
+}
+
+fn rotate(args: RotateArgs) -> Result<()> {
+    reframed(&args.file, args.output.as_deref(), |png| {
+        geometry::rotate(png, args.angle)
+    })
+}
+
+fn flip(args: FlipArgs) -> Result<()> {
+    reframed(&args.file, args.output.as_deref(), |png| {
+        geometry::flip(png, args.axis)
+    })
+}
+
+fn convert(args: ConvertArgs) -> Result<()> {
+    reframed(&args.file, args.output.as_deref(), |png| {
+        png.convert(args.to.color_type, args.to.bit_depth)
+    })
+}
+
+fn apply(args: ApplyArgs) -> Result<()> {
+    let text = fs::read_to_string(&args.plan)
+        .map_err(|error| format!("cannot read {}: {}", args.plan.display(), error))?;
+    // Payload paths are relative to the plan, so that it can be run from anywhere.
+    let base = args.plan.parent().unwrap_or(Path::new(""));
+    let plan = Plan::from_toml(&text, |path| {
+        let path = base.join(path);
+        fs::read(&path).map_err(|error| format!("cannot read {}: {}", path.display(), error))
+    })
+    .map_err(|error| format!("{}: {}", args.plan.display(), error))?;
+    let files = png_files(&args.files)?;
+    // Only write anything once the plan has succeeded on every file.
+    let mut cache = RecompressCache::default();
+    let mut results = Vec::with_capacity(files.len());
+    for file in &files {
+        let mut png = read_png(file)?;
+        plan.apply(&mut png, &mut cache)
+            .map_err(|error| format!("{}: {}", file.display(), error))?;
+        results.push(png);
+    }
+    for (file, png) in files.iter().zip(&results) {
+        write_png(file, png)?;
+    }
+    println!(
+        "applied {} operation(s) to {} file(s)",
+        plan.operations.len(),
+        results.len()
+    );
+    if cache.hits() > 0 {
+        println!(
+            "recompressed {} distinct chunk(s), reusing the result {} time(s)",
+            cache.len(),
+            cache.hits()
+        );
+    }
+    Ok(())
+}
+
+/// `paths`, with each directory replaced by the `.png` files under it, in name order.
+fn png_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        let (directories, entries): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|entry| entry.is_dir());
+        files.extend(entries.into_iter().filter(|entry| {
+            entry
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        }));
+        files.extend(png_files(&directories)?);
+    }
+    Ok(files)
+}
+
+fn shared(args: SharedArgs) -> Result<()> {
+    if args.no_crc && HARDENED.load(Ordering::Relaxed) {
+        return Err("--no-crc is not available in hardened mode".into());
+    }
+    let options = ParseOptions {
+        verify_crc: !args.no_crc,
+        ..parse_options()
+    };
+    let mut statistics = ChunkStatistics::default();
+    for file in png_files(&args.files)? {
+        let png = if args.no_crc {
+            Png::from_bytes_with_options(&fs::read(&file)?, &options)
+                .map_err(|()| i18n::tr("not-a-png", &[("path", file.display().to_string())]))?
+        } else {
+            read_png(&file)?
+        };
+        statistics.add(&png);
+    }
+    let shared = statistics.shared(args.min_files);
+    for chunk in &shared {
+        println!(
+            "{}  {}  {:>10} bytes  in {} file(s), {} time(s)",
+            &hex::encode(&chunk.key)[..16],
+            chunk.chunk_type,
+            chunk.length,
+            chunk.files,
+            chunk.occurrences
+        );
+    }
+    println!(
+        "{} file(s), {} distinct ancillary chunk(s), {} shared; {} bytes duplicated",
+        statistics.files(),
+        statistics.distinct(),
+        shared.len(),
+        shared
+            .iter()
+            .map(|chunk| chunk.duplicated_bytes())
+            .sum::<u64>()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "scripting")]
+fn script(args: crate::args::ScriptArgs) -> Result<()> {
+    let source = fs::read_to_string(&args.script)
+        .map_err(|error| format!("cannot read {}: {}", args.script.display(), error))?;
+    let png = pngme_core::script::run(&source, read_png(&args.file)?)
+        .map_err(|error| format!("{}: {}", args.script.display(), error))?;
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)
+}
+
+fn git_filter(args: GitFilterArgs) -> Result<()> {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let stash = match &args.path {
+        Some(path) => Some(stash_path(&args.store, path)?),
+        None => None,
+    };
+    // Git stores whatever the filter prints, so anything that isn't a PNG goes through as is.
+    let Ok(mut png) = Png::parse(&input, &parse_options()) else {
+        return Ok(std::io::stdout().write_all(&input)?);
+    };
+    if args.clean {
+        let removed = git_filter::clean(&mut png);
+        if let Some(stash) = &stash {
+            if removed.is_empty() {
+                if stash.exists() {
+                    fs::remove_file(stash)?;
+                }
+            } else {
+                fs::create_dir_all(stash.parent().unwrap())?;
+                fs::write(stash, git_filter::stash_bytes(removed))?;
+            }
+        }
+    } else if let Some(stash) = stash.filter(|stash| stash.exists()) {
+        let stashed = git_filter::from_stash(&fs::read(&stash)?)
+            .map_err(|()| format!("{} is not a valid stash", stash.display()))?;
+        git_filter::smudge(&mut png, stashed);
+    }
+    std::io::stdout().write_all(&png.as_bytes())?;
+    Ok(())
+}
+
+/// `STORE/PATH.chunks`, refusing paths that would land outside the store.
+fn stash_path(store: &Path, path: &Path) -> Result<PathBuf> {
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("{} is not a path inside the repository", path.display()).into());
+    }
+    let mut stash = store.join(path).into_os_string();
+    stash.push(".chunks");
+    Ok(stash.into())
+}
+
+const PRE_COMMIT_HOOK: &str =
+    "#!/bin/sh\n# Installed by `pngme hook install`.\nexec pngme hook run\n";
+
+/// Runs git with `args`, returning its standard output.
+fn git(args: &[&str]) -> Result<Vec<u8>> {
+    let output = process::Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(output.stdout)
+}
+
+fn hook_install(force: bool) -> Result<()> {
+    // Asking git finds the hooks of linked worktrees and honours core.hooksPath.
+    let hooks = String::from_utf8(git(&["rev-parse", "--git-path", "hooks"])?)?;
+    let path = Path::new(hooks.trim()).join("pre-commit");
+    if path.exists() && !force {
+        return Err(format!(
+            "{} already exists; use --force to replace it",
+            path.display()
+        )
+        .into());
+    }
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, PRE_COMMIT_HOOK)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    println!("installed {}", path.display());
+    Ok(())
+}
+
+fn hook_run(policy_path: &Path) -> Result<()> {
+    let policy = match fs::read_to_string(policy_path) {
+        Ok(text) => Policy::from_toml(&text)
+            .map_err(|error| format!("{}: {}", policy_path.display(), error))?,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Policy::default(),
+        Err(error) => {
+            return Err(format!("cannot read {}: {}", policy_path.display(), error).into())
+        }
+    };
+    let staged = git(&[
+        "diff",
+        "--cached",
+        "--name-only",
+        "--diff-filter=ACMR",
+        "-z",
+    ])?;
+    let mut failed = 0;
+    for path in staged
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+    {
+        let path = String::from_utf8_lossy(path);
+        if !path.to_ascii_lowercase().ends_with(".png") {
+            continue;
+        }
+        // Check what is being committed, which may differ from the working tree.
+        let bytes = git(&["show", &format!(":{}", path)])?;
+        let findings = match Png::parse(&bytes, &parse_options()) {
+            Ok(png) => policy.check(&png, bytes.len() as u64),
+            Err(_) => {
+                println!("{}: not a valid PNG file", path);
+                failed += 1;
+                continue;
+            }
+        };
+        for finding in &findings {
+            println!("{}: {}: {}", path, finding.rule_id, finding);
+        }
+        if !findings.is_empty() {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} staged PNG file(s) break the policy in {}; commit blocked",
+            failed,
+            policy_path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn strip(args: StripArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let removed =
+        strip::strip(&mut png, &args.category) + strip::strip_selected(&mut png, &args.select);
+    println!("removed {} chunk(s)", removed);
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)
+}
+
+fn chunk_label(chunk: &Chunk) -> Option<&'static str> {
+    match type_label(&chunk.chunk_type().to_string()) {
+        None if xmp::is_xmp_chunk(chunk) => Some("XMP metadata"),
+        label => label,
+    }
+}
+
+/// The labels [`chunk_label`] gives by chunk type alone.
+fn type_label(chunk_type: &str) -> Option<&'static str> {
+    match chunk_type {
+        C2PA_CHUNK_TYPE => Some("C2PA manifest store"),
+        PROVENANCE_CHUNK_TYPE => Some("pngme provenance"),
+        "oFFs" => Some("image offset"),
+        "sCAL" => Some("physical pixel scale"),
+        "pCAL" => Some("pixel calibration"),
+        "gIFg" => Some("GIF graphic control extension"),
+        "gIFx" => Some("GIF application extension"),
+        "gIFt" => Some("GIF plain text extension (deprecated)"),
+        _ => None,
+    }
+}
+
+/// The ancillary chunks that `print --detailed` decodes itself: sBIT and hIST, checked
+/// against IHDR and PLTE, the oFFs, sCAL and pCAL extensions, and gIFg and gIFx.
+fn chunk_details(png: &Png, chunk: &Chunk) -> Option<Vec<String>> {
+    let header = ImageHeader::from_png(png).ok()?;
+    let details = match *chunk.chunk_type() {
+        ChunkType::SBIT => SignificantBits::new(&header, chunk.data().to_vec())
+            .map(|bits| vec![format!("significant bits: {}", bits)]),
+        ChunkType::HIST => {
+            Histogram::from_bytes(chunk.data(), background::palette_len(png)).map(|histogram| {
+                histogram
+                    .frequencies()
+                    .chunks(DUMP_WIDTH)
+                    .enumerate()
+                    .map(|(row, frequencies)| {
+                        let frequencies: Vec<String> =
+                            frequencies.iter().map(u16::to_string).collect();
+                        format!("{:>3}: {}", row * DUMP_WIDTH, frequencies.join(" "))
+                    })
+                    .collect()
+            })
+        }
+        ChunkType::OFFS => {
+            ImageOffset::from_bytes(chunk.data()).map(|offset| vec![offset.to_string()])
+        }
+        ChunkType::SCAL => {
+            PhysicalScale::from_bytes(chunk.data()).map(|scale| vec![scale.to_string()])
+        }
+        ChunkType::PCAL => PixelCalibration::from_bytes(chunk.data())
+            .map(|calibration| vec![calibration.to_string()]),
+        ChunkType::GIFG => {
+            GraphicControl::from_bytes(chunk.data()).map(|control| vec![control.to_string()])
+        }
+        ChunkType::GIFX => ApplicationExtension::from_bytes(chunk.data())
+            .map(|extension| vec![extension.to_string()]),
+        _ => return None,
+    };
+    Some(details.unwrap_or_else(|error| vec![format!("(invalid: {})", error)]))
+}
+
+const DUMP_WIDTH: usize = 16;
+const DUMP_LINES: usize = 4;
+
+fn hex_dump(data: &[u8]) -> Vec<String> {
+    let mut lines: Vec<String> = data
+        .chunks(DUMP_WIDTH)
+        .take(DUMP_LINES)
+        .map(hex::encode)
+        .collect();
+    if data.len() > DUMP_WIDTH * DUMP_LINES {
+        lines.push(format!(
+            "... {} more bytes",
+            data.len() - DUMP_WIDTH * DUMP_LINES
+        ));
+    }
+    lines
+}
+
+/// The chunks of the file at `path`, from its `.pngidx` sidecar while that is current.
+fn indexed(path: &Path) -> Result<Vec<IndexEntry>> {
+    index::cached(path).map_err(|error| format!("{}: {}", path.display(), error).into())
+}
+
+fn index_build(files: &[PathBuf]) -> Result<()> {
+    for file in png_files(files)? {
+        let entries =
+            index::build(&file).map_err(|error| format!("{}: {}", file.display(), error))?;
+        println!("{}: {} chunk(s)", file.display(), entries.len());
+    }
+    Ok(())
+}
+
+fn index_clear(files: &[PathBuf]) -> Result<()> {
+    for file in png_files(files)? {
+        if index::clear(&file)? {
+            println!("removed {}", index::sidecar_path(&file).display());
+        }
+    }
+    Ok(())
+}
+
+fn print(args: PrintArgs, registry: Option<&Path>) -> Result<()> {
+    let format = match args.ndjson {
+        true => PrintFormat::Ndjson,
+        false => args.format,
+    };
+    if format != PrintFormat::Table {
+        if args.detailed || args.lossy || !args.select.is_empty() {
+            return Err("--detailed, --lossy and --select only apply to the table".into());
+        }
+        return print_stream(&args.files, registry, format);
+    }
+    let [file] = &args.files[..] else {
+        return Err(
+            "print lists one file at a time, or any number with --format csv or ndjson".into(),
+        );
+    };
+    // With a sidecar from `pngme index build`, the plain listing needs no parse at all.
+    if !args.detailed
+        && args.select.is_empty()
+        && !args.lossy
+        && plain_parsing()
+        && index::sidecar_path(file).exists()
+    {
+        return print_indexed(file, registry);
+    }
+    let png = read_png_with(file, args.lossy)?;
+    let registry = load_registry(registry)?;
+    let selected = select::selected(&png, &args.select);
+    let mut table = Table::new(&[Align::Right, Align::Left, Align::Right, Align::Left]);
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if !args.select.is_empty() && !selected[index] {
+            continue;
+        }
+        let chunk_type = chunk.chunk_type().to_string();
+        let descriptor = registry.descriptor(&chunk_type);
+        let label = chunk_label(chunk).or(descriptor.map(|descriptor| descriptor.name.as_str()));
+        table.row(vec![
+            (index.to_string(), Style::Dim),
+            (chunk_type, Style::of_chunk_type(chunk.chunk_type())),
+            (output::byte_size(chunk.length() as u64), Style::Plain),
+            (label.unwrap_or_default().to_string(), Style::Plain),
+        ]);
+        if !args.detailed {
+            continue;
+        }
+        let details = match descriptor.map(|descriptor| descriptor.schema.decode(chunk.data())) {
+            Some(Ok(fields)) => schema::format_fields(&fields)
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            Some(Err(())) => {
+                let mut lines = vec![String::from(
+                    "(payload does not match the registered layout)",
+                )];
+                lines.extend(hex_dump(chunk.data()));
+                lines
+            }
+            None => chunk_details(&png, chunk).unwrap_or_else(|| hex_dump(chunk.data())),
+        };
+        for line in details {
+            table.detail(line);
+        }
+    }
+    output::page(&table.render());
+    Ok(())
+}
+
+/// The type's property bits, for `print --format csv`: `critical` or `ancillary`, `public`
+/// or `private`, `reserved` if that bit is set, and `safe-to-copy` or `unsafe-to-copy`.
+fn chunk_flags(chunk_type: &ChunkType) -> String {
+    let mut flags = vec![
+        if chunk_type.is_critical() {
+            "critical"
+        } else {
+            "ancillary"
+        },
+        if chunk_type.is_public() {
+            "public"
+        } else {
+            "private"
+        },
+    ];
+    if !chunk_type.is_reserved_bit_valid() {
+        flags.push("reserved");
+    }
+    flags.push(if chunk_type.is_safe_to_copy() {
+        "safe-to-copy"
+    } else {
+        "unsafe-to-copy"
+    });
+    flags.join(" ")
+}
+
+/// `field` quoted for CSV if it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `print --format csv` and `--ndjson`: a line per chunk, written as each is read so that a
+/// pipeline sees the first file's chunks before the last file is opened. A file that can't
+/// be read to the end gets an NDJSON line with an `error`, or a warning on stderr for CSV,
+/// after the chunks before the problem.
+fn print_stream(paths: &[PathBuf], registry: Option<&Path>, format: PrintFormat) -> Result<()> {
+    let registry = load_registry(registry)?;
+    let files = png_files(paths)?;
+    let failed = match write_stream(&files, &registry, format) {
+        // Whatever reads the output, like `head`, has seen all it wants.
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+        failed => failed?,
+    };
+    if failed > 0 {
+        return Err(format!("{} file(s) could not be read", failed).into());
+    }
+    Ok(())
+}
+
+/// The lines of [`print_stream`], returning how many files couldn't be read.
+fn write_stream(
+    files: &[PathBuf],
+    registry: &Registry,
+    format: PrintFormat,
+) -> std::io::Result<usize> {
+    let hardened = HARDENED.load(Ordering::Relaxed);
+    let mut stdout = std::io::stdout().lock();
+    if format == PrintFormat::Csv {
+        writeln!(stdout, "file,index,offset,type,length,crc,flags")?;
+    }
+    let mut failed = 0;
+    for path in files {
+        let file = path.display().to_string();
+        let mut error = None;
+        let chunks = match fs::File::open(path) {
+            Ok(opened) => Some(index::stream(std::io::BufReader::new(opened))),
+            Err(open_error) => {
+                error = Some(open_error);
+                None
+            }
+        };
+        for (position, result) in chunks.into_iter().flatten().enumerate() {
+            let (entry, chunk) = match result {
+                Ok(read) => read,
+                Err(read_error) => {
+                    error = Some(read_error);
+                    break;
+                }
+            };
+            let chunk_type = entry.chunk_type.to_string();
+            let crc = format!("{:08x}", chunk.crc());
+            if format == PrintFormat::Csv {
+                writeln!(
+                    stdout,
+                    "{},{},{},{},{},{},{}",
+                    csv_field(&file),
+                    position,
+                    entry.offset,
+                    chunk_type,
+                    entry.length,
+                    crc,
+                    chunk_flags(&entry.chunk_type)
+                )?;
+                stdout.flush()?;
+                continue;
+            }
+            let label = chunk_label(&chunk).or(registry
+                .descriptor(&chunk_type)
+                .map(|descriptor| descriptor.name.as_str()));
+            let line = serde_json::json!({
+                "file": file,
+                "index": position,
+                "offset": entry.offset,
+                "type": chunk_type,
+                "length": entry.length,
+                "crc": crc,
+                "critical": entry.chunk_type.is_critical(),
+                "label": label,
+            });
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()?;
+        }
+        if let Some(error) = error {
+            failed += 1;
+            let message = match hardened {
+                true => String::from("not a valid PNG file"),
+                false => error.to_string(),
+            };
+            if format == PrintFormat::Csv {
+                eprintln!("{}: {}", file, message);
+                continue;
+            }
+            let line = serde_json::json!({ "file": file, "error": message });
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(failed)
+}
+
+/// `print` from the chunk index, reading only the iTXt chunks that might be XMP.
+fn print_indexed(path: &Path, registry: Option<&Path>) -> Result<()> {
+    let index = indexed(path)?;
+    let registry = load_registry(registry)?;
+    let mut file = fs::File::open(path)?;
+    let mut table = Table::new(&[Align::Right, Align::Left, Align::Right, Align::Left]);
+    for (position, entry) in index.iter().enumerate() {
+        let chunk_type = entry.chunk_type.to_string();
+        let label = match entry.chunk_type {
+            ChunkType::ITXT => chunk_label(&entry.read(&mut file)?),
+            _ => type_label(&chunk_type),
+        };
+        let descriptor = registry.descriptor(&chunk_type);
+        let label = label.or(descriptor.map(|descriptor| descriptor.name.as_str()));
+        table.row(vec![
+            (position.to_string(), Style::Dim),
+            (chunk_type, Style::of_chunk_type(&entry.chunk_type)),
+            (output::byte_size(entry.length as u64), Style::Plain),
+            (label.unwrap_or_default().to_string(), Style::Plain),
+        ]);
+    }
+    output::page(&table.render());
+    Ok(())
+}
+
+fn warnings_found(count: usize) -> String {
+    i18n::tr("warnings-found", &[("count", count.to_string())])
+}
+
+fn check(args: CheckArgs) -> Result<()> {
+    let bytes = fs::read(&args.file)?;
+    let mut diagnostics = Diagnostics::new();
+    let png = Png::parse_with_diagnostics(&bytes, &parse_options(), &mut diagnostics)
+        .map_err(|error| format!("{}: {}", args.file.display(), error))?;
+
+    let mut warnings = diagnostics.into_findings();
+    warnings.extend(validate::content_findings(&png, &bytes));
+    // Filesystems and platforms without extended attributes simply have none to report.
+    if let Ok(attributes) = xattr::list(&args.file) {
+        warnings.extend(validate::attribute_findings(&attributes));
+    }
+    if args.format == Format::Sarif {
+        let log = sarif::log(&args.file.display().to_string(), &warnings);
+        println!("{}", serde_json::to_string_pretty(&log)?);
+        return if warnings.is_empty() {
+            Ok(())
+        } else {
+            Err(warnings_found(warnings.len()).into())
+        };
+    }
+    let mut text = String::new();
+    for warning in &warnings {
+        let line = i18n::tr("warning", &[("message", warning.to_string())]);
+        text.push_str(&output::paint(&line, Style::Warning));
+        text.push('\n');
+    }
+    output::page(&text);
+    if warnings.is_empty() {
+        let line = i18n::tr("check-ok", &[("path", args.file.display().to_string())]);
+        println!("{}", output::paint(&line, Style::Success));
+        Ok(())
+    } else {
+        Err(warnings_found(warnings.len()).into())
+    }
+}
+
+fn c2pa_extract(file: &Path, output: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    let store = c2pa::manifest_store(&png).ok_or("no C2PA manifest found")?;
+    fs::write(output, store)?;
+    Ok(())
+}
+
+fn stamp(args: StampArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let existing = Provenance::from_png(&png)
+        .transpose()
+        .map_err(|()| format!("{} chunk is not a valid CBOR map", PROVENANCE_CHUNK_TYPE))?;
+
+    if args.show {
+        let provenance = existing.ok_or("no provenance recorded")?;
+        for (key, value) in provenance.entries() {
+            println!("{}={}", key, value);
+        }
+        return Ok(());
+    }
+
+    let mut provenance = existing.unwrap_or_default();
+    for (key, value) in &args.keys {
+        provenance.set(key, value);
+    }
+    while png.remove_chunk(PROVENANCE_CHUNK_TYPE).is_ok() {}
+    png.append_chunk(provenance.to_chunk());
+    write_png(&args.file, &png)
+}
+
+fn xmp_get(file: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    let packet = xmp::xmp(&png)
+        .ok_or("no XMP metadata found")?
+        .map_err(|()| "XMP chunk is compressed or not valid UTF-8")?;
+    println!("{}", packet);
+    Ok(())
+}
+
+fn background_get(file: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    match background::background(&png) {
+        None => Err("no background colour found".into()),
+        Some(Err(())) => Err("the bKGD chunk does not match the image's colour type".into()),
+        Some(Ok(color)) => {
+            println!("{}", color);
+            Ok(())
+        }
+    }
+}
+
+fn background_set(file: &Path, color: Color) -> Result<()> {
+    let mut png = read_png(file)?;
+    background::set_background(&mut png, color)?;
+    write_png(file, &png)
+}
+
+fn background_remove(file: &Path) -> Result<()> {
+    let mut png = read_png(file)?;
+    if !background::remove_background(&mut png) {
+        return Err("no background colour found".into());
+    }
+    write_png(file, &png)
+}
+
+fn transparency_get(file: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    match transparency::transparency(&png) {
+        None => Err("no tRNS chunk found".into()),
+        Some(Err(())) => Err("the tRNS chunk does not match the image's colour type".into()),
+        Some(Ok(transparency)) => {
+            println!("{}", transparency);
+            Ok(())
+        }
+    }
+}
+
+fn transparency_set(file: &Path, color: Color) -> Result<()> {
+    let mut png = read_png(file)?;
+    transparency::set_transparent_color(&mut png, color)?;
+    write_png(file, &png)
+}
+
+fn transparency_remove(file: &Path) -> Result<()> {
+    let mut png = read_png(file)?;
+    if !transparency::remove_transparency(&mut png) {
+        return Err("no tRNS chunk found".into());
+    }
+    write_png(file, &png)
+}
+
+fn splt_list(file: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    let palettes = suggested_palette::palettes(&png);
+    if palettes.is_empty() {
+        return Err("no suggested palettes found".into());
+    }
+    for palette in palettes {
+        match palette {
+            Ok(palette) => println!("{}", palette),
+            Err(error) => println!("(invalid: {})", error),
+        }
+    }
+    Ok(())
+}
+
+fn splt_add(args: SpltAddArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let image = ImageData::from_png(&png).map_err(|()| "cannot decode the image data")?;
+    let palette =
+        SuggestedPalette::from_image(&args.name, args.depth, &image.to_encoded(), args.colors)?;
+    suggested_palette::add(&mut png, &palette)?;
+    println!("{}", palette);
+    write_png(&args.file, &png)
+}
+
+fn splt_remove(file: &Path, name: &str) -> Result<()> {
+    let mut png = read_png(file)?;
+    if !suggested_palette::remove(&mut png, name) {
+        return Err(format!("no suggested palette named `{}` found", name).into());
+    }
+    write_png(file, &png)
+}
+
+fn icc_set(args: IccSetArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let header = ImageHeader::from_png(&png).map_err(|()| "the file has no valid IHDR chunk")?;
+    let path = Path::new(&args.profile);
+    let chunks = if path.is_file() {
+        let profile = fs::read(path)?;
+        icc::validate(&profile, header.color_type)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        let name = match args.name {
+            Some(name) => name,
+            None => path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+        };
+        vec![icc::iccp_chunk(&name, &profile)?]
+    } else {
+        preset_chunks(&args.profile, header.color_type, args.embed)?
+    };
+    icc::assign(&mut png, chunks);
+    write_png(&args.file, &png)
+}
+
+#[cfg(feature = "builtin-profiles")]
+fn preset_chunks(preset: &str, color_type: ColorType, embed: bool) -> Result<Vec<Chunk>> {
+    let preset = icc::Preset::from_str(preset)?;
+    icc::validate(&preset.profile(), color_type)
+        .map_err(|error| format!("the {} profile cannot be used: {}", preset, error))?;
+    Ok(preset.chunks(embed))
+}
+
+#[cfg(not(feature = "builtin-profiles"))]
+fn preset_chunks(preset: &str, _: ColorType, _: bool) -> Result<Vec<Chunk>> {
+    Err(format!(
+        "{} is not a file, and this build has no built-in profiles",
+        preset
+    )
+    .into())
+}
+
+fn xmp_set(file: &Path, xmp_file: &Path) -> Result<()> {
+    let mut png = read_png(file)?;
+    let packet = fs::read_to_string(xmp_file)?;
+    xmp::set_xmp(&mut png, &packet);
+    write_png(file, &png)
+}
+
+fn xmp_remove(file: &Path) -> Result<()> {
+    let mut png = read_png(file)?;
+    if !xmp::remove_xmp(&mut png) {
+        return Err("no XMP metadata found".into());
+    }
+    write_png(file, &png)
+}
+
+fn thumb_extract(file: &Path, output: &Path) -> Result<()> {
+    let png = read_png(file)?;
+    let thumbnail = thumbnail::extract(&png)
+        .ok_or("no embedded thumbnail found")?
+        .map_err(|()| "embedded thumbnail metadata is malformed")?;
+    fs::write(output, thumbnail)?;
+    Ok(())
+}
+
+fn thumb_strip(file: &Path) -> Result<()> {
+    let mut png = read_png(file)?;
+    let changed =
+        thumbnail::strip(&mut png).map_err(|()| "embedded thumbnail metadata is malformed")?;
+    if !changed {
+        return Err("no embedded thumbnail found".into());
+    }
+    write_png(file, &png)
+}
+
+fn channels_split(file: &Path, directory: &Path) -> Result<()> {
+    let planes = channels::split(&read_png(file)?)?;
+    fs::create_dir_all(directory)?;
+    for (name, plane) in planes {
+        write_png(&directory.join(format!("{}.png", name)), &plane)?;
+    }
+    Ok(())
+}
+
+fn channels_merge(output: &Path, files: &[PathBuf]) -> Result<()> {
+    let planes = files
+        .iter()
+        .map(|file| read_png(file))
+        .collect::<Result<Vec<_>>>()?;
+    write_png(output, &channels::merge(&planes)?)
+}
+
+fn compare(args: CompareArgs) -> Result<()> {
+    if let Some(band_height) = band_height() {
+        return compare_in_bands(args, band_height);
+    }
+    let first = read_image(&args.first)?.to_srgb();
+    let second = read_image(&args.second)?.to_srgb();
+    if (first.width, first.height) != (second.width, second.height) {
+        return Err(format!(
+            "dimensions differ: {}x{} vs {}x{}",
+            first.width, first.height, second.width, second.height
+        )
+        .into());
+    }
+
+    if args.metrics {
+        let psnr = metrics::psnr(&first, &second).unwrap();
+        let ssim = metrics::ssim(&first, &second).unwrap();
+        println!("PSNR: {:.2} dB", psnr);
+        println!("SSIM: {:.6}", ssim);
+    } else if first == second {
+        println!("pixels are identical");
+    } else {
+        println!("pixels differ");
+    }
+    Ok(())
+}
+
+fn compare_in_bands(args: CompareArgs, band_height: u32) -> Result<()> {
+    let first_png = read_png(&args.first)?;
+    let second_png = read_png(&args.second)?;
+    // Whole SSIM windows in every band keep the metrics the same as without bands.
+    let band_height = band_height.next_multiple_of(8);
+    let first = Bands::new(&first_png, band_height).map_err(|()| INTERLACED_BANDS)?;
+    let second = Bands::new(&second_png, band_height).map_err(|()| INTERLACED_BANDS)?;
+    let (width, height) = (first.header().width, first.header().height);
+    let other = second.header();
+    if (width, height) != (other.width, other.height) {
+        return Err(format!(
+            "dimensions differ: {}x{} vs {}x{}",
+            width, height, other.width, other.height
+        )
+        .into());
+    }
+
+    let mut comparison = Comparison::new(height);
+    let mut identical = true;
+    for (first, second) in first.zip(second) {
+        let undecodable = "the image data can't be decoded";
+        let first = first.map_err(|()| undecodable)?.image.to_srgb();
+        let second = second.map_err(|()| undecodable)?.image.to_srgb();
+        if args.metrics {
+            comparison.add(&first, &second).unwrap();
+        } else if first != second {
+            identical = false;
+            break;
+        }
+    }
+    if args.metrics {
+        println!("PSNR: {:.2} dB", comparison.psnr());
+        println!("SSIM: {:.6}", comparison.ssim());
+    } else if identical {
+        println!("pixels are identical");
+    } else {
+        println!("pixels differ");
+    }
+    Ok(())
+}
+
+fn list_or_none(items: Vec<String>) -> String {
+    if items.is_empty() {
+        String::from("none")
+    } else {
+        items.join(", ")
+    }
+}
+
+fn inspect(file: &Path) -> Result<()> {
+    let bytes = fs::read(file)?;
+    let png = parse_png(file, &bytes)?;
+    let summary = inspect::summarize(&png, &bytes);
+    let mut table = Table::new(&[Align::Left, Align::Left]);
+    let mut row = |label: &str, value: String, style: Style| {
+        table.row(vec![(label.to_string(), Style::Dim), (value, style)]);
+    };
+
+    row(
+        "file",
+        format!(
+            "{} ({})",
+            file.display(),
+            output::byte_size(bytes.len() as u64)
+        ),
+        Style::Plain,
+    );
+    let image = match summary.header {
+        Some(header) => format!(
+            "{} x {}, {}, {}-bit{}",
+            header.width,
+            header.height,
+            header.color_type,
+            header.bit_depth,
+            if header.interlaced {
+                ", interlaced"
+            } else {
+                ""
+            }
+        ),
+        None => String::from("no valid IHDR chunk"),
+    };
+    row("image", image, Style::Plain);
+    let census = summary
+        .census
+        .iter()
+        .map(|entry| match entry.count {
+            1 => entry.chunk_type.to_string(),
+            count => format!("{} x{}", entry.chunk_type, count),
+        })
+        .collect();
+    row(
+        "chunks",
+        format!("{}: {}", png.chunks().len(), list_or_none(census)),
+        Style::Plain,
+    );
+    row("text", list_or_none(summary.text_keys), Style::Plain);
+    row(
+        "modified",
+        summary
+            .modified
+            .unwrap_or_else(|| String::from("not recorded")),
+        Style::Plain,
+    );
+    row("colour", list_or_none(summary.colour), Style::Plain);
+    let metadata = [(summary.exif, "EXIF"), (summary.xmp, "XMP")]
+        .into_iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    row("metadata", list_or_none(metadata), Style::Plain);
+    let private: Vec<String> = summary
+        .private_types
+        .iter()
+        .map(ChunkType::to_string)
+        .collect();
+    let style = if private.is_empty() {
+        Style::Plain
+    } else {
+        Style::Private
+    };
+    row("private", list_or_none(private), style);
+    let trailers: Vec<String> = summary
+        .trailers
+        .iter()
+        .map(|detection| detection.to_string())
+        .collect();
+    let style = if trailers.is_empty() {
+        Style::Plain
+    } else {
+        Style::Warning
+    };
+    row("trailer", list_or_none(trailers), style);
+    let (stego, style) = match ImageData::from_png(&png)
+        .map_err(|()| "no decodable image data".into())
+        .and_then(|image| steganalyze(&image))
+    {
+        Ok((chi_square, rs)) if steganalysis::is_likely(&chi_square, &rs) => (
+            format!(
+                "likely hidden data in pixel LSBs (chi-square p = {:.4}, RS {:.0}%)",
+                chi_square.probability,
+                rs.estimate * 100.0
+            ),
+            Style::Warning,
+        ),
+        Ok((chi_square, rs)) => (
+            format!(
+                "no sign of LSB embedding (chi-square p = {:.4}, RS {:.0}%)",
+                chi_square.probability,
+                rs.estimate * 100.0
+            ),
+            Style::Success,
+        ),
+        Err(error) => (format!("not analysed: {}", error), Style::Plain),
+    };
+    row("stego", stego, style);
+    output::page(&table.render());
+    Ok(())
+}
+
+fn weigh(file: &Path) -> Result<()> {
+    let bytes = fs::read(file)?;
+    let png = parse_png(file, &bytes)?;
+    let size = bytes.len() as u64;
+    let mut table = Table::new(&[Align::Left, Align::Right, Align::Right]);
+    for (part, part_size) in weigh::weigh(&png, size) {
+        let style = if part_size == 0 {
+            Style::Dim
+        } else {
+            Style::Plain
+        };
+        table.row(vec![
+            (part.to_string(), style),
+            (output::byte_size(part_size), style),
+            (
+                format!("{:.1}%", part_size as f64 * 100.0 / size as f64),
+                style,
+            ),
+        ]);
+    }
+    table.row(vec![
+        (String::from("total"), Style::Plain),
+        (output::byte_size(size), Style::Plain),
+        (String::new(), Style::Plain),
+    ]);
+    let mut text = table.render();
+
+    let savings = weigh::savings(&png, size);
+    if !savings.is_empty() {
+        text.push_str("\nsavings, each on its own:\n");
+        let mut table = Table::new(&[Align::Left, Align::Right]);
+        for saving in savings {
+            table.row(vec![
+                (saving.command, Style::Plain),
+                (output::byte_size(saving.bytes), Style::Success),
+            ]);
+        }
+        for line in table.render().lines() {
+            text.push_str(&format!("  {}\n", line));
+        }
+    }
+    output::page(&text);
+    Ok(())
+}
+
+fn steganalyze(image: &ImageData) -> Result<(ChiSquare, RsAnalysis)> {
+    let unsupported = "steganalysis needs a greyscale or truecolour image";
+    let chi_square = steganalysis::chi_square(image).map_err(|()| unsupported)?;
+    let rs = steganalysis::rs(image).map_err(|()| unsupported)?;
+    Ok((chi_square, rs))
+}
+
+fn steganalysis_summary(chi_square: &ChiSquare, rs: &RsAnalysis) -> [String; 3] {
+    [
+        format!(
+            "chi-square: p = {:.4} (statistic {:.2}, {} degrees of freedom)",
+            chi_square.probability, chi_square.statistic, chi_square.degrees_of_freedom
+        ),
+        format!(
+            "RS: estimated {:.1}% of colour samples embedded (Rm {:.3}, Sm {:.3}, R-m {:.3}, S-m {:.3})",
+            rs.estimate * 100.0,
+            rs.regular,
+            rs.singular,
+            rs.regular_negative,
+            rs.singular_negative
+        ),
+        if steganalysis::is_likely(chi_square, rs) {
+            "LSB embedding is likely".to_string()
+        } else {
+            "no sign of LSB embedding".to_string()
+        },
+    ]
+}
+
+fn analyze(args: FileArgs) -> Result<()> {
+    let (chi_square, rs) = steganalyze(&read_image(&args.file)?)?;
+    for line in steganalysis_summary(&chi_square, &rs) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn hexview(args: HexviewArgs) -> Result<()> {
+    let bytes = fs::read(&args.file)?;
+    if let Some(html) = &args.html {
+        let name = args.file.display().to_string();
+        fs::write(html, hexview::html(&name, &bytes))?;
+        return Ok(());
+    }
+    let mut text = String::new();
+    for section in hexview::sections(&bytes) {
+        let style = match section.chunk_type {
+            Some(chunk_type) if section.crc_ok => Style::of_chunk_type(&chunk_type),
+            Some(_) => Style::Warning,
+            None => Style::Dim,
+        };
+        let heading = format!("{:08x}  {}", section.range.start, section.label);
+        text.push_str(&output::paint(&heading, style));
+        text.push('\n');
+        for start in section.lines() {
+            let end = (start + hexview::WIDTH).min(section.range.end);
+            text.push_str(&output::paint(&format!("{:08x}", start), Style::Dim));
+            text.push(' ');
+            for (offset, byte) in (start..end).zip(&bytes[start..end]) {
+                let style = match section.field_at(offset) {
+                    Field::Signature | Field::Length => Style::Dim,
+                    Field::Type => style,
+                    Field::Data => Style::Plain,
+                    Field::Crc(true) => Style::Success,
+                    Field::Crc(false) | Field::Trailer => Style::Warning,
+                };
+                text.push(' ');
+                text.push_str(&output::paint(&format!("{:02x}", byte), style));
+            }
+            let padding = "   ".repeat(hexview::WIDTH - (end - start));
+            let printable: String = bytes[start..end]
+                .iter()
+                .map(|&byte| hexview::printable(byte))
+                .collect();
+            text.push_str(&format!("{}  {}\n", padding, printable));
+        }
+    }
+    output::page(&text);
+    Ok(())
+}
+
+fn template(args: TemplateArgs) -> Result<()> {
+    let bytes = fs::read(&args.file)?;
+    let format = match args.format {
+        TemplateFormat::Kaitai => Template::Kaitai,
+        TemplateFormat::BinaryTemplate => Template::BinaryTemplate,
+    };
+    let name = args.file.display().to_string();
+    let template = template::render(format, &name, &bytes)
+        .map_err(|error| format!("{}: {}", args.file.display(), error))?;
+    match &args.output {
+        Some(output) => fs::write(output, template)?,
+        None => print!("{}", template),
+    }
+    Ok(())
+}
+
+fn graph(args: GraphArgs) -> Result<()> {
+    let bytes = fs::read(&args.file)?;
+    let png = parse_png(&args.file, &bytes)?;
+    let size = bytes.len() as u64;
+    match args.mermaid {
+        true => print!("{}", graph::mermaid(&png, size)),
+        false => print!("{}", graph::dot(&png, size)),
+    }
+    Ok(())
+}
+
+fn report(args: ReportArgs) -> Result<()> {
+    let bytes = fs::read(&args.file)?;
+    let Recovered {
+        png,
+        offsets,
+        skipped,
+    } = if HARDENED.load(Ordering::Relaxed) {
+        let png = read_png(&args.file)?;
+        let offsets = validate::chunk_offsets(&png);
+        Recovered {
+            png,
+            offsets,
+            skipped: Vec::new(),
+        }
+    } else {
+        Png::from_bytes_lossy(&bytes, &parse_options())
+            .map_err(|error| format!("{}: {}", args.file.display(), error))?
+    };
+    let mut findings: Vec<String> = skipped
+        .iter()
+        .map(|range| {
+            format!(
+                "bytes {}..{} are not part of any valid chunk",
+                range.start, range.end
+            )
+        })
+        .collect();
+    findings.extend(validate::problems(&png, &bytes));
+    if let Ok((chi_square, rs)) = ImageData::from_png(&png)
+        .map_err(|()| "no decodable image data".into())
+        .and_then(|image| steganalyze(&image))
+    {
+        findings.extend(steganalysis_summary(&chi_square, &rs));
+    }
+    let name = args.file.display().to_string();
+    fs::write(
+        &args.output,
+        report::html(&name, &bytes, &png, &offsets, &findings),
+    )?;
+    Ok(())
+}
+
+fn file_digest(path: &Path, algorithm: Algorithm) -> Result<FileDigest> {
+    let bytes = fs::read(path)?;
+    let png = parse_png(path, &bytes)?;
+    Ok(FileDigest::new(
+        path.display().to_string(),
+        algorithm,
+        &bytes,
+        &png,
+    ))
+}
+
+fn hash(args: HashArgs) -> Result<()> {
+    let mut files = Vec::new();
+    for path in &args.files {
+        let digest = file_digest(path, args.algo)?;
+        println!("{}  {}", digest.digest, digest.path);
+        for (index, chunk) in digest.chunks.iter().enumerate() {
+            println!("{:>4}  {}  {}", index, chunk.chunk_type, chunk.digest);
+        }
+        files.push(digest);
+    }
+    if let Some(manifest) = &args.manifest {
+        let manifest_json = Manifest {
+            algorithm: args.algo,
+            files,
+        }
+        .to_json();
+        fs::write(manifest, manifest_json)?;
+    }
+    Ok(())
+}
+
+fn explode(file: &Path, directory: &Path, selectors: &[Selector]) -> Result<()> {
+    let png = read_png(file)?;
+    let mut manifest = explode::Manifest::of(&png);
+    let selected = select::selected(&png, selectors);
+    fs::create_dir_all(directory)?;
+    let mut entries = Vec::new();
+    for ((chunk, entry), selected) in png.chunks().iter().zip(manifest.chunks).zip(selected) {
+        // Files keep their original numbering, so the gaps show what was left out.
+        if selectors.is_empty() || selected {
+            fs::write(directory.join(&entry.file), chunk.data())?;
+            entries.push(entry);
+        }
+    }
+    manifest.chunks = entries;
+    fs::write(directory.join(explode::MANIFEST_FILE), manifest.to_json())?;
+    Ok(())
+}
+
+fn implode(directory: &Path, output: &Path) -> Result<()> {
+    let manifest_path = directory.join(explode::MANIFEST_FILE);
+    let json = fs::read_to_string(&manifest_path)
+        .map_err(|error| format!("cannot read {}: {}", manifest_path.display(), error))?;
+    let manifest = explode::Manifest::from_json(&json)?;
+    let mut chunks = Vec::with_capacity(manifest.chunks.len());
+    for entry in &manifest.chunks {
+        // Keep a hand-edited manifest from reading files outside the directory.
+        if Path::new(&entry.file).file_name() != Some(entry.file.as_ref()) {
+            return Err(format!("`{}` is not a file name", entry.file).into());
+        }
+        let path = directory.join(&entry.file);
+        let data = fs::read(&path)
+            .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+        chunks.push(Chunk::new(parse_chunk_type(&entry.chunk_type)?, data));
+    }
+    write_png(output, &Png::from_chunks(chunks))
+}
+
+fn split_concat(file: &Path, directory: &Path) -> Result<()> {
+    let bytes = fs::read(file)?;
+    let parts = polyglot::split(&bytes);
+    if parts.is_empty() {
+        return Err(format!("{} does not start with a PNG signature", file.display()).into());
+    }
+    if parts.len() == 1 {
+        return Err(format!("{} is a single PNG file", file.display()).into());
+    }
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    fs::create_dir_all(directory)?;
+    for (index, part) in parts.iter().enumerate() {
+        let path = directory.join(format!("{}-{}.{}", stem, index + 1, part.kind.extension()));
+        fs::write(&path, &bytes[part.range.clone()])?;
+        println!(
+            "{}: {}, bytes {}..{}",
+            path.display(),
+            part.kind,
+            part.range.start,
+            part.range.end
+        );
+    }
+    Ok(())
+}
+
+fn make_polyglot(image: &Path, payload: &Path, output: &Path) -> Result<()> {
+    let png = read_png(image)?;
+    let zip = fs::read(payload)?;
+    let bytes = polyglot::embed_zip(&png, &zip)
+        .map_err(|error| format!("{}: {}", payload.display(), error))?;
+    fs::write(output, bytes)?;
+    Ok(())
+}
+
+fn patch(args: PatchArgs) -> Result<()> {
+    let hex: String = args.write_hex.split_whitespace().collect();
+    let data = hex::decode(&hex).map_err(|()| "--write-hex is not a hex string")?;
+    // The file is read raw: patching is for files too damaged for the parser.
+    let mut bytes = fs::read(&args.file)?;
+    patch::write(&mut bytes, args.at, &data)?;
+    if args.fix_crc {
+        for offset in patch::fix_crcs(&mut bytes, args.at..args.at + data.len()) {
+            let chunk_type = String::from_utf8_lossy(&bytes[offset + 4..offset + 8]);
+            eprintln!("fixed the CRC of the {} chunk at {:#x}", chunk_type, offset);
+        }
+    }
+    fs::write(args.output.as_deref().unwrap_or(&args.file), bytes)?;
+    Ok(())
+}
+
+fn repair(args: RepairArgs) -> Result<()> {
+    // Read raw, like patch: the parser would reject the very chunks there are to repair.
+    let mut bytes = fs::read(&args.file)?;
+    let (mut fixed, mut bad) = (0, 0);
+    for chunk in patch::crc_mismatches(&bytes) {
+        let chunk_type =
+            String::from_utf8_lossy(&bytes[chunk.start + 4..chunk.start + 8]).into_owned();
+        let flip = match args.try_bitflips {
+            true => patch::fix_bitflip(&mut bytes, chunk.clone()),
+            false => None,
+        };
+        match flip {
+            Some(patch::BitFlip { offset, bit }) => {
+                fixed += 1;
+                println!(
+                    "fixed the {} chunk at {:#x}: bit {} of byte {:#x} was flipped",
+                    chunk_type, chunk.start, bit, offset
+                );
+            }
+            None => {
+                bad += 1;
+                println!(
+                    "the {} chunk at {:#x} has the wrong CRC",
+                    chunk_type, chunk.start
+                );
+            }
+        }
+    }
+    if fixed > 0 {
+        fs::write(args.output.as_deref().unwrap_or(&args.file), bytes)?;
+    }
+    match (bad, args.try_bitflips) {
+        (0, _) => Ok(()),
+        (_, false) => {
+            Err(format!("{} chunk(s) have the wrong CRC; try --try-bitflips", bad).into())
+        }
+        (_, true) => Err(format!(
+            "{} chunk(s) have more than one bit wrong, or are too large to search",
+            bad
+        )
+        .into()),
+    }
+}
+
+fn watermark_add(args: WatermarkAddArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let mut image = image_of(&args.file, &png)?;
+    match (args.invisible, args.robust) {
+        (true, true) => watermark::add_robust(&mut image, &args.text, args.strength)?,
+        (true, false) => watermark::add_invisible(&mut image, &args.text, args.strength)?,
+        (false, _) => watermark::add_visible(&mut image, &args.text, args.opacity)?,
+    }
+    image
+        .write_to(&mut png)
+        .map_err(|()| "failed to write the image data")?;
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)
+}
+
+fn watermark_detect(file: &Path, text: &str, robust: bool) -> Result<()> {
+    let png = read_png(file)?;
+    let image = image_of(file, &png)?;
+    if robust {
+        let detection = watermark::detect_robust(&image, text)?;
+        let summary = format!(
+            "score {:.1}, {} of {} payload bits, {:.2}% confidence",
+            detection.score,
+            detection.bits,
+            watermark::PAYLOAD_BITS,
+            detection.confidence * 100.0
+        );
+        if !detection.found() {
+            return Err(format!("no robust watermark for `{}` found ({})", text, summary).into());
+        }
+        println!("{}: robust watermark present ({})", file.display(), summary);
+        return Ok(());
+    }
+    let score = watermark::detect(&image, text)?;
+    if score <= watermark::THRESHOLD {
+        return Err(format!(
+            "no watermark for `{}` found (score {:.1}, threshold {})",
+            text,
+            score,
+            watermark::THRESHOLD
+        )
+        .into());
+    }
+    println!("{}: watermark present (score {:.1})", file.display(), score);
+    Ok(())
+}
+
+fn new(args: NewArgs) -> Result<()> {
+    let pattern = match (args.noise, args.gradient) {
+        (true, _) => Pattern::Noise,
+        (_, true) => Pattern::Gradient,
+        _ => Pattern::Perlin,
+    };
+    let carrier = Carrier {
+        pattern,
+        width: args.size.0,
+        height: args.size.1,
+        seed: args.seed,
+        entropy: args.entropy,
+        grayscale: args.grayscale,
+    };
+    let png = carrier.generate()?;
+    write_png(&args.output, &png)?;
+    let capacity = spread::header_capacity(&carrier.header()).unwrap_or(0);
+    println!(
+        "{}: {}x{}, {} bytes of LSB capacity with --method spread",
+        args.output.display(),
+        carrier.width,
+        carrier.height,
+        capacity
+    );
+    Ok(())
+}
+
+fn selftest(args: SelftestArgs) -> Result<()> {
+    let corpus = vectors::corpus();
+    if let Some(directory) = &args.emit {
+        fs::create_dir_all(directory)?;
+        let mut index = String::new();
+        for vector in &corpus {
+            fs::write(directory.join(&vector.name), &vector.bytes)?;
+            index.push_str(&format!(
+                "{}\t{}\t{}\n",
+                vector.name, vector.expect, vector.description
+            ));
+        }
+        fs::write(directory.join("vectors.txt"), index)?;
+        println!("wrote {} files to {}", corpus.len(), directory.display());
+        return Ok(());
+    }
+    let mut failures = 0;
+    for vector in &corpus {
+        match vector.check() {
+            Ok(()) => println!("ok    {}", vector.name),
+            Err(error) => {
+                failures += 1;
+                println!("FAIL  {}: {}", vector.name, error);
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} vectors failed", failures, corpus.len()).into());
+    }
+    Ok(())
+}
+
+fn read_session(path: &Path) -> Result<SessionKey> {
+    let hex = fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    Ok(SessionKey::from_hex(&hex)?)
+}
+
+fn save_session(path: &Path, session: &SessionKey) -> Result<()> {
+    let mut file = private_file(path)?;
+    file.write_all(session.to_hex().as_bytes())?;
+    file.write_all(b"\n")?;
+    println!(
+        "session key saved; check that the other party sees the fingerprint {}",
+        session.fingerprint()
+    );
+    Ok(())
+}
+
+fn their_public_key(path: &Path) -> Result<[u8; 32]> {
+    let key = handshake::public_key_from_png(&read_png(path)?)
+        .ok_or_else(|| format!("{} holds no handshake key", path.display()))?
+        .map_err(|()| {
+            format!(
+                "{} has a corrupt {} chunk",
+                path.display(),
+                HANDSHAKE_CHUNK_TYPE
+            )
+        })?;
+    Ok(key)
+}
+
+fn offer_public_key(carrier: &Path, output: Option<&Path>, key_pair: &KeyPair) -> Result<()> {
+    let mut png = read_png(carrier)?;
+    png.retain_chunks(|chunk| chunk.chunk_type().to_string() != HANDSHAKE_CHUNK_TYPE);
+    png.append_chunk(handshake::public_key_chunk(&key_pair.public_key()));
+    write_png(output.unwrap_or(carrier), &png)
+}
+
+fn handshake_init(carrier: &Path, secret: &Path, output: Option<&Path>) -> Result<()> {
+    let key_pair = KeyPair::generate()?;
+    // Save the secret before writing the carrier, so no key is offered that can't be finished.
+    let mut file = private_file(secret)?;
+    file.write_all(key_pair.to_hex().as_bytes())?;
+    file.write_all(b"\n")?;
+    offer_public_key(carrier, output, &key_pair)
+}
+
+fn handshake_respond(
+    theirs: &Path,
+    carrier: &Path,
+    session: &Path,
+    output: Option<&Path>,
+) -> Result<()> {
+    let key_pair = KeyPair::generate()?;
+    let session_key = SessionKey::derive(&key_pair, &their_public_key(theirs)?)?;
+    save_session(session, &session_key)?;
+    offer_public_key(carrier, output, &key_pair)
+}
+
+fn handshake_finish(theirs: &Path, secret: &Path, session: &Path) -> Result<()> {
+    let hex = fs::read_to_string(secret)
+        .map(Zeroizing::new)
+        .map_err(|error| format!("cannot read {}: {}", secret.display(), error))?;
+    let key_pair = KeyPair::from_hex(&hex)?;
+    save_session(
+        session,
+        &SessionKey::derive(&key_pair, &their_public_key(theirs)?)?,
+    )?;
+    fs::remove_file(secret)?;
+    Ok(())
+}
+
+fn share(args: ShareArgs) -> Result<()> {
+    let count = u8::try_from(args.images.len()).map_err(|_| "at most 255 images")?;
+    if let Some(shares) = args.shares.filter(|&shares| shares != count) {
+        return Err(format!("--shares is {} but {} images were given", shares, count).into());
+    }
+    let secret = fs::read(&args.secret)
+        .map(Zeroizing::new)
+        .map_err(|error| format!("cannot read {}: {}", args.secret.display(), error))?;
+    let shares = share::split(&secret, args.threshold, count)?;
+    // Parse every image before writing any, so a bad one doesn't leave the set incomplete.
+    let mut pngs = args
+        .images
+        .iter()
+        .map(|path| read_png(path))
+        .collect::<Result<Vec<_>>>()?;
+    for ((path, png), share) in args.images.iter().zip(&mut pngs).zip(&shares) {
+        png.retain_chunks(|chunk| chunk.chunk_type().to_string() != SHARE_CHUNK_TYPE);
+        png.append_chunk(share.to_chunk());
+        write_png(path, png)?;
+    }
+    Ok(())
+}
+
+fn reconstruct(args: ReconstructArgs) -> Result<()> {
+    let shares = args
+        .images
+        .iter()
+        .map(|path| {
+            Share::from_png(&read_png(path)?)
+                .ok_or_else(|| format!("{} holds no share", path.display()))?
+                .map_err(|()| {
+                    format!(
+                        "{} has a corrupt {} chunk",
+                        path.display(),
+                        SHARE_CHUNK_TYPE
+                    )
+                    .into()
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let secret = share::combine(&shares)?;
+    match &args.output {
+        Some(path) => private_file(path)?.write_all(&secret)?,
+        None => std::io::stdout().write_all(&secret)?,
+    }
+    Ok(())
+}
+
+fn verify_manifest(path: &Path) -> Result<()> {
+    let manifest = Manifest::from_json(&fs::read_to_string(path)?)
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    let mut failed = 0;
+    for expected in &manifest.files {
+        let differences = match file_digest(Path::new(&expected.path), manifest.algorithm) {
+            Ok(actual) => expected.differences(&actual),
+            Err(error) => vec![error.to_string()],
+        };
+        if differences.is_empty() {
+            println!("{}: OK", expected.path);
+        } else {
+            failed += 1;
+            println!("{}: FAILED", expected.path);
+            for difference in differences {
+                println!("    {}", difference);
+            }
+        }
+    }
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} file(s) failed verification",
+            failed,
+            manifest.files.len()
+        )
+        .into())
+    }
+}
+
+fn seal(args: SealArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let seal = Seal::new(&seal::pieces(&png), args.segment_size)
+        .map_err(|()| "the segment size must be at least 1")?;
+    png.retain_chunks(|chunk| chunk.chunk_type().to_string() != SEAL_CHUNK_TYPE);
+    png.insert_ancillary(seal.to_chunk());
+    write_png(args.output.as_deref().unwrap_or(&args.file), &png)?;
+    println!(
+        "sealed {} segment(s) of {} bytes; root {}",
+        seal.segment_count(),
+        seal.segment_size,
+        hex::encode(&seal.root())
+    );
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<()> {
+    let file = fs::File::open(&args.file)?;
+    // SAFETY: the map is only read, and only while this function runs; a file truncated by
+    // another process meanwhile can still fault, which is the usual caveat for mmap readers.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let (pieces, stored) = seal::scan(&map)
+        .map_err(|()| format!("{} is not a valid PNG file", args.file.display()))?;
+    let stored = stored.ok_or("no seal found; run `pngme seal` first")?;
+    let seal = Seal::from_bytes(stored).map_err(|()| "the seal chunk is corrupt")?;
+    if let Some(root) = &args.root {
+        if !root.eq_ignore_ascii_case(&hex::encode(&seal.root())) {
+            return Err("the seal's root does not match --root".into());
+        }
+    }
+    if let Some(&segment) = args
+        .segments
+        .iter()
+        .find(|&&segment| segment >= seal.segment_count())
+    {
+        return Err(format!(
+            "segment {} is out of range; the seal has {}",
+            segment,
+            seal.segment_count()
+        )
+        .into());
+    }
+    let segments = (!args.segments.is_empty()).then_some(&args.segments[..]);
+    let verification = seal.verify(&pieces, segments);
+    if let Some(length) = verification.stream_len_changed {
+        println!(
+            "image data is {} bytes, but {} were sealed",
+            length, seal.stream_len
+        );
+    }
+    for modified in &verification.modified {
+        let location = match modified.file_offset {
+            Some(offset) => format!("starting at file offset {}", offset),
+            None => String::from("no longer present"),
+        };
+        println!(
+            "segment {} modified: image data bytes {}..{}, {}",
+            modified.segment, modified.stream_range.start, modified.stream_range.end, location
+        );
+    }
+    if verification.is_intact() {
+        println!(
+            "{}: {} of {} segment(s) match the seal",
+            args.file.display(),
+            verification.checked,
+            seal.segment_count()
+        );
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} checked segment(s) modified",
+            verification.modified.len(),
+            verification.checked
+        )
+        .into())
+    }
+}
+
+#[cfg(feature = "conformance")]
+fn conformance(args: crate::args::ConformanceArgs) -> Result<()> {
+    use pngme_core::conformance::{self, Outcome};
+
+    let (mut matched, mut rejected, mut disagreements) = (0, 0, 0);
+    for path in png_files(&args.corpus)? {
+        match conformance::compare(&fs::read(&path)?) {
+            Outcome::Match => matched += 1,
+            Outcome::BothRejected => rejected += 1,
+            outcome => {
+                disagreements += 1;
+                println!("{}: {}", path.display(), outcome);
+            }
+        }
+    }
+    println!(
+        "{} match, {} rejected by both, {} disagree",
+        matched, rejected, disagreements
+    );
+    if disagreements > 0 {
+        return Err(format!("{} file(s) decode differently", disagreements).into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "self-update")]
+fn self_update(args: crate::args::SelfUpdateArgs) -> Result<()> {
+    let release = crate::update::latest()?;
+    if !crate::update::is_newer(&release.version) {
+        println!("pngme {} is the latest release", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    if args.check {
+        println!(
+            "pngme {} is available; this is {}",
+            release.version,
+            env!("CARGO_PKG_VERSION")
+        );
+        return Ok(());
+    }
+    let path = crate::update::install(&release)?;
+    println!("updated {} to pngme {}", path.display(), release.version);
+    Ok(())
+}
+
+/// `own` is `pngme stats --self`: this installation's statistics, and for now the only ones.
+fn usage_stats(own: bool, reset: bool) -> Result<()> {
+    if !own {
+        return Err("only this installation's statistics are recorded; pass --self".into());
+    }
+    if !reset {
+        return Ok(stats::show()?);
+    }
+    if stats::reset()? {
+        println!("usage statistics deleted");
+    } else {
+        println!("there were no usage statistics");
+    }
+    Ok(())
+}
+
+/// What this build can do, for scripts to check before relying on it.
+fn capabilities() -> Vec<(&'static str, Vec<String>)> {
+    let features = [
+        ("builtin-profiles", cfg!(feature = "builtin-profiles")),
+        ("clipboard", cfg!(feature = "clipboard")),
+        ("conformance", cfg!(feature = "conformance")),
+        ("grpc", cfg!(feature = "grpc")),
+        ("scripting", cfg!(feature = "scripting")),
+        ("self-update", cfg!(feature = "self-update")),
+        ("zlib-ng", cfg!(feature = "zlib-ng")),
+        ("zlib-rs", cfg!(feature = "zlib-rs")),
+    ];
+    let mut methods = vec!["chunk", "spread", "lsb"];
+    if cfg!(any(target_os = "linux", target_os = "macos")) {
+        methods.push("xattr");
+    }
+    let mut servers = vec!["stdio"];
+    if cfg!(feature = "grpc") {
+        servers.push("grpc");
+    }
+    let color_types = [
+        ColorType::Grayscale,
+        ColorType::GrayscaleAlpha,
+        ColorType::Rgb,
+        ColorType::Rgba,
+        ColorType::Indexed,
+    ];
+    let formats = color_types.iter().flat_map(|&color_type| {
+        [1, 2, 4, 8, 16]
+            .into_iter()
+            .filter(move |&bit_depth| color_type.allows_bit_depth(bit_depth))
+            .map(move |bit_depth| {
+                Target {
+                    color_type,
+                    bit_depth,
+                }
+                .to_string()
+            })
+    });
+    let pngme_chunks = [
+        C2PA_CHUNK_TYPE,
+        HANDSHAKE_CHUNK_TYPE,
+        history::HISTORY_CHUNK_TYPE,
+        PROVENANCE_CHUNK_TYPE,
+        SEAL_CHUNK_TYPE,
+        SHARE_CHUNK_TYPE,
+        polyglot::ZIP_CHUNK_TYPE,
+    ];
+    let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    vec![
+        (
+            "features",
+            strings(
+                &features
+                    .iter()
+                    .filter(|(_, enabled)| *enabled)
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        (
+            "crypto",
+            strings(&[
+                "age-scrypt",
+                "age-x25519",
+                "chacha20poly1305",
+                "x25519-hkdf-sha256",
+            ]),
+        ),
+        ("hashes", strings(&["sha256", "sha384", "sha512"])),
+        ("deflate", strings(&[parallel::DEFLATE_BACKEND])),
+        ("methods", strings(&methods)),
+        ("servers", strings(&servers)),
+        ("formats", formats.collect()),
+        (
+            "chunks",
+            KnownChunk::ALL
+                .iter()
+                .map(|known| known.chunk_type().to_string())
+                .collect(),
+        ),
+        ("pngme-chunks", strings(&pngme_chunks)),
+    ]
+}
+
+fn features(json: bool) -> Result<()> {
+    let capabilities = capabilities();
+    if json {
+        let mut object = serde_json::Map::new();
+        object.insert("version".into(), env!("CARGO_PKG_VERSION").into());
+        for (name, values) in capabilities {
+            object.insert(name.into(), values.into());
+        }
+        println!("{}", serde_json::to_string_pretty(&object)?);
+        return Ok(());
+    }
+    println!("pngme {}", env!("CARGO_PKG_VERSION"));
+    for (name, values) in capabilities {
+        println!("{}: {}", name, values.join(", "));
+    }
+    Ok(())
+}
+
+fn api(args: ApiArgs) -> Result<()> {
+    let service = api::Service {
+        hardened: HARDENED.load(Ordering::Relaxed),
+    };
+    let defaults = api::Limits::default();
+    let limits = api::Limits {
+        max_request: args.max_request_size.unwrap_or(defaults.max_request),
+        timeout: args
+            .timeout
+            .map_or(defaults.timeout, std::time::Duration::from_secs),
+        concurrency: args
+            .concurrency
+            .map_or(defaults.concurrency, |concurrency| concurrency as usize),
+    };
+    if args.stdio {
+        let stdin = std::io::stdin().lock();
+        return Ok(service.serve_lines(stdin, std::io::stdout().lock(), limits.max_request)?);
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(address) = args.grpc {
+        let runtime = tokio::runtime::Runtime::new()?;
+        eprintln!("serving gRPC on {}", address);
+        return Ok(runtime.block_on(pngme_core::grpc::serve(address, service, limits))?);
+    }
+    unreachable!("clap requires one transport")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_wordlist_recovers_sealed_message() {
+        let carrier = Carrier {
+            pattern: Pattern::Perlin,
+            width: 64,
+            height: 64,
+            seed: 0,
+            entropy: 0.25,
+            grayscale: false,
+        };
+        let mut image = ImageData::from_png(&carrier.generate().unwrap()).unwrap();
+        let passphrases = [Zeroizing::new("hunter2".to_string())];
+        let sealed = envelope::encrypt(&[], &passphrases, b"sealed").unwrap();
+        spread::embed(&mut image, "swordfish", &sealed).unwrap();
+
+        let wordlist = "letmein\nswordfish\nhunter2\n";
+        let (password, message) = search_wordlist(wordlist, |password| {
+            let message = spread::extract(&image, password).ok()?;
+            is_message(&message).then_some(message)
+        })
+        .unwrap();
+        assert_eq!(*password, "swordfish");
+        let decrypt =
+            |passphrase: &str| envelope::decrypt_with_passphrase(passphrase, &message).ok();
+        let (passphrase, message) = search_wordlist(wordlist, decrypt).unwrap();
+        assert_eq!(*passphrase, "hunter2");
+        assert_eq!(*message, b"sealed");
+        assert!(search_wordlist("letmein\nswordfish\n", decrypt).is_err());
+    }
+
+    #[test]
+    fn test_stats_needs_self() {
+        use clap::Parser;
+
+        let error = Cli::try_parse_from(["pngme", "stats"]).err().unwrap();
+        assert_eq!(
+            error.kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+        assert!(Cli::try_parse_from(["pngme", "stats", "--reset"]).is_err());
+        for args in [
+            &["pngme", "stats", "--self"][..],
+            &["pngme", "stats", "--self", "--reset"],
+        ] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert!(matches!(cli.command, Command::Stats { own: true, .. }));
+        }
+        assert!(usage_stats(false, false).is_err());
+    }
+}
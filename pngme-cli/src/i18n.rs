@@ -0,0 +1,231 @@
+//! Translated messages and help text, from the Fluent catalogs in `locales/`.
+//!
+//! English help comes from the doc comments in `args.rs`; a catalog translates it with
+//! messages named after the command path, `help-encode` for `pngme encode`'s about line and
+//! `help-encode--chunk-type` for its `--chunk-type` option. Anything a catalog leaves out
+//! falls back to English.
+
+use std::ffi::OsString;
+use std::sync::OnceLock;
+
+use clap::Command;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+const ENGLISH: &str = "en-US";
+
+const CATALOGS: [(&str, &str); 2] = [
+    (ENGLISH, include_str!("../locales/en-US/pngme.ftl")),
+    ("de", include_str!("../locales/de/pngme.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+struct Catalogs {
+    /// `None` when the chosen language is English.
+    selected: Option<Bundle>,
+    english: Bundle,
+}
+
+static CATALOGS_IN_USE: OnceLock<Catalogs> = OnceLock::new();
+
+fn bundle(language: &str, source: &str) -> Bundle {
+    let language: LanguageIdentifier = language.parse().expect("catalog language is valid");
+    let resource = FluentResource::try_new(source.to_string()).expect("catalogs are valid Fluent");
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // The Unicode isolation marks around arguments show up as junk in most terminals.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .expect("catalogs define each message once");
+    bundle
+}
+
+/// The `--lang` value, read ahead of clap so the help text it prints is already translated,
+/// or else `PNGME_LANG`, `LC_ALL`, `LC_MESSAGES` or `LANG`.
+pub fn requested_language(arguments: &[OsString]) -> Option<String> {
+    let mut arguments = arguments.iter().filter_map(|argument| argument.to_str());
+    while let Some(argument) = arguments.next() {
+        if argument == "--" {
+            break;
+        }
+        if let Some(language) = argument.strip_prefix("--lang=") {
+            return Some(language.to_string());
+        }
+        if argument == "--lang" {
+            return arguments.next().map(str::to_string);
+        }
+    }
+    ["PNGME_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The catalog for a locale such as `de_DE.UTF-8`, matched on its language alone.
+fn catalog_for(locale: &str) -> Option<(&'static str, &'static str)> {
+    let locale = locale.split(['.', '@']).next()?.replace('_', "-");
+    let requested: LanguageIdentifier = locale.parse().ok()?;
+    CATALOGS.into_iter().find(|(language, _)| {
+        language
+            .parse::<LanguageIdentifier>()
+            .is_ok_and(|language| language.language == requested.language)
+    })
+}
+
+fn load(locale: Option<&str>) -> Catalogs {
+    let selected = locale
+        .and_then(catalog_for)
+        .filter(|(language, _)| *language != ENGLISH)
+        .map(|(language, source)| bundle(language, source));
+    Catalogs {
+        selected,
+        english: bundle(ENGLISH, CATALOGS[0].1),
+    }
+}
+
+/// Chooses the language for the rest of the run; until this is called, messages are in
+/// English.
+pub fn init(locale: Option<&str>) {
+    let _ = CATALOGS_IN_USE.set(load(locale));
+}
+
+fn catalogs() -> &'static Catalogs {
+    CATALOGS_IN_USE.get_or_init(|| load(None))
+}
+
+fn format(bundle: &Bundle, id: &str, arguments: &[(&str, String)]) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut fluent_arguments = FluentArgs::new();
+    for (name, value) in arguments {
+        fluent_arguments.set(*name, value.clone());
+    }
+    let mut errors = Vec::new();
+    Some(
+        bundle
+            .format_pattern(pattern, Some(&fluent_arguments), &mut errors)
+            .into_owned(),
+    )
+}
+
+/// Message `id` in the chosen language, or in English if that catalog lacks it.
+pub fn tr(id: &str, arguments: &[(&str, String)]) -> String {
+    let catalogs = catalogs();
+    catalogs
+        .selected
+        .as_ref()
+        .and_then(|bundle| format(bundle, id, arguments))
+        .or_else(|| format(&catalogs.english, id, arguments))
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Replaces the help of `command`, its arguments and its subcommands with whatever the
+/// chosen catalog translates.
+pub fn localize(command: Command) -> Command {
+    match &catalogs().selected {
+        Some(bundle) => localize_at(command, "help", bundle),
+        None => command,
+    }
+}
+
+fn localize_at(mut command: Command, id: &str, bundle: &Bundle) -> Command {
+    // A long form left over from the doc comment would otherwise win in `--help`, so it is
+    // replaced too, where there is one.
+    if let Some(about) = format(bundle, id, &[]) {
+        if command.get_long_about().is_some() {
+            command = command.long_about(about.clone());
+        }
+        command = command.about(about);
+    }
+    let arguments: Vec<String> = command
+        .get_arguments()
+        .map(|argument| argument.get_id().to_string())
+        .collect();
+    for argument in arguments {
+        let argument_id = format!("{}--{}", id, argument.replace('_', "-"));
+        if let Some(help) = format(bundle, &argument_id, &[]) {
+            command = command.mut_arg(argument, |mut argument| {
+                if argument.get_long_help().is_some() {
+                    argument = argument.long_help(help.clone());
+                }
+                argument.help(help)
+            });
+        }
+    }
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        let subcommand_id = format!("{}-{}", id, name);
+        command = command.mut_subcommand(name, |subcommand| {
+            localize_at(subcommand, &subcommand_id, bundle)
+        });
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Cli;
+    use clap::CommandFactory;
+    use std::collections::BTreeSet;
+
+    /// The ids of the messages `source` defines, each checked against the parsed bundle.
+    fn message_ids(language: &str, source: &str) -> BTreeSet<String> {
+        let bundle = bundle(language, source);
+        let ids: BTreeSet<String> = source
+            .lines()
+            .filter(|line| !line.starts_with([' ', '#']))
+            .filter_map(|line| Some(line.split_once(" =")?.0.to_string()))
+            .collect();
+        for id in &ids {
+            assert!(bundle.has_message(id), "{}: {}", language, id);
+        }
+        ids
+    }
+
+    /// Every id `localize` looks up for `command`, its arguments and its subcommands.
+    fn help_ids(command: &Command, id: &str, ids: &mut BTreeSet<String>) {
+        ids.insert(id.to_string());
+        for argument in command.get_arguments() {
+            ids.insert(format!(
+                "{}--{}",
+                id,
+                argument.get_id().as_str().replace('_', "-")
+            ));
+        }
+        for subcommand in command.get_subcommands() {
+            help_ids(
+                subcommand,
+                &format!("{}-{}", id, subcommand.get_name()),
+                ids,
+            );
+        }
+    }
+
+    #[test]
+    fn test_catalogs_match() {
+        let mut help = BTreeSet::new();
+        help_ids(&Cli::command(), "help", &mut help);
+        let messages = |language, source| -> BTreeSet<String> {
+            let (help_ids, messages): (BTreeSet<_>, _) = message_ids(language, source)
+                .into_iter()
+                .partition(|id| id == "help" || id.starts_with("help-"));
+            let unknown: Vec<_> = help_ids.difference(&help).collect();
+            assert!(
+                unknown.is_empty(),
+                "{} translates no such help: {:?}",
+                language,
+                unknown
+            );
+            messages
+        };
+        let english = messages(ENGLISH, CATALOGS[0].1);
+        for (language, source) in &CATALOGS[1..] {
+            assert_eq!(messages(language, source), english, "{}", language);
+        }
+    }
+}
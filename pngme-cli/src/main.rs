@@ -0,0 +1,38 @@
+use std::time::Instant;
+
+use clap::{CommandFactory, FromArgMatches};
+
+mod args;
+mod clipboard;
+mod commands;
+mod i18n;
+mod output;
+mod prompt;
+mod stats;
+#[cfg(feature = "self-update")]
+mod update;
+mod xattr;
+
+fn main() {
+    let arguments: Vec<_> = std::env::args_os().collect();
+    i18n::init(i18n::requested_language(&arguments).as_deref());
+    let matches = i18n::localize(args::Cli::command()).get_matches_from(arguments);
+    let cli = args::Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    // The full subcommand, like `index build`, for the statistics.
+    let mut command = Vec::new();
+    let mut subcommand = matches.subcommand();
+    while let Some((name, matches)) = subcommand {
+        command.push(name);
+        subcommand = matches.subcommand();
+    }
+    let record = cli.stats && command.first() != Some(&"stats");
+    let started = Instant::now();
+    let result = commands::run(cli);
+    if record {
+        stats::record(&command.join(" "), started.elapsed(), result.is_ok());
+    }
+    if let Err(error) = result {
+        eprintln!("{}", i18n::tr("error", &[("message", error.to_string())]));
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,249 @@
+//! How `print` and `check` lay out what they report: colour for chunk kinds and
+//! warnings, aligned columns and readable byte sizes. Colour and rounded sizes are for
+//! people at a terminal; when standard output is piped, the output is plain text with exact
+//! byte counts, so scripts reading it keep working. Output too tall for the terminal goes
+//! through `$PAGER`, as git does.
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, stdout, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pngme_core::chunk_type::ChunkType;
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+static TERMINAL: AtomicBool = AtomicBool::new(false);
+static PAGER: AtomicBool = AtomicBool::new(false);
+
+/// Colour and paging are used only on a terminal, and never with `--no-color` or a
+/// non-empty `NO_COLOR` (see no-color.org), or with `--no-pager` respectively.
+pub fn init(no_color: bool, no_pager: bool) {
+    let terminal = stdout().is_terminal();
+    TERMINAL.store(terminal, Ordering::Relaxed);
+    COLOR.store(
+        uses_color(terminal, no_color, env::var_os("NO_COLOR").as_deref()),
+        Ordering::Relaxed,
+    );
+    PAGER.store(terminal && !no_pager, Ordering::Relaxed);
+}
+
+fn uses_color(terminal: bool, no_color: bool, no_color_env: Option<&OsStr>) -> bool {
+    terminal && !no_color && no_color_env.is_none_or(OsStr::is_empty)
+}
+
+#[cfg(unix)]
+fn terminal_rows() -> Option<usize> {
+    // SAFETY: TIOCGWINSZ only fills in the winsize it is given.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_row > 0).then_some(size.ws_row as usize)
+}
+
+#[cfg(not(unix))]
+fn terminal_rows() -> Option<usize> {
+    None
+}
+
+/// Runs `$PAGER` (`less` by default) through the shell, with `LESS=FRX` unless `LESS` is
+/// set, so that colour comes through and the screen isn't cleared.
+fn run_pager(text: &str) -> io::Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.is_empty())
+        .unwrap_or_else(|| String::from("less"));
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&pager)
+        .env(
+            "LESS",
+            env::var("LESS").unwrap_or_else(|_| String::from("FRX")),
+        )
+        .stdin(Stdio::piped())
+        .spawn()?;
+    let written = child
+        .stdin
+        .take()
+        .expect("the pager's stdin is piped")
+        .write_all(text.as_bytes());
+    child.wait()?;
+    match written {
+        // Quitting the pager early closes the pipe.
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => written,
+    }
+}
+
+/// Prints `text`, through the pager if it has more lines than the terminal.
+pub fn page(text: &str) {
+    let too_tall = PAGER.load(Ordering::Relaxed)
+        && terminal_rows().is_some_and(|rows| text.lines().count() >= rows);
+    if too_tall && run_pager(text).is_ok() {
+        return;
+    }
+    print!("{}", text);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Critical,
+    Ancillary,
+    Private,
+    Warning,
+    Success,
+    Dim,
+}
+
+impl Style {
+    /// Critical chunks stand out; private ones, which only their writer understands, are
+    /// set apart from the registered ancillary types.
+    pub fn of_chunk_type(chunk_type: &ChunkType) -> Self {
+        if chunk_type.is_critical() {
+            Style::Critical
+        } else if chunk_type.is_public() {
+            Style::Ancillary
+        } else {
+            Style::Private
+        }
+    }
+
+    fn sgr(self) -> Option<&'static str> {
+        match self {
+            Style::Plain => None,
+            Style::Critical => Some("1;34"),
+            Style::Ancillary => Some("32"),
+            Style::Private => Some("35"),
+            Style::Warning => Some("33"),
+            Style::Success => Some("32"),
+            Style::Dim => Some("2"),
+        }
+    }
+}
+
+pub fn paint(text: &str, style: Style) -> String {
+    match style.sgr() {
+        Some(sgr) if COLOR.load(Ordering::Relaxed) => format!("\x1b[{}m{}\x1b[0m", sgr, text),
+        _ => text.to_string(),
+    }
+}
+
+const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+/// `1.5 KiB` on a terminal, `1536 bytes` otherwise.
+pub fn byte_size(bytes: u64) -> String {
+    format_size(bytes, TERMINAL.load(Ordering::Relaxed))
+}
+
+fn format_size(bytes: u64, rounded: bool) -> String {
+    if !rounded || bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Up a unit from whatever would round to 1024.0 at one decimal place.
+    while size >= 1023.95 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+struct Row {
+    cells: Vec<(String, Style)>,
+    details: Vec<String>,
+}
+
+/// Columns padded to their widest cell, with indented lines of detail under any row.
+pub struct Table {
+    alignments: Vec<Align>,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new(alignments: &[Align]) -> Self {
+        Self {
+            alignments: alignments.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<(String, Style)>) {
+        self.rows.push(Row {
+            cells,
+            details: Vec::new(),
+        });
+    }
+
+    /// A line to show under the last row.
+    pub fn detail(&mut self, line: String) {
+        if let Some(row) = self.rows.last_mut() {
+            row.details.push(line);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut widths = vec![0; self.alignments.len()];
+        for row in &self.rows {
+            for (width, (text, _)) in widths.iter_mut().zip(&row.cells) {
+                *width = (*width).max(text.chars().count());
+            }
+        }
+        let mut text = String::new();
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .cells
+                .iter()
+                .zip(&self.alignments)
+                .zip(&widths)
+                .map(|(((text, style), align), width)| {
+                    let padding = " ".repeat(width - text.chars().count());
+                    match align {
+                        Align::Right => format!("{}{}", padding, paint(text, *style)),
+                        Align::Left => format!("{}{}", paint(text, *style), padding),
+                    }
+                })
+                .collect();
+            text.push_str(cells.join("  ").trim_end());
+            text.push('\n');
+            for line in &row.details {
+                text.push_str("        ");
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_size() {
+        assert_eq!(format_size(1023, true), "1023 bytes");
+        assert_eq!(format_size(1024, true), "1.0 KiB");
+        assert_eq!(format_size(1536, true), "1.5 KiB");
+        assert_eq!(format_size((1 << 20) - 1, true), "1.0 MiB");
+        assert_eq!(format_size(1 << 20, true), "1.0 MiB");
+        assert_eq!(format_size(5 << 40, true), "5.0 TiB");
+        assert_eq!(format_size(1 << 60, true), "1048576.0 TiB");
+        assert_eq!(format_size(1 << 20, false), "1048576 bytes");
+    }
+
+    #[test]
+    fn test_uses_color() {
+        assert!(uses_color(true, false, None));
+        assert!(uses_color(true, false, Some(OsStr::new(""))));
+        assert!(!uses_color(true, false, Some(OsStr::new("1"))));
+        assert!(!uses_color(true, true, None));
+        assert!(!uses_color(false, false, None));
+    }
+}
@@ -0,0 +1,44 @@
+//! Asking on the terminal for arguments left off the command line. Nothing here prompts
+//! unless both standard input and standard error are terminals, so scripts and pipes still
+//! fail fast on a missing argument.
+
+use std::io::{stderr, stdin, IsTerminal};
+use std::str::FromStr;
+
+use dialoguer::{Input, Password};
+use pngme_core::chunk_type::ChunkType;
+use zeroize::Zeroizing;
+
+pub fn is_interactive() -> bool {
+    stdin().is_terminal() && stderr().is_terminal()
+}
+
+fn prompt_error(error: dialoguer::Error) -> String {
+    format!("cannot read from the terminal: {}", error)
+}
+
+pub fn chunk_type(default: &str) -> Result<String, String> {
+    Input::new()
+        .with_prompt("Chunk type")
+        .default(default.to_string())
+        .validate_with(|input: &String| ChunkType::from_str(input).map(|_| ()))
+        .interact_text()
+        .map_err(prompt_error)
+}
+
+pub fn message() -> Result<String, String> {
+    Input::new()
+        .with_prompt("Message")
+        .interact_text()
+        .map_err(prompt_error)
+}
+
+/// Reads a password without echoing it, asking twice when `confirm` is set, as it is for
+/// one that is about to protect a new message.
+pub fn password(prompt: &str, confirm: bool) -> Result<Zeroizing<String>, String> {
+    let mut input = Password::new().with_prompt(prompt);
+    if confirm {
+        input = input.with_confirmation("Repeat it", "the two entries don't match");
+    }
+    input.interact().map(Zeroizing::new).map_err(prompt_error)
+}
@@ -0,0 +1,129 @@
+//! Opt-in usage statistics, kept in a local file and never sent anywhere: with `--stats` or
+//! `PNGME_STATS=1`, each command adds its running time to
+//! `$XDG_STATE_HOME/pngme/stats.json`, and `pngme stats --self` shows the totals, so a team
+//! can see which steps of a pipeline take the time.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::{self, Align, Style, Table};
+
+/// Times in microseconds.
+#[derive(Default, Serialize, Deserialize)]
+struct CommandStats {
+    runs: u64,
+    failures: u64,
+    total_us: u64,
+    max_us: u64,
+}
+
+fn path() -> Option<PathBuf> {
+    let state = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+    Some(state.join("pngme").join("stats.json"))
+}
+
+/// The statistics so far, by command; none if the file is missing or unreadable.
+fn load(path: &Path) -> BTreeMap<String, CommandStats> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Adds a run of `command`, like `encode` or `index build`, to the statistics file. A failure
+/// to write it is only warned about, since it mustn't fail the command itself.
+pub fn record(command: &str, elapsed: Duration, succeeded: bool) {
+    let Some(path) = path() else {
+        return;
+    };
+    let mut stats = load(&path);
+    let entry = stats.entry(command.to_string()).or_default();
+    let us = elapsed.as_micros() as u64;
+    entry.runs += 1;
+    entry.failures += u64::from(!succeeded);
+    entry.total_us += us;
+    entry.max_us = entry.max_us.max(us);
+    if let Err(error) = save(&path, &stats) {
+        eprintln!(
+            "warning: cannot update usage statistics in {}: {}",
+            path.display(),
+            error
+        );
+    }
+}
+
+/// Writes beside `path` and renames over it, so concurrent runs never leave half a file.
+fn save(path: &Path, stats: &BTreeMap<String, CommandStats>) -> io::Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    let staged = path.with_extension(format!("json.{}", std::process::id()));
+    fs::write(&staged, serde_json::to_vec_pretty(stats)?)?;
+    fs::rename(&staged, path)
+}
+
+fn duration(us: u64) -> String {
+    format!("{:.1}ms", us as f64 / 1000.0)
+}
+
+/// A table of each command's runs, failures, total, mean and longest time, busiest first.
+pub fn show() -> Result<(), String> {
+    let path = path().ok_or("neither XDG_STATE_HOME nor HOME is set")?;
+    let stats = load(&path);
+    if stats.is_empty() {
+        println!(
+            "no usage statistics in {}; run commands with --stats or PNGME_STATS=1 to record them",
+            path.display()
+        );
+        return Ok(());
+    }
+    let mut commands: Vec<_> = stats.iter().collect();
+    commands.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total_us));
+    let mut table = Table::new(&[
+        Align::Left,
+        Align::Right,
+        Align::Right,
+        Align::Right,
+        Align::Right,
+        Align::Right,
+    ]);
+    let heading = |text: &str| (text.to_string(), Style::Dim);
+    table.row(vec![
+        heading("command"),
+        heading("runs"),
+        heading("failed"),
+        heading("total"),
+        heading("mean"),
+        heading("max"),
+    ]);
+    for (command, stats) in commands {
+        table.row(vec![
+            (command.clone(), Style::Plain),
+            (stats.runs.to_string(), Style::Plain),
+            (stats.failures.to_string(), Style::Plain),
+            (duration(stats.total_us), Style::Plain),
+            (duration(stats.total_us / stats.runs.max(1)), Style::Plain),
+            (duration(stats.max_us), Style::Plain),
+        ]);
+    }
+    output::page(&table.render());
+    Ok(())
+}
+
+/// Deletes the statistics file, returning whether there was one.
+pub fn reset() -> Result<bool, String> {
+    let path = path().ok_or("neither XDG_STATE_HOME nor HOME is set")?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(format!("cannot remove {}: {}", path.display(), error)),
+    }
+}
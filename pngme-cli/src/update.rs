@@ -0,0 +1,238 @@
+//! `pngme self-update`: the latest GitHub release of the standalone binary, checked against
+//! the minisign key the release build embeds before it replaces the running executable.
+//! Each asset is signed with `minisign -S -t "pngme <version> <asset>"`, so that the signature
+//! also vouches for which version and platform the binary is.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use minisign_verify::{PublicKey, Signature};
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/ensconced/pngme/releases/latest";
+
+/// The minisign public key releases are signed with, set when the release binary is built. A
+/// build without one can check for updates but not install them.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("PNGME_RELEASE_PUBLIC_KEY");
+
+/// The most any download may be, well above the size of a release binary.
+const MAX_DOWNLOAD: u64 = 256 << 20;
+
+pub struct Release {
+    /// Without the tag's leading `v`.
+    pub version: String,
+    binary: String,
+    signature: String,
+}
+
+/// What this platform's release asset is called, like `pngme-x86_64-linux` or
+/// `pngme-x86_64-windows.exe`; its signature is the same with `.minisig` appended.
+fn asset_name() -> String {
+    format!(
+        "pngme-{}-{}{}",
+        env::consts::ARCH,
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+fn get(url: &str) -> Result<Vec<u8>, String> {
+    let mut response = ureq::get(url)
+        .header("User-Agent", concat!("pngme/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|error| format!("cannot fetch {}: {}", url, error))?;
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD)
+        .read_to_vec()
+        .map_err(|error| format!("cannot fetch {}: {}", url, error))
+}
+
+pub fn latest() -> Result<Release, String> {
+    let release: serde_json::Value = serde_json::from_slice(&get(LATEST_RELEASE)?)
+        .map_err(|error| format!("unexpected reply from GitHub: {}", error))?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or("the latest release has no tag")?;
+    let asset_url = |name: &str| {
+        release["assets"]
+            .as_array()
+            .and_then(|assets| assets.iter().find(|asset| asset["name"] == name))
+            .and_then(|asset| asset["browser_download_url"].as_str())
+            .map(String::from)
+            .ok_or_else(|| format!("release {} has no {}", tag, name))
+    };
+    let name = asset_name();
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        binary: asset_url(&name)?,
+        signature: asset_url(&format!("{}.minisig", name))?,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Number(u64),
+    Text(String),
+}
+
+/// What a version such as `1.2.0-rc.1+build` sorts by, as semver orders them: the numbers
+/// between the dots, then a release ahead of its pre-releases, which go by their own
+/// dot-separated parts, numbers before text. Build metadata is ignored.
+fn precedence(version: &str) -> (Vec<u64>, bool, Vec<Identifier>) {
+    let version = version.split('+').next().unwrap_or_default();
+    let (numbers, pre_release) = match version.split_once('-') {
+        Some((numbers, pre_release)) => (numbers, Some(pre_release)),
+        None => (version, None),
+    };
+    let identifiers = pre_release
+        .into_iter()
+        .flat_map(|pre_release| pre_release.split('.'))
+        .map(|part| match part.parse() {
+            Ok(number) => Identifier::Number(number),
+            Err(_) => Identifier::Text(part.to_string()),
+        })
+        .collect();
+    (
+        numbers
+            .split('.')
+            .map_while(|part| part.parse().ok())
+            .collect(),
+        pre_release.is_none(),
+        identifiers,
+    )
+}
+
+fn is_newer_than(version: &str, current: &str) -> bool {
+    precedence(version) > precedence(current)
+}
+
+/// Whether `version` is later than this build's.
+pub fn is_newer(version: &str) -> bool {
+    is_newer_than(version, env!("CARGO_PKG_VERSION"))
+}
+
+/// Checks `signature` over `binary` against the base64 minisign `key`, and that the trusted
+/// comment, which the signature also covers, names `version` and `asset`: otherwise an older
+/// release or another platform's binary, validly signed, could be served up in its place.
+fn verify(
+    key: &str,
+    version: &str,
+    asset: &str,
+    binary: &[u8],
+    signature: &str,
+) -> Result<(), String> {
+    let key =
+        PublicKey::from_base64(key).map_err(|error| format!("invalid release key: {}", error))?;
+    let signature = Signature::decode(signature)
+        .map_err(|error| format!("invalid release signature: {}", error))?;
+    key.verify(binary, &signature, false)
+        .map_err(|error| format!("the download failed signature verification: {}", error))?;
+    let tagged = format!("v{}", version);
+    let words = || signature.trusted_comment().split_whitespace();
+    if !words().any(|word| word == version || word == tagged) {
+        return Err(format!(
+            "the download is signed as `{}`, not as version {}",
+            signature.trusted_comment(),
+            version
+        ));
+    }
+    if !words().any(|word| word == asset) {
+        return Err(format!(
+            "the download is signed as `{}`, not as {}",
+            signature.trusted_comment(),
+            asset
+        ));
+    }
+    Ok(())
+}
+
+/// Downloads `release`, verifies its signature and puts it in place of the running
+/// executable, returning where that is.
+pub fn install(release: &Release) -> Result<PathBuf, String> {
+    let key = RELEASE_PUBLIC_KEY.ok_or(
+        "this build has no release key to verify updates with; update it the way it was \
+         installed, e.g. with cargo install",
+    )?;
+    let binary = get(&release.binary)?;
+    let signature = String::from_utf8(get(&release.signature)?)
+        .map_err(|_| "the release signature is not text")?;
+    verify(key, &release.version, &asset_name(), &binary, &signature)?;
+    let current = env::current_exe()
+        .map_err(|error| format!("cannot find the running executable: {}", error))?;
+    replace(&current, &binary)
+        .map_err(|error| format!("cannot replace {}: {}", current.display(), error))?;
+    Ok(current)
+}
+
+/// Writes `binary` beside `current` and renames it over, so that an update cut short leaves
+/// the old executable working.
+fn replace(current: &Path, binary: &[u8]) -> io::Result<()> {
+    let staged = current.with_extension("new");
+    fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows won't overwrite a running executable, but will rename it.
+    #[cfg(windows)]
+    fs::rename(current, current.with_extension("old"))?;
+    fs::rename(&staged, current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A throwaway key, and its signature of `pngme 0.10.0` as the 0.10.0 release's
+    /// x86_64 Linux binary.
+    const KEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const SIGNATURE: &str = "untrusted comment: pngme release
+RUQBI0VniavN7/uMPdJwGu1223VedVAEkVfFg7mjnUIhZiPh2MyPJno9uxt7LaVqtqBbnuw/hHtYeypT09jE9/bUQetaQf5q7wQ=
+trusted comment: pngme 0.10.0 pngme-x86_64-linux
+miMRZVeFT1+orr9JkCnDtjcAOZLUCGr3NXFJpbzXI5H2UyCwIRvvGyEncDn4grEp3OHDkDFyOZ0k5gj3m2hDAA==
+";
+    const ASSET: &str = "pngme-x86_64-linux";
+
+    #[test]
+    fn test_is_newer_than() {
+        assert!(is_newer_than("0.10.0", "0.9.0"));
+        assert!(is_newer_than("0.9.1", "0.9.0"));
+        assert!(is_newer_than("1.0.0", "0.99.99"));
+        assert!(!is_newer_than("0.9.0", "0.10.0"));
+        assert!(!is_newer_than("0.9.0", "0.9.0"));
+        assert!(is_newer_than("0.10.0", "0.10.0-rc.1"));
+        assert!(!is_newer_than("0.10.0-rc.1", "0.10.0"));
+        assert!(is_newer_than("0.10.0-rc.1", "0.9.0"));
+        assert!(is_newer_than("0.10.0-rc.2", "0.10.0-rc.1"));
+        assert!(is_newer_than("0.10.0-rc.10", "0.10.0-rc.9"));
+        assert!(is_newer_than("0.10.0-rc.1", "0.10.0-beta.3"));
+        assert!(!is_newer_than("0.9.0+build.7", "0.9.0"));
+    }
+
+    #[test]
+    fn test_verify() {
+        let binary = b"pngme 0.10.0";
+        assert_eq!(verify(KEY, "0.10.0", ASSET, binary, SIGNATURE), Ok(()));
+        let error = verify(KEY, "0.10.0", ASSET, b"pngme 0.10.1", SIGNATURE).unwrap_err();
+        assert!(error.contains("failed signature verification"), "{}", error);
+        let error = verify(KEY, "0.11.0", ASSET, binary, SIGNATURE).unwrap_err();
+        assert!(error.contains("not as version 0.11.0"), "{}", error);
+        assert!(verify(KEY, "0.10", ASSET, binary, SIGNATURE).is_err());
+        let tampered = SIGNATURE.replace("pngme 0.10.0", "pngme 0.11.0");
+        assert!(verify(KEY, "0.11.0", ASSET, binary, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_verify_asset() {
+        let binary = b"pngme 0.10.0";
+        let error = verify(KEY, "0.10.0", "pngme-aarch64-macos", binary, SIGNATURE).unwrap_err();
+        assert!(error.contains("not as pngme-aarch64-macos"), "{}", error);
+        assert!(verify(KEY, "0.10.0", "pngme-x86_64", binary, SIGNATURE).is_err());
+        let tampered = SIGNATURE.replace("x86_64-linux", "aarch64-macos");
+        assert!(verify(KEY, "0.10.0", "pngme-aarch64-macos", binary, &tampered).is_err());
+    }
+}
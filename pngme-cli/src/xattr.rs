@@ -0,0 +1,170 @@
+//! Extended attributes, for `--method xattr` and the `check` warnings about metadata kept
+//! beside a file rather than in it.
+//!
+//! Names are given without the `user.` namespace Linux requires of unprivileged writers, so
+//! `pngme.ruSt` means the same attribute on Linux and macOS.
+
+use std::io;
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+const NAMESPACE: &str = "user.";
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod sys {
+    use std::ffi::{c_char, c_void, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    #[cfg(target_os = "linux")]
+    const NO_ATTRIBUTE: i32 = libc::ENODATA;
+    #[cfg(target_os = "macos")]
+    const NO_ATTRIBUTE: i32 = libc::ENOATTR;
+
+    fn c_string(bytes: &[u8]) -> io::Result<CString> {
+        CString::new(bytes).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+    }
+
+    /// Calls `read` once to size the buffer and again to fill it, retrying if the value grew
+    /// in between.
+    fn read_sized(mut read: impl FnMut(*mut c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+        loop {
+            let size = read(std::ptr::null_mut(), 0);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buffer = vec![0u8; size as usize];
+            let read = read(buffer.as_mut_ptr().cast(), buffer.len());
+            if read >= 0 {
+                buffer.truncate(read as usize);
+                return Ok(buffer);
+            }
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::ERANGE) {
+                return Err(error);
+            }
+        }
+    }
+
+    pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = c_string(path.as_os_str().as_bytes())?;
+        let name = c_string(name.as_bytes())?;
+        // SAFETY: both strings outlive the calls, and `read_sized` passes a buffer of the
+        // size it names.
+        let value = read_sized(|value, size| unsafe {
+            #[cfg(target_os = "linux")]
+            let read = libc::getxattr(path.as_ptr(), name.as_ptr(), value, size);
+            #[cfg(target_os = "macos")]
+            let read = libc::getxattr(path.as_ptr(), name.as_ptr(), value, size, 0, 0);
+            read
+        });
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.raw_os_error() == Some(NO_ATTRIBUTE) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = c_string(path.as_os_str().as_bytes())?;
+        let name = c_string(name.as_bytes())?;
+        // SAFETY: the strings and `value` outlive the call, which only reads them.
+        let result = unsafe {
+            #[cfg(target_os = "linux")]
+            let result = libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            );
+            #[cfg(target_os = "macos")]
+            let result = libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+                0,
+            );
+            result
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn list(path: &Path) -> io::Result<Vec<String>> {
+        let path = c_string(path.as_os_str().as_bytes())?;
+        // SAFETY: as for `get`.
+        let names = read_sized(|names, size| unsafe {
+            #[cfg(target_os = "linux")]
+            let read = libc::listxattr(path.as_ptr(), names as *mut c_char, size);
+            #[cfg(target_os = "macos")]
+            let read = libc::listxattr(path.as_ptr(), names as *mut c_char, size, 0);
+            read
+        })?;
+        Ok(names
+            .split(|&byte| byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are only supported on Linux and macOS",
+        )
+    }
+
+    pub fn get(_: &Path, _: &str) -> io::Result<Option<Vec<u8>>> {
+        Err(unsupported())
+    }
+
+    pub fn set(_: &Path, _: &str, _: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn list(_: &Path) -> io::Result<Vec<String>> {
+        Err(unsupported())
+    }
+}
+
+fn os_name(name: &str) -> String {
+    #[cfg(target_os = "linux")]
+    return format!("{}{}", NAMESPACE, name);
+    #[cfg(not(target_os = "linux"))]
+    name.to_string()
+}
+
+/// The attribute's value, or `None` if the file doesn't have it.
+pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    sys::get(path, &os_name(name))
+}
+
+pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    sys::set(path, &os_name(name), value)
+}
+
+/// The file's attributes and the size of each value.
+pub fn list(path: &Path) -> io::Result<Vec<(String, usize)>> {
+    let mut attributes = Vec::new();
+    for os_name in sys::list(path)? {
+        #[cfg(target_os = "linux")]
+        let name = os_name.strip_prefix(NAMESPACE).unwrap_or(&os_name);
+        #[cfg(not(target_os = "linux"))]
+        let name = os_name.as_str();
+        let size = sys::get(path, &os_name)?.map_or(0, |value| value.len());
+        attributes.push((name.to_string(), size));
+    }
+    Ok(attributes)
+}
@@ -0,0 +1,142 @@
+use std::hint::black_box;
+use std::io::Write;
+use std::str::FromStr;
+
+use crc::crc32::checksum_ieee;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use flate2::{write::ZlibEncoder, Compression};
+use pngme_core::chunk::Chunk;
+use pngme_core::chunk_type::ChunkType;
+use pngme_core::image::{ColorType, ImageData, ImageHeader};
+use pngme_core::png::Png;
+use pngme_core::spread;
+
+const MIB: usize = 1 << 20;
+const IDAT_SIZE: usize = 64 * 1024;
+
+/// Deterministic bytes that deflate about as poorly as photographic image data.
+fn noise(length: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491u32;
+    (0..length)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+/// A well-formed file of about `size` bytes; the IDAT payload is noise, not a real image.
+fn synthetic_file(size: usize) -> Vec<u8> {
+    let header = ImageHeader {
+        width: 1024,
+        height: 1024,
+        bit_depth: 8,
+        color_type: ColorType::Rgb,
+        interlaced: false,
+    };
+    let mut chunks = vec![chunk("IHDR", header.to_bytes().to_vec())];
+    let data = noise(IDAT_SIZE);
+    chunks.extend((0..size / (IDAT_SIZE + 12)).map(|_| chunk("IDAT", data.clone())));
+    chunks.push(chunk("IEND", vec![]));
+    Png::from_chunks(chunks).as_bytes()
+}
+
+/// A real 512×512 RGB image: a gradient with noise in the low bits.
+fn image_file() -> Png {
+    let header = ImageHeader {
+        width: 512,
+        height: 512,
+        bit_depth: 8,
+        color_type: ColorType::Rgb,
+        interlaced: false,
+    };
+    let noise = noise(512 * 512 * 3);
+    let mut raw = Vec::new();
+    for (y, row) in noise.chunks(512 * 3).enumerate() {
+        raw.push(0);
+        raw.extend(
+            row.iter()
+                .enumerate()
+                .map(|(x, bits)| ((x / 6 + y / 2) as u8).wrapping_add(bits & 7)),
+        );
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&raw).unwrap();
+    Png::from_chunks(vec![
+        chunk("IHDR", header.to_bytes().to_vec()),
+        chunk("IDAT", encoder.finish().unwrap()),
+        chunk("IEND", vec![]),
+    ])
+}
+
+fn chunk_parsing(c: &mut Criterion) {
+    let bytes = chunk("IDAT", noise(IDAT_SIZE)).as_bytes();
+    let mut group = c.benchmark_group("chunk");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("parse 64 KiB", |b| {
+        b.iter(|| Chunk::try_from(black_box(&bytes)).unwrap())
+    });
+    group.finish();
+}
+
+fn crc(c: &mut Criterion) {
+    let data = noise(MIB);
+    let mut group = c.benchmark_group("crc");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("1 MiB", |b| b.iter(|| checksum_ieee(black_box(&data))));
+    group.finish();
+}
+
+fn file_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("png");
+    group.sample_size(10);
+    for megabytes in [1, 50, 500] {
+        let bytes = synthetic_file(megabytes * MIB);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("parse", format!("{} MiB", megabytes)),
+            &bytes,
+            |b, bytes| b.iter(|| Png::try_from(&bytes[..]).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn inflate(c: &mut Criterion) {
+    let png = image_file();
+    let mut group = c.benchmark_group("image");
+    group.throughput(Throughput::Bytes(512 * 512 * 3));
+    group.bench_function("decode 512x512 RGB", |b| {
+        b.iter(|| ImageData::from_png(black_box(&png)).unwrap())
+    });
+    group.finish();
+}
+
+fn lsb(c: &mut Criterion) {
+    let png = image_file();
+    let message = noise(4096);
+    let mut embedded = ImageData::from_png(&png).unwrap();
+    spread::embed(&mut embedded, "bench", &message).unwrap();
+    let mut group = c.benchmark_group("spread");
+    group.throughput(Throughput::Bytes(message.len() as u64));
+    group.bench_function("embed 4 KiB", |b| {
+        b.iter_batched_ref(
+            || ImageData::from_png(&png).unwrap(),
+            |image| spread::embed(image, "bench", &message).unwrap(),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("extract 4 KiB", |b| {
+        b.iter(|| spread::extract(black_box(&embedded), "bench").unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, chunk_parsing, crc, file_parsing, inflate, lsb);
+criterion_main!(benches);
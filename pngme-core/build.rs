@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/pngme.proto");
+        let descriptors = protox::compile(["proto/pngme.proto"], ["proto"])?;
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_fds(descriptors)?;
+    }
+    Ok(())
+}
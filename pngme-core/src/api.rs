@@ -0,0 +1,498 @@
+use std::io::{self, BufRead, Read, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::ImageData;
+use crate::png::{Png, PngHandle};
+use crate::{spread, validate};
+
+pub(crate) const DEFAULT_CHUNK_TYPE: &str = "ruSt";
+
+fn default_chunk_type() -> String {
+    DEFAULT_CHUNK_TYPE.to_string()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Chunk,
+    Spread,
+}
+
+/// One request line. PNG files and payloads travel as base64 strings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Request {
+    /// Echoed back in the response so callers can match them up.
+    #[serde(default)]
+    pub id: Option<Value>,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Encode {
+        png: String,
+        message: String,
+        #[serde(default = "default_chunk_type")]
+        chunk_type: String,
+        #[serde(default)]
+        method: Method,
+        password: Option<String>,
+    },
+    Decode {
+        png: String,
+        #[serde(default = "default_chunk_type")]
+        chunk_type: String,
+        #[serde(default)]
+        method: Method,
+        password: Option<String>,
+    },
+    Inspect {
+        png: String,
+    },
+    Check {
+        png: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChunkSummary {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub length: u32,
+    pub crc: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Outcome {
+    Encoded { png: String },
+    Decoded { message: String },
+    Inspected { chunks: Vec<ChunkSummary> },
+    Checked { problems: Vec<String> },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub ok: bool,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// What one client may ask of a server, so that one exposed on a network can't be made to
+/// buffer or work without end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The largest request in bytes, as a gRPC message or a line of JSON, and the largest
+    /// gRPC response.
+    pub max_request: usize,
+    /// How long a gRPC request may run before it is cancelled.
+    pub timeout: Duration,
+    /// Requests each gRPC connection may have in flight at once.
+    pub concurrency: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_request: 64 << 20,
+            timeout: Duration::from_secs(60),
+            concurrency: 8,
+        }
+    }
+}
+
+/// The operations behind every transport, on in-memory PNGs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Service {
+    /// Parse with `Png::from_bytes_hardened` and hide the reason for every failure.
+    pub hardened: bool,
+}
+
+impl Service {
+    pub fn encode(
+        &self,
+        png: &[u8],
+        message: &[u8],
+        chunk_type: &str,
+        method: Method,
+        password: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let mut png = self.parse(png)?;
+        self.encode_png(&mut png, message, chunk_type, method, password)?;
+        Ok(png.as_bytes())
+    }
+
+    /// Parses `png` once, for any number of threads to run
+    /// [`decode_png`](Service::decode_png) and [`inspect_png`](Service::inspect_png) on
+    /// the same file.
+    pub fn parse(&self, png: &[u8]) -> Result<PngHandle, String> {
+        self.parse_png(png).map(PngHandle::new)
+    }
+
+    /// [`encode`](Service::encode) on a parsed file, copying it first if another handle
+    /// still reads it.
+    pub fn encode_png(
+        &self,
+        png: &mut PngHandle,
+        message: &[u8],
+        chunk_type: &str,
+        method: Method,
+        password: Option<&str>,
+    ) -> Result<(), String> {
+        match method {
+            Method::Chunk => {
+                let chunk = Chunk::new(parse_chunk_type(chunk_type)?, message.to_vec());
+                png.make_mut().append_chunk(chunk)
+            }
+            Method::Spread => {
+                let password = password.ok_or("the spread method needs a password")?;
+                let mut image = read_image(png)?;
+                spread::embed(&mut image, password, message)
+                    .map_err(|()| "the message does not fit in the image")?;
+                image
+                    .write_to(png.make_mut())
+                    .map_err(|()| "failed to write the image data")?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn decode(
+        &self,
+        png: &[u8],
+        chunk_type: &str,
+        method: Method,
+        password: Option<&str>,
+    ) -> Result<Zeroizing<Vec<u8>>, String> {
+        self.decode_png(&self.parse_png(png)?, chunk_type, method, password)
+    }
+
+    /// [`decode`](Service::decode) on a parsed file.
+    pub fn decode_png(
+        &self,
+        png: &Png,
+        chunk_type: &str,
+        method: Method,
+        password: Option<&str>,
+    ) -> Result<Zeroizing<Vec<u8>>, String> {
+        match method {
+            Method::Chunk => {
+                let chunk_type = parse_chunk_type(chunk_type)?.to_string();
+                let chunk = png
+                    .chunk_by_type(&chunk_type)
+                    .ok_or_else(|| format!("no {} chunk found", chunk_type))?;
+                Ok(Zeroizing::new(chunk.data().to_vec()))
+            }
+            Method::Spread => {
+                let password = password.ok_or("the spread method needs a password")?;
+                spread::extract(&read_image(png)?, password)
+                    .map_err(|()| "no message found; is the password right?".to_string())
+            }
+        }
+    }
+
+    pub fn inspect(&self, png: &[u8]) -> Result<Vec<ChunkSummary>, String> {
+        Ok(self.inspect_png(&self.parse_png(png)?))
+    }
+
+    /// [`inspect`](Service::inspect) on a parsed file.
+    pub fn inspect_png(&self, png: &Png) -> Vec<ChunkSummary> {
+        png.chunks()
+            .iter()
+            .map(|chunk| ChunkSummary {
+                chunk_type: chunk.chunk_type().to_string(),
+                length: chunk.length(),
+                crc: chunk.crc(),
+            })
+            .collect()
+    }
+
+    /// The warnings `pngme check` would print.
+    pub fn check(&self, png: &[u8]) -> Result<Vec<String>, String> {
+        Ok(validate::problems(&self.parse_png(png)?, png))
+    }
+
+    /// The message to show a client for `error`: in hardened mode, always the same one.
+    pub fn public_error(&self, error: String) -> String {
+        if self.hardened {
+            "invalid input".to_string()
+        } else {
+            error
+        }
+    }
+
+    pub fn handle(&self, request: Request) -> Response {
+        let outcome = self
+            .run(request.operation)
+            .unwrap_or_else(|error| Outcome::Failed {
+                error: self.public_error(error),
+            });
+        Response {
+            id: request.id,
+            ok: !matches!(outcome, Outcome::Failed { .. }),
+            outcome,
+        }
+    }
+
+    /// Parses and handles one line of JSON, answering malformed lines with an error.
+    pub fn handle_line(&self, line: &str) -> Response {
+        match serde_json::from_str(line) {
+            Ok(request) => self.handle(request),
+            Err(error) => self.failure(format!("invalid request: {}", error)),
+        }
+    }
+
+    /// Answers each line of `input` on a line of `output` until `input` ends. A line longer
+    /// than `max_line` bytes is answered with an error and skipped without being buffered.
+    pub fn serve_lines(
+        &self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+        max_line: usize,
+    ) -> io::Result<()> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = Read::take(&mut input, max_line as u64 + 1).read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(());
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            } else if line.len() > max_line {
+                input.skip_until(b'\n')?;
+                line.clear();
+                let error = format!("invalid request: longer than {} bytes", max_line);
+                self.write_response(&mut output, self.failure(error))?;
+                continue;
+            }
+            let response = match std::str::from_utf8(&line) {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => self.handle_line(line),
+                Err(_) => self.failure("invalid request: not UTF-8".to_string()),
+            };
+            self.write_response(&mut output, response)?;
+        }
+    }
+
+    fn failure(&self, error: String) -> Response {
+        Response {
+            id: None,
+            ok: false,
+            outcome: Outcome::Failed {
+                error: self.public_error(error),
+            },
+        }
+    }
+
+    fn write_response(&self, output: &mut impl Write, response: Response) -> io::Result<()> {
+        serde_json::to_writer(&mut *output, &response)?;
+        writeln!(output)?;
+        output.flush()
+    }
+
+    fn run(&self, operation: Operation) -> Result<Outcome, String> {
+        match operation {
+            Operation::Encode {
+                png,
+                message,
+                chunk_type,
+                method,
+                password,
+            } => {
+                let png = self.encode(
+                    &decode_base64("png", &png)?,
+                    &decode_base64("message", &message)?,
+                    &chunk_type,
+                    method,
+                    password.as_deref(),
+                )?;
+                Ok(Outcome::Encoded {
+                    png: STANDARD.encode(png),
+                })
+            }
+            Operation::Decode {
+                png,
+                chunk_type,
+                method,
+                password,
+            } => {
+                let message = self.decode(
+                    &decode_base64("png", &png)?,
+                    &chunk_type,
+                    method,
+                    password.as_deref(),
+                )?;
+                Ok(Outcome::Decoded {
+                    message: STANDARD.encode(message),
+                })
+            }
+            Operation::Inspect { png } => Ok(Outcome::Inspected {
+                chunks: self.inspect(&decode_base64("png", &png)?)?,
+            }),
+            Operation::Check { png } => Ok(Outcome::Checked {
+                problems: self.check(&decode_base64("png", &png)?)?,
+            }),
+        }
+    }
+
+    fn parse_png(&self, bytes: &[u8]) -> Result<Png, String> {
+        let png = if self.hardened {
+            Png::from_bytes_hardened(bytes)
+        } else {
+            Png::try_from(bytes)
+        };
+        png.map_err(|()| "png is not a valid PNG file".to_string())
+    }
+}
+
+fn decode_base64(field: &str, encoded: &str) -> Result<Vec<u8>, String> {
+    STANDARD
+        .decode(encoded)
+        .map_err(|error| format!("{} is not valid base64: {}", field, error))
+}
+
+fn parse_chunk_type(chunk_type: &str) -> Result<ChunkType, String> {
+    ChunkType::from_str(chunk_type)
+        .map_err(|error| format!("`{}` is not a valid chunk type: {}", chunk_type, error))
+}
+
+fn read_image(png: &Png) -> Result<ImageData, String> {
+    ImageData::from_png(png).map_err(|()| "png has no decodable image data".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    /// [`png_with_header`]'s RGB image, base64-encoded as requests carry it.
+    fn encoded_png() -> String {
+        STANDARD.encode(png_with_header(ColorType::Rgb, 8, 0).as_bytes())
+    }
+
+    fn call(service: Service, request: Value) -> Value {
+        serde_json::to_value(service.handle_line(&request.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_encode_and_decode() {
+        let service = Service::default();
+        let encoded = call(
+            service,
+            serde_json::json!({
+                "id": 7,
+                "op": "encode",
+                "png": encoded_png(),
+                "message": STANDARD.encode("over the wire"),
+            }),
+        );
+        assert_eq!(encoded["id"], 7);
+        assert_eq!(encoded["ok"], true);
+        let decoded = call(
+            service,
+            serde_json::json!({ "op": "decode", "png": encoded["png"] }),
+        );
+        assert_eq!(decoded["message"], STANDARD.encode("over the wire"));
+        assert!(decoded.get("id").is_none());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let response = call(
+            Service::default(),
+            serde_json::json!({ "op": "inspect", "png": encoded_png() }),
+        );
+        assert_eq!(response["chunks"][0]["type"], "IHDR");
+        assert_eq!(response["chunks"][1]["length"], 0);
+    }
+
+    #[test]
+    fn test_concurrent_requests_on_one_file() {
+        let service = Service::default();
+        let png = service
+            .parse(&STANDARD.decode(encoded_png()).unwrap())
+            .unwrap();
+        let mut edited = png.clone();
+        service
+            .encode_png(&mut edited, b"shared", "ruSt", Method::Chunk, None)
+            .unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(service.inspect_png(&png).len(), 3);
+                    let message = service.decode_png(&edited, "ruSt", Method::Chunk, None);
+                    assert_eq!(*message.unwrap(), b"shared");
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_check() {
+        let response = call(
+            Service::default(),
+            serde_json::json!({ "op": "check", "png": encoded_png() }),
+        );
+        assert_eq!(response["problems"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let service = Service::default();
+        let response = call(service, serde_json::json!({ "op": "inspect", "png": "%%" }));
+        assert_eq!(response["ok"], false);
+        assert!(response["error"].as_str().unwrap().contains("base64"));
+        let response = call(
+            service,
+            serde_json::json!({ "op": "decode", "png": encoded_png(), "method": "spread" }),
+        );
+        assert_eq!(response["error"], "the spread method needs a password");
+        assert!(!service.handle_line("{").ok);
+
+        let hardened = Service { hardened: true };
+        let response = call(
+            hardened,
+            serde_json::json!({ "op": "inspect", "png": "%%" }),
+        );
+        assert_eq!(response["error"], "invalid input");
+    }
+
+    #[test]
+    fn test_serve_lines() {
+        let request = serde_json::json!({ "id": 1, "op": "check", "png": encoded_png() });
+        let input = format!("{}\n\n{}\n{}", request, "x".repeat(5000), request);
+        let mut output = Vec::new();
+        Service::default()
+            .serve_lines(input.as_bytes(), &mut output, 1000)
+            .unwrap();
+        let responses: Vec<Value> = output
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["ok"], true);
+        assert_eq!(responses[1]["ok"], false);
+        assert!(responses[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("longer than 1000 bytes"));
+        assert_eq!(responses[2]["id"], 1);
+    }
+}
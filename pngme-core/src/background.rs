@@ -0,0 +1,69 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::{Color, ImageHeader};
+use crate::png::Png;
+
+/// How many entries the image's PLTE chunk holds.
+pub fn palette_len(png: &Png) -> usize {
+    png.chunk_by_type(ChunkType::PLTE)
+        .map_or(0, |chunk| chunk.data().len() / 3)
+}
+
+/// The bKGD colour, in the form the image's colour type gives it.
+pub fn background(png: &Png) -> Option<Result<Color, ()>> {
+    let chunk = png.chunk_by_type(ChunkType::BKGD)?;
+    Some(
+        ImageHeader::from_png(png)
+            .and_then(|header| Color::from_bytes(chunk.data(), header.color_type)),
+    )
+}
+
+/// Replaces any bKGD chunk with one holding `color`, once it has been checked against IHDR
+/// and the palette.
+pub fn set_background(png: &mut Png, color: Color) -> Result<(), String> {
+    let header = ImageHeader::from_png(png).map_err(|()| "the file has no valid IHDR chunk")?;
+    color.check(&header, palette_len(png))?;
+    remove_background(png);
+    png.insert_ancillary(Chunk::new(
+        ChunkType::BKGD,
+        color.to_bytes(header.color_type),
+    ));
+    Ok(())
+}
+
+pub fn remove_background(png: &mut Png) -> bool {
+    let before = png.chunks().len();
+    png.retain_chunks(|chunk| *chunk.chunk_type() != ChunkType::BKGD);
+    png.chunks().len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    #[test]
+    fn test_set_background() {
+        let mut png = png_with_header(ColorType::Rgb, 16, 0);
+        assert!(background(&png).is_none());
+        set_background(&mut png, Color::Rgb([65535, 0, 1])).unwrap();
+        assert_eq!(background(&png), Some(Ok(Color::Rgb([65535, 0, 1]))));
+        set_background(&mut png, Color::Rgb([1, 2, 3])).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunks()[1].data(), [0, 1, 0, 2, 0, 3]);
+        assert!(set_background(&mut png, Color::Level(7)).is_err());
+
+        let mut png = png_with_header(ColorType::Indexed, 4, 3);
+        set_background(&mut png, Color::Level(2)).unwrap();
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "bKGD");
+        assert_eq!(png.chunks()[2].data(), [2]);
+        assert!(set_background(&mut png, Color::Level(3)).is_err());
+
+        let mut png = png_with_header(ColorType::GrayscaleAlpha, 8, 0);
+        assert!(set_background(&mut png, Color::Level(256)).is_err());
+        set_background(&mut png, Color::Level(255)).unwrap();
+        assert!(remove_background(&mut png));
+        assert!(!remove_background(&mut png));
+    }
+}
@@ -0,0 +1,237 @@
+//! Files composed from scratch, for test fixtures and procedurally generated carriers:
+//!
+//! ```
+//! # use pngme_core::builder::PngBuilder;
+//! # use pngme_core::image::{ColorType, ImageHeader};
+//! let header = ImageHeader {
+//!     width: 2,
+//!     height: 1,
+//!     bit_depth: 8,
+//!     color_type: ColorType::Indexed,
+//!     interlaced: false,
+//! };
+//! let png = PngBuilder::new(header)
+//!     .palette([[0, 0, 0], [255, 255, 255]])
+//!     .text("Author", "me")
+//!     .image_rows([vec![0, 1]])
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(png.chunks().len(), 5);
+//! ```
+//!
+//! The chunks go where the specification wants them whatever order they were given in, and
+//! everything is checked against the header when the file is built.
+
+use flate2::Compression;
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::image::{self, ColorType, ImageHeader};
+use crate::parallel;
+use crate::png::Png;
+
+pub struct PngBuilder {
+    header: ImageHeader,
+    palette: Option<Vec<[u8; 3]>>,
+    ancillary: Vec<Chunk>,
+    rows: Vec<Vec<u16>>,
+    /// The first problem found while adding chunks, reported by `build`.
+    error: Option<String>,
+}
+
+impl PngBuilder {
+    pub fn new(header: ImageHeader) -> Self {
+        Self {
+            header,
+            palette: None,
+            ancillary: Vec::new(),
+            rows: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// The PLTE entries, which indexed images need and truecolour images may suggest.
+    pub fn palette(mut self, entries: impl IntoIterator<Item = [u8; 3]>) -> Self {
+        self.palette = Some(entries.into_iter().collect());
+        self
+    }
+
+    /// A tEXt chunk, or iTXt if `text` isn't all Latin-1.
+    pub fn text(mut self, keyword: &str, text: &str) -> Self {
+        match chunk::text_chunk(keyword, text) {
+            Ok(chunk) => self.ancillary.push(chunk),
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    /// Any other ancillary chunk, placed ahead of PLTE or the image data as its type needs.
+    pub fn chunk(mut self, chunk: Chunk) -> Self {
+        if chunk.chunk_type().is_critical() {
+            self.error.get_or_insert(format!(
+                "{} is a critical chunk; the builder writes those itself",
+                chunk.chunk_type()
+            ));
+        } else {
+            self.ancillary.push(chunk);
+        }
+        self
+    }
+
+    /// The image, top row first, each row its samples as in `ImageData::samples`: palette
+    /// indices for indexed images, otherwise every channel of each pixel in turn.
+    pub fn image_rows<R: AsRef<[u16]>>(mut self, rows: impl IntoIterator<Item = R>) -> Self {
+        self.rows
+            .extend(rows.into_iter().map(|row| row.as_ref().to_vec()));
+        self
+    }
+
+    pub fn build(self) -> Result<Png, String> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let header = ImageHeader {
+            interlaced: false,
+            ..self.header
+        };
+        let color_type = header.color_type;
+        if header.width == 0 || header.height == 0 {
+            return Err("the image must be at least 1x1".to_string());
+        }
+        if !color_type.allows_bit_depth(header.bit_depth) {
+            return Err(format!(
+                "{} images can't be {}-bit",
+                color_type, header.bit_depth
+            ));
+        }
+        let palette_len = self.palette.as_ref().map_or(0, Vec::len);
+        match (&self.palette, color_type) {
+            (None, ColorType::Indexed) => return Err("indexed images need a palette".to_string()),
+            (Some(_), ColorType::Grayscale | ColorType::GrayscaleAlpha) => {
+                return Err("greyscale images can't have a palette".to_string())
+            }
+            _ if palette_len > 256 || self.palette.is_some() && palette_len == 0 => {
+                return Err(format!(
+                    "a palette holds 1 to 256 entries, not {}",
+                    palette_len
+                ))
+            }
+            _ => {}
+        }
+        if self.rows.len() != header.height as usize {
+            return Err(format!(
+                "{} rows given for an image {} high",
+                self.rows.len(),
+                header.height
+            ));
+        }
+        let row_samples = header.width as usize * color_type.channels();
+        let max = match color_type {
+            ColorType::Indexed => palette_len as u32 - 1,
+            _ => (1 << header.bit_depth) - 1,
+        };
+        let mut raw = Vec::with_capacity((header.row_bytes(header.width) + 1) * self.rows.len());
+        for (y, row) in self.rows.iter().enumerate() {
+            if row.len() != row_samples {
+                return Err(format!(
+                    "row {} has {} samples, not {}",
+                    y,
+                    row.len(),
+                    row_samples
+                ));
+            }
+            if let Some(sample) = row.iter().find(|&&sample| sample as u32 > max) {
+                return Err(format!(
+                    "row {} has a sample of {}, above {}",
+                    y, sample, max
+                ));
+            }
+            raw.push(0);
+            raw.extend(image::pack(row, header.bit_depth));
+        }
+
+        let mut chunks = vec![Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec())];
+        if let Some(palette) = &self.palette {
+            chunks.push(Chunk::new(ChunkType::PLTE, palette.concat()));
+        }
+        chunks.push(Chunk::new(
+            ChunkType::IDAT,
+            parallel::compress(&raw, Compression::default())
+                .map_err(|()| "failed to compress the image data".to_string())?,
+        ));
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        let mut png = Png::from_chunks(chunks);
+        for chunk in self.ancillary {
+            png.insert_ancillary(chunk);
+        }
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageData;
+
+    fn header(color_type: ColorType, bit_depth: u8) -> ImageHeader {
+        ImageHeader {
+            width: 3,
+            height: 2,
+            bit_depth,
+            color_type,
+            interlaced: false,
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let png = PngBuilder::new(header(ColorType::Indexed, 2))
+            .text("Author", "me")
+            .chunk(Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec()))
+            .image_rows([[0, 1, 2], [2, 1, 0]])
+            .palette([[255, 0, 0], [0, 255, 0], [0, 0, 255]])
+            .build()
+            .unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "PLTE", "tEXt", "IDAT", "IEND"]);
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.samples(), &[0, 1, 2, 2, 1, 0]);
+        assert!(crate::validate::problems(&png, &png.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let rows = [[0u16; 9], [0; 9]];
+        let rgb = || PngBuilder::new(header(ColorType::Rgb, 8)).image_rows(rows);
+        assert!(rgb().build().is_ok());
+        assert!(rgb().image_rows([[0; 9]]).build().is_err());
+        assert!(rgb().text("", "no keyword").build().is_err());
+        assert!(rgb()
+            .chunk(Chunk::new(ChunkType::IDAT, vec![]))
+            .build()
+            .is_err());
+        assert!(PngBuilder::new(header(ColorType::Indexed, 8))
+            .image_rows([[0; 3], [0; 3]])
+            .build()
+            .is_err());
+        assert!(PngBuilder::new(header(ColorType::Indexed, 8))
+            .palette([[0; 3]])
+            .image_rows([[0, 0, 1], [0; 3]])
+            .build()
+            .is_err());
+        assert!(PngBuilder::new(header(ColorType::Grayscale, 4))
+            .image_rows([[15, 16, 0], [0; 3]])
+            .build()
+            .is_err());
+        assert!(PngBuilder::new(header(ColorType::Rgb, 4))
+            .image_rows(rows)
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use std::ops::Range;
+
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::cbor::Value;
+use crate::png::Png;
+
+/// Chunk carrying a C2PA (content credentials) manifest store as a JUMBF box.
+pub const C2PA_CHUNK_TYPE: &str = "caBX";
+
+const SUPERBOX: [u8; 4] = *b"jumb";
+const DESCRIPTION_BOX: [u8; 4] = *b"jumd";
+const CBOR_BOX: [u8; 4] = *b"cbor";
+const LABEL_PRESENT: u8 = 0x02;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BindingStatus {
+    Valid,
+    Invalid,
+    Unverifiable(&'static str),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DataHash {
+    pub alg: String,
+    pub hash: Vec<u8>,
+    pub exclusions: Vec<Range<u64>>,
+}
+
+/// Returns the raw JUMBF manifest store, if the image carries one.
+pub fn manifest_store(png: &Png) -> Option<&[u8]> {
+    png.chunk_by_type(C2PA_CHUNK_TYPE).map(|chunk| chunk.data())
+}
+
+/// Finds the `c2pa.hash.data` hard binding of the active (last) manifest.
+pub fn data_hash(store: &[u8]) -> Result<Option<DataHash>, ()> {
+    let (box_type, payload) = *boxes(store)?.first().ok_or(())?;
+    if box_type != SUPERBOX || superbox_label(payload)?.as_deref() != Some("c2pa") {
+        return Err(());
+    }
+    let manifest = match superbox_children(payload)?.last() {
+        Some(manifest) => *manifest,
+        None => return Ok(None),
+    };
+    let assertions = match find_superbox(manifest, |label| label == "c2pa.assertions")? {
+        Some(assertions) => assertions,
+        None => return Ok(None),
+    };
+    let is_data_hash =
+        |label: &str| label == "c2pa.hash.data" || label.starts_with("c2pa.hash.data__");
+    let assertion = match find_superbox(assertions, is_data_hash)? {
+        Some(assertion) => assertion,
+        None => return Ok(None),
+    };
+    let (_, cbor) = *boxes(assertion)?
+        .iter()
+        .find(|(box_type, _)| *box_type == CBOR_BOX)
+        .ok_or(())?;
+    let value = Value::decode(cbor)?;
+
+    let alg = match value.get("alg") {
+        Some(alg) => alg.as_text().ok_or(())?.to_string(),
+        None => String::from("sha256"),
+    };
+    let hash = value
+        .get("hash")
+        .and_then(Value::as_bytes)
+        .ok_or(())?
+        .to_vec();
+    let mut exclusions = Vec::new();
+    if let Some(list) = value.get("exclusions") {
+        for exclusion in list.as_array().ok_or(())? {
+            let start = exclusion.get("start").and_then(Value::as_u64).ok_or(())?;
+            let length = exclusion.get("length").and_then(Value::as_u64).ok_or(())?;
+            exclusions.push(start..start.checked_add(length).ok_or(())?);
+        }
+    }
+    Ok(Some(DataHash {
+        alg,
+        hash,
+        exclusions,
+    }))
+}
+
+/// Recomputes the manifest's data hash over `file_bytes` (the file exactly as stored).
+pub fn check_binding(file_bytes: &[u8], store: &[u8]) -> BindingStatus {
+    let data_hash = match data_hash(store) {
+        Ok(Some(data_hash)) => data_hash,
+        Ok(None) => return BindingStatus::Unverifiable("manifest has no data hash assertion"),
+        Err(()) => return BindingStatus::Unverifiable("manifest store is malformed"),
+    };
+    let mut exclusions = data_hash.exclusions.clone();
+    exclusions.sort_by_key(|range| range.start);
+    if exclusions
+        .iter()
+        .any(|range| range.end > file_bytes.len() as u64)
+    {
+        return BindingStatus::Invalid;
+    }
+    let mut included = Vec::new();
+    let mut position = 0;
+    for range in exclusions {
+        let start = range.start as usize;
+        if start > position {
+            included.push(&file_bytes[position..start]);
+        }
+        position = position.max(range.end as usize);
+    }
+    included.push(&file_bytes[position..]);
+
+    let computed = match data_hash.alg.as_str() {
+        "sha256" => digest::<Sha256>(&included),
+        "sha384" => digest::<Sha384>(&included),
+        "sha512" => digest::<Sha512>(&included),
+        _ => return BindingStatus::Unverifiable("unsupported hash algorithm"),
+    };
+    if computed == data_hash.hash {
+        BindingStatus::Valid
+    } else {
+        BindingStatus::Invalid
+    }
+}
+
+fn digest<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+type JumbfBox<'a> = ([u8; 4], &'a [u8]);
+
+fn boxes(mut bytes: &[u8]) -> Result<Vec<JumbfBox<'_>>, ()> {
+    let mut result = Vec::new();
+    while !bytes.is_empty() {
+        if bytes.len() < 8 {
+            return Err(());
+        }
+        let length = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as u64;
+        let box_type: [u8; 4] = bytes[4..8].try_into().unwrap();
+        let (header_len, length) = match length {
+            0 => (8, bytes.len() as u64),
+            1 => {
+                let extended = bytes.get(8..16).ok_or(())?;
+                (16, u64::from_be_bytes(extended.try_into().unwrap()))
+            }
+            length => (8, length),
+        };
+        if length < header_len as u64 || length > bytes.len() as u64 {
+            return Err(());
+        }
+        result.push((box_type, &bytes[header_len..length as usize]));
+        bytes = &bytes[length as usize..];
+    }
+    Ok(result)
+}
+
+fn superbox_label(payload: &[u8]) -> Result<Option<String>, ()> {
+    let (box_type, description) = *boxes(payload)?.first().ok_or(())?;
+    if box_type != DESCRIPTION_BOX || description.len() < 17 {
+        return Err(());
+    }
+    if description[16] & LABEL_PRESENT == 0 {
+        return Ok(None);
+    }
+    let label = &description[17..];
+    let end = label.iter().position(|&byte| byte == 0).ok_or(())?;
+    String::from_utf8(label[..end].to_vec())
+        .map(Some)
+        .map_err(|_| ())
+}
+
+fn superbox_children(payload: &[u8]) -> Result<Vec<&[u8]>, ()> {
+    Ok(boxes(payload)?
+        .into_iter()
+        .filter(|(box_type, _)| *box_type == SUPERBOX)
+        .map(|(_, child)| child)
+        .collect())
+}
+
+fn find_superbox(payload: &[u8], matches: impl Fn(&str) -> bool) -> Result<Option<&[u8]>, ()> {
+    for child in superbox_children(payload)? {
+        if superbox_label(child)?.as_deref().is_some_and(&matches) {
+            return Ok(Some(child));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn jumbf_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(box_type);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn superbox(label: &str, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut description = vec![0; 16];
+        description.push(LABEL_PRESENT);
+        description.extend(label.bytes());
+        description.push(0);
+        let mut payload = jumbf_box(&DESCRIPTION_BOX, &description);
+        for child in children {
+            payload.extend(child);
+        }
+        jumbf_box(&SUPERBOX, &payload)
+    }
+
+    fn manifest_store_with(hash: &[u8], exclusion: Range<u64>) -> Vec<u8> {
+        let data_hash = Value::Map(vec![
+            (
+                Value::Text("exclusions".into()),
+                Value::Array(vec![Value::Map(vec![
+                    (
+                        Value::Text("start".into()),
+                        Value::Unsigned(exclusion.start),
+                    ),
+                    (
+                        Value::Text("length".into()),
+                        Value::Unsigned(exclusion.end - exclusion.start),
+                    ),
+                ])]),
+            ),
+            (Value::Text("alg".into()), Value::Text("sha256".into())),
+            (Value::Text("hash".into()), Value::Bytes(hash.to_vec())),
+        ]);
+        let assertion = superbox(
+            "c2pa.hash.data",
+            &[jumbf_box(&CBOR_BOX, &data_hash.encode())],
+        );
+        let assertions = superbox("c2pa.assertions", &[assertion]);
+        let manifest = superbox("urn:uuid:test", &[assertions]);
+        superbox("c2pa", &[manifest])
+    }
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn signed_png_bytes() -> Vec<u8> {
+        let header = chunk("IHDR", &[0; 13]);
+        let start = (Png::STANDARD_HEADER.len() + header.as_bytes().len()) as u64;
+        let mut exclusion = start..start;
+        while exclusion.end - exclusion.start
+            != 12 + manifest_store_with(&[0; 32], exclusion.clone()).len() as u64
+        {
+            exclusion.end =
+                start + 12 + manifest_store_with(&[0; 32], exclusion.clone()).len() as u64;
+        }
+        let placeholder = manifest_store_with(&[0; 32], exclusion.clone());
+
+        let unsigned = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk(C2PA_CHUNK_TYPE, &placeholder),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes();
+        let mut hasher = Sha256::new();
+        hasher.update(&unsigned[..exclusion.start as usize]);
+        hasher.update(&unsigned[exclusion.end as usize..]);
+        let hash = hasher.finalize();
+
+        Png::from_chunks(vec![
+            header,
+            chunk(C2PA_CHUNK_TYPE, &manifest_store_with(&hash, exclusion)),
+            chunk("IEND", &[]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_data_hash() {
+        let store = manifest_store_with(&[7; 32], 33..100);
+        let data_hash = data_hash(&store).unwrap().unwrap();
+        assert_eq!(data_hash.alg, "sha256");
+        assert_eq!(data_hash.hash, vec![7; 32]);
+        assert_eq!(data_hash.exclusions, vec![33..100]);
+    }
+
+    #[test]
+    fn test_valid_binding() {
+        let bytes = signed_png_bytes();
+        let png = Png::try_from(&bytes[..]).unwrap();
+        let store = manifest_store(&png).unwrap();
+        assert_eq!(check_binding(&bytes, store), BindingStatus::Valid);
+    }
+
+    #[test]
+    fn test_modified_file_invalidates_binding() {
+        let bytes = signed_png_bytes();
+        let mut png = Png::try_from(&bytes[..]).unwrap();
+        png.append_chunk(chunk("teXt", b"edited"));
+        let modified = png.as_bytes();
+        let store = manifest_store(&png).unwrap();
+        assert_eq!(check_binding(&modified, store), BindingStatus::Invalid);
+    }
+
+    #[test]
+    fn test_malformed_store() {
+        assert_eq!(
+            check_binding(&[], &[0, 0, 0, 4]),
+            BindingStatus::Unverifiable("manifest store is malformed")
+        );
+        let store = superbox("c2pa", &[superbox("urn:uuid:test", &[])]);
+        assert_eq!(data_hash(&store), Ok(None));
+    }
+}
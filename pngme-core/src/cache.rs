@@ -0,0 +1,187 @@
+//! Content-addressed chunk bookkeeping for runs over many files: a cache so that work on a
+//! chunk repeated across files (the same iCCP profile in every asset, say) is only done once,
+//! and statistics on which chunks the files share.
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// SHA-256 of a chunk's type and payload, the same bytes its CRC covers.
+pub type ChunkKey = [u8; 32];
+
+pub fn chunk_key(chunk: &Chunk) -> ChunkKey {
+    let mut hasher = Sha256::new();
+    hasher.update(chunk.chunk_type().bytes());
+    hasher.update(chunk.data());
+    hasher.finalize().into()
+}
+
+/// Results of some computation on chunks, remembered by content.
+#[derive(Debug)]
+pub struct ChunkCache<T> {
+    entries: HashMap<ChunkKey, T>,
+    hits: usize,
+}
+
+impl<T> Default for ChunkCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            hits: 0,
+        }
+    }
+}
+
+impl<T: Clone> ChunkCache<T> {
+    /// The cached result for a chunk with this content, computing it with `compute` the
+    /// first time.
+    pub fn get_or_compute(&mut self, chunk: &Chunk, compute: impl FnOnce(&Chunk) -> T) -> T {
+        let key = chunk_key(chunk);
+        if let Some(result) = self.entries.get(&key) {
+            self.hits += 1;
+            return result.clone();
+        }
+        let result = compute(chunk);
+        self.entries.insert(key, result.clone());
+        result
+    }
+    /// Lookups answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+    /// Distinct chunks computed so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// An ancillary chunk found, byte for byte, in more than one place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedChunk {
+    pub key: ChunkKey,
+    pub chunk_type: ChunkType,
+    pub length: u32,
+    /// How many files hold it.
+    pub files: usize,
+    /// How many times it appears in all, counting repeats within a file.
+    pub occurrences: usize,
+}
+
+impl SharedChunk {
+    /// Payload bytes that storing the chunk once would save.
+    pub fn duplicated_bytes(&self) -> u64 {
+        self.length as u64 * (self.occurrences as u64 - 1)
+    }
+}
+
+/// Tallies of the ancillary chunks in a set of files, by content.
+#[derive(Debug, Default)]
+pub struct ChunkStatistics {
+    chunks: HashMap<ChunkKey, SharedChunk>,
+    files: usize,
+}
+
+impl ChunkStatistics {
+    pub fn add(&mut self, png: &Png) {
+        self.files += 1;
+        let mut seen = Vec::new();
+        for chunk in png.chunks() {
+            if chunk.chunk_type().is_critical() {
+                continue;
+            }
+            let key = chunk_key(chunk);
+            let entry = self.chunks.entry(key).or_insert(SharedChunk {
+                key,
+                chunk_type: *chunk.chunk_type(),
+                length: chunk.length(),
+                files: 0,
+                occurrences: 0,
+            });
+            entry.occurrences += 1;
+            if !seen.contains(&key) {
+                entry.files += 1;
+                seen.push(key);
+            }
+        }
+    }
+    pub fn files(&self) -> usize {
+        self.files
+    }
+    /// Distinct ancillary chunks seen.
+    pub fn distinct(&self) -> usize {
+        self.chunks.len()
+    }
+    /// Chunks that appear more than once, in at least `min_files` files, most duplicated
+    /// bytes first.
+    pub fn shared(&self, min_files: usize) -> Vec<&SharedChunk> {
+        let mut shared: Vec<&SharedChunk> = self
+            .chunks
+            .values()
+            .filter(|chunk| chunk.files >= min_files && chunk.occurrences > 1)
+            .collect();
+        shared.sort_by(|a, b| {
+            b.duplicated_bytes()
+                .cmp(&a.duplicated_bytes())
+                .then(a.key.cmp(&b.key))
+        });
+        shared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn png_of(chunks: &[(&str, &[u8])]) -> Png {
+        Png::from_chunks(
+            chunks
+                .iter()
+                .map(|(chunk_type, data)| {
+                    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = ChunkCache::default();
+        let mut computed = 0;
+        let png = png_of(&[("tEXt", b"a\0b"), ("zTXt", b"a\0b"), ("tEXt", b"a\0b")]);
+        for chunk in png.chunks() {
+            cache.get_or_compute(chunk, |chunk| {
+                computed += 1;
+                chunk.length()
+            });
+        }
+        assert_eq!((computed, cache.len(), cache.hits()), (2, 2, 1));
+        assert_ne!(chunk_key(&png.chunks()[0]), chunk_key(&png.chunks()[1]));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut statistics = ChunkStatistics::default();
+        statistics.add(&png_of(&[
+            ("IHDR", b"same"),
+            ("iCCP", b"profile"),
+            ("tEXt", b"x"),
+        ]));
+        statistics.add(&png_of(&[("IHDR", b"same"), ("iCCP", b"profile")]));
+        statistics.add(&png_of(&[("tEXt", b"y"), ("tEXt", b"y")]));
+        assert_eq!(statistics.files(), 3);
+        assert_eq!(statistics.distinct(), 3);
+        let shared = statistics.shared(2);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].chunk_type.to_string(), "iCCP");
+        assert_eq!((shared[0].files, shared[0].duplicated_bytes()), (2, 7));
+        assert_eq!(statistics.shared(1).len(), 2);
+    }
+}
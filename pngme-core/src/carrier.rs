@@ -0,0 +1,242 @@
+//! Pseudo-random carrier images for tests and demos, the same on every platform for the same
+//! seed and settings. Each is a pattern, a flat colour, a gradient or Perlin noise, mixed
+//! with uniform noise in the proportion `entropy`: at 0 the pattern alone, smooth and easy
+//! to detect changes in, at 1 noise alone, as random as 8-bit samples get.
+//!
+//! Only integer arithmetic and IEEE-exact floating-point operations are used, with no
+//! library functions such as `sin` whose results vary between platforms.
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use crate::builder::PngBuilder;
+use crate::image::{ColorType, ImageHeader};
+use crate::png::Png;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Uniform noise over a flat colour.
+    Noise,
+    /// A linear blend between two colours, in a direction picked by the seed.
+    Gradient,
+    /// Four octaves of Perlin noise, like clouds or marble.
+    Perlin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Carrier {
+    pub pattern: Pattern,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    /// How much uniform noise is mixed in, from 0 to 1.
+    pub entropy: f64,
+    /// Greyscale rather than RGB.
+    pub grayscale: bool,
+}
+
+/// SplitMix64, which is small, fast and good enough for pictures.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in [0, 1).
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Directions for the Perlin gradients, chosen from a table rather than by angle.
+const GRADIENTS: [(f64, f64); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
+struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    fn new(rng: &mut Rng) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        for i in (1..table.len()).rev() {
+            table.swap(i, rng.below(i + 1));
+        }
+        let mut permutation = [0; 512];
+        for (i, entry) in permutation.iter_mut().enumerate() {
+            *entry = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    fn gradient(&self, x: usize, y: usize) -> (f64, f64) {
+        let hash = self.permutation[self.permutation[x % 256] as usize + y % 256];
+        GRADIENTS[hash as usize % GRADIENTS.len()]
+    }
+
+    /// Noise at `(x, y)`, roughly in [-1, 1].
+    fn at(&self, x: f64, y: f64) -> f64 {
+        let (cell_x, cell_y) = (x.floor(), y.floor());
+        let (dx, dy) = (x - cell_x, y - cell_y);
+        let (cell_x, cell_y) = (cell_x as usize, cell_y as usize);
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let corner = |offset_x: usize, offset_y: usize| {
+            let (gx, gy) = self.gradient(cell_x + offset_x, cell_y + offset_y);
+            gx * (dx - offset_x as f64) + gy * (dy - offset_y as f64)
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let (u, v) = (fade(dx), fade(dy));
+        lerp(
+            lerp(corner(0, 0), corner(1, 0), u),
+            lerp(corner(0, 1), corner(1, 1), u),
+            v,
+        )
+    }
+
+    /// Four octaves, each twice the frequency and half the weight of the last, scaled to
+    /// [0, 1].
+    fn fractal(&self, x: f64, y: f64) -> f64 {
+        let (mut total, mut weight, mut frequency) = (0.0, 1.0, 1.0);
+        for _ in 0..4 {
+            total += weight * self.at(x * frequency, y * frequency);
+            weight /= 2.0;
+            frequency *= 2.0;
+        }
+        (total / 1.875 * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+impl Carrier {
+    pub fn header(&self) -> ImageHeader {
+        ImageHeader {
+            width: self.width,
+            height: self.height,
+            bit_depth: 8,
+            color_type: match self.grayscale {
+                true => ColorType::Grayscale,
+                false => ColorType::Rgb,
+            },
+            interlaced: false,
+        }
+    }
+
+    pub fn generate(&self) -> Result<Png, String> {
+        if !(0.0..=1.0).contains(&self.entropy) {
+            return Err(format!("entropy must be from 0 to 1, not {}", self.entropy));
+        }
+        let header = self.header();
+        let channels = header.color_type.channels();
+        let mut rng = Rng(self.seed);
+        let colour =
+            |rng: &mut Rng| -> Vec<f64> { (0..channels).map(|_| rng.unit() * 255.0).collect() };
+        let (from, to) = (colour(&mut rng), colour(&mut rng));
+        let (mut dx, dy) = (rng.unit() * 2.0 - 1.0, rng.unit() * 2.0 - 1.0);
+        if dx.abs() + dy.abs() < 0.01 {
+            dx = 1.0;
+        }
+        let perlin: Vec<Perlin> = (0..channels).map(|_| Perlin::new(&mut rng)).collect();
+        // Perlin cells about a quarter of the image across, but not smaller than 8 pixels.
+        let cell = (self.width.min(self.height) as f64 / 4.0).max(8.0);
+        let (width, height) = (self.width.max(2) - 1, self.height.max(2) - 1);
+
+        let mut rows = Vec::with_capacity(self.height as usize);
+        for y in 0..self.height {
+            let mut row = Vec::with_capacity(self.width as usize * channels);
+            for x in 0..self.width {
+                for channel in 0..channels {
+                    let base = match self.pattern {
+                        Pattern::Noise => from[channel],
+                        Pattern::Gradient => {
+                            // From 0 in the corner the direction points away from to 1 in
+                            // the opposite one.
+                            let along =
+                                x as f64 / width as f64 * dx + y as f64 / height as f64 * dy;
+                            let t = (along - dx.min(0.0) - dy.min(0.0)) / (dx.abs() + dy.abs());
+                            from[channel] + (to[channel] - from[channel]) * t
+                        }
+                        Pattern::Perlin => {
+                            let t = perlin[channel].fractal(x as f64 / cell, y as f64 / cell);
+                            from[channel] + (to[channel] - from[channel]) * t
+                        }
+                    };
+                    let noise = rng.unit() * 256.0;
+                    let sample = base * (1.0 - self.entropy) + noise * self.entropy;
+                    row.push(sample.floor().clamp(0.0, 255.0) as u16);
+                }
+            }
+            rows.push(row);
+        }
+        PngBuilder::new(header).image_rows(rows).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Algorithm;
+    use crate::image::ImageData;
+    use crate::report;
+
+    fn carrier(pattern: Pattern, entropy: f64) -> Carrier {
+        Carrier {
+            pattern,
+            width: 64,
+            height: 48,
+            seed: 42,
+            entropy,
+            grayscale: false,
+        }
+    }
+
+    fn samples(carrier: Carrier) -> Vec<u8> {
+        let png = carrier.generate().unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        image.samples().iter().map(|&sample| sample as u8).collect()
+    }
+
+    #[test]
+    fn test_generate() {
+        for pattern in [Pattern::Noise, Pattern::Gradient, Pattern::Perlin] {
+            let smooth = samples(carrier(pattern, 0.0));
+            let noisy = samples(carrier(pattern, 1.0));
+            assert_eq!(smooth.len(), 64 * 48 * 3);
+            assert_eq!(smooth, samples(carrier(pattern, 0.0)));
+            assert!(report::entropy(&noisy) > 7.9);
+            assert!(report::entropy(&smooth) < report::entropy(&noisy));
+        }
+        let other_seed = Carrier {
+            seed: 43,
+            ..carrier(Pattern::Perlin, 0.1)
+        };
+        assert_ne!(samples(other_seed), samples(carrier(Pattern::Perlin, 0.1)));
+        let grey = Carrier {
+            grayscale: true,
+            ..carrier(Pattern::Gradient, 0.0)
+        };
+        assert_eq!(samples(grey).len(), 64 * 48);
+        assert!(carrier(Pattern::Noise, 1.5).generate().is_err());
+
+        // The same pixels on every platform, release after release.
+        let digest = Algorithm::Sha256.digest(&samples(carrier(Pattern::Perlin, 0.25)));
+        assert_eq!(
+            digest,
+            "ae2a8e258b1cab2d3dce18e283e602ed1e62bfc1c30b26ecd5fd8a8730e303b1"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Minimal CBOR (RFC 8949) codec covering the definite-length subset used by
+//! pngme's own chunks and by the C2PA assertions it inspects.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Negative(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Bool(bool),
+    Null,
+    Undefined,
+    Float(f64),
+}
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+impl Value {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Unsigned(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Value::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+    /// Looks up a text key in a map value.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map()?
+            .iter()
+            .find(|(k, _)| k.as_text() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_into(&mut bytes);
+        bytes
+    }
+
+    fn encode_into(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Value::Unsigned(value) => write_header(bytes, MAJOR_UNSIGNED, *value),
+            Value::Negative(value) => write_header(bytes, MAJOR_NEGATIVE, *value),
+            Value::Bytes(data) => {
+                write_header(bytes, MAJOR_BYTES, data.len() as u64);
+                bytes.extend_from_slice(data);
+            }
+            Value::Text(text) => {
+                write_header(bytes, MAJOR_TEXT, text.len() as u64);
+                bytes.extend_from_slice(text.as_bytes());
+            }
+            Value::Array(values) => {
+                write_header(bytes, MAJOR_ARRAY, values.len() as u64);
+                for value in values {
+                    value.encode_into(bytes);
+                }
+            }
+            Value::Map(entries) => {
+                write_header(bytes, MAJOR_MAP, entries.len() as u64);
+                for (key, value) in entries {
+                    key.encode_into(bytes);
+                    value.encode_into(bytes);
+                }
+            }
+            Value::Tag(tag, value) => {
+                write_header(bytes, MAJOR_TAG, *tag);
+                value.encode_into(bytes);
+            }
+            Value::Bool(false) => bytes.push(MAJOR_SIMPLE << 5 | 20),
+            Value::Bool(true) => bytes.push(MAJOR_SIMPLE << 5 | 21),
+            Value::Null => bytes.push(MAJOR_SIMPLE << 5 | 22),
+            Value::Undefined => bytes.push(MAJOR_SIMPLE << 5 | 23),
+            Value::Float(value) => {
+                bytes.push(MAJOR_SIMPLE << 5 | 27);
+                bytes.extend(value.to_be_bytes());
+            }
+        }
+    }
+
+    /// Decodes exactly one value; trailing bytes are an error.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ()> {
+        let mut reader = Reader { bytes, position: 0 };
+        let value = reader.value(0)?;
+        if reader.position == bytes.len() {
+            Ok(value)
+        } else {
+            Err(())
+        }
+    }
+}
+
+fn write_header(bytes: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    if argument < 24 {
+        bytes.push(major | argument as u8);
+    } else if argument <= u8::MAX as u64 {
+        bytes.push(major | 24);
+        bytes.push(argument as u8);
+    } else if argument <= u16::MAX as u64 {
+        bytes.push(major | 25);
+        bytes.extend((argument as u16).to_be_bytes());
+    } else if argument <= u32::MAX as u64 {
+        bytes.push(major | 26);
+        bytes.extend((argument as u32).to_be_bytes());
+    } else {
+        bytes.push(major | 27);
+        bytes.extend(argument.to_be_bytes());
+    }
+}
+
+const MAX_DEPTH: usize = 64;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], ()> {
+        let end = self.position.checked_add(count).ok_or(())?;
+        let slice = self.bytes.get(self.position..end).ok_or(())?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn argument(&mut self, additional: u8) -> Result<u64, ()> {
+        Ok(match additional {
+            0..=23 => additional as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err(()),
+        })
+    }
+
+    fn length(&mut self, additional: u8) -> Result<usize, ()> {
+        let length = usize::try_from(self.argument(additional)?).map_err(|_| ())?;
+        // Every element takes at least one byte, so this bounds allocations by input size.
+        if length > self.bytes.len() - self.position {
+            return Err(());
+        }
+        Ok(length)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, ()> {
+        if depth > MAX_DEPTH {
+            return Err(());
+        }
+        let initial = self.take(1)?[0];
+        let (major, additional) = (initial >> 5, initial & 0x1f);
+        Ok(match major {
+            MAJOR_UNSIGNED => Value::Unsigned(self.argument(additional)?),
+            MAJOR_NEGATIVE => Value::Negative(self.argument(additional)?),
+            MAJOR_BYTES => {
+                let length = self.length(additional)?;
+                Value::Bytes(self.take(length)?.to_vec())
+            }
+            MAJOR_TEXT => {
+                let length = self.length(additional)?;
+                Value::Text(String::from_utf8(self.take(length)?.to_vec()).map_err(|_| ())?)
+            }
+            MAJOR_ARRAY => {
+                let length = self.length(additional)?;
+                let mut values = Vec::with_capacity(length);
+                for _ in 0..length {
+                    values.push(self.value(depth + 1)?);
+                }
+                Value::Array(values)
+            }
+            MAJOR_MAP => {
+                let length = self.length(additional)?;
+                let mut entries = Vec::with_capacity(length);
+                for _ in 0..length {
+                    let key = self.value(depth + 1)?;
+                    let value = self.value(depth + 1)?;
+                    entries.push((key, value));
+                }
+                Value::Map(entries)
+            }
+            MAJOR_TAG => {
+                let tag = self.argument(additional)?;
+                Value::Tag(tag, Box::new(self.value(depth + 1)?))
+            }
+            _ => match additional {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                22 => Value::Null,
+                23 => Value::Undefined,
+                25 => Value::Float(half_to_f64(u16::from_be_bytes(
+                    self.take(2)?.try_into().unwrap(),
+                ))),
+                26 => Value::Float(f32::from_be_bytes(self.take(4)?.try_into().unwrap()) as f64),
+                27 => Value::Float(f64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+                _ => return Err(()),
+            },
+        })
+    }
+}
+
+fn half_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1f;
+    let mantissa = (half & 0x3ff) as f64;
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_small_values() {
+        assert_eq!(Value::Unsigned(10).encode(), vec![0x0a]);
+        assert_eq!(Value::Unsigned(1000).encode(), vec![0x19, 0x03, 0xe8]);
+        assert_eq!(Value::Negative(0).encode(), vec![0x20]);
+        assert_eq!(Value::Text("a".into()).encode(), vec![0x61, b'a']);
+        assert_eq!(Value::Bool(true).encode(), vec![0xf5]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = Value::Map(vec![
+            (Value::Text("alg".into()), Value::Text("sha256".into())),
+            (Value::Text("hash".into()), Value::Bytes(vec![1, 2, 3])),
+            (
+                Value::Text("exclusions".into()),
+                Value::Array(vec![Value::Map(vec![(
+                    Value::Text("start".into()),
+                    Value::Unsigned(u32::MAX as u64 + 1),
+                )])]),
+            ),
+            (Value::Unsigned(7), Value::Tag(1, Box::new(Value::Null))),
+        ]);
+        assert_eq!(Value::decode(&value.encode()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_get() {
+        let value = Value::Map(vec![(Value::Text("pad".into()), Value::Bytes(vec![0; 4]))]);
+        assert_eq!(
+            value.get("pad").and_then(Value::as_bytes),
+            Some(&[0u8; 4][..])
+        );
+        assert!(value.get("hash").is_none());
+    }
+
+    #[test]
+    fn test_decode_half_float() {
+        assert_eq!(
+            Value::decode(&[0xf9, 0x3c, 0x00]).unwrap(),
+            Value::Float(1.0)
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(Value::decode(&[]).is_err());
+        assert!(Value::decode(&[0x62, b'a']).is_err());
+        assert!(Value::decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(Value::decode(&[0x01, 0x02]).is_err());
+        assert!(Value::decode(&[0x5f]).is_err());
+    }
+}
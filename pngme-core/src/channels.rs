@@ -0,0 +1,157 @@
+//! `pngme channels`: each channel of an image as a greyscale image of its own, and back.
+//! Looking at the planes one at a time is the usual first step of visual steganalysis, since
+//! a message in the low bits of one channel often shows up as noise only there.
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::{ColorType, Colorimetry, ImageData, ImageHeader};
+use crate::png::Png;
+
+/// The channels of an image of `color_type`, in sample order. Indexed images are split into
+/// the red, green, blue and alpha of their palette entries.
+pub fn names(color_type: ColorType) -> &'static [&'static str] {
+    match color_type {
+        ColorType::Grayscale => &["gray"],
+        ColorType::GrayscaleAlpha => &["gray", "alpha"],
+        ColorType::Rgb => &["red", "green", "blue"],
+        ColorType::Rgba | ColorType::Indexed => &["red", "green", "blue", "alpha"],
+    }
+}
+
+fn new_png(header: ImageHeader, samples: Vec<u16>) -> Result<Png, String> {
+    let mut png = Png::from_chunks(vec![
+        Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+        Chunk::new(ChunkType::IDAT, vec![]),
+        Chunk::new(ChunkType::IEND, vec![]),
+    ]);
+    let colorimetry = Colorimetry::from_png(&png);
+    ImageData::from_samples(header, samples, colorimetry)
+        .write_to(&mut png)
+        .map_err(|()| "failed to write the image data".to_string())?;
+    Ok(png)
+}
+
+/// One greyscale image per channel, named as [`names`] gives them, at the image's bit depth
+/// (8 bits for the palette channels of an indexed image).
+pub fn split(png: &Png) -> Result<Vec<(&'static str, Png)>, String> {
+    let image = ImageData::from_png(png)
+        .map_err(|()| "the file has no decodable image data".to_string())?;
+    let mut header = ImageHeader {
+        color_type: ColorType::Grayscale,
+        interlaced: false,
+        ..*image.header()
+    };
+    let samples: Vec<u16> = if image.header().color_type == ColorType::Indexed {
+        header.bit_depth = 8;
+        image
+            .to_encoded()
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.map(|value| (value * 255.0).round() as u16))
+            .collect()
+    } else {
+        image.samples().to_vec()
+    };
+    let names = names(image.header().color_type);
+    names
+        .iter()
+        .enumerate()
+        .map(|(channel, &name)| {
+            let plane = samples
+                .iter()
+                .skip(channel)
+                .step_by(names.len())
+                .copied()
+                .collect();
+            Ok((name, new_png(header, plane)?))
+        })
+        .collect()
+}
+
+/// Interleaves one to four greyscale images of the same size and bit depth into a greyscale,
+/// greyscale with alpha, truecolour or truecolour with alpha image, in that order of
+/// channels.
+pub fn merge(planes: &[Png]) -> Result<Png, String> {
+    let color_type = match planes.len() {
+        1 => ColorType::Grayscale,
+        2 => ColorType::GrayscaleAlpha,
+        3 => ColorType::Rgb,
+        4 => ColorType::Rgba,
+        count => return Err(format!("{} channels given; merge takes 1 to 4", count)),
+    };
+    let images = planes
+        .iter()
+        .enumerate()
+        .map(|(channel, plane)| {
+            ImageData::from_png(plane)
+                .map_err(|()| format!("channel {} has no decodable image data", channel + 1))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let first = *images[0].header();
+    for (channel, image) in images.iter().enumerate() {
+        let header = image.header();
+        if header.color_type != ColorType::Grayscale {
+            return Err(format!(
+                "channel {} is {}, not greyscale",
+                channel + 1,
+                header.color_type
+            ));
+        }
+        if (header.width, header.height, header.bit_depth)
+            != (first.width, first.height, first.bit_depth)
+        {
+            return Err(format!(
+                "channel {} is {}x{} at {} bits, but channel 1 is {}x{} at {} bits",
+                channel + 1,
+                header.width,
+                header.height,
+                header.bit_depth,
+                first.width,
+                first.height,
+                first.bit_depth
+            ));
+        }
+    }
+    let header = ImageHeader {
+        color_type,
+        interlaced: false,
+        ..first
+    };
+    if !color_type.allows_bit_depth(header.bit_depth) {
+        return Err(format!(
+            "{} images can't be {}-bit",
+            color_type, header.bit_depth
+        ));
+    }
+    let pixel_count = first.width as usize * first.height as usize;
+    let samples = (0..pixel_count)
+        .flat_map(|pixel| images.iter().map(move |image| image.samples()[pixel]))
+        .collect();
+    new_png(header, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::rgba8_png;
+
+    #[test]
+    fn test_split_and_merge() {
+        let pixels = [1, 2, 3, 4, 5, 6, 7, 8];
+        let png = rgba8_png(2, 1, &pixels).unwrap();
+        let planes = split(&png).unwrap();
+        let names: Vec<&str> = planes.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["red", "green", "blue", "alpha"]);
+        let green = ImageData::from_png(&planes[1].1).unwrap();
+        assert_eq!(green.header().color_type, ColorType::Grayscale);
+        assert_eq!(green.samples(), &[2, 6]);
+
+        let planes: Vec<Png> = planes.into_iter().map(|(_, plane)| plane).collect();
+        let merged = ImageData::from_png(&merge(&planes).unwrap()).unwrap();
+        assert_eq!(merged.header().color_type, ColorType::Rgba);
+        assert_eq!(merged.samples(), pixels.map(u16::from));
+
+        assert!(merge(&[png]).is_err());
+        assert!(merge(&[]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Text in whatever encoding a message was written in: the one a byte-order mark names,
+//! UTF-16 if it has the NUL bytes of mostly-ASCII UTF-16, UTF-8 if it is valid UTF-8, and
+//! otherwise chardetng's guess among the legacy encodings such as Latin-1 and Shift_JIS.
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use zeroize::Zeroizing;
+
+pub struct Decoded {
+    pub text: Zeroizing<String>,
+    /// The WHATWG name of the encoding used, like `UTF-8` or `Shift_JIS`.
+    pub encoding: &'static str,
+    /// Whether some bytes weren't valid in it and became U+FFFD.
+    pub had_errors: bool,
+}
+
+/// UTF-16 without a byte-order mark, if at least a third of the code units have a zero high
+/// byte and the low bytes don't: nearly ASCII text, which other encodings never look like.
+fn unmarked_utf16(data: &[u8]) -> Option<&'static Encoding> {
+    if data.len() < 4 || !data.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = data.len() / 2;
+    let zeros = |parity: usize| {
+        data.iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    if odd * 3 >= units && even == 0 {
+        Some(UTF_16LE)
+    } else if even * 3 >= units && odd == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// The encoding `data` is most likely in.
+pub fn detect(data: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(data) {
+        return encoding;
+    }
+    // Checked first because ASCII in UTF-16 is also valid UTF-8, NULs and all.
+    if let Some(encoding) = unmarked_utf16(data) {
+        return encoding;
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(data, true);
+    detector.guess(None, Utf8Detection::Allow)
+}
+
+/// Decodes `data` as the encoding `label` names, like `latin1` or `shift_jis`, or as the one
+/// [`detect`] picks. A byte-order mark overrides the label, and is dropped.
+pub fn decode(data: &[u8], label: Option<&str>) -> Result<Decoded, String> {
+    let encoding = match label {
+        Some(label) => Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| format!("unknown text encoding `{}`", label))?,
+        None => detect(data),
+    };
+    let (text, encoding, had_errors) = encoding.decode(data);
+    Ok(Decoded {
+        text: Zeroizing::new(text.into_owned()),
+        encoding: encoding.name(),
+        had_errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let decoded = decode("grüße".as_bytes(), None).unwrap();
+        assert_eq!(
+            (decoded.text.as_str(), decoded.encoding),
+            ("grüße", "UTF-8")
+        );
+
+        let latin1 = b"Le caf\xe9 est d\xe9j\xe0 froid, n'est-ce pas\xa0?";
+        let decoded = decode(latin1, None).unwrap();
+        assert_eq!(decoded.encoding, "windows-1252");
+        assert!(decoded.text.starts_with("Le café"));
+
+        let utf16: Vec<u8> = "hidden message"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let decoded = decode(&utf16, None).unwrap();
+        assert_eq!(
+            (decoded.text.as_str(), decoded.encoding),
+            ("hidden message", "UTF-16LE")
+        );
+        let mut marked = vec![0xfe, 0xff];
+        marked.extend("hi".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(&marked, None).unwrap().text.as_str(), "hi");
+
+        let shift_jis = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x81\x41\x90\xa2\x8a\x45";
+        let decoded = decode(shift_jis, None).unwrap();
+        assert_eq!(
+            (decoded.text.as_str(), decoded.encoding),
+            ("こんにちは、世界", "Shift_JIS")
+        );
+
+        let forced = decode(shift_jis, Some("latin1")).unwrap();
+        assert_eq!(forced.encoding, "windows-1252");
+        assert!(decode(b"x", Some("klingon")).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    fmt::{Debug, Display, Formatter},
+    string::FromUtf8Error,
+};
+
+use crate::chunk_type::ChunkType;
+use crate::hex;
+use crc::crc32::checksum_ieee;
+
+/// The largest length the PNG specification allows, 2^31 - 1.
+pub const MAX_LENGTH: u32 = (1 << 31) - 1;
+
+/// The longest keyword the PNG specification allows, for text chunks, iCCP and sPLT.
+pub const MAX_KEYWORD_LEN: usize = 79;
+
+/// How much of the payload `Debug` shows before eliding the rest.
+const DEBUG_DATA_BYTES: usize = 32;
+
+#[derive(Clone)]
+pub struct Chunk {
+    length: u32,
+    chunk_type: ChunkType,
+    data: Vec<u8>,
+    crc: u32,
+}
+
+/// What is wrong with the bytes given to [`Chunk::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkError {
+    /// The input ends before the chunk does.
+    Truncated,
+    /// Over the specification's 2^31 - 1.
+    LengthTooLarge(u32),
+    /// The type bytes aren't all ASCII letters.
+    InvalidType,
+    CrcMismatch,
+}
+
+impl Display for ChunkError {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ChunkError::Truncated => fmt.write_str("truncated"),
+            ChunkError::LengthTooLarge(length) => {
+                write!(fmt, "length {} is over the limit of 2^31 - 1", length)
+            }
+            ChunkError::InvalidType => fmt.write_str("invalid chunk type"),
+            ChunkError::CrcMismatch => fmt.write_str("CRC mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
+pub struct TakenFrom {
+    pub chunk: Chunk,
+    pub bytes_remaining: u32,
+}
+
+fn four_bytes_from_slice(slice: &[u8]) -> Result<[u8; 4], ()> {
+    if let Ok(result) = slice.try_into() {
+        Ok(result)
+    } else {
+        Err(())
+    }
+}
+
+impl Chunk {
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    pub fn data_as_string(&self) -> Result<String, FromUtf8Error> {
+        String::from_utf8(self.data.clone())
+    }
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(self.length.to_be_bytes());
+        bytes.extend(self.chunk_type.bytes());
+        bytes.extend(&self.data);
+        bytes.extend(self.crc.to_be_bytes());
+        bytes
+    }
+
+    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Self {
+        let mut data_for_crc = Vec::new();
+        data_for_crc.extend_from_slice(&chunk_type.bytes());
+        data_for_crc.extend(data.iter());
+        let crc = checksum_ieee(&data_for_crc);
+
+        Self {
+            length: data.len() as u32,
+            chunk_type,
+            data,
+            crc,
+        }
+    }
+
+    pub fn take_from(bytes: &[u8]) -> Result<TakenFrom, ()> {
+        Self::take_from_with(bytes, true)
+    }
+
+    /// Like [`Chunk::take_from`], but when `verify_crc` is false the stored CRC is kept
+    /// without being checked against the data.
+    pub fn take_from_with(bytes: &[u8], verify_crc: bool) -> Result<TakenFrom, ()> {
+        Self::parse(bytes, verify_crc).map_err(|_| ())
+    }
+
+    /// [`Chunk::take_from_with`], saying what was wrong.
+    pub fn parse(bytes: &[u8], verify_crc: bool) -> Result<TakenFrom, ChunkError> {
+        if bytes.len() < 12 {
+            return Err(ChunkError::Truncated);
+        }
+        let first_four_bytes = four_bytes_from_slice(&bytes[0..4]).unwrap();
+        let length = u32::from_be_bytes(first_four_bytes);
+        if length > MAX_LENGTH {
+            return Err(ChunkError::LengthTooLarge(length));
+        }
+        let second_four_bytes = four_bytes_from_slice(&bytes[4..8]).unwrap();
+        let chunk_type =
+            ChunkType::try_from(second_four_bytes).map_err(|_| ChunkError::InvalidType)?;
+        let crc_start = 8 + length as usize;
+        if bytes.len() - 12 < length as usize {
+            return Err(ChunkError::Truncated);
+        }
+        let provided_crc_bytes = four_bytes_from_slice(&bytes[crc_start..crc_start + 4]).unwrap();
+        let provided_crc = u32::from_be_bytes(provided_crc_bytes);
+        if verify_crc && provided_crc != checksum_ieee(&bytes[4..crc_start]) {
+            return Err(ChunkError::CrcMismatch);
+        }
+        Ok(TakenFrom {
+            chunk: Self {
+                length,
+                chunk_type,
+                data: bytes[8..crc_start].to_vec(),
+                crc: provided_crc,
+            },
+            bytes_remaining: bytes.len() as u32 - 4 - 4 - length - 4,
+        })
+    }
+}
+
+/// The data length a chunk starting at `bytes` claims, if its type is four ASCII letters and
+/// it fits in `bytes`: what can be checked before checksumming anything.
+pub fn claimed_length(bytes: &[u8]) -> Option<usize> {
+    let length = u32::from_be_bytes(bytes.get(..4)?.try_into().unwrap());
+    let letters = bytes.get(4..8)?.iter().all(u8::is_ascii_alphabetic);
+    let fits = (length as usize)
+        .checked_add(12)
+        .is_some_and(|end| end <= bytes.len());
+    (letters && length <= MAX_LENGTH && fits).then_some(length as usize)
+}
+
+/// The Latin-1 bytes of a keyword such as a text chunk's key or a profile or palette name,
+/// checked against the specification's rules: 1 to 79 printable characters, without
+/// leading, trailing or repeated spaces.
+pub fn keyword_bytes(keyword: &str) -> Result<Vec<u8>, String> {
+    let printable = |c: char| matches!(c as u32, 32..=126 | 161..=255);
+    if keyword.is_empty()
+        || keyword.chars().count() > MAX_KEYWORD_LEN
+        || !keyword.chars().all(printable)
+        || keyword.starts_with(' ')
+        || keyword.ends_with(' ')
+        || keyword.contains("  ")
+    {
+        return Err(format!(
+            "`{}` is not a valid keyword: it must be 1 to {} printable Latin-1 characters, \
+             without leading, trailing or repeated spaces",
+            keyword, MAX_KEYWORD_LEN
+        ));
+    }
+    Ok(keyword.chars().map(|c| c as u8).collect())
+}
+
+/// A tEXt chunk for `text` under `keyword`, or an uncompressed iTXt chunk if `text` isn't
+/// all Latin-1.
+pub fn text_chunk(keyword: &str, text: &str) -> Result<Chunk, String> {
+    let mut data = keyword_bytes(keyword)?;
+    data.push(0);
+    if text.chars().all(|c| (c as u32) < 256) {
+        data.extend(text.chars().map(|c| c as u8));
+        return Ok(Chunk::new(ChunkType::TEXT, data));
+    }
+    // Compression flag and method, then empty language and translated keyword.
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(text.as_bytes());
+    Ok(Chunk::new(ChunkType::ITXT, data))
+}
+
+/// The keyword of a tEXt, zTXt or iTXt chunk, or `None` for any other chunk.
+pub fn text_keyword(chunk: &Chunk) -> Option<&[u8]> {
+    let text_types = [ChunkType::TEXT, ChunkType::ZTXT, ChunkType::ITXT];
+    if !text_types.contains(chunk.chunk_type()) {
+        return None;
+    }
+    chunk.data().split(|&byte| byte == 0).next()
+}
+
+impl TryFrom<&Vec<u8>> for Chunk {
+    type Error = ();
+    fn try_from(bytes: &Vec<u8>) -> Result<Self, ()> {
+        if let Ok(TakenFrom {
+            chunk,
+            bytes_remaining,
+        }) = Self::take_from(&bytes[..])
+        {
+            if bytes_remaining == 0 {
+                Ok(chunk)
+            } else {
+                Err(())
+            }
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// A one-line summary that works for any payload; use `data_as_string` for the text itself.
+impl Display for Chunk {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{} ({} bytes, crc={:#010x})",
+            self.chunk_type, self.length, self.crc
+        )
+    }
+}
+
+impl Debug for Chunk {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let shown = &self.data[..self.data.len().min(DEBUG_DATA_BYTES)];
+        let elided = if shown.len() < self.data.len() {
+            "..."
+        } else {
+            ""
+        };
+        fmt.debug_struct("Chunk")
+            .field("chunk_type", &format_args!("{}", self.chunk_type))
+            .field("length", &self.length)
+            .field("data", &format_args!("{}{}", hex::encode(shown), elided))
+            .field("crc", &format_args!("{:#010x}", self.crc))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use proptest::prelude::*;
+    use std::str::FromStr;
+
+    fn testing_chunk() -> Chunk {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        Chunk::try_from(chunk_data.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_chunk_length() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.length(), 42);
+    }
+
+    #[test]
+    fn test_chunk_type() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
+    }
+
+    #[test]
+    fn test_chunk_string() {
+        let chunk = testing_chunk();
+        let chunk_string = chunk.data_as_string().unwrap();
+        let expected_chunk_string = String::from("This is where your secret message will be!");
+        assert_eq!(chunk_string, expected_chunk_string);
+    }
+
+    #[test]
+    fn test_chunk_crc() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_valid_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref()).unwrap();
+
+        let chunk_string = chunk.data_as_string().unwrap();
+        let expected_chunk_string = String::from("This is where your secret message will be!");
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
+        assert_eq!(chunk_string, expected_chunk_string);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_invalid_chunk_from_bytes() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656333;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+
+        assert!(chunk.is_err());
+    }
+
+    #[test]
+    pub fn test_chunk_trait_impls() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk: Chunk = TryFrom::try_from(chunk_data.as_ref()).unwrap();
+
+        let _chunk_string = format!("{}", chunk);
+    }
+
+    #[test]
+    fn test_chunk_length_limit() {
+        let mut bytes = testing_chunk().as_bytes();
+        bytes[0] |= 0x80;
+        assert!(Chunk::take_from(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_chunk_formatting() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.to_string(), "RuSt (42 bytes, crc=0xabd1d84e)");
+        let binary = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0xff; 40]);
+        assert_eq!(format!("{}", binary), "ruSt (40 bytes, crc=0xfce72d8c)");
+        assert_eq!(
+            format!("{:?}", binary),
+            format!(
+                "Chunk {{ chunk_type: ruSt, length: 40, data: {}..., crc: 0xfce72d8c }}",
+                "ff".repeat(32)
+            )
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(chunk in testing::chunk()) {
+            let bytes = chunk.as_bytes();
+            prop_assert_eq!(Chunk::try_from(&bytes).unwrap().as_bytes(), bytes);
+        }
+
+        #[test]
+        fn prop_take_from_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = Chunk::take_from(&bytes);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use core::str::FromStr;
+use std::fmt::{Display, Formatter};
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct ChunkType {
+    bytes: [u8; 4],
+}
+
+/// Defines a `ChunkType` constant and a `KnownChunk` variant for each standard type.
+macro_rules! known_chunks {
+    ($($constant:ident $variant:ident $bytes:literal,)*) => {
+        impl ChunkType {
+            $(pub const $constant: ChunkType = ChunkType { bytes: *$bytes };)*
+
+            /// The standard chunk this type names, if any; the match is case-sensitive.
+            pub fn known(&self) -> Option<KnownChunk> {
+                match &self.bytes {
+                    $($bytes => Some(KnownChunk::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// Chunk types defined by the PNG specification, including APNG, and the registered
+        /// extensions `extensions` and `legacy` read.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KnownChunk {
+            $($variant,)*
+        }
+
+        impl KnownChunk {
+            pub const ALL: &'static [KnownChunk] = &[$(KnownChunk::$variant,)*];
+
+            pub fn chunk_type(self) -> ChunkType {
+                match self {
+                    $(KnownChunk::$variant => ChunkType::$constant,)*
+                }
+            }
+        }
+    };
+}
+
+known_chunks! {
+    IHDR Ihdr b"IHDR",
+    PLTE Plte b"PLTE",
+    IDAT Idat b"IDAT",
+    IEND Iend b"IEND",
+    CHRM Chrm b"cHRM",
+    GAMA Gama b"gAMA",
+    ICCP Iccp b"iCCP",
+    SBIT Sbit b"sBIT",
+    SRGB Srgb b"sRGB",
+    CICP Cicp b"cICP",
+    MDCV Mdcv b"mDCV",
+    CLLI Clli b"cLLI",
+    BKGD Bkgd b"bKGD",
+    HIST Hist b"hIST",
+    TRNS Trns b"tRNS",
+    PHYS Phys b"pHYs",
+    SPLT Splt b"sPLT",
+    EXIF Exif b"eXIf",
+    TIME Time b"tIME",
+    ITXT Itxt b"iTXt",
+    TEXT Text b"tEXt",
+    ZTXT Ztxt b"zTXt",
+    ACTL Actl b"acTL",
+    FCTL Fctl b"fcTL",
+    FDAT Fdat b"fdAT",
+    OFFS Offs b"oFFs",
+    PCAL Pcal b"pCAL",
+    SCAL Scal b"sCAL",
+    GIFG Gifg b"gIFg",
+    GIFX Gifx b"gIFx",
+    GIFT Gift b"gIFt",
+}
+
+/// Checks the spec rule that a chunk type is four ASCII letters, naming the first byte that isn't.
+fn validate(bytes: &[u8]) -> Result<[u8; 4], String> {
+    let bytes: [u8; 4] = bytes
+        .try_into()
+        .map_err(|_| format!("a chunk type is 4 bytes, not {}", bytes.len()))?;
+    match bytes.iter().position(|byte| !byte.is_ascii_alphabetic()) {
+        Some(index) => Err(format!(
+            "byte {} of the chunk type is {:#04x}, not an ASCII letter",
+            index, bytes[index]
+        )),
+        None => Ok(bytes),
+    }
+}
+
+/// Sets or clears a property bit, which is bit 5 of its byte (set means lowercase).
+fn with_case(byte: u8, uppercase: bool) -> u8 {
+    if uppercase {
+        byte.to_ascii_uppercase()
+    } else {
+        byte.to_ascii_lowercase()
+    }
+}
+
+impl ChunkType {
+    pub fn try_from(bytes: [u8; 4]) -> Result<Self, String> {
+        Ok(Self {
+            bytes: validate(&bytes)?,
+        })
+    }
+    /// `base` with its critical, public and safe-to-copy bits replaced; the reserved bit and the
+    /// letters themselves are kept.
+    pub fn with_property_bits(
+        base: ChunkType,
+        critical: bool,
+        public: bool,
+        safe_to_copy: bool,
+    ) -> ChunkType {
+        let [ancillary, private, reserved, copy] = base.bytes;
+        ChunkType {
+            bytes: [
+                with_case(ancillary, critical),
+                with_case(private, public),
+                reserved,
+                with_case(copy, !safe_to_copy),
+            ],
+        }
+    }
+    pub fn bytes(&self) -> [u8; 4] {
+        self.bytes
+    }
+    pub fn is_critical(&self) -> bool {
+        self.bytes[0].is_ascii_uppercase()
+    }
+    pub fn is_public(&self) -> bool {
+        self.bytes[1].is_ascii_uppercase()
+    }
+    pub fn is_reserved_bit_valid(&self) -> bool {
+        self.bytes[2].is_ascii_uppercase()
+    }
+    pub fn is_safe_to_copy(&self) -> bool {
+        self.bytes[3].is_ascii_lowercase()
+    }
+    pub fn is_valid(&self) -> bool {
+        self.is_reserved_bit_valid()
+    }
+}
+
+impl AsRef<[u8]> for ChunkType {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl TryFrom<[u8; 4]> for ChunkType {
+    type Error = String;
+    fn try_from(value: [u8; 4]) -> Result<Self, String> {
+        Ok(Self {
+            bytes: validate(&value)?,
+        })
+    }
+}
+
+impl FromStr for ChunkType {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            bytes: validate(str.as_bytes())?,
+        })
+    }
+}
+
+impl Display for ChunkType {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        if let Ok(str) = std::str::from_utf8(&self.bytes) {
+            write!(fmt, "{}", str)
+        } else {
+            Err(std::fmt::Error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_chunk_type_from_bytes() {
+        let expected = [82, 117, 83, 116];
+        let actual = ChunkType::try_from([82, 117, 83, 116]).unwrap();
+
+        assert_eq!(expected, actual.bytes());
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_str() {
+        let expected = ChunkType::try_from([82, 117, 83, 116]).unwrap();
+        let actual = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_critical() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.is_critical());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_not_critical() {
+        let chunk = ChunkType::from_str("ruSt").unwrap();
+        assert!(!chunk.is_critical());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_public() {
+        let chunk = ChunkType::from_str("RUSt").unwrap();
+        assert!(chunk.is_public());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_not_public() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(!chunk.is_public());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_reserved_bit_valid() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.is_reserved_bit_valid());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_reserved_bit_invalid() {
+        let chunk = ChunkType::from_str("Rust").unwrap();
+        assert!(!chunk.is_reserved_bit_valid());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_safe_to_copy() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_unsafe_to_copy() {
+        let chunk = ChunkType::from_str("RuST").unwrap();
+        assert!(!chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_valid_chunk_is_valid() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.is_valid());
+    }
+
+    #[test]
+    pub fn test_invalid_chunk_is_valid() {
+        let chunk = ChunkType::from_str("Rust").unwrap();
+        assert!(!chunk.is_valid());
+
+        let chunk = ChunkType::from_str("Ru1t");
+        assert!(chunk.is_err());
+    }
+
+    #[test]
+    pub fn test_invalid_chunk_type_errors() {
+        assert_eq!(
+            ChunkType::from_str("Ru1t").unwrap_err(),
+            "byte 2 of the chunk type is 0x31, not an ASCII letter"
+        );
+        assert_eq!(
+            ChunkType::try_from([82, 117, 0, 116]).unwrap_err(),
+            "byte 2 of the chunk type is 0x00, not an ASCII letter"
+        );
+        assert_eq!(
+            ChunkType::from_str("RuStt").unwrap_err(),
+            "a chunk type is 4 bytes, not 5"
+        );
+        assert!(ChunkType::from_str("RuSé").is_err());
+    }
+
+    #[test]
+    pub fn test_chunk_type_with_property_bits() {
+        let base = ChunkType::from_str("ruSt").unwrap();
+        let derived = ChunkType::with_property_bits(base, true, true, false);
+        assert_eq!(derived.to_string(), "RUST");
+        assert!(derived.is_critical() && derived.is_public() && !derived.is_safe_to_copy());
+        let derived = ChunkType::with_property_bits(derived, false, false, true);
+        assert_eq!(derived, base);
+        let reserved = ChunkType::from_str("Rust").unwrap();
+        assert!(!ChunkType::with_property_bits(reserved, true, true, true).is_reserved_bit_valid());
+    }
+
+    #[test]
+    pub fn test_chunk_type_string() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(&chunk.to_string(), "RuSt");
+    }
+
+    #[test]
+    pub fn test_known_chunks() {
+        assert_eq!(ChunkType::TEXT.to_string(), "tEXt");
+        let chunk = ChunkType::from_str("IDAT").unwrap();
+        assert_eq!(chunk.known(), Some(KnownChunk::Idat));
+        assert_eq!(KnownChunk::Idat.chunk_type(), chunk);
+        assert_eq!(ChunkType::from_str("IdAT").unwrap().known(), None);
+        assert_eq!(ChunkType::from_str("ruSt").unwrap().known(), None);
+    }
+
+    #[test]
+    pub fn test_chunk_type_trait_impls() {
+        let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
+        let chunk_type_2: ChunkType = FromStr::from_str("RuSt").unwrap();
+        let _chunk_string = format!("{}", chunk_type_1);
+        let _are_chunks_equal = chunk_type_1 == chunk_type_2;
+    }
+}
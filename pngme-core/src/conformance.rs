@@ -0,0 +1,153 @@
+//! A differential check of the pixel decoder against the `png` crate, for `pngme
+//! conformance`: both decode a file with no transformations, so palette indices and sub-byte
+//! samples are compared as stored, and any disagreement, over whether the file decodes at
+//! all or over a single sample, is a finding worth a look.
+
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+use png::{Decoder, Transformations};
+
+use crate::image::{self, ImageData};
+use crate::png::Png;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Both decoders gave the same samples.
+    Match,
+    /// Both decoders rejected the file.
+    BothRejected,
+    /// Only pngme rejected the file.
+    OursRejected,
+    /// Only the reference decoder rejected the file, with its error.
+    ReferenceRejected(String),
+    /// The decoders disagree on the width, height or samples per pixel.
+    ShapeMismatch {
+        ours: (u32, u32, usize),
+        reference: (u32, u32, usize),
+    },
+    /// The first sample that differs.
+    SampleMismatch {
+        x: u32,
+        y: u32,
+        channel: usize,
+        ours: u16,
+        reference: u16,
+    },
+}
+
+impl Outcome {
+    pub fn agrees(&self) -> bool {
+        matches!(self, Outcome::Match | Outcome::BothRejected)
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Match => write!(fmt, "samples match"),
+            Outcome::BothRejected => write!(fmt, "rejected by both decoders"),
+            Outcome::OursRejected => write!(fmt, "rejected by pngme but not the reference"),
+            Outcome::ReferenceRejected(error) => {
+                write!(fmt, "rejected by the reference but not pngme: {}", error)
+            }
+            Outcome::ShapeMismatch { ours, reference } => write!(
+                fmt,
+                "pngme decodes {}x{} with {} channels, the reference {}x{} with {}",
+                ours.0, ours.1, ours.2, reference.0, reference.1, reference.2
+            ),
+            Outcome::SampleMismatch {
+                x,
+                y,
+                channel,
+                ours,
+                reference,
+            } => write!(
+                fmt,
+                "channel {} of pixel ({}, {}) is {} for pngme but {} for the reference",
+                channel, x, y, ours, reference
+            ),
+        }
+    }
+}
+
+/// The image in `bytes` as the `png` crate decodes it: its width, height, samples per pixel
+/// and samples.
+fn reference(bytes: &[u8]) -> Result<(u32, u32, usize, Vec<u16>), String> {
+    let mut decoder = Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(Transformations::IDENTITY);
+    let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+    let size = reader
+        .output_buffer_size()
+        .ok_or("the image is too large to decode")?;
+    let mut buffer = vec![0; size];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|error| error.to_string())?;
+    let channels = info.color_type.samples();
+    let bit_depth = info.bit_depth as u8;
+    let row_samples = info.width as usize * channels;
+    let samples = buffer
+        .chunks(info.line_size)
+        .take(info.height as usize)
+        .flat_map(|row| image::unpack(row, bit_depth, row_samples))
+        .collect();
+    Ok((info.width, info.height, channels, samples))
+}
+
+/// Decodes `bytes` with both decoders and compares the results.
+pub fn compare(bytes: &[u8]) -> Outcome {
+    let ours = Png::try_from(bytes).and_then(|png| ImageData::from_png(&png));
+    let (ours, reference) = match (ours, reference(bytes)) {
+        (Err(()), Err(_)) => return Outcome::BothRejected,
+        (Err(()), Ok(_)) => return Outcome::OursRejected,
+        (Ok(_), Err(error)) => return Outcome::ReferenceRejected(error),
+        (Ok(ours), Ok(reference)) => (ours, reference),
+    };
+    let (width, height, channels, samples) = reference;
+    let shape = (ours.width(), ours.height(), ours.channels());
+    if shape != (width, height, channels) || samples.len() != ours.samples().len() {
+        return Outcome::ShapeMismatch {
+            ours: shape,
+            reference: (width, height, channels),
+        };
+    }
+    let Some(index) = (0..samples.len()).find(|&i| samples[i] != ours.samples()[i]) else {
+        return Outcome::Match;
+    };
+    let pixel = index / channels;
+    Outcome::SampleMismatch {
+        x: (pixel % width as usize) as u32,
+        y: (pixel / width as usize) as u32,
+        channel: index % channels,
+        ours: ours.samples()[index],
+        reference: samples[index],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PngBuilder;
+    use crate::image::{ColorType, ImageHeader};
+
+    #[test]
+    fn test_compare() {
+        let header = ImageHeader {
+            width: 3,
+            height: 2,
+            bit_depth: 2,
+            color_type: ColorType::Indexed,
+            interlaced: false,
+        };
+        let png = PngBuilder::new(header)
+            .palette([[0, 0, 0], [9, 9, 9], [255, 255, 255]])
+            .image_rows([[0, 1, 2], [2, 1, 0]])
+            .build()
+            .unwrap();
+        let bytes = png.as_bytes();
+        assert_eq!(compare(&bytes), Outcome::Match);
+        assert_eq!(compare(&bytes[..20]), Outcome::BothRejected);
+        assert!(compare(&bytes).agrees());
+    }
+}
@@ -0,0 +1,497 @@
+//! `pngme convert`: rewrite the image in another colour type and bit depth, for a smaller
+//! file or for a carrier with more low bits to hide a message in.
+//!
+//! Greyscale targets use the Rec. 709 luma of colour pixels, targets without alpha keep
+//! simple transparency as a tRNS colour where they can, and indexed targets get a palette
+//! by median cut when the image has more colours than fit. Each way the result differs from
+//! the original is reported.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::background::{self, palette_len};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::geometry;
+use crate::image::{Color, ColorType, ImageData, ImageHeader};
+use crate::png::Png;
+
+/// A colour type and bit depth, written like `rgba8`, `gray16` or `indexed4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub color_type: ColorType,
+    pub bit_depth: u8,
+}
+
+const NAMES: [(&str, ColorType); 7] = [
+    ("graya", ColorType::GrayscaleAlpha),
+    ("greya", ColorType::GrayscaleAlpha),
+    ("gray", ColorType::Grayscale),
+    ("grey", ColorType::Grayscale),
+    ("rgba", ColorType::Rgba),
+    ("rgb", ColorType::Rgb),
+    ("indexed", ColorType::Indexed),
+];
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "`{}` is not a colour type and bit depth like rgba8, rgb16, gray8, graya8 or indexed8",
+                s
+            )
+        };
+        let (name, color_type) = NAMES
+            .iter()
+            .find(|(name, _)| {
+                s.strip_prefix(name)
+                    .is_some_and(|depth| depth.bytes().all(|byte| byte.is_ascii_digit()))
+            })
+            .ok_or_else(invalid)?;
+        let bit_depth = s[name.len()..].parse().map_err(|_| invalid())?;
+        if !color_type.allows_bit_depth(bit_depth) {
+            return Err(format!("{} images can't be {}-bit", color_type, bit_depth));
+        }
+        Ok(Self {
+            color_type: *color_type,
+            bit_depth,
+        })
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let name = match self.color_type {
+            ColorType::Grayscale => "gray",
+            ColorType::GrayscaleAlpha => "graya",
+            ColorType::Rgb => "rgb",
+            ColorType::Rgba => "rgba",
+            ColorType::Indexed => "indexed",
+        };
+        write!(fmt, "{}{}", name, self.bit_depth)
+    }
+}
+
+/// A 16-bit sample at `bit_depth` bits, rounded to nearest.
+fn reduce(sample: u16, bit_depth: u8) -> u16 {
+    let max = (1u32 << bit_depth) - 1;
+    ((sample as u32 * max + 32767) / 65535) as u16
+}
+
+/// A sample at `bit_depth` bits, scaled up to 16.
+fn expand(sample: u16, bit_depth: u8) -> u16 {
+    let max = (1u32 << bit_depth) - 1;
+    (sample as u32 * 65535 / max) as u16
+}
+
+fn luma([red, green, blue]: [u16; 3]) -> u16 {
+    if red == green && green == blue {
+        return red;
+    }
+    (0.2126 * red as f64 + 0.7152 * green as f64 + 0.0722 * blue as f64).round() as u16
+}
+
+/// The bKGD colour as 16-bit RGB, looked up in the palette for indexed images.
+fn background_rgb(png: &Png, header: &ImageHeader) -> Option<[u16; 3]> {
+    let color = background::background(png)?.ok()?;
+    match (header.color_type, color) {
+        (ColorType::Indexed, Color::Level(index)) => {
+            let palette = png.chunk_by_type(ChunkType::PLTE)?.data();
+            let entry = palette.get(index as usize * 3..index as usize * 3 + 3)?;
+            Some([entry[0], entry[1], entry[2]].map(|sample| sample as u16 * 257))
+        }
+        (_, Color::Level(level)) => Some([expand(level, header.bit_depth); 3]),
+        (_, Color::Rgb(rgb)) => Some(rgb.map(|sample| expand(sample, header.bit_depth))),
+    }
+}
+
+fn distance(a: [u8; 4], b: [u8; 4]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+fn nearest(palette: &[[u8; 4]], color: [u8; 4]) -> u16 {
+    (0..palette.len())
+        .min_by_key(|&index| distance(palette[index], color))
+        .unwrap_or(0) as u16
+}
+
+/// At most `size` colours standing for `colours` and their pixel counts: the box of colours
+/// with the widest channel is split at its median, over and over, and each box becomes its
+/// weighted average.
+fn median_cut(colours: Vec<([u8; 4], u64)>, size: usize) -> Vec<[u8; 4]> {
+    let widest = |colours: &[([u8; 4], u64)]| {
+        (0..4)
+            .map(|channel| {
+                let values = colours.iter().map(|(colour, _)| colour[channel]);
+                let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+                (channel, range)
+            })
+            .max_by_key(|&(_, range)| range)
+            .unwrap_or((0, 0))
+    };
+    let mut boxes = vec![colours];
+    while boxes.len() < size {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colours)| colours.len() > 1)
+            .map(|(index, colours)| (index, widest(colours)))
+            .max_by_key(|&(_, (_, range))| range)
+            .map(|(index, (channel, _))| (index, channel))
+        else {
+            break;
+        };
+        let mut lower = boxes.swap_remove(index);
+        lower.sort_by_key(|(colour, _)| colour[channel]);
+        let total: u64 = lower.iter().map(|(_, count)| count).sum();
+        let mut running = 0;
+        let median = lower
+            .iter()
+            .position(|(_, count)| {
+                running += count;
+                running * 2 >= total
+            })
+            .unwrap_or(0);
+        let upper = lower.split_off((median + 1).min(lower.len() - 1));
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+    boxes
+        .iter()
+        .map(|colours| {
+            let total: u64 = colours.iter().map(|(_, count)| count).sum();
+            let mut average = [0; 4];
+            for (channel, value) in average.iter_mut().enumerate() {
+                let sum: u64 = colours
+                    .iter()
+                    .map(|(colour, count)| colour[channel] as u64 * count)
+                    .sum();
+                *value = ((sum + total / 2) / total) as u8;
+            }
+            average
+        })
+        .collect()
+}
+
+/// The image as indexed colour: palette indices, the palette, and its alpha values with the
+/// translucent entries first so the tRNS chunk stays short.
+fn quantize(
+    pixels: &[[u16; 4]],
+    bit_depth: u8,
+    notes: &mut Vec<String>,
+) -> (Vec<u16>, Vec<[u8; 4]>) {
+    let colours: Vec<[u8; 4]> = pixels
+        .iter()
+        .map(|pixel| pixel.map(|sample| reduce(sample, 8) as u8))
+        .collect();
+    let mut counts: Vec<([u8; 4], u64)> = Vec::new();
+    let mut seen = HashMap::new();
+    for &colour in &colours {
+        let index = *seen.entry(colour).or_insert_with(|| {
+            counts.push((colour, 0));
+            counts.len() - 1
+        });
+        counts[index].1 += 1;
+    }
+    let size = 1usize << bit_depth;
+    let unique = counts.len();
+    let mut palette: Vec<[u8; 4]> = if unique <= size {
+        counts.into_iter().map(|(colour, _)| colour).collect()
+    } else {
+        notes.push(format!(
+            "reduced {} colours to a palette of {}",
+            unique, size
+        ));
+        median_cut(counts, size)
+    };
+    palette.sort_by_key(|colour| colour[3] == 255);
+    let mut lookup: HashMap<[u8; 4], u16> = HashMap::new();
+    let indices = colours
+        .iter()
+        .map(|&colour| {
+            *lookup
+                .entry(colour)
+                .or_insert_with(|| nearest(&palette, colour))
+        })
+        .collect();
+    (indices, palette)
+}
+
+impl Png {
+    /// Rewrites the image data as `color_type` at `bit_depth` bits per sample, converting
+    /// PLTE, tRNS, bKGD and hIST to match. Returns a note for each way the result differs
+    /// from the original, or for each chunk dropped.
+    pub fn convert(&mut self, color_type: ColorType, bit_depth: u8) -> Result<Vec<String>, String> {
+        if !color_type.allows_bit_depth(bit_depth) {
+            return Err(format!("{} images can't be {}-bit", color_type, bit_depth));
+        }
+        let image = ImageData::from_png(self)
+            .map_err(|()| "the file has no decodable image data".to_string())?;
+        let old = *image.header();
+        let pixels: Vec<[u16; 4]> = image
+            .to_encoded()
+            .pixels
+            .iter()
+            .map(|pixel| pixel.map(|value| (value * 65535.0).round() as u16))
+            .collect();
+        let background = background_rgb(self, &old);
+        let had_histogram = self.chunk_by_type(ChunkType::HIST).is_some();
+        let mut notes = Vec::new();
+
+        let colour_lost = matches!(color_type, ColorType::Grayscale | ColorType::GrayscaleAlpha)
+            && pixels
+                .iter()
+                .any(|&[red, green, blue, _]| red != green || green != blue);
+        if colour_lost {
+            notes.push(String::from(
+                "colour was lost: the greys are each pixel's Rec. 709 luma",
+            ));
+        }
+        // Palette entries are 8 bits a sample, whatever the depth of the indices.
+        let sample_depth = if color_type == ColorType::Indexed {
+            8
+        } else {
+            bit_depth
+        };
+        let precision_lost = pixels
+            .iter()
+            .flatten()
+            .any(|&sample| expand(reduce(sample, sample_depth), sample_depth) != sample);
+        if precision_lost {
+            notes.push(format!("samples were rounded to {} bits", sample_depth));
+        }
+
+        let header = ImageHeader {
+            color_type,
+            bit_depth,
+            ..old
+        };
+        let colour_samples = |[red, green, blue, _]: [u16; 4]| -> Vec<u16> {
+            match color_type {
+                ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                    vec![reduce(luma([red, green, blue]), bit_depth)]
+                }
+                _ => [red, green, blue]
+                    .map(|sample| reduce(sample, bit_depth))
+                    .to_vec(),
+            }
+        };
+        let mut palette = Vec::new();
+        let mut transparent = None;
+        let samples: Vec<u16> = match color_type {
+            ColorType::Indexed => {
+                let (indices, quantized) = quantize(&pixels, bit_depth, &mut notes);
+                palette = quantized;
+                indices
+            }
+            ColorType::GrayscaleAlpha | ColorType::Rgba => pixels
+                .iter()
+                .flat_map(|&pixel| {
+                    let mut samples = colour_samples(pixel);
+                    samples.push(reduce(pixel[3], bit_depth));
+                    samples
+                })
+                .collect(),
+            ColorType::Grayscale | ColorType::Rgb => {
+                let opaque = |pixel: &[u16; 4]| pixel[3] == u16::MAX;
+                let binary = pixels.iter().all(|pixel| opaque(pixel) || pixel[3] == 0);
+                // A single colour no opaque pixel uses can stand for every transparent one.
+                let key = pixels
+                    .iter()
+                    .find(|pixel| !opaque(pixel))
+                    .map(|&pixel| colour_samples(pixel))
+                    .filter(|key| {
+                        binary
+                            && !pixels
+                                .iter()
+                                .any(|pixel| opaque(pixel) && colour_samples(*pixel) == *key)
+                    });
+                if pixels.iter().any(|pixel| !opaque(pixel)) && key.is_none() {
+                    notes.push(String::from(
+                        "transparency was lost: the pixels were flattened onto black",
+                    ));
+                }
+                transparent = key.clone();
+                pixels
+                    .iter()
+                    .flat_map(|&pixel| match &key {
+                        Some(key) if !opaque(&pixel) => key.clone(),
+                        Some(_) => colour_samples(pixel),
+                        None => {
+                            let alpha = pixel[3] as u32;
+                            let flattened =
+                                pixel.map(|sample| (sample as u32 * alpha / 65535) as u16);
+                            colour_samples(flattened)
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        ImageData::from_samples(header, samples, *image.colorimetry())
+            .write_to(self)
+            .map_err(|()| "failed to write the image data".to_string())?;
+        let changed = old.color_type != color_type || old.bit_depth != bit_depth;
+        if changed && self.chunk_by_type(ChunkType::SBIT).is_some() {
+            notes.push(String::from(
+                "dropped sBIT: it describes the old colour type and bit depth",
+            ));
+        }
+        let rewritten = [
+            ChunkType::PLTE,
+            ChunkType::TRNS,
+            ChunkType::BKGD,
+            ChunkType::HIST,
+        ];
+        self.retain_chunks(|chunk| {
+            let chunk_type = *chunk.chunk_type();
+            let stale =
+                rewritten.contains(&chunk_type) || (changed && chunk_type == ChunkType::SBIT);
+            !stale
+        });
+
+        if color_type == ColorType::Indexed {
+            let rgb: Vec<u8> = palette
+                .iter()
+                .flat_map(|colour| &colour[..3])
+                .copied()
+                .collect();
+            self.insert_before_data(Chunk::new(ChunkType::PLTE, rgb));
+            let alpha: Vec<u8> = palette
+                .iter()
+                .map(|colour| colour[3])
+                .take_while(|&alpha| alpha < 255)
+                .collect();
+            if !alpha.is_empty() {
+                self.insert_ancillary(Chunk::new(ChunkType::TRNS, alpha));
+            }
+        } else if let Some(key) = transparent {
+            let color = match key[..] {
+                [level] => Color::Level(level),
+                [red, green, blue] => Color::Rgb([red, green, blue]),
+                _ => unreachable!("greyscale and truecolour have one or three samples"),
+            };
+            self.insert_ancillary(Chunk::new(ChunkType::TRNS, color.to_bytes(color_type)));
+        }
+        if let Some(rgb) = background {
+            let color = match color_type {
+                ColorType::Indexed => {
+                    let colour = rgb.map(|sample| reduce(sample, 8) as u8);
+                    Color::Level(nearest(&palette, [colour[0], colour[1], colour[2], 255]))
+                }
+                ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                    Color::Level(reduce(luma(rgb), bit_depth))
+                }
+                ColorType::Rgb | ColorType::Rgba => {
+                    Color::Rgb(rgb.map(|sample| reduce(sample, bit_depth)))
+                }
+            };
+            background::set_background(self, color)?;
+        }
+        if had_histogram {
+            if color_type == ColorType::Indexed {
+                let indices = ImageData::from_png(self)
+                    .map_err(|()| "failed to read back the image data".to_string())?;
+                let histogram = geometry::histogram(indices.samples(), palette_len(self))?;
+                self.insert_ancillary(histogram.to_chunk());
+                notes.push(String::from("recounted hIST for the new palette"));
+            } else {
+                notes.push(String::from(
+                    "dropped hIST: the image no longer has a palette",
+                ));
+            }
+        }
+        notes.extend(geometry::drop_unsafe_to_copy(self));
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    // A 2x2 8-bit RGBA image: opaque red, opaque grey, transparent black twice.
+    fn rgba() -> Png {
+        let pixels = [255, 0, 0, 255, 128, 128, 128, 255, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut png = crate::image::rgba8_png(2, 2, &pixels).unwrap();
+        png.insert_ancillary(Chunk::new(ChunkType::BKGD, [0, 0, 0, 0, 0, 255].to_vec()));
+        png
+    }
+
+    #[test]
+    fn test_parse() {
+        let target: Target = "rgba16".parse().unwrap();
+        assert_eq!((target.color_type, target.bit_depth), (ColorType::Rgba, 16));
+        assert_eq!("graya8".parse::<Target>().unwrap().to_string(), "graya8");
+        assert!("indexed16".parse::<Target>().is_err());
+        assert!("rgb".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_convert_to_indexed() {
+        let mut png = rgba();
+        assert!(png.convert(ColorType::Indexed, 2).unwrap().is_empty());
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.header().color_type, ColorType::Indexed);
+        assert_eq!(image.samples(), &[1, 2, 0, 0]);
+        assert_eq!(
+            png.chunk_by_type(ChunkType::PLTE).unwrap().data(),
+            &[0, 0, 0, 255, 0, 0, 128, 128, 128]
+        );
+        assert_eq!(png.chunk_by_type(ChunkType::TRNS).unwrap().data(), &[0]);
+        // bKGD's blue isn't in the palette; the nearest entry stands in.
+        assert_eq!(png.chunk_by_type(ChunkType::BKGD).unwrap().data(), &[2]);
+
+        let notes = png.convert(ColorType::Indexed, 1).unwrap();
+        assert_eq!(notes, ["reduced 3 colours to a palette of 2"]);
+        assert_eq!(background::palette_len(&png), 2);
+    }
+
+    #[test]
+    fn test_convert_to_grayscale() {
+        let mut png = rgba();
+        let notes = png.convert(ColorType::Grayscale, 16).unwrap();
+        assert_eq!(notes.len(), 1);
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.samples(), &[13933, 32896, 0, 0]);
+        // The transparent pixels become a tRNS grey no opaque pixel uses.
+        assert_eq!(png.chunk_by_type(ChunkType::TRNS).unwrap().data(), &[0, 0]);
+        assert_eq!(
+            png.chunk_by_type(ChunkType::BKGD).unwrap().data(),
+            &[18, 124]
+        );
+
+        assert!(png.convert(ColorType::Rgba, 16).unwrap().is_empty());
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(&image.samples()[8..], &[0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let header = ImageHeader {
+            width: 1,
+            height: 1,
+            bit_depth: 8,
+            color_type: ColorType::GrayscaleAlpha,
+            interlaced: false,
+        };
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::none());
+        encoder.write_all(&[0, 200, 128]).unwrap();
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+            Chunk::new(ChunkType::IDAT, encoder.finish().unwrap()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        let notes = png.convert(ColorType::Grayscale, 8).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(ImageData::from_png(&png).unwrap().samples(), &[100]);
+    }
+}
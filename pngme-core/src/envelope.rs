@@ -0,0 +1,555 @@
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use age::secrecy::{ExposeSecret, SecretString};
+use age::{scrypt, x25519};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use zeroize::Zeroizing;
+
+/// Every age file starts with this version line.
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1\n";
+/// Starts a message readable with any of several passphrases: a count byte, then for each
+/// passphrase a big-endian `u32` length and an age file holding the content key, then the
+/// message as an age file encrypted to that key.
+const PASSPHRASE_MAGIC: &[u8] = b"pngme-passphrases/v1\n";
+/// Starts a message encrypted with a shared key, such as one from `pngme handshake`: a
+/// 12-byte nonce follows, then the ChaCha20-Poly1305 ciphertext.
+const SESSION_MAGIC: &[u8] = b"pngme-session/v1\n";
+const NONCE_LEN: usize = 12;
+/// Starts the plaintext line that carries an expiry date, ahead of any encryption so that
+/// expired payloads can be found without the key. An encrypted message carries the line a
+/// second time inside the ciphertext, which is the copy that counts.
+const EXPIRY_PREFIX: &[u8] = b"pngme-expires:";
+/// Starts the header declaring the message's MIME type, ahead of the message itself and so
+/// encrypted along with it. A flag byte follows: `TYPED` and then the type and a newline, or
+/// `UNTYPED` for a message that would otherwise be mistaken for having a header.
+const TYPE_MAGIC: &[u8] = b"pngme-type/v1\n";
+const UNTYPED: u8 = 0;
+const TYPED: u8 = 1;
+
+/// A calendar date in UTC, written as `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self::from_days(seconds / 86_400)
+    }
+
+    /// The date `days` after 1970-01-01, using Howard Hinnant's `civil_from_days`.
+    fn from_days(days: u64) -> Self {
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+    fn from_str(date: &str) -> Result<Self, String> {
+        let invalid = || format!("`{}` is not a date in the form YYYY-MM-DD", date);
+        let mut parts = date.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(invalid());
+        }
+        let year: u16 = year.parse().map_err(|_| invalid())?;
+        let month: u8 = month.parse().map_err(|_| invalid())?;
+        let day: u8 = day.parse().map_err(|_| invalid())?;
+        if !(1..=12).contains(&month) || day == 0 || day > Self::days_in_month(year, month) {
+            return Err(invalid());
+        }
+        Ok(Self { year, month, day })
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(fmt, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Puts a line in front of `message` saying it expires at the end of `expires`.
+pub fn with_expiry(expires: Date, message: &[u8]) -> Zeroizing<Vec<u8>> {
+    let line = format!("{}\n", expires);
+    let mut data = Zeroizing::new(Vec::with_capacity(
+        EXPIRY_PREFIX.len() + line.len() + message.len(),
+    ));
+    data.extend_from_slice(EXPIRY_PREFIX);
+    data.extend_from_slice(line.as_bytes());
+    data.extend_from_slice(message);
+    data
+}
+
+/// The expiry written by `with_expiry`, if any, and the message after it. A payload that
+/// starts like an expiry line but doesn't hold a valid date is passed through unchanged.
+pub fn split_expiry(data: &[u8]) -> (Option<Date>, &[u8]) {
+    let parsed = data.strip_prefix(EXPIRY_PREFIX).and_then(|rest| {
+        let end = rest.iter().position(|&byte| byte == b'\n')?;
+        let date = std::str::from_utf8(&rest[..end]).ok()?.parse().ok()?;
+        Some((date, &rest[end + 1..]))
+    });
+    match parsed {
+        Some((date, message)) => (Some(date), message),
+        None => (None, data),
+    }
+}
+
+/// The expiry that `with_expiry` sealed inside a decrypted message, and the message after
+/// it. `outer` is the copy in front of the ciphertext, which anyone can change, so a
+/// message whose two dates disagree has been tampered with and is rejected.
+pub fn sealed_expiry(
+    outer: Option<Date>,
+    plaintext: &[u8],
+) -> Result<(Option<Date>, &[u8]), String> {
+    let (inner, message) = split_expiry(plaintext);
+    if outer.is_some() && outer != inner {
+        return Err("the message's expiry date was changed after it was encrypted".into());
+    }
+    Ok((inner, message))
+}
+
+/// Puts a header in front of `message` declaring it to be of type `mime`, which must be
+/// valid by `mime::validate`. With no type the message is left as it is, unless it starts
+/// like a header itself and so needs one saying it has no type.
+pub fn with_content_type(mime: Option<&str>, message: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut data = Zeroizing::new(Vec::with_capacity(
+        TYPE_MAGIC.len() + 1 + mime.map_or(0, |mime| mime.len() + 1) + message.len(),
+    ));
+    match mime {
+        Some(mime) => {
+            data.extend_from_slice(TYPE_MAGIC);
+            data.push(TYPED);
+            data.extend_from_slice(mime.as_bytes());
+            data.push(b'\n');
+        }
+        None if message.starts_with(TYPE_MAGIC) => {
+            data.extend_from_slice(TYPE_MAGIC);
+            data.push(UNTYPED);
+        }
+        None => {}
+    }
+    data.extend_from_slice(message);
+    data
+}
+
+/// The type written by `with_content_type`, if any, and the message after it. A payload
+/// with a malformed header is passed through unchanged.
+pub fn split_content_type(data: &[u8]) -> (Option<&str>, &[u8]) {
+    let parsed = data
+        .strip_prefix(TYPE_MAGIC)
+        .and_then(|rest| match rest.split_first()? {
+            (&UNTYPED, message) => Some((None, message)),
+            (&TYPED, rest) => {
+                let end = rest.iter().position(|&byte| byte == b'\n')?;
+                let mime = std::str::from_utf8(&rest[..end]).ok()?;
+                crate::mime::validate(mime).ok()?;
+                Some((Some(mime), &rest[end + 1..]))
+            }
+            _ => None,
+        });
+    parsed.unwrap_or((None, data))
+}
+
+/// Whether a payload expiring at the end of `expires` has expired by `today`.
+pub fn is_expired(expires: Date, today: Date) -> bool {
+    today > expires
+}
+
+pub fn is_envelope(data: &[u8]) -> bool {
+    data.starts_with(AGE_MAGIC)
+        || data.starts_with(PASSPHRASE_MAGIC)
+        || data.starts_with(SESSION_MAGIC)
+}
+
+fn age_encrypt<'a>(
+    recipients: impl Iterator<Item = &'a dyn age::Recipient>,
+    message: &[u8],
+) -> Result<Vec<u8>, String> {
+    let encryptor =
+        age::Encryptor::with_recipients(recipients).map_err(|error| error.to_string())?;
+    let mut ciphertext = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .map_err(|error| error.to_string())?;
+    writer
+        .write_all(message)
+        .and_then(|()| writer.finish())
+        .map_err(|error| error.to_string())?;
+    Ok(ciphertext)
+}
+
+fn age_decrypt<'a>(
+    identities: impl Iterator<Item = &'a dyn age::Identity>,
+    ciphertext: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let decryptor = age::Decryptor::new_buffered(ciphertext).map_err(|error| error.to_string())?;
+    let mut reader = decryptor
+        .decrypt(identities)
+        .map_err(|error| error.to_string())?;
+    // The plaintext is shorter than the ciphertext, so the buffer never reallocates and
+    // leaves no unwiped copies behind.
+    let mut message = Zeroizing::new(Vec::with_capacity(ciphertext.len()));
+    reader
+        .read_to_end(&mut message)
+        .map_err(|error| error.to_string())?;
+    Ok(message)
+}
+
+/// Encrypts `message` so that it can be read with the identity of any `age1...` public key
+/// in `recipients` or with any of `passphrases`.
+pub fn encrypt(
+    recipients: &[String],
+    passphrases: &[Zeroizing<String>],
+    message: &[u8],
+) -> Result<Vec<u8>, String> {
+    encrypt_with_work_factor(recipients, passphrases, message, None)
+}
+
+/// `encrypt`, with scrypt's log2 work factor in place of age's calibrated default.
+fn encrypt_with_work_factor(
+    recipients: &[String],
+    passphrases: &[Zeroizing<String>],
+    message: &[u8],
+    work_factor: Option<u8>,
+) -> Result<Vec<u8>, String> {
+    let mut recipients = recipients
+        .iter()
+        .map(|key| {
+            key.parse::<x25519::Recipient>()
+                .map_err(|error| format!("invalid recipient `{}`: {}", key, error))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if passphrases.is_empty() {
+        return age_encrypt(
+            recipients
+                .iter()
+                .map(|recipient| recipient as &dyn age::Recipient),
+            message,
+        );
+    }
+    let count = u8::try_from(passphrases.len()).map_err(|_| "at most 255 passphrases")?;
+    // age allows only one passphrase per file, so the message goes to a fresh key instead,
+    // and each passphrase gets its own copy of that key.
+    let content_key = x25519::Identity::generate();
+    recipients.push(content_key.to_public());
+    let mut envelope = PASSPHRASE_MAGIC.to_vec();
+    envelope.push(count);
+    for passphrase in passphrases {
+        let mut recipient = scrypt::Recipient::new(SecretString::from(passphrase.to_string()));
+        if let Some(work_factor) = work_factor {
+            recipient.set_work_factor(work_factor);
+        }
+        let wrapped = age_encrypt(
+            std::iter::once(&recipient as &dyn age::Recipient),
+            content_key.to_string().expose_secret().as_bytes(),
+        )?;
+        envelope.extend_from_slice(&(wrapped.len() as u32).to_be_bytes());
+        envelope.extend_from_slice(&wrapped);
+    }
+    envelope.extend(age_encrypt(
+        recipients
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient),
+        message,
+    )?);
+    Ok(envelope)
+}
+
+/// The passphrase-wrapped copies of the content key, if `data` starts with them, and the age
+/// file after them.
+fn split_wrapped_keys(data: &[u8]) -> Result<(Vec<&[u8]>, &[u8]), String> {
+    let Some(mut rest) = data.strip_prefix(PASSPHRASE_MAGIC) else {
+        return Ok((Vec::new(), data));
+    };
+    let truncated = || String::from("the passphrase header is truncated");
+    let (&count, after_count) = rest.split_first().ok_or_else(truncated)?;
+    rest = after_count;
+    let mut wrapped = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let length = rest.get(..4).ok_or_else(truncated)?;
+        let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+        let key = rest.get(4..4 + length).ok_or_else(truncated)?;
+        wrapped.push(key);
+        rest = &rest[4 + length..];
+    }
+    Ok((wrapped, rest))
+}
+
+/// Decrypts with the identities in an age identity file, as written by `age-keygen`.
+pub fn decrypt(identity_file: &str, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let identities = age::IdentityFile::from_buffer(identity_file.as_bytes())
+        .map_err(|error| format!("invalid identity file: {}", error))?
+        .into_identities()
+        .map_err(|error| format!("invalid identity file: {}", error))?;
+    let (_, ciphertext) = split_wrapped_keys(ciphertext)?;
+    age_decrypt(
+        identities
+            .iter()
+            .map(|identity| identity.as_ref() as &dyn age::Identity),
+        ciphertext,
+    )
+}
+
+/// Decrypts with one of the passphrases given to `encrypt`, or the passphrase of a plain
+/// age file.
+pub fn decrypt_with_passphrase(
+    passphrase: &str,
+    ciphertext: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let identity = scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+    let (wrapped, ciphertext) = split_wrapped_keys(ciphertext)?;
+    if wrapped.is_empty() {
+        return age_decrypt(std::iter::once(&identity as &dyn age::Identity), ciphertext);
+    }
+    let content_key = wrapped
+        .iter()
+        .find_map(|key| age_decrypt(std::iter::once(&identity as &dyn age::Identity), key).ok())
+        .ok_or("the passphrase does not match")?;
+    let content_key = std::str::from_utf8(&content_key)
+        .ok()
+        .and_then(|key| key.parse::<x25519::Identity>().ok())
+        .ok_or("the passphrase header holds an invalid key")?;
+    age_decrypt(
+        std::iter::once(&content_key as &dyn age::Identity),
+        ciphertext,
+    )
+}
+
+/// Encrypts `message` with a 32-byte key both parties already hold.
+pub fn encrypt_with_key(key: &[u8; 32], message: &[u8]) -> Result<Vec<u8>, String> {
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|error| format!("cannot get random bytes: {}", error))?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), message)
+        .map_err(|_| "encryption failed")?;
+    let mut envelope = SESSION_MAGIC.to_vec();
+    envelope.extend_from_slice(&nonce);
+    envelope.extend(ciphertext);
+    Ok(envelope)
+}
+
+pub fn decrypt_with_key(key: &[u8; 32], ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let sealed = ciphertext
+        .strip_prefix(SESSION_MAGIC)
+        .filter(|sealed| sealed.len() >= NONCE_LEN)
+        .ok_or("the message is not encrypted with a session key")?;
+    let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| "the session key does not match, or the message was modified".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity_file(identity: &x25519::Identity) -> String {
+        format!("# test key\n{}\n", identity.to_string().expose_secret())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let alice = x25519::Identity::generate();
+        let bob = x25519::Identity::generate();
+        let recipients = [alice.to_public().to_string(), bob.to_public().to_string()];
+        let ciphertext = encrypt(&recipients, &[], b"dead drop").unwrap();
+        assert!(is_envelope(&ciphertext));
+        assert_eq!(
+            *decrypt(&identity_file(&alice), &ciphertext).unwrap(),
+            b"dead drop"
+        );
+        assert_eq!(
+            *decrypt(&identity_file(&bob), &ciphertext).unwrap(),
+            b"dead drop"
+        );
+    }
+
+    #[test]
+    fn test_passphrases() {
+        let alice = x25519::Identity::generate();
+        let passphrases =
+            ["correct horse", "battery staple"].map(|p| Zeroizing::new(p.to_string()));
+        let recipients = [alice.to_public().to_string()];
+        let ciphertext =
+            encrypt_with_work_factor(&recipients, &passphrases, b"dead drop", Some(2)).unwrap();
+        assert!(is_envelope(&ciphertext));
+        for passphrase in &passphrases {
+            assert_eq!(
+                *decrypt_with_passphrase(passphrase, &ciphertext).unwrap(),
+                b"dead drop"
+            );
+        }
+        assert_eq!(
+            *decrypt(&identity_file(&alice), &ciphertext).unwrap(),
+            b"dead drop"
+        );
+        assert!(decrypt_with_passphrase("wrong", &ciphertext).is_err());
+        assert!(decrypt_with_passphrase("correct horse", &ciphertext[..30]).is_err());
+    }
+
+    #[test]
+    fn test_session_key() {
+        let key = [7; 32];
+        let ciphertext = encrypt_with_key(&key, b"dead drop").unwrap();
+        assert!(is_envelope(&ciphertext));
+        assert_ne!(ciphertext, encrypt_with_key(&key, b"dead drop").unwrap());
+        assert_eq!(*decrypt_with_key(&key, &ciphertext).unwrap(), b"dead drop");
+        assert!(decrypt_with_key(&[8; 32], &ciphertext).is_err());
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_with_key(&key, &tampered).is_err());
+        assert!(decrypt_with_key(&key, b"dead drop").is_err());
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20_453).to_string(), "2025-12-31");
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!((date.year, date.month, date.day), (2024, 2, 29));
+        for invalid in [
+            "2023-02-29",
+            "2025-13-01",
+            "2025-1-01",
+            "25-01-01",
+            "2025-01-01-",
+        ] {
+            assert!(invalid.parse::<Date>().is_err(), "{}", invalid);
+        }
+        assert!("2025-12-31".parse::<Date>().unwrap() < "2026-01-01".parse().unwrap());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let expires: Date = "2025-12-31".parse().unwrap();
+        let data = with_expiry(expires, b"dead drop");
+        assert_eq!(&data[..], b"pngme-expires:2025-12-31\ndead drop");
+        assert_eq!(split_expiry(&data), (Some(expires), &b"dead drop"[..]));
+        assert_eq!(split_expiry(b"dead drop"), (None, &b"dead drop"[..]));
+        let bogus = b"pngme-expires:soon\nx";
+        assert_eq!(split_expiry(bogus), (None, &bogus[..]));
+        assert!(!is_expired(expires, expires));
+        assert!(is_expired(expires, "2026-01-01".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_sealed_expiry() {
+        let key = [7; 32];
+        let expires: Date = "2025-12-31".parse().unwrap();
+        let sealed = encrypt_with_key(&key, &with_expiry(expires, b"dead drop")).unwrap();
+        let payload = with_expiry(expires, &sealed);
+
+        let (outer, ciphertext) = split_expiry(&payload);
+        let plaintext = decrypt_with_key(&key, ciphertext).unwrap();
+        assert_eq!(
+            sealed_expiry(outer, &plaintext),
+            Ok((Some(expires), &b"dead drop"[..]))
+        );
+        // Moving the readable date on, or adding one, is caught by the sealed copy.
+        let later = "2099-01-01".parse().unwrap();
+        assert!(sealed_expiry(Some(later), &plaintext).is_err());
+        let unsealed = decrypt_with_key(&key, &encrypt_with_key(&key, b"x").unwrap()).unwrap();
+        assert!(sealed_expiry(Some(later), &unsealed).is_err());
+        // Stripping the readable date leaves the sealed one in force.
+        assert_eq!(
+            sealed_expiry(None, &plaintext),
+            Ok((Some(expires), &b"dead drop"[..]))
+        );
+        // And the sealed date can't be edited without breaking the authentication tag.
+        let mut tampered = sealed.clone();
+        tampered[SESSION_MAGIC.len() + NONCE_LEN + EXPIRY_PREFIX.len()] ^= 1;
+        assert!(decrypt_with_key(&key, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_content_type() {
+        let data = with_content_type(Some("image/png"), b"\x89PNG");
+        assert_eq!(&data[..], b"pngme-type/v1\n\x01image/png\n\x89PNG");
+        assert_eq!(
+            split_content_type(&data),
+            (Some("image/png"), &b"\x89PNG"[..])
+        );
+        assert_eq!(&with_content_type(None, b"dead drop")[..], b"dead drop");
+        assert_eq!(split_content_type(b"dead drop"), (None, &b"dead drop"[..]));
+        let bogus = b"pngme-type/v1\n\x01nonsense\nx";
+        assert_eq!(split_content_type(bogus), (None, &bogus[..]));
+    }
+
+    #[test]
+    fn test_content_type_lookalike() {
+        for message in [
+            &b"pngme-type/v1\n\x01text/plain\nnot a header"[..],
+            b"pngme-type/v1\n\x00",
+            b"pngme-type:text/plain\n",
+        ] {
+            for mime in [None, Some("text/plain")] {
+                let data = with_content_type(mime, message);
+                assert_eq!(split_content_type(&data), (mime, message));
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrong_identity() {
+        let recipient = x25519::Identity::generate().to_public().to_string();
+        let ciphertext = encrypt(&[recipient], &[], b"dead drop").unwrap();
+        let eve = x25519::Identity::generate();
+        assert!(decrypt(&identity_file(&eve), &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(encrypt(&["age1nope".to_string()], &[], b"").is_err());
+        assert!(encrypt(&[], &[], b"").is_err());
+        let identity = identity_file(&x25519::Identity::generate());
+        assert!(decrypt(&identity, b"not an age file").is_err());
+        assert!(decrypt("AGE-SECRET-KEY-BOGUS", b"").is_err());
+        assert!(!is_envelope(b"plain text"));
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::png::Png;
+
+/// Written alongside the payloads by `pngme explode`, listing the chunks in file order.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The payload's file name, relative to the manifest.
+    pub file: String,
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    /// The original length and CRC, for reference; rebuilding uses the payload as it is now.
+    pub length: u32,
+    pub crc: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub chunks: Vec<Entry>,
+}
+
+/// `NNN_type.bin`, numbered from zero so that the files sort in chunk order.
+pub fn file_name(index: usize, chunk_type: &str) -> String {
+    format!("{:03}_{}.bin", index, chunk_type)
+}
+
+impl Manifest {
+    pub fn of(png: &Png) -> Self {
+        let chunks = png
+            .chunks()
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let chunk_type = chunk.chunk_type().to_string();
+                Entry {
+                    file: file_name(index, &chunk_type),
+                    chunk_type,
+                    length: chunk.length(),
+                    crc: format!("{:08x}", chunk.crc()),
+                }
+            })
+            .collect();
+        Self { chunks }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|error| format!("invalid manifest: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+
+    #[test]
+    fn test_manifest() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        let manifest = Manifest::of(&png);
+        assert_eq!(manifest.chunks[0].file, "000_IHDR.bin");
+        assert_eq!(manifest.chunks[1].file, "001_IEND.bin");
+        assert_eq!(manifest.chunks[1].crc, "ae426082");
+        let json = manifest.to_json();
+        assert!(json.contains("\"type\": \"IHDR\""));
+        assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+        assert!(Manifest::from_json("[]").is_err());
+    }
+}
@@ -0,0 +1,414 @@
+//! The registered extension chunks scientific and scanning software writes: oFFs, sCAL and
+//! pCAL, from the PNG Extensions document.
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// The extensions' signed integers exclude -2^31, like the specification's.
+fn read_i32(bytes: &[u8]) -> Result<i32, String> {
+    let value = i32::from_be_bytes(bytes.try_into().unwrap());
+    if value == i32::MIN {
+        return Err("-2147483648 is outside the range a PNG integer allows".into());
+    }
+    Ok(value)
+}
+
+/// Checks that `text` is an ASCII floating-point number in the form the extensions allow:
+/// an optional sign, digits with at most one point, then an optional exponent.
+fn check_float(text: &str, what: &str) -> Result<(), String> {
+    let invalid = || format!("`{}` is not a valid {}", text, what);
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+    let mantissa = mantissa.strip_prefix(['+', '-']).unwrap_or(mantissa);
+    let digits = mantissa.replacen('.', "", 1);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if let Some(exponent) = exponent {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if exponent.is_empty() || !exponent.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetUnit {
+    Pixel,
+    Micrometre,
+}
+
+/// oFFs: where the image sits on a larger page or scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageOffset {
+    pub x: i32,
+    pub y: i32,
+    pub unit: OffsetUnit,
+}
+
+impl ImageOffset {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() != 9 {
+            return Err(format!("oFFs is {} bytes, not 9", data.len()));
+        }
+        let unit = match data[8] {
+            0 => OffsetUnit::Pixel,
+            1 => OffsetUnit::Micrometre,
+            unit => return Err(format!("oFFs unit {} is not 0 or 1", unit)),
+        };
+        Ok(Self {
+            x: read_i32(&data[..4])?,
+            y: read_i32(&data[4..8])?,
+            unit,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.x.to_be_bytes().to_vec();
+        data.extend_from_slice(&self.y.to_be_bytes());
+        data.push(self.unit as u8);
+        data
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, String>> {
+        let chunk = png.chunk_by_type(ChunkType::OFFS)?;
+        Some(Self::from_bytes(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::OFFS, self.to_bytes())
+    }
+}
+
+impl Display for ImageOffset {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let unit = match self.unit {
+            OffsetUnit::Pixel => "pixels",
+            OffsetUnit::Micrometre => "micrometres",
+        };
+        write!(fmt, "x {}, y {} {}", self.x, self.y, unit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleUnit {
+    Metre,
+    Radian,
+}
+
+/// sCAL: the physical size of a pixel. The values are kept as the text the file stores them
+/// in, so that they round-trip exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhysicalScale {
+    pub unit: ScaleUnit,
+    pub width: String,
+    pub height: String,
+}
+
+impl PhysicalScale {
+    /// Checks that both values are positive floating-point numbers.
+    pub fn new(unit: ScaleUnit, width: &str, height: &str) -> Result<Self, String> {
+        for value in [width, height] {
+            check_float(value, "pixel size")?;
+            if value.starts_with('-') || value.parse::<f64>().map_or(true, |value| value <= 0.0) {
+                return Err(format!("the pixel size {} is not positive", value));
+            }
+        }
+        Ok(Self {
+            unit,
+            width: width.to_string(),
+            height: height.to_string(),
+        })
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let (&unit, values) = data.split_first().ok_or("sCAL is empty")?;
+        let unit = match unit {
+            1 => ScaleUnit::Metre,
+            2 => ScaleUnit::Radian,
+            unit => return Err(format!("sCAL unit {} is not 1 or 2", unit)),
+        };
+        let values = std::str::from_utf8(values).map_err(|_| "sCAL values are not ASCII")?;
+        let (width, height) = values
+            .split_once('\0')
+            .ok_or("sCAL has no null separator between its values")?;
+        Self::new(unit, width, height)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let unit = match self.unit {
+            ScaleUnit::Metre => 1,
+            ScaleUnit::Radian => 2,
+        };
+        [&[unit], self.width.as_bytes(), &[0], self.height.as_bytes()].concat()
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, String>> {
+        let chunk = png.chunk_by_type(ChunkType::SCAL)?;
+        Some(Self::from_bytes(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SCAL, self.to_bytes())
+    }
+}
+
+impl Display for PhysicalScale {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let unit = match self.unit {
+            ScaleUnit::Metre => "metres",
+            ScaleUnit::Radian => "radians",
+        };
+        write!(fmt, "{} by {} {} per pixel", self.width, self.height, unit)
+    }
+}
+
+/// How pCAL maps stored samples to physical values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equation {
+    Linear,
+    Exponential,
+    ArbitraryExponential,
+    Hyperbolic,
+}
+
+impl Equation {
+    fn from_byte(byte: u8) -> Result<Self, String> {
+        match byte {
+            0 => Ok(Equation::Linear),
+            1 => Ok(Equation::Exponential),
+            2 => Ok(Equation::ArbitraryExponential),
+            3 => Ok(Equation::Hyperbolic),
+            _ => Err(format!("pCAL equation type {} is not 0 to 3", byte)),
+        }
+    }
+
+    pub fn parameter_count(self) -> usize {
+        match self {
+            Equation::Linear => 2,
+            Equation::Exponential => 3,
+            Equation::ArbitraryExponential | Equation::Hyperbolic => 4,
+        }
+    }
+
+    /// The equation from the specification, where `x` is the stored sample scaled to the
+    /// range `x0..x1` and `p0`, `p1`... are the parameters.
+    pub fn formula(self) -> &'static str {
+        match self {
+            Equation::Linear => "p0 + p1 * x / (x1 - x0)",
+            Equation::Exponential => "p0 + p1 * e^(p2 * x / (x1 - x0))",
+            Equation::ArbitraryExponential => "p0 + p1 * p3^(p2 * x / (x1 - x0))",
+            Equation::Hyperbolic => "p0 + p1 * sinh(p2 * (x - p3) / (x1 - x0))",
+        }
+    }
+}
+
+/// pCAL: what the stored samples mean physically, such as temperature or elevation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelCalibration {
+    pub name: String,
+    pub x0: i32,
+    pub x1: i32,
+    pub equation: Equation,
+    pub unit: String,
+    pub parameters: Vec<String>,
+}
+
+impl PixelCalibration {
+    /// Checks the name, that `x0` and `x1` differ, and the number and form of the parameters.
+    pub fn new(
+        name: &str,
+        x0: i32,
+        x1: i32,
+        equation: Equation,
+        unit: &str,
+        parameters: Vec<String>,
+    ) -> Result<Self, String> {
+        chunk::keyword_bytes(name)?;
+        if x0 == x1 {
+            return Err(format!("pCAL's x0 and x1 are both {}", x0));
+        }
+        if parameters.len() != equation.parameter_count() {
+            return Err(format!(
+                "a {:?} pCAL equation takes {} parameters, not {}",
+                equation,
+                equation.parameter_count(),
+                parameters.len()
+            ));
+        }
+        for parameter in &parameters {
+            check_float(parameter, "pCAL parameter")?;
+        }
+        if unit.contains('\0') || unit.chars().any(|c| c as u32 > 255) {
+            return Err(format!("`{}` is not a Latin-1 unit name", unit));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            x0,
+            x1,
+            equation,
+            unit: unit.to_string(),
+            parameters,
+        })
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let latin1 = |bytes: &[u8]| bytes.iter().map(|&byte| byte as char).collect::<String>();
+        let separator = data
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("pCAL has no null separator after its name")?;
+        let name = latin1(&data[..separator]);
+        let fields = data
+            .get(separator + 1..separator + 11)
+            .ok_or("pCAL ends before its equation")?;
+        let x0 = read_i32(&fields[..4])?;
+        let x1 = read_i32(&fields[4..8])?;
+        let equation = Equation::from_byte(fields[8])?;
+        let count = fields[9] as usize;
+        let rest = &data[separator + 11..];
+        let unit_end = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("pCAL has no null separator after its unit name")?;
+        let parameters: Vec<String> = if count == 0 {
+            Vec::new()
+        } else {
+            rest[unit_end + 1..]
+                .split(|&byte| byte == 0)
+                .map(latin1)
+                .collect()
+        };
+        if parameters.len() != count {
+            return Err(format!(
+                "pCAL says it has {} parameters but holds {}",
+                count,
+                parameters.len()
+            ));
+        }
+        Self::new(
+            &name,
+            x0,
+            x1,
+            equation,
+            &latin1(&rest[..unit_end]),
+            parameters,
+        )
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = chunk::keyword_bytes(&self.name).unwrap();
+        data.push(0);
+        data.extend_from_slice(&self.x0.to_be_bytes());
+        data.extend_from_slice(&self.x1.to_be_bytes());
+        data.push(self.equation as u8);
+        data.push(self.parameters.len() as u8);
+        data.extend(self.unit.chars().map(|c| c as u8));
+        data.push(0);
+        data.extend_from_slice(self.parameters.join("\0").as_bytes());
+        data
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, String>> {
+        let chunk = png.chunk_by_type(ChunkType::PCAL)?;
+        Some(Self::from_bytes(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::PCAL, self.to_bytes())
+    }
+}
+
+impl Display for PixelCalibration {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{}: {} = {} for x0 {}, x1 {}, parameters {}",
+            self.name,
+            if self.unit.is_empty() {
+                "value"
+            } else {
+                &self.unit
+            },
+            self.equation.formula(),
+            self.x0,
+            self.x1,
+            self.parameters.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_offset() {
+        let offset = ImageOffset {
+            x: -5,
+            y: 1200,
+            unit: OffsetUnit::Micrometre,
+        };
+        let bytes = offset.to_bytes();
+        assert_eq!(bytes, [255, 255, 255, 251, 0, 0, 4, 176, 1]);
+        assert_eq!(ImageOffset::from_bytes(&bytes), Ok(offset));
+        assert_eq!(offset.to_string(), "x -5, y 1200 micrometres");
+        assert!(ImageOffset::from_bytes(&bytes[..8]).is_err());
+        assert!(ImageOffset::from_bytes(&[128, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(ImageOffset::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_physical_scale() {
+        let scale = PhysicalScale::from_bytes(b"\x011.5e-6\x000.25").unwrap();
+        assert_eq!(scale.unit, ScaleUnit::Metre);
+        assert_eq!(scale.to_bytes(), b"\x011.5e-6\x000.25");
+        assert_eq!(scale.to_string(), "1.5e-6 by 0.25 metres per pixel");
+        for invalid in [
+            &b"\x031\x001"[..],
+            b"\x011",
+            b"\x01-1\x001",
+            b"\x010\x001",
+            b"\x01inf\x001",
+            b"\x011e\x001",
+            b"\x011..2\x001",
+        ] {
+            assert!(PhysicalScale::from_bytes(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_pixel_calibration() {
+        let parameters = vec!["-40".to_string(), "1.2E2".to_string()];
+        let calibration =
+            PixelCalibration::new("temperature", 0, 65535, Equation::Linear, "C", parameters)
+                .unwrap();
+        let bytes = calibration.to_bytes();
+        assert!(bytes.ends_with(b"C\0-40\x001.2E2"));
+        assert_eq!(
+            PixelCalibration::from_bytes(&bytes),
+            Ok(calibration.clone())
+        );
+        assert_eq!(
+            calibration.to_string(),
+            "temperature: C = p0 + p1 * x / (x1 - x0) for x0 0, x1 65535, parameters -40, 1.2E2"
+        );
+
+        let mut wrong_count = bytes.clone();
+        wrong_count[21] = 3;
+        assert!(PixelCalibration::from_bytes(&wrong_count).is_err());
+        assert!(
+            PixelCalibration::new("x", 1, 1, Equation::Linear, "", vec!["0".into(); 2]).is_err()
+        );
+        assert!(
+            PixelCalibration::new("x", 0, 1, Equation::Hyperbolic, "", vec!["0".into(); 2])
+                .is_err()
+        );
+        assert!(PixelCalibration::from_bytes(b"x\0\0\0").is_err());
+    }
+}
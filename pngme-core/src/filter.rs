@@ -0,0 +1,161 @@
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use crate::hex;
+
+/// Decompressed output beyond this is treated as a decompression bomb.
+const MAX_INFLATED: u64 = 64 << 20;
+
+/// A reversible transform commonly used to obfuscate a hidden message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Rot13,
+    /// XOR with a repeating key.
+    Xor(Vec<u8>),
+    Base64Decode,
+    /// Inflates gzip or zlib data.
+    Gunzip,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+    fn from_str(filter: &str) -> Result<Self, String> {
+        match filter.trim() {
+            "rot13" => Ok(Filter::Rot13),
+            "base64d" => Ok(Filter::Base64Decode),
+            "gunzip" => Ok(Filter::Gunzip),
+            other => {
+                let key = other.strip_prefix("xor:").ok_or_else(|| {
+                    format!(
+                        "unknown filter `{}`; expected rot13, xor:<key>, base64d or gunzip",
+                        other
+                    )
+                })?;
+                let key = match key.strip_prefix("0x") {
+                    Some(digits) => {
+                        hex::decode(digits).map_err(|()| format!("`{}` is not a hex key", key))?
+                    }
+                    None => key.as_bytes().to_vec(),
+                };
+                if key.is_empty() {
+                    return Err(String::from("xor needs a non-empty key"));
+                }
+                Ok(Filter::Xor(key))
+            }
+        }
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Filter::Rot13 => write!(fmt, "rot13"),
+            Filter::Xor(key) => write!(fmt, "xor:0x{}", hex::encode(key)),
+            Filter::Base64Decode => write!(fmt, "base64d"),
+            Filter::Gunzip => write!(fmt, "gunzip"),
+        }
+    }
+}
+
+fn rot13(byte: u8) -> u8 {
+    match byte {
+        b'a'..=b'z' => (byte - b'a' + 13) % 26 + b'a',
+        b'A'..=b'Z' => (byte - b'A' + 13) % 26 + b'A',
+        _ => byte,
+    }
+}
+
+fn inflate(reader: impl Read) -> Result<Vec<u8>, ()> {
+    let mut output = Vec::new();
+    reader
+        .take(MAX_INFLATED + 1)
+        .read_to_end(&mut output)
+        .map_err(|_| ())?;
+    if output.len() as u64 > MAX_INFLATED {
+        return Err(());
+    }
+    Ok(output)
+}
+
+impl Filter {
+    pub fn apply(&self, data: &[u8]) -> Result<Vec<u8>, ()> {
+        match self {
+            Filter::Rot13 => Ok(data.iter().map(|&byte| rot13(byte)).collect()),
+            Filter::Xor(key) => Ok(data
+                .iter()
+                .zip(key.iter().cycle())
+                .map(|(byte, key)| byte ^ key)
+                .collect()),
+            Filter::Base64Decode => {
+                let encoded: Vec<u8> = data
+                    .iter()
+                    .copied()
+                    .filter(|byte| !byte.is_ascii_whitespace())
+                    .collect();
+                STANDARD.decode(encoded).map_err(|_| ())
+            }
+            Filter::Gunzip => match data {
+                [0x1f, 0x8b, ..] => inflate(GzDecoder::new(data)),
+                _ => inflate(ZlibDecoder::new(data)),
+            },
+        }
+    }
+}
+
+/// Runs `data` through each filter in turn, reporting the index of the first that fails.
+pub fn apply_all(filters: &[Filter], data: &[u8]) -> Result<Vec<u8>, usize> {
+    filters
+        .iter()
+        .enumerate()
+        .try_fold(data.to_vec(), |data, (index, filter)| {
+            filter.apply(&data).map_err(|()| index)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("rot13".parse(), Ok(Filter::Rot13));
+        assert_eq!("xor:ab".parse(), Ok(Filter::Xor(b"ab".to_vec())));
+        assert_eq!("xor:0x1f20".parse(), Ok(Filter::Xor(vec![0x1f, 0x20])));
+        assert!("xor:".parse::<Filter>().is_err());
+        assert!("xor:0xzz".parse::<Filter>().is_err());
+        assert!("reverse".parse::<Filter>().is_err());
+        assert_eq!(Filter::Xor(b"ab".to_vec()).to_string(), "xor:0x6162");
+    }
+
+    #[test]
+    fn test_rot13_and_xor() {
+        assert_eq!(
+            Filter::Rot13.apply(b"Hello, World!").unwrap(),
+            b"Uryyb, Jbeyq!"
+        );
+        let xor = Filter::Xor(vec![1, 2]);
+        assert_eq!(xor.apply(&[0, 0, 0]).unwrap(), [1, 2, 1]);
+        assert_eq!(
+            xor.apply(&xor.apply(b"secret").unwrap()).unwrap(),
+            b"secret"
+        );
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"flag{chained}").unwrap();
+        let payload = STANDARD.encode(encoder.finish().unwrap());
+        let rotated = Filter::Rot13.apply(payload.as_bytes()).unwrap();
+        let filters = [Filter::Rot13, Filter::Base64Decode, Filter::Gunzip];
+        assert_eq!(apply_all(&filters, &rotated).unwrap(), b"flag{chained}");
+        assert_eq!(apply_all(&filters, b"not base64!"), Err(1));
+        assert_eq!(apply_all(&[], b"as is").unwrap(), b"as is");
+    }
+}
@@ -0,0 +1,477 @@
+//! `pngme crop`, `pad`, `rotate` and `flip`: rearrange the image without changing any sample
+//! values, then bring the ancillary chunks into line with the new frame.
+//!
+//! The PNG specification says an editor that changes the image data must drop ancillary
+//! chunks it doesn't understand unless they are marked safe to copy. The ones understood
+//! here stay when they still hold (colour space, transparency, background and the like), are
+//! rewritten when they can be (oFFs, hIST), and are reported when they might carry the old
+//! dimensions somewhere pngme can't fix (EXIF, XMP).
+
+use std::str::FromStr;
+
+use crate::background::{self, palette_len};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::extensions::{ImageOffset, OffsetUnit, PhysicalScale};
+use crate::histogram::Histogram;
+use crate::image::{Color, ColorType, ImageData};
+use crate::png::Png;
+use crate::xmp;
+
+/// Chunks that aren't safe to copy but describe the colours or palette rather than the
+/// layout, so they hold for any crop or border of the same samples.
+const UNSAFE_BUT_UNAFFECTED: [ChunkType; 14] = [
+    ChunkType::CHRM,
+    ChunkType::GAMA,
+    ChunkType::ICCP,
+    ChunkType::SBIT,
+    ChunkType::SRGB,
+    ChunkType::CICP,
+    ChunkType::MDCV,
+    ChunkType::CLLI,
+    ChunkType::BKGD,
+    ChunkType::TRNS,
+    ChunkType::SPLT,
+    ChunkType::TIME,
+    ChunkType::SCAL,
+    ChunkType::PCAL,
+];
+
+/// pHYs unit meaning pixels per metre.
+const PHYS_METRE: u8 = 1;
+
+/// `WxH+X+Y`, as ImageMagick writes it; the offset may be left out to mean `+0+0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("`{}` is not a region like 100x50+10+20", s);
+        let (size, offset) = match s.find('+') {
+            Some(plus) => (&s[..plus], Some(&s[plus + 1..])),
+            None => (s, None),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let (x, y) = match offset {
+            Some(offset) => offset.split_once('+').ok_or_else(invalid)?,
+            None => ("0", "0"),
+        };
+        let number = |value: &str| value.parse::<u32>().map_err(|_| invalid());
+        Ok(Self {
+            width: number(width)?,
+            height: number(height)?,
+            x: number(x)?,
+            y: number(y)?,
+        })
+    }
+}
+
+/// Border widths, written like CSS margins: `N` for all four sides, `V,H`, or
+/// `TOP,RIGHT,BOTTOM,LEFT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Border(pub [u32; 4]);
+
+impl FromStr for Border {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("`{}` is not N, V,H or TOP,RIGHT,BOTTOM,LEFT", s);
+        let sides: Vec<u32> = s
+            .split(',')
+            .map(|side| side.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        match sides[..] {
+            [all] => Ok(Border([all; 4])),
+            [vertical, horizontal] => Ok(Border([vertical, horizontal, vertical, horizontal])),
+            [top, right, bottom, left] => Ok(Border([top, right, bottom, left])),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A clockwise rotation: `90`, `180` or `270` degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+    quarter_turns: u32,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "90" => Ok(Rotation { quarter_turns: 1 }),
+            "180" => Ok(Rotation { quarter_turns: 2 }),
+            "270" => Ok(Rotation { quarter_turns: 3 }),
+            _ => Err(format!("`{}` is not 90, 180 or 270", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    /// Left to right, `h`.
+    Horizontal,
+    /// Top to bottom, `v`.
+    Vertical,
+}
+
+impl FromStr for Flip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "h" | "horizontal" => Ok(Flip::Horizontal),
+            "v" | "vertical" => Ok(Flip::Vertical),
+            _ => Err(format!("`{}` is not h or v", s)),
+        }
+    }
+}
+
+/// Micrometres per pixel along each axis, if pHYs gives a physical density.
+fn micrometres_per_pixel(png: &Png) -> Option<(f64, f64)> {
+    let data = png.chunk_by_type(ChunkType::PHYS)?.data();
+    if data.len() != 9 || data[8] != PHYS_METRE {
+        return None;
+    }
+    let x = u32::from_be_bytes(data[0..4].try_into().unwrap());
+    let y = u32::from_be_bytes(data[4..8].try_into().unwrap());
+    (x > 0 && y > 0).then(|| (1e6 / x as f64, 1e6 / y as f64))
+}
+
+/// hIST frequencies for palette `indices`, scaled to fit 16 bits while keeping every used
+/// entry above zero.
+pub(crate) fn histogram(indices: &[u16], palette_len: usize) -> Result<Histogram, String> {
+    let mut counts = vec![0u64; palette_len];
+    for &index in indices {
+        if let Some(count) = counts.get_mut(index as usize) {
+            *count += 1;
+        }
+    }
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let frequencies = counts
+        .iter()
+        .map(|&count| {
+            if max <= u16::MAX as u64 {
+                count as u16
+            } else {
+                (count * u16::MAX as u64).div_ceil(max) as u16
+            }
+        })
+        .collect();
+    Histogram::new(frequencies, palette_len)
+}
+
+/// Writes `image` into `png`, whose top left pixel was at `origin` in the old frame, and
+/// fixes up the ancillary chunks. Returns a note for each chunk changed, dropped or left
+/// possibly wrong; `stale` says what EXIF and XMP may still describe.
+fn reframe(
+    png: &mut Png,
+    image: &ImageData,
+    origin: (i64, i64),
+    stale: &str,
+) -> Result<Vec<String>, String> {
+    image
+        .write_to(png)
+        .map_err(|()| "failed to write the image data".to_string())?;
+    let mut notes = Vec::new();
+
+    if let Some(offset) = ImageOffset::from_png(png) {
+        let mut offset = offset?;
+        let shift = match offset.unit {
+            OffsetUnit::Pixel => Some((origin.0 as f64, origin.1 as f64)),
+            OffsetUnit::Micrometre => {
+                micrometres_per_pixel(png).map(|(x, y)| (origin.0 as f64 * x, origin.1 as f64 * y))
+            }
+        };
+        match shift {
+            Some((x, y)) => {
+                offset.x = (offset.x as f64 + x).round() as i32;
+                offset.y = (offset.y as f64 + y).round() as i32;
+                png.retain_chunks(|chunk| *chunk.chunk_type() != ChunkType::OFFS);
+                png.insert_ancillary(offset.to_chunk());
+                if origin != (0, 0) {
+                    notes.push(format!("moved the oFFs position to {}", offset));
+                }
+            }
+            None => {
+                png.retain_chunks(|chunk| *chunk.chunk_type() != ChunkType::OFFS);
+                notes.push(String::from(
+                    "dropped oFFs: its micrometre position can't be moved without a pHYs density",
+                ));
+            }
+        }
+    }
+    if png.chunk_by_type(ChunkType::HIST).is_some() {
+        let histogram = histogram(image.samples(), palette_len(png))?;
+        png.retain_chunks(|chunk| *chunk.chunk_type() != ChunkType::HIST);
+        png.insert_ancillary(histogram.to_chunk());
+        notes.push(String::from("recounted hIST for the new pixels"));
+    }
+
+    notes.extend(drop_unsafe_to_copy(png));
+    if png.chunk_by_type(ChunkType::EXIF).is_some() {
+        notes.push(format!("eXIf may still give the old {}", stale));
+    }
+    if png.chunks().iter().any(xmp::is_xmp_chunk) {
+        notes.push(format!("XMP may still give the old {}", stale));
+    }
+    Ok(notes)
+}
+
+/// Drops the ancillary chunks that are marked unsafe to copy and aren't known to survive a
+/// change to the image data, as the specification asks of editors. Returns a note for each.
+pub(crate) fn drop_unsafe_to_copy(png: &mut Png) -> Vec<String> {
+    let mut dropped = Vec::new();
+    png.retain_chunks(|chunk| {
+        let chunk_type = chunk.chunk_type();
+        let keep = chunk_type.is_critical()
+            || chunk_type.is_safe_to_copy()
+            || UNSAFE_BUT_UNAFFECTED.contains(chunk_type)
+            || *chunk_type == ChunkType::HIST;
+        if !keep {
+            dropped.push(chunk_type.to_string());
+        }
+        keep
+    });
+    dropped
+        .into_iter()
+        .map(|chunk_type| {
+            format!(
+                "dropped {}: it isn't safe to copy once the image data changes",
+                chunk_type
+            )
+        })
+        .collect()
+}
+
+fn image_of(png: &Png) -> Result<ImageData, String> {
+    ImageData::from_png(png).map_err(|()| "the file has no decodable image data".to_string())
+}
+
+pub fn crop(png: &mut Png, region: Region) -> Result<Vec<String>, String> {
+    let image = image_of(png)?;
+    let cropped = image
+        .cropped(region.x, region.y, region.width, region.height)
+        .map_err(|()| {
+            format!(
+                "{}x{}+{}+{} is empty or reaches outside the {}x{} image",
+                region.width,
+                region.height,
+                region.x,
+                region.y,
+                image.width(),
+                image.height()
+            )
+        })?;
+    reframe(
+        png,
+        &cropped,
+        (region.x as i64, region.y as i64),
+        "dimensions",
+    )
+}
+
+/// Adds `border` around the image in `fill`, which defaults to the bKGD colour, or else
+/// transparent black (palette entry 0 for indexed images). An explicit or bKGD fill is
+/// opaque.
+pub fn pad(png: &mut Png, border: Border, fill: Option<Color>) -> Result<Vec<String>, String> {
+    let image = image_of(png)?;
+    let header = *image.header();
+    let fill = match fill {
+        Some(fill) => Some(fill),
+        None => background::background(png).and_then(Result::ok),
+    };
+    let max = ((1u32 << header.bit_depth) - 1) as u16;
+    let samples = match fill {
+        Some(fill) => {
+            fill.check(&header, palette_len(png))?;
+            let mut samples = match fill {
+                Color::Level(level) => vec![level],
+                Color::Rgb(rgb) => rgb.to_vec(),
+            };
+            if matches!(
+                header.color_type,
+                ColorType::GrayscaleAlpha | ColorType::Rgba
+            ) {
+                samples.push(max);
+            }
+            samples
+        }
+        None => vec![0; image.channels()],
+    };
+    let [top, _, _, left] = border.0;
+    let padded = image
+        .padded(border.0, &samples)
+        .map_err(|()| "the padded image would be too large".to_string())?;
+    reframe(png, &padded, (-(left as i64), -(top as i64)), "dimensions")
+}
+
+/// Swaps the horizontal and vertical values of pHYs and sCAL, for a quarter turn, where
+/// they differ.
+fn swap_axes(png: &mut Png) -> Result<Vec<String>, String> {
+    let mut notes = Vec::new();
+    let mut swapped = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = *chunk.chunk_type();
+        let data = chunk.data();
+        if chunk_type == ChunkType::PHYS && data.len() == 9 && data[0..4] != data[4..8] {
+            let data = [&data[4..8], &data[0..4], &data[8..]].concat();
+            swapped.push((index, Chunk::new(chunk_type, data)));
+        } else if chunk_type == ChunkType::SCAL {
+            let mut scale = PhysicalScale::from_bytes(data)?;
+            if scale.width == scale.height {
+                continue;
+            }
+            std::mem::swap(&mut scale.width, &mut scale.height);
+            swapped.push((index, scale.to_chunk()));
+        } else {
+            continue;
+        }
+        notes.push(format!("swapped the {} axes", chunk_type));
+    }
+    for (index, chunk) in swapped {
+        png.replace_chunk(index, chunk);
+    }
+    Ok(notes)
+}
+
+/// Turns the image clockwise. The oFFs position still gives the top left corner of the
+/// turned image.
+pub fn rotate(png: &mut Png, rotation: Rotation) -> Result<Vec<String>, String> {
+    let image = image_of(png)?.rotated(rotation.quarter_turns);
+    let mut notes = if rotation.quarter_turns % 2 == 1 {
+        swap_axes(png)?
+    } else {
+        Vec::new()
+    };
+    notes.extend(reframe(png, &image, (0, 0), "orientation")?);
+    Ok(notes)
+}
+
+pub fn flip(png: &mut Png, flip: Flip) -> Result<Vec<String>, String> {
+    let image = image_of(png)?.flipped(flip == Flip::Horizontal);
+    reframe(png, &image, (0, 0), "orientation")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageHeader;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    // A 3x2 indexed image, one palette index per byte, with the given extra chunks.
+    fn indexed(extra: Vec<Chunk>) -> Png {
+        let header = ImageHeader {
+            width: 3,
+            height: 2,
+            bit_depth: 8,
+            color_type: ColorType::Indexed,
+            interlaced: false,
+        };
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::none());
+        encoder.write_all(&[0, 0, 1, 2, 0, 2, 2, 2]).unwrap();
+        let mut chunks = vec![
+            Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+            Chunk::new(ChunkType::PLTE, vec![0; 9]),
+        ];
+        chunks.extend(extra);
+        chunks.push(Chunk::new(ChunkType::IDAT, encoder.finish().unwrap()));
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "2x1+1+0".parse(),
+            Ok(Region {
+                width: 2,
+                height: 1,
+                x: 1,
+                y: 0
+            })
+        );
+        assert_eq!("2x1".parse::<Region>().unwrap().x, 0);
+        assert!("2x1+1".parse::<Region>().is_err());
+        assert_eq!("1,2".parse(), Ok(Border([1, 2, 1, 2])));
+        assert!("1,2,3".parse::<Border>().is_err());
+    }
+
+    #[test]
+    fn test_crop() {
+        let offset = ImageOffset {
+            x: 10,
+            y: 10,
+            unit: OffsetUnit::Pixel,
+        };
+        let mut png = indexed(vec![
+            offset.to_chunk(),
+            Chunk::new(ChunkType::HIST, vec![0, 1, 0, 1, 0, 4]),
+            Chunk::new(ChunkType::from_str("prVT").unwrap(), vec![]),
+            Chunk::new(ChunkType::from_str("prvt").unwrap(), vec![]),
+        ]);
+        let notes = crop(&mut png, "2x2+1+0".parse().unwrap()).unwrap();
+        assert_eq!(notes.len(), 3);
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.samples(), &[1, 2, 2, 2]);
+        let offset = ImageOffset::from_png(&png).unwrap().unwrap();
+        assert_eq!((offset.x, offset.y), (11, 10));
+        let histogram = Histogram::from_png(&png).unwrap().unwrap();
+        assert_eq!(histogram.frequencies(), &[0, 1, 3]);
+        assert!(png
+            .chunk_by_type(ChunkType::from_str("prVT").unwrap())
+            .is_none());
+        assert!(png
+            .chunk_by_type(ChunkType::from_str("prvt").unwrap())
+            .is_some());
+        assert!(crop(&mut png, "3x1+0+0".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        let mut png = indexed(vec![Chunk::new(ChunkType::BKGD, vec![1])]);
+        pad(&mut png, Border([1, 0, 0, 1]), None).unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 3));
+        assert_eq!(image.samples(), &[1, 1, 1, 1, 1, 0, 1, 2, 1, 2, 2, 2]);
+        assert!(pad(&mut png, Border([1; 4]), Some(Color::Level(5))).is_err());
+    }
+
+    #[test]
+    fn test_rotate_and_flip() {
+        let mut png = indexed(vec![Chunk::new(
+            ChunkType::PHYS,
+            vec![0, 0, 0, 1, 0, 0, 0, 2, 1],
+        )]);
+        let notes = rotate(&mut png, "90".parse().unwrap()).unwrap();
+        assert_eq!(notes, ["swapped the pHYs axes"]);
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 3));
+        assert_eq!(image.samples(), &[2, 0, 2, 1, 2, 2]);
+        let phys = png.chunk_by_type(ChunkType::PHYS).unwrap();
+        assert_eq!(phys.data(), &[0, 0, 0, 2, 0, 0, 0, 1, 1]);
+
+        rotate(&mut png, "270".parse().unwrap()).unwrap();
+        flip(&mut png, Flip::Horizontal).unwrap();
+        assert_eq!(
+            ImageData::from_png(&png).unwrap().samples(),
+            &[2, 1, 0, 2, 2, 2]
+        );
+        flip(&mut png, "v".parse().unwrap()).unwrap();
+        assert_eq!(
+            ImageData::from_png(&png).unwrap().samples(),
+            &[2, 2, 2, 2, 1, 0]
+        );
+        assert!("45".parse::<Rotation>().is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! The clean and smudge halves of `pngme git-filter`, which keep volatile metadata out of
+//! committed images so that re-exporting an unchanged picture doesn't show up as a diff.
+
+use std::str::FromStr;
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::png::{Png, UNIQUE_ANCILLARY};
+
+/// Text keywords whose value changes every time an image is saved: the registered
+/// `Creation Time`, and the ones ImageMagick writes.
+pub const VOLATILE_KEYWORDS: [&str; 4] = [
+    "Creation Time",
+    "date:create",
+    "date:modify",
+    "date:timestamp",
+];
+
+/// Private chunks Adobe Fireworks keeps its editable document in.
+pub const EDITOR_CHUNK_TYPES: [&str; 4] = ["mkBF", "mkBS", "mkBT", "mkTS"];
+
+/// Whether `chunk` is a timestamp, a volatile text entry or an editor's private state.
+pub fn is_volatile(chunk: &Chunk) -> bool {
+    let chunk_type = chunk.chunk_type();
+    *chunk_type == ChunkType::TIME
+        || EDITOR_CHUNK_TYPES
+            .iter()
+            .any(|name| ChunkType::from_str(name).is_ok_and(|editor| *chunk_type == editor))
+        || chunk::text_keyword(chunk).is_some_and(|keyword| {
+            VOLATILE_KEYWORDS
+                .iter()
+                .any(|volatile| keyword == volatile.as_bytes())
+        })
+}
+
+/// Removes the volatile chunks, returning them in file order.
+pub fn clean(png: &mut Png) -> Vec<Chunk> {
+    let mut removed = Vec::new();
+    png.retain_chunks(|chunk| {
+        if is_volatile(chunk) {
+            removed.push(Chunk::new(*chunk.chunk_type(), chunk.data().to_vec()));
+        }
+        !is_volatile(chunk)
+    });
+    removed
+}
+
+/// Puts back chunks taken out by `clean`, skipping any the file already has, or whose type
+/// may only appear once and is already present.
+pub fn smudge(png: &mut Png, stashed: Vec<Chunk>) {
+    for chunk in stashed {
+        let present = png.chunks().iter().any(|existing| {
+            existing.chunk_type() == chunk.chunk_type()
+                && (UNIQUE_ANCILLARY.contains(chunk.chunk_type())
+                    || existing.data() == chunk.data())
+        });
+        if !present {
+            png.insert_ancillary(chunk);
+        }
+    }
+}
+
+/// Stashed chunks are kept as a PNG signature followed by the chunks and IEND, so that
+/// `pngme print` can read the stash too.
+pub fn stash_bytes(mut chunks: Vec<Chunk>) -> Vec<u8> {
+    chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+    Png::from_chunks(chunks).as_bytes()
+}
+
+pub fn from_stash(bytes: &[u8]) -> Result<Vec<Chunk>, ()> {
+    let png = Png::try_from(bytes)?;
+    Ok(png
+        .chunks()
+        .iter()
+        .filter(|chunk| *chunk.chunk_type() != ChunkType::IEND)
+        .map(|chunk| Chunk::new(*chunk.chunk_type(), chunk.data().to_vec()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_and_smudge() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        png.insert_ancillary(Chunk::new(ChunkType::TIME, vec![7; 7]));
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"date:modify\0now".to_vec()));
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Title\0kept".to_vec()));
+        png.insert_ancillary(Chunk::new(ChunkType::from_str("mkBF").unwrap(), vec![1]));
+
+        let stashed = clean(&mut png);
+        assert_eq!(types(&png), ["IHDR", "tEXt", "IDAT", "IEND"]);
+        assert!(clean(&mut png).is_empty());
+        let stashed = from_stash(&stash_bytes(stashed)).unwrap();
+        assert_eq!(stashed.len(), 3);
+
+        // A tIME added since the clean wins over the stashed one.
+        png.insert_ancillary(Chunk::new(ChunkType::TIME, vec![8; 7]));
+        smudge(&mut png, stashed);
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.chunk_by_type(ChunkType::TIME).unwrap().data(), [8; 7]);
+        assert!(from_stash(b"junk").is_err());
+    }
+}
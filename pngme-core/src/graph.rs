@@ -0,0 +1,207 @@
+//! Diagrams of a file's layout for `pngme graph`: the signature, each run of chunks of one
+//! type in file order, and anything after IEND. Graphviz draws the boxes to scale, their
+//! heights in proportion to their bytes with a minimum that keeps small chunks legible;
+//! Mermaid can't size boxes, so there the sizes are only in the labels.
+
+use std::fmt::Write;
+
+use crate::png::Png;
+
+/// Length, type and CRC around every chunk's payload.
+const CHUNK_OVERHEAD: u64 = 12;
+
+/// Height in inches of a box holding the whole file.
+const FULL_HEIGHT: f64 = 8.0;
+const MIN_HEIGHT: f64 = 0.35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Signature,
+    Critical,
+    Ancillary,
+    Trailer,
+}
+
+impl Kind {
+    fn class(self) -> &'static str {
+        match self {
+            Kind::Signature => "signature",
+            Kind::Critical => "critical",
+            Kind::Ancillary => "ancillary",
+            Kind::Trailer => "trailer",
+        }
+    }
+
+    fn colour(self) -> &'static str {
+        match self {
+            Kind::Signature => "#d9d9d9",
+            Kind::Critical => "#f4a582",
+            Kind::Ancillary => "#92c5de",
+            Kind::Trailer => "#e7298a",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Segment {
+    name: String,
+    /// How many chunks of the type in a row, or zero for the signature and trailer.
+    chunks: usize,
+    bytes: u64,
+    kind: Kind,
+}
+
+impl Segment {
+    fn label(&self, total: u64, line_break: &str) -> String {
+        let name = match self.chunks {
+            0 | 1 => self.name.clone(),
+            chunks => format!("{} ×{}", self.name, chunks),
+        };
+        let share = 100.0 * self.bytes as f64 / total.max(1) as f64;
+        format!("{}{}{} bytes ({:.1}%)", name, line_break, self.bytes, share)
+    }
+}
+
+/// The parts of a `file_size`-byte file that parsed as `png`, with each run of chunks of
+/// one type merged, so that a file of a thousand IDAT chunks is still one box.
+fn segments(png: &Png, file_size: u64) -> Vec<Segment> {
+    let mut segments = vec![Segment {
+        name: String::from("signature"),
+        chunks: 0,
+        bytes: Png::STANDARD_HEADER.len() as u64,
+        kind: Kind::Signature,
+    }];
+    let mut png_size = Png::STANDARD_HEADER.len() as u64;
+    for chunk in png.chunks() {
+        let name = chunk.chunk_type().to_string();
+        let bytes = CHUNK_OVERHEAD + chunk.length() as u64;
+        png_size += bytes;
+        match segments.last_mut() {
+            Some(last) if last.chunks > 0 && last.name == name => {
+                last.chunks += 1;
+                last.bytes += bytes;
+            }
+            _ => segments.push(Segment {
+                name,
+                chunks: 1,
+                bytes,
+                kind: match chunk.chunk_type().is_critical() {
+                    true => Kind::Critical,
+                    false => Kind::Ancillary,
+                },
+            }),
+        }
+    }
+    if file_size > png_size {
+        segments.push(Segment {
+            name: String::from("trailing data"),
+            chunks: 0,
+            bytes: file_size - png_size,
+            kind: Kind::Trailer,
+        });
+    }
+    segments
+}
+
+/// The layout as a Graphviz graph, for `dot -Tsvg`.
+pub fn dot(png: &Png, file_size: u64) -> String {
+    let segments = segments(png, file_size);
+    let total: u64 = segments.iter().map(|segment| segment.bytes).sum();
+    let mut dot = String::from(
+        "digraph png {\n    node [shape=box, style=filled, fixedsize=true, width=3, \
+         fontname=\"Helvetica\"];\n    edge [arrowhead=none];\n",
+    );
+    for (index, segment) in segments.iter().enumerate() {
+        let height = (FULL_HEIGHT * segment.bytes as f64 / total.max(1) as f64).max(MIN_HEIGHT);
+        writeln!(
+            dot,
+            "    n{} [label=\"{}\", height={:.2}, fillcolor=\"{}\"];",
+            index,
+            segment.label(total, "\\n"),
+            height,
+            segment.kind.colour()
+        )
+        .unwrap();
+    }
+    if segments.len() > 1 {
+        let path: Vec<String> = (0..segments.len())
+            .map(|index| format!("n{}", index))
+            .collect();
+        writeln!(dot, "    {};", path.join(" -> ")).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The layout as a Mermaid flowchart, for Markdown that renders Mermaid.
+pub fn mermaid(png: &Png, file_size: u64) -> String {
+    let segments = segments(png, file_size);
+    let total: u64 = segments.iter().map(|segment| segment.bytes).sum();
+    let mut mermaid = String::from("flowchart TB\n");
+    for (index, segment) in segments.iter().enumerate() {
+        write!(
+            mermaid,
+            "    n{}[\"{}\"]:::{}",
+            index,
+            segment.label(total, "<br/>"),
+            segment.kind.class()
+        )
+        .unwrap();
+        if index > 0 {
+            write!(mermaid, "\n    n{} --- n{}", index - 1, index).unwrap();
+        }
+        mermaid.push('\n');
+    }
+    for kind in [
+        Kind::Signature,
+        Kind::Critical,
+        Kind::Ancillary,
+        Kind::Trailer,
+    ] {
+        writeln!(
+            mermaid,
+            "    classDef {} fill:{}",
+            kind.class(),
+            kind.colour()
+        )
+        .unwrap();
+    }
+    mermaid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::image::ColorType;
+    use crate::testing;
+
+    #[test]
+    fn test_graph() {
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        png.insert_before_data(Chunk::new(ChunkType::TEXT, b"Title\0Graph".to_vec()));
+        png.append_chunk(Chunk::new(ChunkType::IDAT, vec![0; 100]));
+        png.append_chunk(Chunk::new(ChunkType::IDAT, vec![0; 100]));
+        let size = png.as_bytes().len() as u64 + 5;
+        let segments = segments(&png, size);
+        let names: Vec<&str> = segments.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["signature", "IHDR", "tEXt", "IDAT", "IEND", "trailing data"]
+        );
+        assert_eq!((segments[3].chunks, segments[3].bytes), (3, 236));
+        assert_eq!(segments[2].kind, Kind::Ancillary);
+        assert_eq!(segments.last().unwrap().bytes, 5);
+        assert_eq!(segments.iter().map(|s| s.bytes).sum::<u64>(), size);
+
+        let dot = dot(&png, size);
+        assert!(dot.starts_with("digraph png {"));
+        assert!(dot.contains("label=\"IDAT ×3\\n236 bytes"));
+        assert!(dot.contains("n0 -> n1 -> n2 -> n3 -> n4 -> n5;"));
+        let mermaid = mermaid(&png, size);
+        assert!(mermaid.starts_with("flowchart TB"));
+        assert!(mermaid.contains("n4 --- n5"));
+        assert!(mermaid.contains(":::trailer"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::net::SocketAddr;
+
+use tonic::{Request, Response, Status};
+
+use crate::api::{self, Limits, Service, DEFAULT_CHUNK_TYPE};
+
+mod proto {
+    tonic::include_proto!("pngme");
+}
+
+use proto::pngme_server::{Pngme, PngmeServer};
+
+/// Answers the RPCs in `proto/pngme.proto` with an `api::Service`.
+struct Server {
+    service: Service,
+}
+
+impl Server {
+    fn status(&self, error: String) -> Status {
+        Status::invalid_argument(self.service.public_error(error))
+    }
+}
+
+fn method(value: i32) -> Result<api::Method, Status> {
+    match proto::Method::try_from(value) {
+        Ok(proto::Method::Chunk) => Ok(api::Method::Chunk),
+        Ok(proto::Method::Spread) => Ok(api::Method::Spread),
+        Err(_) => Err(Status::invalid_argument("unknown method")),
+    }
+}
+
+fn chunk_type(chunk_type: &str) -> &str {
+    if chunk_type.is_empty() {
+        DEFAULT_CHUNK_TYPE
+    } else {
+        chunk_type
+    }
+}
+
+/// Runs `work` on the blocking pool: the spread method and image checks take long enough
+/// to stall every other connection if run on the runtime's own threads.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<Result<T, String>, Status> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|_| Status::internal("the request failed"))
+}
+
+#[tonic::async_trait]
+impl Pngme for Server {
+    async fn encode(
+        &self,
+        request: Request<proto::EncodeRequest>,
+    ) -> Result<Response<proto::EncodeResponse>, Status> {
+        let request = request.into_inner();
+        let (service, method) = (self.service, method(request.method)?);
+        let png = blocking(move || {
+            service.encode(
+                &request.png,
+                &request.message,
+                chunk_type(&request.chunk_type),
+                method,
+                request.password.as_deref(),
+            )
+        })
+        .await?
+        .map_err(|error| self.status(error))?;
+        Ok(Response::new(proto::EncodeResponse { png }))
+    }
+
+    async fn decode(
+        &self,
+        request: Request<proto::DecodeRequest>,
+    ) -> Result<Response<proto::DecodeResponse>, Status> {
+        let request = request.into_inner();
+        let (service, method) = (self.service, method(request.method)?);
+        let message = blocking(move || {
+            service.decode(
+                &request.png,
+                chunk_type(&request.chunk_type),
+                method,
+                request.password.as_deref(),
+            )
+        })
+        .await?
+        .map_err(|error| self.status(error))?;
+        Ok(Response::new(proto::DecodeResponse {
+            message: message.to_vec(),
+        }))
+    }
+
+    async fn inspect(
+        &self,
+        request: Request<proto::InspectRequest>,
+    ) -> Result<Response<proto::InspectResponse>, Status> {
+        let (service, png) = (self.service, request.into_inner().png);
+        let chunks = blocking(move || service.inspect(&png))
+            .await?
+            .map_err(|error| self.status(error))?;
+        Ok(Response::new(proto::InspectResponse {
+            chunks: chunks
+                .into_iter()
+                .map(|chunk| proto::ChunkSummary {
+                    r#type: chunk.chunk_type,
+                    length: chunk.length,
+                    crc: chunk.crc,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn check(
+        &self,
+        request: Request<proto::CheckRequest>,
+    ) -> Result<Response<proto::CheckResponse>, Status> {
+        let (service, png) = (self.service, request.into_inner().png);
+        let problems = blocking(move || service.check(&png))
+            .await?
+            .map_err(|error| self.status(error))?;
+        Ok(Response::new(proto::CheckResponse { problems }))
+    }
+}
+
+/// Serves the gRPC interface on `address` until the process is stopped, holding each client
+/// to `limits`.
+pub async fn serve(
+    address: SocketAddr,
+    service: Service,
+    limits: Limits,
+) -> Result<(), tonic::transport::Error> {
+    let server = PngmeServer::new(Server { service })
+        .max_decoding_message_size(limits.max_request)
+        .max_encoding_message_size(limits.max_request);
+    tonic::transport::Server::builder()
+        .timeout(limits.timeout)
+        .concurrency_limit_per_connection(limits.concurrency)
+        .add_service(server)
+        .serve(address)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    #[test]
+    fn test_encode_and_inspect() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = Server {
+            service: Service::default(),
+        };
+        let request = proto::EncodeRequest {
+            png: png_with_header(ColorType::Rgb, 8, 0).as_bytes(),
+            message: b"rpc".to_vec(),
+            ..Default::default()
+        };
+        let png = runtime
+            .block_on(server.encode(Request::new(request)))
+            .unwrap()
+            .into_inner()
+            .png;
+        let chunks = runtime
+            .block_on(server.inspect(Request::new(proto::InspectRequest { png })))
+            .unwrap()
+            .into_inner()
+            .chunks;
+        assert_eq!(chunks[2].r#type, DEFAULT_CHUNK_TYPE);
+        assert_eq!(chunks[2].length, 3);
+
+        let request = proto::DecodeRequest {
+            png: png_with_header(ColorType::Rgb, 8, 0).as_bytes(),
+            method: 7,
+            ..Default::default()
+        };
+        let status = runtime
+            .block_on(server.decode(Request::new(request)))
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
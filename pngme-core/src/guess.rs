@@ -0,0 +1,109 @@
+use crate::filter::Filter;
+use crate::png::Png;
+
+/// Decodings tried on every payload, in order.
+const DECODINGS: [(&str, &[Filter]); 4] = [
+    ("raw", &[]),
+    ("inflate", &[Filter::Gunzip]),
+    ("base64", &[Filter::Base64Decode]),
+    ("base64+inflate", &[Filter::Base64Decode, Filter::Gunzip]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub chunk_index: usize,
+    pub chunk_type: String,
+    pub decoding: &'static str,
+    pub text: String,
+    /// Fraction of characters that are printable, from 0 to 1.
+    pub printability: f64,
+}
+
+pub fn printability(text: &str) -> f64 {
+    let (mut printable, mut total) = (0, 0);
+    for c in text.chars() {
+        total += 1;
+        if c != char::REPLACEMENT_CHARACTER && (!c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        {
+            printable += 1;
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        printable as f64 / total as f64
+    }
+}
+
+/// Tries each decoding on every non-empty ancillary chunk, most printable first.
+pub fn candidates(png: &Png) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        if chunk.chunk_type().is_critical() || chunk.data().is_empty() {
+            continue;
+        }
+        for (decoding, filters) in DECODINGS {
+            let Ok(data) = crate::filter::apply_all(filters, chunk.data()) else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(&data).into_owned();
+            candidates.push(Candidate {
+                chunk_index: index,
+                chunk_type: chunk.chunk_type().to_string(),
+                decoding,
+                printability: printability(&text),
+                text,
+            });
+        }
+    }
+    candidates.sort_by(|a, b| b.printability.total_cmp(&a.printability));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_printability() {
+        assert_eq!(printability("plain text\n"), 1.0);
+        assert_eq!(printability("ab\0\u{1}"), 0.5);
+        assert_eq!(printability(""), 0.0);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"the hidden message").unwrap();
+        let compressed = STANDARD.encode(encoder.finish().unwrap());
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", b"critical chunks are skipped"),
+            chunk("abCd", &[0xff, 0xfe, 0x00, 0x01]),
+            chunk("ruSt", compressed.as_bytes()),
+            chunk("eMPt", b""),
+        ]);
+        let candidates = candidates(&png);
+        assert!(candidates
+            .iter()
+            .all(|c| c.chunk_index == 1 || c.chunk_index == 2));
+        let best = &candidates[0];
+        assert_eq!(printability(&best.text), 1.0);
+        let decoded = candidates
+            .iter()
+            .find(|c| c.decoding == "base64+inflate")
+            .unwrap();
+        assert_eq!(decoded.text, "the hidden message");
+        assert_eq!(decoded.chunk_type, "ruSt");
+        assert!(candidates.last().unwrap().printability < 0.5);
+    }
+}
@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::hex;
+use crate::png::Png;
+
+/// Private, ancillary, safe-to-copy chunk holding an X25519 public key offered for a handshake.
+pub const HANDSHAKE_CHUNK_TYPE: &str = "hsKe";
+
+const KEY_LEN: usize = 32;
+/// Binds derived keys to this protocol, so the same key pair used elsewhere yields a different key.
+const INFO: &[u8] = b"pngme handshake v1";
+
+fn key_from_hex(hex: &str, what: &str) -> Result<Zeroizing<[u8; KEY_LEN]>, String> {
+    let bytes = hex::decode(hex.trim())
+        .map(Zeroizing::new)
+        .map_err(|()| format!("the {} is not hex", what))?;
+    if bytes.len() != KEY_LEN {
+        return Err(format!("the {} is not {} bytes", what, KEY_LEN));
+    }
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// One party's half of a handshake. The public key travels in a carrier image; the secret
+/// stays in a file until the other party's public key arrives.
+pub struct KeyPair {
+    secret: StaticSecret,
+}
+
+impl KeyPair {
+    pub fn generate() -> Result<Self, String> {
+        let mut bytes = Zeroizing::new([0; KEY_LEN]);
+        getrandom::fill(&mut *bytes)
+            .map_err(|error| format!("cannot get random bytes: {}", error))?;
+        Ok(Self {
+            secret: StaticSecret::from(*bytes),
+        })
+    }
+
+    pub fn public_key(&self) -> [u8; KEY_LEN] {
+        PublicKey::from(&self.secret).to_bytes()
+    }
+
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(self.secret.as_bytes()))
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let bytes = key_from_hex(hex, "handshake secret")?;
+        Ok(Self {
+            secret: StaticSecret::from(*bytes),
+        })
+    }
+}
+
+pub fn public_key_chunk(public_key: &[u8; KEY_LEN]) -> Chunk {
+    let chunk_type = ChunkType::from_str(HANDSHAKE_CHUNK_TYPE).unwrap();
+    Chunk::new(chunk_type, public_key.to_vec())
+}
+
+pub fn public_key_from_png(png: &Png) -> Option<Result<[u8; KEY_LEN], ()>> {
+    png.chunk_by_type(HANDSHAKE_CHUNK_TYPE)
+        .map(|chunk| chunk.data().try_into().map_err(|_| ()))
+}
+
+/// The key both parties derive from their own secret and the other's public key.
+pub struct SessionKey {
+    key: Zeroizing<[u8; KEY_LEN]>,
+}
+
+impl SessionKey {
+    /// HKDF-SHA256 of the X25519 shared secret, salted with both public keys in sorted order
+    /// so that either side gets the same key.
+    pub fn derive(ours: &KeyPair, theirs: &[u8; KEY_LEN]) -> Result<Self, String> {
+        let shared = ours.secret.diffie_hellman(&PublicKey::from(*theirs));
+        if !shared.was_contributory() {
+            return Err("the other party's public key is invalid".into());
+        }
+        let mut public_keys = [ours.public_key(), *theirs];
+        public_keys.sort();
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        Hkdf::<Sha256>::new(Some(&public_keys.concat()), shared.as_bytes())
+            .expand(INFO, &mut *key)
+            .unwrap();
+        Ok(Self { key })
+    }
+
+    pub fn key(&self) -> &[u8; KEY_LEN] {
+        &self.key
+    }
+
+    /// A short digest of the key for the two parties to compare over another channel, which
+    /// shows that nobody replaced either carrier image in transit.
+    pub fn fingerprint(&self) -> String {
+        hex::encode(&Sha256::digest(*self.key)[..8])
+    }
+
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(&*self.key))
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        Ok(Self {
+            key: key_from_hex(hex, "session key")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_both_sides_derive_the_same_key() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let alice_key = SessionKey::derive(&alice, &bob.public_key()).unwrap();
+        let bob_key = SessionKey::derive(&bob, &alice.public_key()).unwrap();
+        assert_eq!(alice_key.key(), bob_key.key());
+        assert_eq!(alice_key.fingerprint(), bob_key.fingerprint());
+        let eve = KeyPair::generate().unwrap();
+        let eve_key = SessionKey::derive(&eve, &bob.public_key()).unwrap();
+        assert_ne!(eve_key.key(), bob_key.key());
+        assert!(SessionKey::derive(&alice, &[0; KEY_LEN]).is_err());
+    }
+
+    #[test]
+    fn test_hex_round_trips() {
+        let alice = KeyPair::generate().unwrap();
+        let restored = KeyPair::from_hex(&alice.to_hex()).unwrap();
+        assert_eq!(restored.public_key(), alice.public_key());
+        let session =
+            SessionKey::derive(&alice, &KeyPair::generate().unwrap().public_key()).unwrap();
+        let restored = SessionKey::from_hex(&format!("{}\n", *session.to_hex())).unwrap();
+        assert_eq!(restored.key(), session.key());
+        assert!(SessionKey::from_hex("abcd").is_err());
+        assert!(KeyPair::from_hex("not hex").is_err());
+    }
+
+    #[test]
+    fn test_public_key_chunk() {
+        let public_key = KeyPair::generate().unwrap().public_key();
+        let mut png = Png::from_chunks(vec![Chunk::new(ChunkType::IEND, vec![])]);
+        assert!(public_key_from_png(&png).is_none());
+        png.append_chunk(public_key_chunk(&public_key));
+        assert_eq!(public_key_from_png(&png), Some(Ok(public_key)));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::hex;
+use crate::png::Png;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    /// The hex digest of `bytes`.
+    pub fn digest(self, bytes: &[u8]) -> String {
+        match self {
+            Algorithm::Sha256 => hex::encode(&Sha256::digest(bytes)),
+            Algorithm::Sha384 => hex::encode(&Sha384::digest(bytes)),
+            Algorithm::Sha512 => hex::encode(&Sha512::digest(bytes)),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+    fn from_str(algorithm: &str) -> Result<Self, String> {
+        match algorithm {
+            "sha256" => Ok(Algorithm::Sha256),
+            "sha384" => Ok(Algorithm::Sha384),
+            "sha512" => Ok(Algorithm::Sha512),
+            other => Err(format!(
+                "unknown algorithm `{}`; expected sha256, sha384 or sha512",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Algorithm::Sha256 => write!(fmt, "sha256"),
+            Algorithm::Sha384 => write!(fmt, "sha384"),
+            Algorithm::Sha512 => write!(fmt, "sha512"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkDigest {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    /// Digest of the chunk's payload.
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    /// As given to `pngme hash`, so relative paths are verified from the same directory.
+    pub path: String,
+    /// Digest of the whole file.
+    pub digest: String,
+    pub chunks: Vec<ChunkDigest>,
+}
+
+impl FileDigest {
+    /// Digests `bytes`, a file that parses as `png`, and each of its chunks.
+    pub fn new(path: String, algorithm: Algorithm, bytes: &[u8], png: &Png) -> Self {
+        let chunks = png
+            .chunks()
+            .iter()
+            .map(|chunk| ChunkDigest {
+                chunk_type: chunk.chunk_type().to_string(),
+                digest: algorithm.digest(chunk.data()),
+            })
+            .collect();
+        Self {
+            path,
+            digest: algorithm.digest(bytes),
+            chunks,
+        }
+    }
+
+    /// How `actual`, a later digest of the same file, differs from this one; empty if it doesn't.
+    pub fn differences(&self, actual: &FileDigest) -> Vec<String> {
+        if self.digest == actual.digest {
+            return Vec::new();
+        }
+        let mut differences = Vec::new();
+        for (index, (expected, found)) in self.chunks.iter().zip(&actual.chunks).enumerate() {
+            if expected.chunk_type != found.chunk_type {
+                differences.push(format!(
+                    "chunk {} is {}, expected {}",
+                    index, found.chunk_type, expected.chunk_type
+                ));
+            } else if expected.digest != found.digest {
+                differences.push(format!(
+                    "chunk {} ({}) payload changed",
+                    index, found.chunk_type
+                ));
+            }
+        }
+        if self.chunks.len() != actual.chunks.len() {
+            differences.push(format!(
+                "{} chunks, expected {}",
+                actual.chunks.len(),
+                self.chunks.len()
+            ));
+        }
+        if differences.is_empty() {
+            differences.push(String::from("bytes outside the chunks changed"));
+        }
+        differences
+    }
+}
+
+/// Digests of a set of files, as written by `pngme hash --manifest`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub algorithm: Algorithm,
+    pub files: Vec<FileDigest>,
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|error| format!("invalid manifest: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+
+    fn file_digest(payload: &[u8], extra: bool) -> FileDigest {
+        let mut chunks = vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::TEXT, payload.to_vec()),
+        ];
+        if extra {
+            chunks.push(Chunk::new(ChunkType::TIME, vec![0; 7]));
+        }
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        let png = Png::from_chunks(chunks);
+        FileDigest::new("a.png".into(), Algorithm::Sha256, &png.as_bytes(), &png)
+    }
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(
+            Algorithm::Sha256.digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(Algorithm::Sha512.digest(b"").len(), 128);
+        assert_eq!("sha384".parse::<Algorithm>(), Ok(Algorithm::Sha384));
+        assert!("md5".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_differences() {
+        let original = file_digest(b"one", false);
+        assert!(original.differences(&file_digest(b"one", false)).is_empty());
+        assert_eq!(
+            original.differences(&file_digest(b"two", false)),
+            ["chunk 1 (tEXt) payload changed"]
+        );
+        assert_eq!(
+            original.differences(&file_digest(b"one", true)),
+            ["chunk 2 is tIME, expected IEND", "4 chunks, expected 3"]
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let manifest = Manifest {
+            algorithm: Algorithm::Sha256,
+            files: vec![file_digest(b"one", false)],
+        };
+        let json = manifest.to_json();
+        assert!(json.contains("\"algorithm\": \"sha256\""));
+        assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+        assert!(Manifest::from_json("{}").is_err());
+    }
+}
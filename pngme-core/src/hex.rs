@@ -0,0 +1,33 @@
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes pairs of hex digits, ignoring whitespace between them.
+pub fn decode(hex: &str) -> Result<Vec<u8>, ()> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).map_err(|_| ()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(encode(&[0, 0x7f, 0xff]), "007fff");
+        assert_eq!(decode("00 7F\nff").unwrap(), [0, 0x7f, 0xff]);
+    }
+
+    #[test]
+    fn test_invalid_hex() {
+        assert!(decode("abc").is_err());
+        assert!(decode("zz").is_err());
+        assert!(decode("+1").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! An annotated hex dump of a whole file for `pngme hexview`: the signature, then each
+//! chunk with its length, type, payload and CRC told apart, then anything after IEND or
+//! past the last chunk that could be read. The file is walked as bytes rather than parsed,
+//! so damaged files come out as far as their chunk headers make sense.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::chunk::{Chunk, ChunkError};
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::report;
+
+/// Bytes on each line of the dump.
+pub const WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Signature,
+    Length,
+    Type,
+    Data,
+    /// Whether the CRC matches the type and data.
+    Crc(bool),
+    /// Bytes after IEND, or that don't form a chunk.
+    Trailer,
+}
+
+impl Field {
+    fn class(self) -> &'static str {
+        match self {
+            Field::Signature => "sig",
+            Field::Length => "len",
+            Field::Type => "type",
+            Field::Data => "data",
+            Field::Crc(true) => "crc",
+            Field::Crc(false) => "bad",
+            Field::Trailer => "trail",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Field::Signature => "PNG signature",
+            Field::Length => "data length",
+            Field::Type => "chunk type",
+            Field::Data => "chunk data",
+            Field::Crc(true) => "CRC",
+            Field::Crc(false) => "CRC, which doesn't match",
+            Field::Trailer => "not part of any chunk",
+        }
+    }
+}
+
+/// A run of the file shown as one block under its own heading.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Section {
+    pub label: String,
+    pub range: Range<usize>,
+    /// The chunk type, for chunk sections.
+    pub chunk_type: Option<ChunkType>,
+    pub crc_ok: bool,
+}
+
+impl Section {
+    /// What the byte at `offset`, which is within the section, belongs to.
+    pub fn field_at(&self, offset: usize) -> Field {
+        if self.chunk_type.is_none() {
+            return match self.range.start {
+                0 => Field::Signature,
+                _ => Field::Trailer,
+            };
+        }
+        match offset - self.range.start {
+            0..4 => Field::Length,
+            4..8 => Field::Type,
+            _ if self.range.end - offset > 4 => Field::Data,
+            _ => Field::Crc(self.crc_ok),
+        }
+    }
+
+    /// The offsets each line of the section starts at.
+    pub fn lines(&self) -> impl Iterator<Item = usize> {
+        self.range.clone().step_by(WIDTH)
+    }
+}
+
+/// The sections of `bytes`, which cover the whole of it in order.
+pub fn sections(bytes: &[u8]) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut offset = 0;
+    if bytes.starts_with(&Png::STANDARD_HEADER) {
+        sections.push(Section {
+            label: String::from("signature"),
+            range: 0..Png::STANDARD_HEADER.len(),
+            chunk_type: None,
+            crc_ok: true,
+        });
+        offset = Png::STANDARD_HEADER.len();
+        let mut index = 0;
+        while offset < bytes.len() {
+            let (chunk_type, crc_ok) = match Chunk::parse(&bytes[offset..], true) {
+                Ok(taken) => (*taken.chunk.chunk_type(), true),
+                Err(ChunkError::CrcMismatch) => {
+                    let type_bytes: [u8; 4] = bytes[offset + 4..offset + 8].try_into().unwrap();
+                    (ChunkType::try_from(type_bytes).unwrap(), false)
+                }
+                Err(_) => break,
+            };
+            let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap());
+            let end = offset + 12 + length as usize;
+            sections.push(Section {
+                label: format!(
+                    "chunk #{} {}, {} bytes of data{}",
+                    index,
+                    chunk_type,
+                    length,
+                    if crc_ok { "" } else { ", CRC mismatch" }
+                ),
+                range: offset..end,
+                chunk_type: Some(chunk_type),
+                crc_ok,
+            });
+            offset = end;
+            index += 1;
+            if chunk_type == ChunkType::IEND {
+                break;
+            }
+        }
+    }
+    if offset < bytes.len() {
+        sections.push(Section {
+            label: match offset {
+                0 => String::from("not a PNG file"),
+                _ => format!("{} bytes not part of any chunk", bytes.len() - offset),
+            },
+            range: offset..bytes.len(),
+            chunk_type: None,
+            crc_ok: true,
+        });
+    }
+    sections
+}
+
+/// The printable ASCII character for `byte`, or `.`.
+pub fn printable(byte: u8) -> char {
+    match byte {
+        0x20..=0x7e => byte as char,
+        _ => '.',
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+pre{font-size:.85em;line-height:1.3;margin:.2em 0 1em}h2{font-size:1em;margin:0}\
+.off{color:#999}.sig{background:#ddd}.len{background:#fde0c5}.type{background:#f4a582;\
+font-weight:bold}.crc{background:#c7e9c0}.bad{background:#e7298a;color:#fff}\
+.trail{background:#fbb4ae}";
+
+/// A self-contained HTML page of the dump, each field coloured and titled with what it is.
+pub fn html(name: &str, bytes: &[u8]) -> String {
+    let title = report::escape(name);
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>pngme hexview: {}</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>{}</h1>\n<p>{} bytes. <span class=\"len\">length</span> \
+         <span class=\"type\">type</span> data <span class=\"crc\">CRC</span> \
+         <span class=\"bad\">bad CRC</span> <span class=\"trail\">trailing</span></p>",
+        title,
+        STYLE,
+        title,
+        bytes.len()
+    )
+    .unwrap();
+    for section in sections(bytes) {
+        writeln!(
+            html,
+            "<h2>{:08x} {}</h2>\n<pre>",
+            section.range.start,
+            report::escape(&section.label)
+        )
+        .unwrap();
+        for start in section.lines() {
+            let end = (start + WIDTH).min(section.range.end);
+            write!(html, "<span class=\"off\">{:08x}</span> ", start).unwrap();
+            // One span for each run of the line in the same field.
+            let mut run = start;
+            while run < end {
+                let field = section.field_at(run);
+                let run_end = (run..end)
+                    .find(|&offset| section.field_at(offset) != field)
+                    .unwrap_or(end);
+                let hex: Vec<String> = bytes[run..run_end]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                write!(
+                    html,
+                    "<span class=\"{}\" title=\"{}\">{}</span> ",
+                    field.class(),
+                    field.description(),
+                    hex.join(" ")
+                )
+                .unwrap();
+                run = run_end;
+            }
+            let padding = "   ".repeat(WIDTH - (end - start));
+            let text: String = bytes[start..end].iter().map(|&b| printable(b)).collect();
+            writeln!(html, "{} {}", padding, report::escape(&text)).unwrap();
+        }
+        html.push_str("</pre>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing;
+
+    #[test]
+    fn test_sections() {
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        png.append_chunk(Chunk::new(ChunkType::TEXT, b"Title\0<hex>".to_vec()));
+        let mut bytes = png.as_bytes();
+        bytes.extend(b"tail");
+        let sections = sections(&bytes);
+        assert_eq!(sections.len(), png.chunks().len() + 2);
+        assert_eq!(sections[0].field_at(7), Field::Signature);
+        let text = &sections[3];
+        assert_eq!(text.chunk_type, Some(ChunkType::TEXT));
+        assert_eq!(text.label, "chunk #2 tEXt, 11 bytes of data");
+        let start = text.range.start;
+        assert_eq!(text.field_at(start + 3), Field::Length);
+        assert_eq!(text.field_at(start + 4), Field::Type);
+        assert_eq!(text.field_at(start + 8), Field::Data);
+        assert_eq!(text.field_at(text.range.end - 4), Field::Crc(true));
+        assert_eq!(text.lines().count(), 2);
+        let tail = sections.last().unwrap();
+        assert_eq!(tail.range.len(), 4);
+        assert_eq!(tail.field_at(tail.range.start), Field::Trailer);
+
+        let crc = sections[1].range.end - 1;
+        bytes[crc] ^= 1;
+        let damaged = super::sections(&bytes);
+        assert!(damaged[1].label.ends_with("CRC mismatch"));
+        assert_eq!(damaged[1].field_at(crc), Field::Crc(false));
+        assert_eq!(damaged.len(), sections.len());
+        assert_eq!(super::sections(b"GIF89a")[0].label, "not a PNG file");
+
+        let html = html("a.png", &bytes);
+        assert!(html.contains("Title.&lt;h"));
+        assert!(html.contains("class=\"bad\""));
+    }
+}
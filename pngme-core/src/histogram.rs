@@ -0,0 +1,84 @@
+use crate::background::palette_len;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// hIST: how often each palette entry is used, relative to the others, for a viewer that has
+/// to choose a smaller palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    frequencies: Vec<u16>,
+}
+
+impl Histogram {
+    /// Checks that there is one frequency for each of the `palette_len` palette entries.
+    pub fn new(frequencies: Vec<u16>, palette_len: usize) -> Result<Self, String> {
+        if palette_len == 0 {
+            return Err("hIST needs a PLTE chunk to describe".into());
+        }
+        if frequencies.len() != palette_len {
+            return Err(format!(
+                "hIST has {} entries but PLTE has {}",
+                frequencies.len(),
+                palette_len
+            ));
+        }
+        Ok(Self { frequencies })
+    }
+
+    pub fn from_bytes(data: &[u8], palette_len: usize) -> Result<Self, String> {
+        if !data.len().is_multiple_of(2) {
+            return Err(format!(
+                "hIST is {} bytes, not a whole number of 2-byte entries",
+                data.len()
+            ));
+        }
+        let frequencies = data
+            .chunks_exact(2)
+            .map(|entry| u16::from_be_bytes([entry[0], entry[1]]))
+            .collect();
+        Self::new(frequencies, palette_len)
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, String>> {
+        let chunk = png.chunk_by_type(ChunkType::HIST)?;
+        Some(Self::from_bytes(chunk.data(), palette_len(png)))
+    }
+
+    pub fn frequencies(&self) -> &[u16] {
+        &self.frequencies
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = self
+            .frequencies
+            .iter()
+            .flat_map(|frequency| frequency.to_be_bytes())
+            .collect();
+        Chunk::new(ChunkType::HIST, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    #[test]
+    fn test_histogram() {
+        let mut png = png_with_header(ColorType::Indexed, 2, 3);
+        assert!(Histogram::from_png(&png).is_none());
+        let histogram = Histogram::new(vec![10, 0, 65535], 3).unwrap();
+        png.insert_ancillary(histogram.to_chunk());
+        assert_eq!(png.chunks()[2].data(), [0, 10, 0, 0, 255, 255]);
+        assert_eq!(Histogram::from_png(&png), Some(Ok(histogram)));
+
+        assert_eq!(
+            Histogram::new(vec![1, 2], 3).unwrap_err(),
+            "hIST has 2 entries but PLTE has 3"
+        );
+        assert!(Histogram::new(vec![], 0).is_err());
+        assert!(Histogram::from_bytes(&[0, 1, 2], 1).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Private, ancillary, safe-to-copy chunk holding one earlier version of a message chunk.
+pub const HISTORY_CHUNK_TYPE: &str = "hiSt";
+
+/// One version of the message stored in chunks of `chunk_type`, numbered from 1 for the oldest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub chunk_type: ChunkType,
+    pub sequence: u32,
+    pub data: Vec<u8>,
+}
+
+impl Version {
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::from_str(HISTORY_CHUNK_TYPE).unwrap();
+        Chunk::new(chunk_type, self.to_bytes())
+    }
+
+    /// The chunk type of the message, the sequence number as a big-endian `u32`, then the payload.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.data.len());
+        bytes.extend_from_slice(&self.chunk_type.bytes());
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() < 8 {
+            return Err(());
+        }
+        let chunk_type = ChunkType::try_from(bytes[..4].try_into().unwrap()).map_err(|_| ())?;
+        let sequence = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        Ok(Self {
+            chunk_type,
+            sequence,
+            data: bytes[8..].to_vec(),
+        })
+    }
+}
+
+/// Every version of the message in chunks of `chunk_type`, oldest first. The first such chunk
+/// in the file is the latest version; the earlier ones come from history chunks.
+pub fn versions(png: &Png, chunk_type: ChunkType) -> Result<Vec<Version>, ()> {
+    let mut versions = Vec::new();
+    for chunk in png.chunks() {
+        if chunk.chunk_type().to_string() == HISTORY_CHUNK_TYPE {
+            let version = Version::from_bytes(chunk.data())?;
+            if version.chunk_type == chunk_type {
+                versions.push(version);
+            }
+        }
+    }
+    versions.sort_by_key(|version| version.sequence);
+    if let Some(current) = png.chunk_by_type(chunk_type) {
+        versions.push(Version {
+            chunk_type,
+            sequence: versions.last().map_or(1, |last| last.sequence + 1),
+            data: current.data().to_vec(),
+        });
+    }
+    Ok(versions)
+}
+
+/// Stores `chunk` as the latest version of its message, moving the chunk it replaces into a
+/// history chunk rather than discarding it.
+pub fn append(png: &mut Png, chunk: Chunk) -> Result<(), ()> {
+    let chunk_type = *chunk.chunk_type();
+    let position = png
+        .chunks()
+        .iter()
+        .position(|existing| *existing.chunk_type() == chunk_type);
+    let Some(position) = position else {
+        png.append_chunk(chunk);
+        return Ok(());
+    };
+    let sequence = versions(png, chunk_type)?
+        .last()
+        .map_or(1, |last| last.sequence);
+    let previous = png.replace_chunk(position, chunk);
+    let version = Version {
+        chunk_type,
+        sequence,
+        data: previous.data().to_vec(),
+    };
+    png.append_chunk(version.to_chunk());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_round_trip() {
+        let version = Version {
+            chunk_type: ChunkType::from_str("ruSt").unwrap(),
+            sequence: 3,
+            data: b"old".to_vec(),
+        };
+        assert_eq!(version.to_bytes(), b"ruSt\0\0\0\x03old");
+        assert_eq!(Version::from_bytes(&version.to_bytes()), Ok(version));
+        assert!(Version::from_bytes(b"ruSt\0").is_err());
+        assert!(Version::from_bytes(b"ru5t\0\0\0\x01").is_err());
+    }
+
+    #[test]
+    fn test_append() {
+        let rust = ChunkType::from_str("ruSt").unwrap();
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        assert!(versions(&png, rust).unwrap().is_empty());
+        for message in ["one", "two", "three"] {
+            append(&mut png, Chunk::new(rust, message.as_bytes().to_vec())).unwrap();
+        }
+        append(&mut png, Chunk::new(ChunkType::TEXT, b"other".to_vec())).unwrap();
+        let versions = versions(&png, rust).unwrap();
+        let summary: Vec<(u32, &[u8])> = versions
+            .iter()
+            .map(|version| (version.sequence, &version.data[..]))
+            .collect();
+        assert_eq!(
+            summary,
+            [(1, &b"one"[..]), (2, &b"two"[..]), (3, &b"three"[..])]
+        );
+        assert_eq!(png.chunk_by_type(rust).unwrap().data(), b"three");
+        assert_eq!(png.chunks().len(), 6);
+        assert_eq!(*png.chunks().last().unwrap().chunk_type(), ChunkType::IEND);
+    }
+}
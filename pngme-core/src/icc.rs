@@ -0,0 +1,398 @@
+use std::io::{Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::image::ColorType;
+use crate::png::Png;
+
+/// The fixed-size part of an ICC profile, before the tag count and table.
+const HEADER_LEN: usize = 128;
+const SIGNATURE: &[u8] = b"acsp";
+/// Input, display, output and colour space profiles: the classes PNG allows in iCCP.
+const CLASSES: [&[u8]; 4] = [b"scnr", b"mntr", b"prtr", b"spac"];
+
+/// Chunks that say how to interpret the image's colours, any of which would override or
+/// contradict a newly assigned profile.
+pub(crate) const COLOUR_SPACE_CHUNKS: [ChunkType; 5] = [
+    ChunkType::ICCP,
+    ChunkType::SRGB,
+    ChunkType::CICP,
+    ChunkType::GAMA,
+    ChunkType::CHRM,
+];
+
+fn read_u32(bytes: &[u8], offset: usize) -> usize {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+/// Checks the parts of an ICC profile header that readers depend on, and that the profile's
+/// colour space suits an image of `color_type`: RGB for colour, GRAY for greyscale.
+pub fn validate(profile: &[u8], color_type: ColorType) -> Result<(), String> {
+    if profile.len() < HEADER_LEN + 4 {
+        return Err(format!(
+            "the profile is {} bytes, too short for an ICC header",
+            profile.len()
+        ));
+    }
+    if profile[36..40] != *SIGNATURE {
+        return Err("the profile has no 'acsp' signature".into());
+    }
+    let size = read_u32(profile, 0);
+    if size != profile.len() {
+        return Err(format!(
+            "the profile's header gives its size as {} bytes but it is {}",
+            size,
+            profile.len()
+        ));
+    }
+    if !(2..=4).contains(&profile[8]) {
+        return Err(format!("ICC version {} is not supported", profile[8]));
+    }
+    if !CLASSES.contains(&&profile[12..16]) {
+        return Err(format!(
+            "'{}' profiles cannot be embedded in a PNG",
+            String::from_utf8_lossy(&profile[12..16])
+        ));
+    }
+    let expected: &[u8] = match color_type {
+        ColorType::Grayscale | ColorType::GrayscaleAlpha => b"GRAY",
+        ColorType::Rgb | ColorType::Indexed | ColorType::Rgba => b"RGB ",
+    };
+    if profile[16..20] != *expected {
+        return Err(format!(
+            "the profile is for '{}' data but the image needs '{}'",
+            String::from_utf8_lossy(&profile[16..20]).trim_end(),
+            String::from_utf8_lossy(expected).trim_end()
+        ));
+    }
+    if ![&b"XYZ "[..], b"Lab "].contains(&&profile[20..24]) {
+        return Err("the profile's connection space is neither XYZ nor Lab".into());
+    }
+    let tags = read_u32(profile, HEADER_LEN);
+    let table_end = tags
+        .checked_mul(12)
+        .and_then(|len| len.checked_add(HEADER_LEN + 4))
+        .filter(|&end| end <= size)
+        .ok_or("the profile's tag table runs past its end")?;
+    for entry in (HEADER_LEN + 4..table_end).step_by(12) {
+        let offset = read_u32(profile, entry + 4);
+        let len = read_u32(profile, entry + 8);
+        if offset.checked_add(len).is_none_or(|end| end > size) {
+            return Err(format!(
+                "the profile's '{}' tag runs past its end",
+                String::from_utf8_lossy(&profile[entry..entry + 4])
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// An iCCP chunk: the profile name, a null separator, compression method 0 and the
+/// zlib-compressed profile.
+pub fn iccp_chunk(name: &str, profile: &[u8]) -> Result<Chunk, String> {
+    let mut data = chunk::keyword_bytes(name)?;
+    data.extend_from_slice(&[0, 0]);
+    let mut encoder = ZlibEncoder::new(data, Compression::best());
+    encoder.write_all(profile).unwrap();
+    Ok(Chunk::new(ChunkType::ICCP, encoder.finish().unwrap()))
+}
+
+/// The name and decompressed profile from the image's iCCP chunk.
+pub fn profile(png: &Png) -> Option<Result<(String, Vec<u8>), ()>> {
+    let chunk = png.chunk_by_type(ChunkType::ICCP)?;
+    Some((|| {
+        let data = chunk.data();
+        let separator = data.iter().position(|&byte| byte == 0).ok_or(())?;
+        if data.get(separator + 1) != Some(&0) {
+            return Err(());
+        }
+        let name = data[..separator].iter().map(|&byte| byte as char).collect();
+        let mut profile = Vec::new();
+        ZlibDecoder::new(&data[separator + 2..])
+            .read_to_end(&mut profile)
+            .map_err(|_| ())?;
+        Ok((name, profile))
+    })())
+}
+
+/// Replaces the image's colour-space chunks with `chunks`.
+pub fn assign(png: &mut Png, chunks: Vec<Chunk>) {
+    png.retain_chunks(|chunk| !COLOUR_SPACE_CHUNKS.contains(chunk.chunk_type()));
+    for chunk in chunks {
+        png.insert_ancillary(chunk);
+    }
+}
+
+#[cfg(feature = "builtin-profiles")]
+pub use presets::Preset;
+
+#[cfg(feature = "builtin-profiles")]
+mod presets {
+    use std::fmt::{Display, Formatter};
+    use std::str::FromStr;
+
+    use super::HEADER_LEN;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::image::{
+        adaptation, multiply, rgb_to_xyz, Chromaticities, Matrix, SRGB_CHROMATICITIES,
+    };
+
+    /// The ICC profile connection space's white point, D50, as XYZ.
+    const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+    const DISPLAY_P3_CHROMATICITIES: Chromaticities = Chromaticities {
+        white: (0.3127, 0.3290),
+        red: (0.680, 0.320),
+        green: (0.265, 0.690),
+        blue: (0.150, 0.060),
+    };
+    /// The sRGB transfer function as an ICC parametric curve of type 3: g, a, b, c and d.
+    const SRGB_CURVE: [f64; 5] = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+    /// The gAMA value the PNG specification recommends alongside an sRGB chunk.
+    const SRGB_GAMMA: u32 = 45455;
+
+    /// Colour spaces `pngme icc set` can assign without a profile file.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Preset {
+        Srgb,
+        DisplayP3,
+    }
+
+    impl FromStr for Preset {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.to_ascii_lowercase().as_str() {
+                "srgb" => Ok(Preset::Srgb),
+                "display-p3" | "p3" => Ok(Preset::DisplayP3),
+                _ => Err(format!(
+                    "`{}` is not a built-in profile: use srgb or display-p3",
+                    s
+                )),
+            }
+        }
+    }
+
+    impl Display for Preset {
+        fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+            write!(fmt, "{}", self.name())
+        }
+    }
+
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+        [b"XYZ \0\0\0\0".to_vec(), xyz.map(s15_fixed16).concat()].concat()
+    }
+
+    fn text_tag(text: &str) -> Vec<u8> {
+        let text: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let mut tag = b"mluc\0\0\0\0".to_vec();
+        tag.extend_from_slice(&1u32.to_be_bytes());
+        tag.extend_from_slice(&12u32.to_be_bytes());
+        tag.extend_from_slice(b"enUS");
+        tag.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        tag.extend_from_slice(&28u32.to_be_bytes());
+        tag.extend_from_slice(&text);
+        tag
+    }
+
+    impl Preset {
+        pub fn name(self) -> &'static str {
+            match self {
+                Preset::Srgb => "sRGB",
+                Preset::DisplayP3 => "Display P3",
+            }
+        }
+
+        fn chromaticities(self) -> Chromaticities {
+            match self {
+                Preset::Srgb => SRGB_CHROMATICITIES,
+                Preset::DisplayP3 => DISPLAY_P3_CHROMATICITIES,
+            }
+        }
+
+        /// An ICC v4.3 display profile built from the preset's primaries and the sRGB transfer
+        /// function, which Display P3 shares.
+        pub fn profile(self) -> Vec<u8> {
+            let chromaticities = self.chromaticities();
+            let white = crate::image::xyz(chromaticities.white);
+            let chad: Matrix = adaptation(white, D50).unwrap();
+            let colorants = multiply(&chad, &rgb_to_xyz(&chromaticities).unwrap());
+            let column = |index: usize| colorants.map(|row| row[index]);
+            let mut curve = b"para\0\0\0\0\0\x03\0\0".to_vec();
+            curve.extend(SRGB_CURVE.iter().flat_map(|&value| s15_fixed16(value)));
+            let tags: [(&[u8; 4], Vec<u8>); 10] = [
+                (b"desc", text_tag(self.name())),
+                (b"cprt", text_tag("No copyright, use freely")),
+                (b"wtpt", xyz_tag(D50)),
+                (
+                    b"chad",
+                    [
+                        b"sf32\0\0\0\0".to_vec(),
+                        chad.iter()
+                            .flatten()
+                            .flat_map(|&value| s15_fixed16(value))
+                            .collect(),
+                    ]
+                    .concat(),
+                ),
+                (b"rXYZ", xyz_tag(column(0))),
+                (b"gXYZ", xyz_tag(column(1))),
+                (b"bXYZ", xyz_tag(column(2))),
+                (b"rTRC", curve.clone()),
+                (b"gTRC", curve.clone()),
+                (b"bTRC", curve),
+            ];
+
+            let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+            let mut data = Vec::new();
+            let data_start = HEADER_LEN + 4 + 12 * tags.len();
+            for (signature, tag) in &tags {
+                table.extend_from_slice(*signature);
+                table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+                table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+                data.extend_from_slice(tag);
+                data.resize(data.len().next_multiple_of(4), 0);
+            }
+
+            let mut profile = vec![0; HEADER_LEN];
+            let size = (data_start + data.len()) as u32;
+            profile[..4].copy_from_slice(&size.to_be_bytes());
+            profile[8..12].copy_from_slice(&[4, 0x30, 0, 0]);
+            profile[12..16].copy_from_slice(b"mntr");
+            profile[16..20].copy_from_slice(b"RGB ");
+            profile[20..24].copy_from_slice(b"XYZ ");
+            // Fixed, so that the file is the same whenever the preset is assigned.
+            for (index, field) in [2024u16, 1, 1, 0, 0, 0].iter().enumerate() {
+                profile[24 + 2 * index..26 + 2 * index].copy_from_slice(&field.to_be_bytes());
+            }
+            profile[36..40].copy_from_slice(super::SIGNATURE);
+            profile[68..80].copy_from_slice(&D50.map(s15_fixed16).concat());
+            profile.extend_from_slice(&table);
+            profile.extend_from_slice(&data);
+            profile
+        }
+
+        /// The chunks that assign the preset. sRGB images get an sRGB chunk, with the gAMA
+        /// and cHRM values the PNG specification recommends alongside it for older
+        /// decoders, unless `embed` asks for the profile itself.
+        pub fn chunks(self, embed: bool) -> Vec<Chunk> {
+            if self == Preset::Srgb && !embed {
+                let chromaticities: Vec<u8> = [
+                    SRGB_CHROMATICITIES.white,
+                    SRGB_CHROMATICITIES.red,
+                    SRGB_CHROMATICITIES.green,
+                    SRGB_CHROMATICITIES.blue,
+                ]
+                .iter()
+                .flat_map(|&(x, y)| [x, y])
+                .flat_map(|value| ((value * 100_000.0).round() as u32).to_be_bytes())
+                .collect();
+                // Rendering intent 0, perceptual.
+                return vec![
+                    Chunk::new(ChunkType::SRGB, vec![0]),
+                    Chunk::new(ChunkType::GAMA, SRGB_GAMMA.to_be_bytes().to_vec()),
+                    Chunk::new(ChunkType::CHRM, chromaticities),
+                ];
+            }
+            vec![super::iccp_chunk(self.name(), &self.profile()).unwrap()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut profile = vec![0; HEADER_LEN + 4];
+        profile[..4].copy_from_slice(&(HEADER_LEN as u32 + 4).to_be_bytes());
+        profile[8] = 2;
+        profile[12..24].copy_from_slice(b"mntrGRAYXYZ ");
+        profile[36..40].copy_from_slice(SIGNATURE);
+        assert_eq!(validate(&profile, ColorType::GrayscaleAlpha), Ok(()));
+        assert!(validate(&profile, ColorType::Rgb)
+            .unwrap_err()
+            .contains("'GRAY' data"));
+        assert!(validate(&profile[..100], ColorType::Grayscale).is_err());
+
+        let mut bad_size = profile.clone();
+        bad_size.push(0);
+        assert!(validate(&bad_size, ColorType::Grayscale).is_err());
+        let mut link = profile.clone();
+        link[12..16].copy_from_slice(b"link");
+        assert!(validate(&link, ColorType::Grayscale).is_err());
+        let mut tags = profile.clone();
+        tags[HEADER_LEN + 3] = 1;
+        assert!(validate(&tags, ColorType::Grayscale).is_err());
+    }
+
+    #[test]
+    fn test_iccp_chunk() {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::GAMA, vec![0; 4]),
+            Chunk::new(ChunkType::IDAT, vec![]),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ]);
+        assert!(profile(&png).is_none());
+        assign(&mut png, vec![iccp_chunk("Café", b"profile").unwrap()]);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "iCCP", "IDAT", "IEND"]);
+        assert_eq!(
+            profile(&png),
+            Some(Ok(("Café".into(), b"profile".to_vec())))
+        );
+        for name in ["", " x", "x  y", "tab\t", &"x".repeat(80), "€"] {
+            assert!(iccp_chunk(name, b"").is_err(), "{:?}", name);
+        }
+    }
+
+    #[cfg(feature = "builtin-profiles")]
+    #[test]
+    fn test_presets() {
+        use std::str::FromStr;
+
+        assert_eq!(Preset::from_str("Display-P3"), Ok(Preset::DisplayP3));
+        assert!(Preset::from_str("adobe-rgb").is_err());
+        for preset in [Preset::Srgb, Preset::DisplayP3] {
+            let profile = preset.profile();
+            assert_eq!(validate(&profile, ColorType::Rgb), Ok(()));
+            assert!(validate(&profile, ColorType::Grayscale).is_err());
+        }
+        // The D50-adapted sRGB red colorant published with the ICC's sRGB profiles.
+        let srgb = Preset::Srgb.profile();
+        let entry = (HEADER_LEN + 4..)
+            .step_by(12)
+            .find(|&entry| &srgb[entry..entry + 4] == b"rXYZ")
+            .unwrap();
+        let red = &srgb[read_u32(&srgb, entry + 4)..];
+        let component = |index: usize| {
+            i32::from_be_bytes(red[8 + 4 * index..12 + 4 * index].try_into().unwrap()) as f64
+                / 65536.0
+        };
+        assert!((component(0) - 0.4361).abs() < 0.0005);
+        assert!((component(1) - 0.2225).abs() < 0.0005);
+        assert!((component(2) - 0.0139).abs() < 0.0005);
+
+        let types = |chunks: Vec<Chunk>| -> Vec<String> {
+            chunks
+                .iter()
+                .map(|chunk| chunk.chunk_type().to_string())
+                .collect()
+        };
+        assert_eq!(types(Preset::Srgb.chunks(false)), ["sRGB", "gAMA", "cHRM"]);
+        assert_eq!(types(Preset::Srgb.chunks(true)), ["iCCP"]);
+        assert_eq!(types(Preset::DisplayP3.chunks(false)), ["iCCP"]);
+    }
+}
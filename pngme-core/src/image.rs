@@ -0,0 +1,1371 @@
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::parallel;
+use crate::png::Png;
+use crate::progress::{self, CancelToken, Interrupted, Progress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    Indexed,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+impl ColorType {
+    pub fn from_byte(byte: u8) -> Result<Self, ()> {
+        match byte {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::Rgb),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::Rgba),
+            _ => Err(()),
+        }
+    }
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Grayscale => 0,
+            Self::Rgb => 2,
+            Self::Indexed => 3,
+            Self::GrayscaleAlpha => 4,
+            Self::Rgba => 6,
+        }
+    }
+    pub fn channels(self) -> usize {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+    pub fn allows_bit_depth(self, bit_depth: u8) -> bool {
+        match self {
+            Self::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            Self::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+            _ => matches!(bit_depth, 8 | 16),
+        }
+    }
+}
+
+/// The colour type's name in the PNG specification.
+impl Display for ColorType {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        fmt.write_str(match self {
+            Self::Grayscale => "greyscale",
+            Self::Rgb => "truecolour",
+            Self::Indexed => "indexed-colour",
+            Self::GrayscaleAlpha => "greyscale with alpha",
+            Self::Rgba => "truecolour with alpha",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: ColorType,
+    pub interlaced: bool,
+}
+
+impl ImageHeader {
+    pub fn from_png(png: &Png) -> Result<Self, ()> {
+        Self::from_bytes(png.chunk_by_type(ChunkType::IHDR).ok_or(())?.data())
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ()> {
+        if data.len() != 13 {
+            return Err(());
+        }
+        let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+        let bit_depth = data[8];
+        let color_type = ColorType::from_byte(data[9])?;
+        let (compression, filter, interlace) = (data[10], data[11], data[12]);
+        if width == 0
+            || height == 0
+            || !color_type.allows_bit_depth(bit_depth)
+            || compression != 0
+            || filter != 0
+            || interlace > 1
+        {
+            return Err(());
+        }
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced: interlace == 1,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 13] {
+        let mut bytes = [0; 13];
+        bytes[0..4].copy_from_slice(&self.width.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.height.to_be_bytes());
+        bytes[8] = self.bit_depth;
+        bytes[9] = self.color_type.to_byte();
+        bytes[12] = self.interlaced as u8;
+        bytes
+    }
+
+    pub fn bits_per_pixel(&self) -> usize {
+        self.bit_depth as usize * self.color_type.channels()
+    }
+
+    /// Bytes in one unfiltered scanline of `width` pixels, excluding the filter byte.
+    pub fn row_bytes(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+}
+
+/// A colour as raw sample values at the image's bit depth: one value for a palette index or
+/// grey level, or red, green and blue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Level(u16),
+    Rgb([u16; 3]),
+}
+
+impl Color {
+    /// Checks that the colour suits an image with `header` and `palette_len` palette entries.
+    pub fn check(self, header: &ImageHeader, palette_len: usize) -> Result<(), String> {
+        let max = (1u32 << header.bit_depth) - 1;
+        let values = match (header.color_type, self) {
+            (ColorType::Indexed, Color::Level(index)) => {
+                if index as usize >= palette_len {
+                    return Err(format!(
+                        "palette index {} is out of range: the palette has {} entries",
+                        index, palette_len
+                    ));
+                }
+                vec![index]
+            }
+            (ColorType::Grayscale | ColorType::GrayscaleAlpha, Color::Level(level)) => vec![level],
+            (ColorType::Rgb | ColorType::Rgba, Color::Rgb(rgb)) => rgb.to_vec(),
+            (ColorType::Indexed, Color::Rgb(_)) => {
+                return Err("the image is palette-based: give a palette index".into())
+            }
+            (ColorType::Grayscale | ColorType::GrayscaleAlpha, Color::Rgb(_)) => {
+                return Err("the image is greyscale: give a single grey level".into())
+            }
+            (ColorType::Rgb | ColorType::Rgba, Color::Level(_)) => {
+                return Err("the image is RGB: give red, green and blue values".into())
+            }
+        };
+        match values.into_iter().find(|&value| value as u32 > max) {
+            Some(value) => Err(format!(
+                "{} does not fit in the image's {} bits per sample",
+                value, header.bit_depth
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The colour as bKGD stores it, and tRNS for greyscale and RGB images: one byte for a
+    /// palette index, otherwise two bytes per sample.
+    pub fn to_bytes(self, color_type: ColorType) -> Vec<u8> {
+        match self {
+            Color::Level(index) if color_type == ColorType::Indexed => vec![index as u8],
+            Color::Level(level) => level.to_be_bytes().to_vec(),
+            Color::Rgb(rgb) => rgb.iter().flat_map(|sample| sample.to_be_bytes()).collect(),
+        }
+    }
+
+    pub fn from_bytes(data: &[u8], color_type: ColorType) -> Result<Self, ()> {
+        let sample = |index: usize| u16::from_be_bytes([data[2 * index], data[2 * index + 1]]);
+        match (color_type, data.len()) {
+            (ColorType::Indexed, 1) => Ok(Color::Level(data[0] as u16)),
+            (ColorType::Grayscale | ColorType::GrayscaleAlpha, 2) => Ok(Color::Level(sample(0))),
+            (ColorType::Rgb | ColorType::Rgba, 6) => {
+                Ok(Color::Rgb([sample(0), sample(1), sample(2)]))
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// `N`, or `R,G,B`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<u16> = s
+            .split(',')
+            .map(|value| value.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("`{}` is not a sample value or R,G,B triple", s))?;
+        match values[..] {
+            [level] => Ok(Color::Level(level)),
+            [red, green, blue] => Ok(Color::Rgb([red, green, blue])),
+            _ => Err(format!("`{}` is not a sample value or R,G,B triple", s)),
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Color::Level(level) => write!(fmt, "{}", level),
+            Color::Rgb([red, green, blue]) => write!(fmt, "{},{},{}", red, green, blue),
+        }
+    }
+}
+
+/// How stored sample values relate to light intensity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    Srgb,
+    /// The gAMA exponent: encoded = linear ^ gamma.
+    Gamma(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticities {
+    pub white: (f64, f64),
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+}
+
+pub const SRGB_CHROMATICITIES: Chromaticities = Chromaticities {
+    white: (0.3127, 0.3290),
+    red: (0.64, 0.33),
+    green: (0.30, 0.60),
+    blue: (0.15, 0.06),
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colorimetry {
+    pub transfer: TransferFunction,
+    pub chromaticities: Chromaticities,
+}
+
+impl Colorimetry {
+    /// An sRGB chunk overrides gAMA and cHRM; images with no colour information are assumed sRGB.
+    pub fn from_png(png: &Png) -> Self {
+        let srgb = Self {
+            transfer: TransferFunction::Srgb,
+            chromaticities: SRGB_CHROMATICITIES,
+        };
+        if png.chunk_by_type(ChunkType::SRGB).is_some() {
+            return srgb;
+        }
+        let transfer = png
+            .chunk_by_type(ChunkType::GAMA)
+            .and_then(|chunk| <[u8; 4]>::try_from(chunk.data()).ok())
+            .map(u32::from_be_bytes)
+            .filter(|&gamma| gamma > 0)
+            .map_or(srgb.transfer, |gamma| {
+                TransferFunction::Gamma(gamma as f64 / 100_000.0)
+            });
+        let chromaticities = png
+            .chunk_by_type(ChunkType::CHRM)
+            .and_then(|chunk| parse_chromaticities(chunk.data()))
+            .unwrap_or(SRGB_CHROMATICITIES);
+        Self {
+            transfer,
+            chromaticities,
+        }
+    }
+
+    fn to_linear(self, value: f64) -> f64 {
+        match self.transfer {
+            TransferFunction::Srgb => srgb_to_linear(value),
+            TransferFunction::Gamma(gamma) => value.powf(1.0 / gamma),
+        }
+    }
+}
+
+fn parse_chromaticities(data: &[u8]) -> Option<Chromaticities> {
+    if data.len() != 32 {
+        return None;
+    }
+    let value = |index: usize| {
+        u32::from_be_bytes(data[index * 4..index * 4 + 4].try_into().unwrap()) as f64 / 100_000.0
+    };
+    let point = |index: usize| (value(index), value(index + 1));
+    let chromaticities = Chromaticities {
+        white: point(0),
+        red: point(2),
+        green: point(4),
+        blue: point(6),
+    };
+    let points = [
+        chromaticities.white,
+        chromaticities.red,
+        chromaticities.green,
+        chromaticities.blue,
+    ];
+    points
+        .iter()
+        .all(|&(_, y)| y > 0.0)
+        .then_some(chromaticities)
+}
+
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub(crate) type Matrix = [[f64; 3]; 3];
+
+pub(crate) fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 3]; 3];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (column, value) in result_row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[row][k] * b[k][column]).sum();
+        }
+    }
+    result
+}
+
+fn apply(matrix: &Matrix, vector: [f64; 3]) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value = (0..3).map(|k| matrix[row][k] * vector[k]).sum();
+    }
+    result
+}
+
+fn invert(m: &Matrix) -> Option<Matrix> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let determinant = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2)
+        + m[0][2] * cofactor(1, 2, 0, 1);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+    Some(adjugate.map(|row| row.map(|value| value / determinant)))
+}
+
+pub(crate) fn xyz(point: (f64, f64)) -> [f64; 3] {
+    let (x, y) = point;
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+pub(crate) fn rgb_to_xyz(chromaticities: &Chromaticities) -> Option<Matrix> {
+    let [r, g, b] = [
+        chromaticities.red,
+        chromaticities.green,
+        chromaticities.blue,
+    ]
+    .map(xyz);
+    let primaries = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+    let scale = apply(&invert(&primaries)?, xyz(chromaticities.white));
+    Some(primaries.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]]))
+}
+
+const BRADFORD: Matrix = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// The Bradford transform taking XYZ colours seen under white point `from` to `to`.
+pub(crate) fn adaptation(from: [f64; 3], to: [f64; 3]) -> Option<Matrix> {
+    let source_cone = apply(&BRADFORD, from);
+    let destination_cone = apply(&BRADFORD, to);
+    let mut scale = [[0.0; 3]; 3];
+    for axis in 0..3 {
+        scale[axis][axis] = destination_cone[axis] / source_cone[axis];
+    }
+    Some(multiply(&invert(&BRADFORD)?, &multiply(&scale, &BRADFORD)))
+}
+
+/// Linear RGB in the image's primaries to linear sRGB, adapting the white point with Bradford.
+fn to_srgb_primaries(chromaticities: &Chromaticities) -> Option<Matrix> {
+    let source = rgb_to_xyz(chromaticities)?;
+    let destination = invert(&rgb_to_xyz(&SRGB_CHROMATICITIES)?)?;
+    let adaptation = adaptation(xyz(chromaticities.white), xyz(SRGB_CHROMATICITIES.white))?;
+    Some(multiply(&destination, &multiply(&adaptation, &source)))
+}
+
+/// RGBA pixels with every component in `0.0..=1.0` and straight (unpremultiplied) alpha.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+pub struct ImageData {
+    header: ImageHeader,
+    samples: Vec<u16>,
+    palette: Vec<[u8; 3]>,
+    palette_alpha: Vec<u8>,
+    transparent: Option<Vec<u16>>,
+    colorimetry: Colorimetry,
+}
+
+pub(crate) const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+impl ImageData {
+    pub fn from_png(png: &Png) -> Result<Self, ()> {
+        let mut image = Self::metadata(png)?;
+        let header = image.header;
+        let compressed: Vec<u8> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+            .flat_map(|chunk| chunk.data().iter().copied())
+            .collect();
+
+        let passes: Vec<(usize, usize, usize, usize)> = if header.interlaced {
+            ADAM7.to_vec()
+        } else {
+            vec![(0, 0, 1, 1)]
+        };
+        let (width, height) = (header.width as usize, header.height as usize);
+        let pass_size = |&(x0, y0, dx, dy): &(usize, usize, usize, usize)| {
+            (
+                (width + dx - 1 - x0.min(width)) / dx,
+                (height + dy - 1 - y0.min(height)) / dy,
+            )
+        };
+        let mut expected: usize = 0;
+        for pass in &passes {
+            let (pass_width, pass_height) = pass_size(pass);
+            if pass_width > 0 && pass_height > 0 {
+                let row = header
+                    .row_bytes(pass_width as u32)
+                    .checked_add(1)
+                    .ok_or(())?;
+                expected = expected
+                    .checked_add(row.checked_mul(pass_height).ok_or(())?)
+                    .ok_or(())?;
+            }
+        }
+
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&compressed[..])
+            .take(expected as u64 + 1)
+            .read_to_end(&mut raw)
+            .map_err(|_| ())?;
+        if raw.len() != expected {
+            return Err(());
+        }
+
+        let channels = header.color_type.channels();
+        let mut samples = vec![0; width.checked_mul(height).ok_or(())? * channels];
+        let mut offset = 0;
+        for pass in &passes {
+            let (pass_width, pass_height) = pass_size(pass);
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+            let row_bytes = header.row_bytes(pass_width as u32);
+            let length = (row_bytes + 1) * pass_height;
+            let rows = parallel::unfilter(
+                &raw[offset..offset + length],
+                row_bytes,
+                header.bits_per_pixel(),
+            )?;
+            offset += length;
+
+            let (x0, y0, dx, dy) = *pass;
+            for (pass_y, row) in rows.chunks(row_bytes).enumerate() {
+                let row_samples = unpack(row, header.bit_depth, pass_width * channels);
+                for (pass_x, pixel) in row_samples.chunks(channels).enumerate() {
+                    let index = ((y0 + pass_y * dy) * width + x0 + pass_x * dx) * channels;
+                    samples[index..index + channels].copy_from_slice(pixel);
+                }
+            }
+        }
+
+        image.check_indices(&samples)?;
+        image.samples = samples;
+        Ok(image)
+    }
+
+    /// The image without its samples: the header, palette, transparency and colour space,
+    /// for decoding the pixels a band at a time.
+    pub(crate) fn metadata(png: &Png) -> Result<Self, ()> {
+        let header = ImageHeader::from_png(png)?;
+        let channels = header.color_type.channels();
+        let palette: Vec<[u8; 3]> = png
+            .chunk_by_type(ChunkType::PLTE)
+            .map(|chunk| {
+                chunk
+                    .data()
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                    .collect()
+            })
+            .unwrap_or_default();
+        let transparency = png.chunk_by_type(ChunkType::TRNS).map(|chunk| chunk.data());
+        let (palette_alpha, transparent) = match (header.color_type, transparency) {
+            (ColorType::Indexed, Some(alpha)) => (alpha.to_vec(), None),
+            (ColorType::Grayscale | ColorType::Rgb, Some(color)) => {
+                let color: Vec<u16> = color
+                    .chunks_exact(2)
+                    .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                    .collect();
+                let valid = color.len() == channels;
+                (Vec::new(), valid.then_some(color))
+            }
+            _ => (Vec::new(), None),
+        };
+
+        Ok(Self {
+            header,
+            samples: vec![],
+            palette,
+            palette_alpha,
+            transparent,
+            colorimetry: Colorimetry::from_png(png),
+        })
+    }
+
+    /// Fails if `samples` of an indexed image refer past the end of the palette.
+    pub(crate) fn check_indices(&self, samples: &[u16]) -> Result<(), ()> {
+        if self.header.color_type == ColorType::Indexed
+            && samples
+                .iter()
+                .any(|&index| index as usize >= self.palette.len())
+        {
+            return Err(());
+        }
+        Ok(())
+    }
+
+    /// `rows` rows of `samples` with this image's palette, transparency and colour space.
+    pub(crate) fn band(&self, rows: u32, samples: Vec<u16>) -> Self {
+        Self {
+            header: ImageHeader {
+                height: rows,
+                interlaced: false,
+                ..self.header
+            },
+            samples,
+            palette: self.palette.clone(),
+            palette_alpha: self.palette_alpha.clone(),
+            transparent: self.transparent.clone(),
+            colorimetry: self.colorimetry,
+        }
+    }
+
+    /// `samples` in row order for `header`, with no palette or transparency, ready for
+    /// [`ImageData::write_to`].
+    pub(crate) fn from_samples(
+        header: ImageHeader,
+        samples: Vec<u16>,
+        colorimetry: Colorimetry,
+    ) -> Self {
+        Self {
+            header,
+            samples,
+            palette: vec![],
+            palette_alpha: vec![],
+            transparent: None,
+            colorimetry,
+        }
+    }
+
+    pub fn header(&self) -> &ImageHeader {
+        &self.header
+    }
+    pub fn width(&self) -> u32 {
+        self.header.width
+    }
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+    /// Raw samples in row-major order, `channels()` per pixel; palette indices for indexed images.
+    pub fn samples(&self) -> &[u16] {
+        &self.samples
+    }
+    pub fn channels(&self) -> usize {
+        self.header.color_type.channels()
+    }
+    pub fn samples_mut(&mut self) -> &mut [u16] {
+        &mut self.samples
+    }
+    pub fn colorimetry(&self) -> &Colorimetry {
+        &self.colorimetry
+    }
+
+    /// The `width` by `height` pixels whose top left corner is at `x`, `y`, or an error if
+    /// that reaches outside the image or is empty.
+    pub fn cropped(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Self, ()> {
+        let fits = |start: u32, length: u32, limit: u32| {
+            length > 0 && start.checked_add(length).is_some_and(|end| end <= limit)
+        };
+        if !fits(x, width, self.width()) || !fits(y, height, self.height()) {
+            return Err(());
+        }
+        let channels = self.channels();
+        let row_samples = self.width() as usize * channels;
+        let mut samples = Vec::with_capacity(width as usize * height as usize * channels);
+        for row in y as usize..(y + height) as usize {
+            let start = row * row_samples + x as usize * channels;
+            samples.extend_from_slice(&self.samples[start..start + width as usize * channels]);
+        }
+        Ok(Self {
+            header: ImageHeader {
+                width,
+                height,
+                ..self.header
+            },
+            samples,
+            palette: self.palette.clone(),
+            palette_alpha: self.palette_alpha.clone(),
+            transparent: self.transparent.clone(),
+            colorimetry: self.colorimetry,
+        })
+    }
+
+    /// The image inside a border of `fill`, one pixel's samples, `[top, right, bottom, left]`
+    /// pixels wide.
+    pub fn padded(&self, border: [u32; 4], fill: &[u16]) -> Result<Self, ()> {
+        let [top, right, bottom, left] = border;
+        let width = left
+            .checked_add(self.width())
+            .and_then(|width| width.checked_add(right))
+            .ok_or(())?;
+        let height = top
+            .checked_add(self.height())
+            .and_then(|height| height.checked_add(bottom))
+            .ok_or(())?;
+        let channels = self.channels();
+        if fill.len() != channels {
+            return Err(());
+        }
+        let fill_pixels = |count: u32| fill.iter().copied().cycle().take(count as usize * channels);
+        let mut samples = Vec::with_capacity(width as usize * height as usize * channels);
+        samples.extend(fill_pixels(width * top));
+        for row in self.samples.chunks(self.width() as usize * channels) {
+            samples.extend(fill_pixels(left));
+            samples.extend_from_slice(row);
+            samples.extend(fill_pixels(right));
+        }
+        samples.extend(fill_pixels(width * bottom));
+        Ok(Self {
+            header: ImageHeader {
+                width,
+                height,
+                ..self.header
+            },
+            samples,
+            palette: self.palette.clone(),
+            palette_alpha: self.palette_alpha.clone(),
+            transparent: self.transparent.clone(),
+            colorimetry: self.colorimetry,
+        })
+    }
+
+    /// A `width` by `height` image whose pixel at `x`, `y` is this one's at `source(x, y)`.
+    fn remapped(&self, width: u32, height: u32, source: impl Fn(u32, u32) -> (u32, u32)) -> Self {
+        let channels = self.channels();
+        let mut samples = Vec::with_capacity(width as usize * height as usize * channels);
+        for y in 0..height {
+            for x in 0..width {
+                let (source_x, source_y) = source(x, y);
+                let start =
+                    (source_y as usize * self.width() as usize + source_x as usize) * channels;
+                samples.extend_from_slice(&self.samples[start..start + channels]);
+            }
+        }
+        Self {
+            header: ImageHeader {
+                width,
+                height,
+                ..self.header
+            },
+            samples,
+            palette: self.palette.clone(),
+            palette_alpha: self.palette_alpha.clone(),
+            transparent: self.transparent.clone(),
+            colorimetry: self.colorimetry,
+        }
+    }
+
+    /// The image turned clockwise by `quarter_turns` right angles.
+    pub fn rotated(&self, quarter_turns: u32) -> Self {
+        let (width, height) = (self.width(), self.height());
+        match quarter_turns % 4 {
+            0 => self.remapped(width, height, |x, y| (x, y)),
+            1 => self.remapped(height, width, |x, y| (y, height - 1 - x)),
+            2 => self.remapped(width, height, |x, y| (width - 1 - x, height - 1 - y)),
+            _ => self.remapped(height, width, |x, y| (width - 1 - y, x)),
+        }
+    }
+
+    /// The image mirrored left to right if `horizontal` is set, else top to bottom.
+    pub fn flipped(&self, horizontal: bool) -> Self {
+        let (width, height) = (self.width(), self.height());
+        if horizontal {
+            self.remapped(width, height, |x, y| (width - 1 - x, y))
+        } else {
+            self.remapped(width, height, |x, y| (x, height - 1 - y))
+        }
+    }
+
+    /// Replaces the IHDR and IDAT chunks of `png` with these samples. The image is
+    /// always written non-interlaced with no scanline filtering.
+    pub fn write_to(&self, png: &mut Png) -> Result<(), ()> {
+        let header = ImageHeader {
+            interlaced: false,
+            ..self.header
+        };
+        let row_samples = self.width() as usize * self.channels();
+        let mut raw =
+            Vec::with_capacity((header.row_bytes(header.width) + 1) * header.height as usize);
+        for row in self.samples.chunks(row_samples) {
+            raw.push(0);
+            raw.extend(pack(row, header.bit_depth));
+        }
+        let compressed = parallel::compress(&raw, Compression::default())?;
+        replace_image_data(png, &header, compressed)
+    }
+
+    /// Encoded (non-linear) RGBA of one pixel, normalised to `0.0..=1.0`.
+    fn encoded_rgba(&self, pixel: usize) -> [f64; 4] {
+        let channels = self.channels();
+        let samples = &self.samples[pixel * channels..(pixel + 1) * channels];
+        let max = ((1u32 << self.header.bit_depth) - 1) as f64;
+        let normalize = |sample: u16| sample as f64 / max;
+        let transparent = self
+            .transparent
+            .as_deref()
+            .is_some_and(|color| color == samples);
+        let opaque = if transparent { 0.0 } else { 1.0 };
+        match self.header.color_type {
+            ColorType::Grayscale => {
+                let gray = normalize(samples[0]);
+                [gray, gray, gray, opaque]
+            }
+            ColorType::GrayscaleAlpha => {
+                let gray = normalize(samples[0]);
+                [gray, gray, gray, normalize(samples[1])]
+            }
+            ColorType::Rgb => [
+                normalize(samples[0]),
+                normalize(samples[1]),
+                normalize(samples[2]),
+                opaque,
+            ],
+            ColorType::Rgba => [
+                normalize(samples[0]),
+                normalize(samples[1]),
+                normalize(samples[2]),
+                normalize(samples[3]),
+            ],
+            ColorType::Indexed => {
+                let index = samples[0] as usize;
+                let [r, g, b] = self.palette[index];
+                let alpha = self.palette_alpha.get(index).copied().unwrap_or(255);
+                [
+                    r as f64 / 255.0,
+                    g as f64 / 255.0,
+                    b as f64 / 255.0,
+                    alpha as f64 / 255.0,
+                ]
+            }
+        }
+    }
+
+    /// RGBA as stored, with palette and tRNS applied but no colour conversion.
+    pub fn to_encoded(&self) -> RgbaImage {
+        let pixel_count = self.width() as usize * self.height() as usize;
+        RgbaImage {
+            width: self.width(),
+            height: self.height(),
+            pixels: (0..pixel_count)
+                .map(|pixel| self.encoded_rgba(pixel).map(|value| value as f32))
+                .collect(),
+        }
+    }
+
+    /// Linear-light RGBA in sRGB primaries, honouring the file's gAMA, sRGB and cHRM chunks.
+    pub fn to_linear(&self) -> RgbaImage {
+        let matrix = if self.colorimetry.chromaticities == SRGB_CHROMATICITIES {
+            None
+        } else {
+            to_srgb_primaries(&self.colorimetry.chromaticities)
+        };
+        let pixel_count = self.width() as usize * self.height() as usize;
+        let pixels = (0..pixel_count)
+            .map(|pixel| {
+                let [r, g, b, a] = self.encoded_rgba(pixel);
+                let mut rgb = [r, g, b].map(|value| self.colorimetry.to_linear(value));
+                if let Some(matrix) = &matrix {
+                    rgb = apply(matrix, rgb);
+                }
+                [
+                    rgb[0].clamp(0.0, 1.0) as f32,
+                    rgb[1].clamp(0.0, 1.0) as f32,
+                    rgb[2].clamp(0.0, 1.0) as f32,
+                    a as f32,
+                ]
+            })
+            .collect();
+        RgbaImage {
+            width: self.width(),
+            height: self.height(),
+            pixels,
+        }
+    }
+
+    /// The image re-encoded with the sRGB transfer curve, so files with different
+    /// colour metadata can be compared value for value.
+    pub fn to_srgb(&self) -> RgbaImage {
+        let mut image = self.to_linear();
+        for pixel in &mut image.pixels {
+            for component in &mut pixel[..3] {
+                *component = linear_to_srgb(*component as f64) as f32;
+            }
+        }
+        image
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// The most image data one IDAT chunk is given; the format allows 2^31 - 1 bytes.
+const MAX_IDAT_LENGTH: usize = 1 << 30;
+
+/// Replaces IHDR with `header` and the IDAT chunks with `compressed`, where the first IDAT
+/// was.
+pub(crate) fn replace_image_data(
+    png: &mut Png,
+    header: &ImageHeader,
+    compressed: Vec<u8>,
+) -> Result<(), ()> {
+    let is_type = |chunk: &Chunk, chunk_type: ChunkType| *chunk.chunk_type() == chunk_type;
+    let header_index = png
+        .chunks()
+        .iter()
+        .position(|chunk| is_type(chunk, ChunkType::IHDR))
+        .ok_or(())?;
+    let data_index = png
+        .chunks()
+        .iter()
+        .position(|chunk| is_type(chunk, ChunkType::IDAT))
+        .ok_or(())?;
+    png.replace_chunk(
+        header_index,
+        Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+    );
+    png.retain_chunks(|chunk| !is_type(chunk, ChunkType::IDAT));
+    if compressed.len() <= MAX_IDAT_LENGTH {
+        png.insert_chunk(data_index, Chunk::new(ChunkType::IDAT, compressed));
+    } else {
+        for (offset, data) in compressed.chunks(MAX_IDAT_LENGTH).enumerate() {
+            png.insert_chunk(
+                data_index + offset,
+                Chunk::new(ChunkType::IDAT, data.to_vec()),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A new PNG of 8-bit RGBA pixels, `width * height * 4` bytes in row order, such as an
+/// image taken from the clipboard.
+pub fn rgba8_png(width: u32, height: u32, rgba: &[u8]) -> Result<Png, ()> {
+    let header = ImageHeader {
+        width,
+        height,
+        bit_depth: 8,
+        color_type: ColorType::Rgba,
+        interlaced: false,
+    };
+    let size = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or(())?;
+    if width == 0 || height == 0 || rgba.len() != size {
+        return Err(());
+    }
+    let mut png = Png::from_chunks(vec![
+        Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+        Chunk::new(ChunkType::IDAT, vec![]),
+        Chunk::new(ChunkType::IEND, vec![]),
+    ]);
+    let image = ImageData {
+        header,
+        samples: rgba.iter().map(|&sample| sample as u16).collect(),
+        palette: vec![],
+        palette_alpha: vec![],
+        transparent: None,
+        colorimetry: Colorimetry::from_png(&png),
+    };
+    image.write_to(&mut png)?;
+    Ok(png)
+}
+
+/// Recompresses the image data at the highest zlib level into a single IDAT chunk, keeping
+/// the scanlines and their filters as they are. Returns whether the file got smaller; if it
+/// would not, `png` is left unchanged.
+pub fn recompress(png: &mut Png) -> Result<bool, ()> {
+    recompress_with_progress(png, progress::NO_PROGRESS, &CancelToken::new()).map_err(|_| ())
+}
+
+/// [`recompress`], reporting the bytes of image data deflated so far; `cancel` is also
+/// checked while the data is inflated first.
+pub fn recompress_with_progress(
+    png: &mut Png,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> Result<bool, Interrupted> {
+    let is_data = |chunk: &Chunk| *chunk.chunk_type() == ChunkType::IDAT;
+    let data_index = png.chunks().iter().position(is_data).ok_or(())?;
+    let compressed: Vec<u8> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| is_data(chunk))
+        .flat_map(|chunk| chunk.data().iter().copied())
+        .collect();
+    let data_chunks = png.chunks().iter().filter(|chunk| is_data(chunk)).count();
+    let mut raw = Vec::new();
+    let mut decoder = ZlibDecoder::new(&compressed[..]);
+    loop {
+        cancel.check()?;
+        let inflated = (&mut decoder)
+            .take(1 << 20)
+            .read_to_end(&mut raw)
+            .map_err(|_| ())?;
+        if inflated == 0 {
+            break;
+        }
+    }
+    let recompressed =
+        parallel::compress_with_progress(&raw, Compression::best(), progress, cancel)?;
+    // Each IDAT chunk merged away saves its 12 bytes of framing.
+    if recompressed.len() >= compressed.len() + 12 * (data_chunks - 1) {
+        return Ok(false);
+    }
+    png.retain_chunks(|chunk| !is_data(chunk));
+    png.insert_chunk(data_index, Chunk::new(ChunkType::IDAT, recompressed));
+    Ok(true)
+}
+
+/// The most `recompress_chunk` inflates, far beyond any real profile or text; a stream that
+/// expands past it is left compressed as it was.
+const MAX_INFLATED_CHUNK: u64 = 16 << 20;
+
+/// Recompresses the zlib stream of an iCCP, zTXt or compressed iTXt chunk at the highest
+/// level, returning the new payload, or `None` if the chunk has no such stream, it inflates
+/// past `MAX_INFLATED_CHUNK`, or it would not get smaller.
+pub fn recompress_chunk(chunk: &Chunk) -> Result<Option<Vec<u8>>, ()> {
+    let data = chunk.data();
+    let keyword_end = || data.iter().position(|&byte| byte == 0).ok_or(());
+    // Where the stream starts: after the keyword and compression method, and for iTXt after
+    // the compression flag, language and translated keyword too.
+    let start = match *chunk.chunk_type() {
+        ChunkType::ICCP | ChunkType::ZTXT => keyword_end()? + 2,
+        ChunkType::ITXT => {
+            let flag = keyword_end()? + 1;
+            if data.get(flag) != Some(&1) {
+                return Ok(None);
+            }
+            let mut start = flag + 2;
+            for _ in 0..2 {
+                start += data[start.min(data.len())..]
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .ok_or(())?
+                    + 1;
+            }
+            start
+        }
+        _ => return Ok(None),
+    };
+    let stream = data.get(start..).ok_or(())?;
+    let mut raw = Vec::new();
+    ZlibDecoder::new(stream)
+        .take(MAX_INFLATED_CHUNK + 1)
+        .read_to_end(&mut raw)
+        .map_err(|_| ())?;
+    if raw.len() as u64 > MAX_INFLATED_CHUNK {
+        return Ok(None);
+    }
+    let mut encoder = ZlibEncoder::new(data[..start].to_vec(), Compression::best());
+    encoder.write_all(&raw).map_err(|_| ())?;
+    let recompressed = encoder.finish().map_err(|_| ())?;
+    Ok((recompressed.len() < data.len()).then_some(recompressed))
+}
+
+/// Reverses per-scanline filtering, returning the rows without their filter bytes.
+pub fn unfilter(data: &[u8], row_bytes: usize, bits_per_pixel: usize) -> Result<Vec<u8>, ()> {
+    let bpp = bits_per_pixel.div_ceil(8);
+    let mut rows = Vec::with_capacity(data.len());
+    let mut previous = vec![0; row_bytes];
+    for line in data.chunks(row_bytes + 1) {
+        if line.len() != row_bytes + 1 {
+            return Err(());
+        }
+        let (filter, line) = (line[0], &line[1..]);
+        let mut row = line.to_vec();
+        for i in 0..row_bytes {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = previous[i];
+            let upper_left = if i >= bpp { previous[i - bpp] } else { 0 };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, upper_left),
+                _ => return Err(()),
+            };
+            row[i] = row[i].wrapping_add(predictor);
+        }
+        rows.extend_from_slice(&row);
+        previous = row;
+    }
+    Ok(rows)
+}
+
+pub(crate) fn unpack(row: &[u8], bit_depth: u8, count: usize) -> Vec<u16> {
+    match bit_depth {
+        16 => row
+            .chunks_exact(2)
+            .take(count)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect(),
+        8 => row.iter().take(count).map(|&byte| byte as u16).collect(),
+        _ => {
+            let per_byte = 8 / bit_depth as usize;
+            let mask = (1u8 << bit_depth) - 1;
+            (0..count)
+                .map(|i| {
+                    let shift = 8 - bit_depth as usize * (i % per_byte + 1);
+                    ((row[i / per_byte] >> shift) & mask) as u16
+                })
+                .collect()
+        }
+    }
+}
+
+pub(crate) fn pack(samples: &[u16], bit_depth: u8) -> Vec<u8> {
+    match bit_depth {
+        16 => samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes())
+            .collect(),
+        8 => samples.iter().map(|&sample| sample as u8).collect(),
+        _ => {
+            let per_byte = 8 / bit_depth as usize;
+            samples
+                .chunks(per_byte)
+                .map(|group| {
+                    group.iter().enumerate().fold(0u8, |byte, (i, &sample)| {
+                        byte | (sample as u8) << (8 - bit_depth as usize * (i + 1))
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn header(width: u32, height: u32, bit_depth: u8, color_type: ColorType) -> ImageHeader {
+        ImageHeader {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced: false,
+        }
+    }
+
+    fn png_with(header: ImageHeader, raw: &[u8], extra: Vec<Chunk>) -> Png {
+        let mut chunks = vec![chunk("IHDR", &header.to_bytes())];
+        chunks.extend(extra);
+        chunks.push(chunk("IDAT", &compress(raw)));
+        chunks.push(chunk("IEND", &[]));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = header(3, 7, 16, ColorType::Rgba);
+        assert_eq!(ImageHeader::from_bytes(&header.to_bytes()).unwrap(), header);
+        assert_eq!(header.row_bytes(3), 24);
+    }
+
+    #[test]
+    fn test_recompress_chunk() {
+        let text = b"the quick brown fox ".repeat(50);
+        let mut encoder = ZlibEncoder::new(b"Comment\0\0".to_vec(), Compression::none());
+        encoder.write_all(&text).unwrap();
+        let loose = chunk("zTXt", &encoder.finish().unwrap());
+        let recompressed = recompress_chunk(&loose).unwrap().unwrap();
+        assert!(recompressed.len() < loose.data().len());
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(&recompressed[b"Comment\0\0".len()..])
+            .read_to_end(&mut inflated)
+            .unwrap();
+        assert_eq!(inflated, text);
+
+        // A zlib bomb is left alone rather than inflated in full.
+        let mut bomb = b"Comment\0\0".to_vec();
+        bomb.extend(compress(&vec![0; MAX_INFLATED_CHUNK as usize + 1]));
+        assert_eq!(recompress_chunk(&chunk("zTXt", &bomb)), Ok(None));
+        assert!(recompress_chunk(&chunk("zTXt", b"Comment\0\0bogus")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut bytes = header(1, 1, 8, ColorType::Rgb).to_bytes();
+        bytes[8] = 4;
+        assert!(ImageHeader::from_bytes(&bytes).is_err());
+        assert!(ImageHeader::from_bytes(&bytes[..12]).is_err());
+    }
+
+    #[test]
+    fn test_decode_filtered_rgb() {
+        #[rustfmt::skip]
+        let raw = [
+            1, 10, 20, 30, 5, 5, 5,     // Sub
+            2, 1, 1, 1, 1, 1, 1,        // Up
+            4, 1, 1, 1, 0, 0, 0,        // Paeth
+        ];
+        let png = png_with(header(2, 3, 8, ColorType::Rgb), &raw, vec![]);
+        let image = ImageData::from_png(&png).unwrap();
+        #[rustfmt::skip]
+        let expected = [
+            10, 20, 30, 15, 25, 35,
+            11, 21, 31, 16, 26, 36,
+            12, 22, 32, 16, 26, 36,
+        ];
+        assert_eq!(image.samples(), &expected);
+    }
+
+    #[test]
+    fn test_decode_packed_grayscale() {
+        let png = png_with(
+            header(5, 1, 2, ColorType::Grayscale),
+            &[0, 0b00011011, 0b11000000],
+            vec![],
+        );
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.samples(), &[0, 1, 2, 3, 3]);
+    }
+
+    #[test]
+    fn test_decode_interlaced() {
+        let mut header = header(2, 2, 8, ColorType::Grayscale);
+        header.interlaced = true;
+        // Passes 1, 6 and 7 carry pixels (0,0), (1,0) and the whole second row.
+        let raw = [0, 1, 0, 2, 0, 3, 4];
+        let image = ImageData::from_png(&png_with(header, &raw, vec![])).unwrap();
+        assert_eq!(image.samples(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        for (header, raw) in [
+            (
+                header(5, 1, 2, ColorType::Grayscale),
+                vec![0, 0b00011011, 0b11000000],
+            ),
+            (
+                header(1, 2, 16, ColorType::GrayscaleAlpha),
+                vec![0, 1, 2, 3, 4, 0, 5, 6, 7, 8],
+            ),
+        ] {
+            let mut png = png_with(header, &raw, vec![]);
+            let mut image = ImageData::from_png(&png).unwrap();
+            image.samples_mut()[0] ^= 1;
+            image.write_to(&mut png).unwrap();
+            let rewritten = ImageData::from_png(&png).unwrap();
+            assert_eq!(rewritten.samples(), image.samples());
+        }
+    }
+
+    #[test]
+    fn test_write_deinterlaces() {
+        let mut header = header(2, 2, 8, ColorType::Grayscale);
+        header.interlaced = true;
+        let mut png = png_with(header, &[0, 1, 0, 2, 0, 3, 4], vec![]);
+        png.insert_before_data(chunk("IDAT", &[]));
+        ImageData::from_png(&png)
+            .unwrap()
+            .write_to(&mut png)
+            .unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        let image = ImageData::from_png(&png).unwrap();
+        assert!(!image.header().interlaced);
+        assert_eq!(image.samples(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_rgba8_png() {
+        let rgba = [1, 2, 3, 255, 4, 5, 6, 0];
+        let png = rgba8_png(2, 1, &rgba).unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.header().color_type, ColorType::Rgba);
+        assert_eq!(image.samples(), rgba.map(u16::from));
+        assert!(rgba8_png(2, 2, &rgba).is_err());
+        assert!(rgba8_png(0, 1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_truncated_data() {
+        let png = png_with(header(2, 2, 8, ColorType::Grayscale), &[0, 1, 2], vec![]);
+        assert!(ImageData::from_png(&png).is_err());
+    }
+
+    #[test]
+    fn test_palette_with_transparency() {
+        let extra = vec![chunk("PLTE", &[255, 0, 0, 0, 0, 255]), chunk("tRNS", &[0])];
+        let png = png_with(header(2, 1, 8, ColorType::Indexed), &[0, 0, 1], extra);
+        let image = ImageData::from_png(&png).unwrap().to_srgb();
+        assert_eq!(image.pixels[0], [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(image.pixels[1], [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_color() {
+        assert_eq!(Color::from_str("7"), Ok(Color::Level(7)));
+        assert_eq!(Color::from_str("1, 2,3"), Ok(Color::Rgb([1, 2, 3])));
+        assert!(Color::from_str("1,2").is_err());
+        assert!(Color::from_str("#ff0000").is_err());
+        assert_eq!(Color::Rgb([1, 2, 3]).to_string(), "1,2,3");
+
+        let header = ImageHeader::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 1, 2, 0, 0, 0, 0]).unwrap();
+        assert_eq!(Color::Level(3).check(&header, 0), Ok(()));
+        assert!(Color::Level(4).check(&header, 0).is_err());
+        assert!(Color::Rgb([0, 0, 0]).check(&header, 0).is_err());
+        let bytes = Color::Level(3).to_bytes(ColorType::Grayscale);
+        assert_eq!(bytes, [0, 3]);
+        assert_eq!(
+            Color::from_bytes(&bytes, ColorType::Grayscale),
+            Ok(Color::Level(3))
+        );
+        assert_eq!(Color::Level(3).to_bytes(ColorType::Indexed), [3]);
+        assert!(Color::from_bytes(&bytes, ColorType::Rgb).is_err());
+    }
+
+    #[test]
+    fn test_srgb_linearization() {
+        let png = png_with(header(1, 1, 8, ColorType::Grayscale), &[0, 128], vec![]);
+        let linear = ImageData::from_png(&png).unwrap().to_linear();
+        assert!((linear.pixels[0][0] - 0.2158).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_gamma_is_honoured() {
+        let gamma = chunk("gAMA", &100_000u32.to_be_bytes());
+        let png = png_with(
+            header(1, 1, 8, ColorType::Grayscale),
+            &[0, 128],
+            vec![gamma],
+        );
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.colorimetry().transfer, TransferFunction::Gamma(1.0));
+        let linear = image.to_linear();
+        assert!((linear.pixels[0][0] - 128.0 / 255.0).abs() < 1e-6);
+        let srgb = image.to_srgb();
+        assert!((srgb.pixels[0][0] - linear_to_srgb(128.0 / 255.0) as f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_srgb_chunk_overrides_gamma() {
+        let extra = vec![
+            chunk("sRGB", &[0]),
+            chunk("gAMA", &100_000u32.to_be_bytes()),
+        ];
+        let png = png_with(header(1, 1, 8, ColorType::Grayscale), &[0, 0], extra);
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.colorimetry().transfer, TransferFunction::Srgb);
+    }
+
+    #[test]
+    fn test_chromaticities_white_maps_to_white() {
+        // Adobe RGB (1998) primaries with a D50 white point.
+        let mut data = Vec::new();
+        for value in [34567, 35850, 64000, 33000, 21000, 71000, 15000, 6000u32] {
+            data.extend(value.to_be_bytes());
+        }
+        let extra = vec![chunk("cHRM", &data)];
+        let png = png_with(header(1, 1, 8, ColorType::Rgb), &[0, 255, 255, 255], extra);
+        let linear = ImageData::from_png(&png).unwrap().to_linear();
+        for component in &linear.pixels[0][..3] {
+            assert!((component - 1.0).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! Where each chunk of a file sits, found by reading the 8-byte chunk headers and seeking
+//! past the data, so that one chunk can be read out of a multi-gigabyte file without parsing
+//! the rest. Nothing but the signature and the headers is checked until a chunk is read.
+//!
+//! `pngme index build` caches the index in a `<file>.pngidx` sidecar, which [`cached`] uses
+//! while the file still has the size, modification time and last 4 KiB it was built from.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::hash::Algorithm;
+use crate::png::Png;
+
+/// How much of the end of the file the sidecar's digest covers: where IEND is, and where
+/// most edits insert or append chunks.
+const TAIL_LENGTH: u64 = 4096;
+
+/// One chunk's position: `offset` is where its length field starts, and `length` is that of
+/// its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub chunk_type: ChunkType,
+    pub length: u32,
+}
+
+impl IndexEntry {
+    /// Reads the chunk from `reader`, the file it was indexed from, checking its CRC.
+    pub fn read(&self, reader: &mut (impl Read + Seek)) -> io::Result<Chunk> {
+        reader.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = vec![0; self.length as usize + 12];
+        reader.read_exact(&mut bytes)?;
+        match Chunk::try_from(&bytes) {
+            Ok(chunk) if *chunk.chunk_type() == self.chunk_type => Ok(chunk),
+            _ => Err(invalid(format!("the {} chunk is corrupt", self))),
+        }
+    }
+}
+
+impl std::fmt::Display for IndexEntry {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{} at byte {}", self.chunk_type, self.offset)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The chunks of a file, in order, up to and including IEND.
+pub fn scan(reader: &mut (impl Read + Seek)) -> io::Result<Vec<IndexEntry>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut signature = [0; 8];
+    reader
+        .read_exact(&mut signature)
+        .map_err(|_| invalid("not a PNG file".to_string()))?;
+    if signature != Png::STANDARD_HEADER {
+        return Err(invalid("not a PNG file".to_string()));
+    }
+    let mut entries: Vec<IndexEntry> = Vec::new();
+    let mut offset = 8;
+    // Anything after IEND is ignored, as decoders do.
+    while offset < end
+        && entries
+            .last()
+            .is_none_or(|entry| entry.chunk_type != ChunkType::IEND)
+    {
+        let mut header = [0; 8];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| invalid(format!("truncated chunk header at byte {}", offset)))?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&header[4..]).unwrap())
+            .map_err(|_| invalid(format!("invalid chunk type at byte {}", offset)))?;
+        let next = offset + 12 + length as u64;
+        if length > chunk::MAX_LENGTH || next > end {
+            return Err(invalid(format!(
+                "the {} chunk at byte {} runs past the end of the file",
+                chunk_type, offset
+            )));
+        }
+        entries.push(IndexEntry {
+            offset,
+            chunk_type,
+            length,
+        });
+        offset = next;
+    }
+    Ok(entries)
+}
+
+/// The chunks of a file read one at a time, each with where it sits, for listing files too
+/// big to hold whole. Reading stops after IEND, or at the first chunk that is truncated or
+/// fails its CRC check, which is the last item.
+pub struct Stream<R> {
+    reader: R,
+    offset: u64,
+    done: bool,
+}
+
+/// Reads chunks from `reader`, which must be at the start of the file.
+pub fn stream<R: Read>(reader: R) -> Stream<R> {
+    Stream {
+        reader,
+        offset: 0,
+        done: false,
+    }
+}
+
+impl<R: Read> Stream<R> {
+    fn next_chunk(&mut self) -> io::Result<Option<(IndexEntry, Chunk)>> {
+        if self.offset == 0 {
+            let mut signature = [0; 8];
+            self.reader
+                .read_exact(&mut signature)
+                .map_err(|_| invalid("not a PNG file".to_string()))?;
+            if signature != Png::STANDARD_HEADER {
+                return Err(invalid("not a PNG file".to_string()));
+            }
+            self.offset = 8;
+        }
+        let offset = self.offset;
+        let mut bytes = vec![0; 8];
+        match self.reader.read(&mut bytes[..1])? {
+            0 => return Ok(None),
+            _ => self
+                .reader
+                .read_exact(&mut bytes[1..])
+                .map_err(|_| invalid(format!("truncated chunk header at byte {}", offset)))?,
+        }
+        let length = u32::from_be_bytes(bytes[..4].try_into().unwrap());
+        let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&bytes[4..]).unwrap())
+            .map_err(|_| invalid(format!("invalid chunk type at byte {}", offset)))?;
+        if length > chunk::MAX_LENGTH {
+            return Err(invalid(format!(
+                "the {} chunk at byte {} is too long",
+                chunk_type, offset
+            )));
+        }
+        // Read rather than allocated up front, so a bogus length costs only what is there.
+        (&mut self.reader)
+            .take(length as u64 + 4)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != length as usize + 12 {
+            return Err(invalid(format!(
+                "the {} chunk at byte {} runs past the end of the file",
+                chunk_type, offset
+            )));
+        }
+        let chunk = Chunk::try_from(&bytes).map_err(|()| {
+            invalid(format!(
+                "the {} chunk at byte {} fails its CRC check",
+                chunk_type, offset
+            ))
+        })?;
+        self.offset += bytes.len() as u64;
+        let entry = IndexEntry {
+            offset,
+            chunk_type,
+            length,
+        };
+        Ok(Some((entry, chunk)))
+    }
+}
+
+impl<R: Read> Iterator for Stream<R> {
+    type Item = io::Result<(IndexEntry, Chunk)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_chunk().transpose();
+        // Anything after IEND is ignored, as in `scan`.
+        self.done = match &next {
+            Some(Ok((entry, _))) => entry.chunk_type == ChunkType::IEND,
+            _ => true,
+        };
+        next
+    }
+}
+
+impl Png {
+    /// Indexes the chunks of the file at `path` without reading their data; see [`scan`].
+    pub fn index(path: impl AsRef<Path>) -> io::Result<Vec<IndexEntry>> {
+        scan(&mut File::open(path)?)
+    }
+}
+
+/// What identifies the version of a file an index was built from.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    /// Nanoseconds since the Unix epoch.
+    modified: u128,
+    /// SHA-256 of the last [`TAIL_LENGTH`] bytes.
+    tail: String,
+}
+
+impl Stamp {
+    fn of(file: &mut File) -> io::Result<Self> {
+        let metadata = file.metadata()?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        let size = metadata.len();
+        file.seek(SeekFrom::Start(size.saturating_sub(TAIL_LENGTH)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        Ok(Self {
+            size,
+            modified,
+            tail: Algorithm::Sha256.digest(&tail),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SidecarChunk {
+    offset: u64,
+    #[serde(rename = "type")]
+    chunk_type: String,
+    length: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    stamp: Stamp,
+    chunks: Vec<SidecarChunk>,
+}
+
+/// Where the index of the file at `path` is cached: `path` with `.pngidx` appended.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".pngidx");
+    PathBuf::from(sidecar)
+}
+
+/// Indexes the file at `path` and writes the index to its sidecar.
+pub fn build(path: &Path) -> io::Result<Vec<IndexEntry>> {
+    let mut file = File::open(path)?;
+    let entries = scan(&mut file)?;
+    let sidecar = Sidecar {
+        stamp: Stamp::of(&mut file)?,
+        chunks: entries
+            .iter()
+            .map(|entry| SidecarChunk {
+                offset: entry.offset,
+                chunk_type: entry.chunk_type.to_string(),
+                length: entry.length,
+            })
+            .collect(),
+    };
+    fs::write(sidecar_path(path), serde_json::to_vec(&sidecar)?)?;
+    Ok(entries)
+}
+
+/// Removes the sidecar of the file at `path`, returning whether there was one.
+pub fn clear(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(sidecar_path(path)) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// The index in the sidecar of the file at `path`, if it has one that still matches.
+fn load(path: &Path, file: &mut File) -> Option<Vec<IndexEntry>> {
+    let sidecar: Sidecar = serde_json::from_slice(&fs::read(sidecar_path(path)).ok()?).ok()?;
+    if sidecar.stamp != Stamp::of(file).ok()? {
+        return None;
+    }
+    sidecar
+        .chunks
+        .into_iter()
+        .map(|chunk| {
+            Some(IndexEntry {
+                offset: chunk.offset,
+                chunk_type: ChunkType::from_str(&chunk.chunk_type).ok()?,
+                length: chunk.length,
+            })
+        })
+        .collect()
+}
+
+/// Indexes the file at `path` from its sidecar if that is still current. A stale sidecar is
+/// rebuilt; without one the file is scanned and nothing is written.
+pub fn cached(path: &Path) -> io::Result<Vec<IndexEntry>> {
+    let mut file = File::open(path)?;
+    if let Some(entries) = load(path, &mut file) {
+        return Ok(entries);
+    }
+    if sidecar_path(path).exists() {
+        return build(path);
+    }
+    scan(&mut file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_scan() {
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        png.append_chunk(Chunk::new(ChunkType::TEXT, b"Title\0Index".to_vec()));
+        let bytes = png.as_bytes();
+        let mut reader = Cursor::new(bytes.clone());
+        let entries = scan(&mut reader).unwrap();
+        assert_eq!(entries.len(), png.chunks().len());
+        for (entry, chunk) in entries.iter().zip(png.chunks()) {
+            assert_eq!(entry.chunk_type, *chunk.chunk_type());
+            assert_eq!(
+                entry.read(&mut reader).unwrap().as_bytes(),
+                chunk.as_bytes()
+            );
+        }
+
+        let mut truncated = Cursor::new(bytes[..bytes.len() - 1].to_vec());
+        assert!(scan(&mut truncated).is_err());
+        let mut corrupt = bytes.clone();
+        corrupt[8 + 12] ^= 1;
+        let mut corrupt = Cursor::new(corrupt);
+        let entries = scan(&mut corrupt).unwrap();
+        assert!(entries[0].read(&mut corrupt).is_err());
+        assert!(scan(&mut Cursor::new(bytes[1..].to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        png.append_chunk(Chunk::new(ChunkType::TEXT, b"Title\0Stream".to_vec()));
+        let mut bytes = png.as_bytes();
+        let entries = scan(&mut Cursor::new(&bytes)).unwrap();
+        bytes.extend(b"trailing");
+        let streamed: Vec<(IndexEntry, Chunk)> =
+            stream(&bytes[..]).collect::<io::Result<_>>().unwrap();
+        assert_eq!(streamed.len(), entries.len());
+        for ((entry, chunk), (expected_entry, expected)) in
+            streamed.iter().zip(entries.iter().zip(png.chunks()))
+        {
+            assert_eq!(entry, expected_entry);
+            assert_eq!(chunk.as_bytes(), expected.as_bytes());
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[8 + 12] ^= 1;
+        let results: Vec<_> = stream(&corrupt[..]).collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+        let cut = &bytes[..entries[1].offset as usize + 10];
+        let results: Vec<_> = stream(cut).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok() && results[1].is_err());
+        assert!(stream(&bytes[1..]).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let directory = std::env::temp_dir().join(format!("pngme-index-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("image.png");
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        fs::write(&path, png.as_bytes()).unwrap();
+        assert!(!clear(&path).unwrap());
+        assert_eq!(cached(&path).unwrap().len(), 3);
+        assert!(!sidecar_path(&path).exists());
+
+        build(&path).unwrap();
+        assert_eq!(cached(&path).unwrap().len(), 3);
+        png.append_chunk(Chunk::new(ChunkType::TEXT, b"Title\0Index".to_vec()));
+        fs::write(&path, png.as_bytes()).unwrap();
+        let entries = cached(&path).unwrap();
+        assert_eq!(entries[2].chunk_type, ChunkType::TEXT);
+        // The stale sidecar was rebuilt.
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(load(&path, &mut file).unwrap(), entries);
+        assert!(clear(&path).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
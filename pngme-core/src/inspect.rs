@@ -0,0 +1,178 @@
+//! The overview `pngme inspect` prints: what the image is, which chunks it has, the
+//! metadata worth knowing about and anything stuck on after the PNG.
+
+use crate::chunk;
+use crate::chunk_type::ChunkType;
+use crate::icc;
+use crate::image::ImageHeader;
+use crate::png::Png;
+use crate::polyglot::{self, Detection};
+use crate::xmp;
+
+const RENDERING_INTENTS: [&str; 4] = [
+    "perceptual",
+    "relative colorimetric",
+    "saturation",
+    "absolute colorimetric",
+];
+
+/// How many chunks of one type the file has, and their payload bytes together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Census {
+    pub chunk_type: ChunkType,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// `None` if IHDR is missing or malformed.
+    pub header: Option<ImageHeader>,
+    /// By type, in the order each type first appears.
+    pub census: Vec<Census>,
+    /// Keywords of the tEXt, zTXt and iTXt chunks.
+    pub text_keys: Vec<String>,
+    /// The tIME chunk, as `YYYY-MM-DD HH:MM:SS UTC`.
+    pub modified: Option<String>,
+    /// How colour is specified: ICC profile, sRGB, gamma and so on.
+    pub colour: Vec<String>,
+    pub exif: bool,
+    pub xmp: bool,
+    /// Private chunk types, which only their writer understands and where hidden messages
+    /// usually live.
+    pub private_types: Vec<ChunkType>,
+    /// Files appended to or interleaved with the PNG.
+    pub trailers: Vec<Detection>,
+}
+
+fn modification_time(data: &[u8]) -> Option<String> {
+    let &[year_high, year_low, month, day, hour, minute, second] = data else {
+        return None;
+    };
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        u16::from_be_bytes([year_high, year_low]),
+        month,
+        day,
+        hour,
+        minute,
+        second
+    ))
+}
+
+fn colour(png: &Png) -> Vec<String> {
+    let mut colour = Vec::new();
+    match icc::profile(png) {
+        Some(Ok((name, profile))) => {
+            colour.push(format!("ICC profile `{}` ({} bytes)", name, profile.len()))
+        }
+        Some(Err(())) => colour.push(String::from("corrupt ICC profile")),
+        None => {}
+    }
+    if let Some(chunk) = png.chunk_by_type(ChunkType::SRGB) {
+        let intent = chunk
+            .data()
+            .first()
+            .and_then(|&intent| RENDERING_INTENTS.get(intent as usize))
+            .unwrap_or(&"unknown intent");
+        colour.push(format!("sRGB ({})", intent));
+    }
+    if let Some(chunk) = png.chunk_by_type(ChunkType::CICP) {
+        colour.push(format!("cICP {}", crate::hex::encode(chunk.data())));
+    }
+    if let Some(chunk) = png.chunk_by_type(ChunkType::GAMA) {
+        if let Ok(gamma) = <[u8; 4]>::try_from(chunk.data()) {
+            colour.push(format!(
+                "gamma {:.5}",
+                u32::from_be_bytes(gamma) as f64 / 100_000.0
+            ));
+        }
+    }
+    if png.chunk_by_type(ChunkType::CHRM).is_some() {
+        colour.push(String::from("chromaticities"));
+    }
+    colour
+}
+
+pub fn summarize(png: &Png, bytes: &[u8]) -> Summary {
+    let mut census: Vec<Census> = Vec::new();
+    let mut text_keys = Vec::new();
+    let mut private_types = Vec::new();
+    for chunk in png.chunks() {
+        let chunk_type = *chunk.chunk_type();
+        match census
+            .iter_mut()
+            .find(|entry| entry.chunk_type == chunk_type)
+        {
+            Some(entry) => {
+                entry.count += 1;
+                entry.bytes += chunk.length() as u64;
+            }
+            None => census.push(Census {
+                chunk_type,
+                count: 1,
+                bytes: chunk.length() as u64,
+            }),
+        }
+        if let Some(keyword) = chunk::text_keyword(chunk) {
+            let keyword: String = keyword.iter().map(|&byte| byte as char).collect();
+            if !text_keys.contains(&keyword) {
+                text_keys.push(keyword);
+            }
+        }
+        if !chunk_type.is_public() && !private_types.contains(&chunk_type) {
+            private_types.push(chunk_type);
+        }
+    }
+    Summary {
+        header: ImageHeader::from_png(png).ok(),
+        census,
+        text_keys,
+        modified: png
+            .chunk_by_type(ChunkType::TIME)
+            .and_then(|chunk| modification_time(chunk.data())),
+        colour: colour(png),
+        exif: png.chunk_by_type(ChunkType::EXIF).is_some(),
+        xmp: png.chunks().iter().any(xmp::is_xmp_chunk),
+        private_types,
+        trailers: polyglot::detect(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_summarize() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Title\0a".to_vec()));
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Title\0b".to_vec()));
+        png.insert_ancillary(Chunk::new(ChunkType::TIME, vec![7, 232, 2, 29, 13, 5, 9]));
+        png.insert_ancillary(Chunk::new(ChunkType::SRGB, vec![1]));
+        png.insert_ancillary(Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec()));
+        png.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 3]));
+        let summary = summarize(&png, &png.as_bytes());
+
+        assert_eq!(summary.header.unwrap().color_type, ColorType::Rgb);
+        let text = summary
+            .census
+            .iter()
+            .find(|entry| entry.chunk_type == ChunkType::TEXT)
+            .unwrap();
+        assert_eq!((text.count, text.bytes), (2, 14));
+        assert_eq!(summary.text_keys, ["Title"]);
+        assert_eq!(summary.modified.as_deref(), Some("2024-02-29 13:05:09 UTC"));
+        assert_eq!(
+            summary.colour,
+            ["sRGB (relative colorimetric)", "gamma 0.45455"]
+        );
+        assert_eq!(summary.private_types[0].to_string(), "ruSt");
+        assert!(!summary.exif && !summary.xmp);
+        assert!(summary.trailers.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! The GIF-conversion extension chunks gIFg, gIFx and the deprecated gIFt, which old GIF to
+//! PNG converters wrote to carry over what PNG has no place for.
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+
+pub const LEGACY_CHUNK_TYPES: [ChunkType; 3] = [ChunkType::GIFG, ChunkType::GIFX, ChunkType::GIFT];
+
+/// gIFg: a GIF Graphic Control Extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphicControl {
+    pub disposal_method: u8,
+    pub user_input: bool,
+    /// In hundredths of a second.
+    pub delay: u16,
+}
+
+impl GraphicControl {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let [disposal_method, user_input, delay @ ..]: [u8; 4] = data
+            .try_into()
+            .map_err(|_| format!("gIFg is {} bytes, not 4", data.len()))?;
+        Ok(Self {
+            disposal_method,
+            user_input: user_input != 0,
+            delay: u16::from_be_bytes(delay),
+        })
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = vec![self.disposal_method, self.user_input as u8];
+        data.extend_from_slice(&self.delay.to_be_bytes());
+        Chunk::new(ChunkType::GIFG, data)
+    }
+}
+
+impl Display for GraphicControl {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let disposal = match self.disposal_method {
+            0 => "unspecified",
+            1 => "leave in place",
+            2 => "restore to background",
+            3 => "restore to previous",
+            _ => "reserved",
+        };
+        write!(
+            fmt,
+            "disposal {} ({}), delay {} ms",
+            self.disposal_method,
+            disposal,
+            self.delay as u32 * 10
+        )?;
+        if self.user_input {
+            write!(fmt, ", waits for user input")?;
+        }
+        Ok(())
+    }
+}
+
+/// gIFx: a GIF Application Extension, such as Netscape's looping block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationExtension {
+    pub identifier: [u8; 8],
+    pub authentication_code: [u8; 3],
+    pub data: Vec<u8>,
+}
+
+impl ApplicationExtension {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 11 {
+            return Err(format!(
+                "gIFx is {} bytes, shorter than its 11-byte header",
+                data.len()
+            ));
+        }
+        Ok(Self {
+            identifier: data[..8].try_into().unwrap(),
+            authentication_code: data[8..11].try_into().unwrap(),
+            data: data[11..].to_vec(),
+        })
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [&self.identifier[..], &self.authentication_code, &self.data].concat();
+        Chunk::new(ChunkType::GIFX, data)
+    }
+}
+
+impl Display for ApplicationExtension {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "application {}{}, {} bytes of data",
+            String::from_utf8_lossy(&self.identifier).trim_end(),
+            String::from_utf8_lossy(&self.authentication_code),
+            self.data.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphic_control() {
+        let control = GraphicControl::from_bytes(&[2, 1, 0, 50]).unwrap();
+        assert_eq!(
+            control.to_string(),
+            "disposal 2 (restore to background), delay 500 ms, waits for user input"
+        );
+        assert_eq!(control.to_chunk().data(), [2, 1, 0, 50]);
+        assert!(GraphicControl::from_bytes(&[0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_application_extension() {
+        let extension = ApplicationExtension::from_bytes(b"NETSCAPE2.0\x03\x01\x00\x00").unwrap();
+        assert_eq!(
+            extension.to_string(),
+            "application NETSCAPE2.0, 4 bytes of data"
+        );
+        assert_eq!(extension.to_chunk().data(), b"NETSCAPE2.0\x03\x01\x00\x00");
+        assert!(ApplicationExtension::from_bytes(b"NETSCAPE").is_err());
+    }
+}
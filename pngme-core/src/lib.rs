@@ -0,0 +1,73 @@
+#![allow(clippy::result_unit_err)]
+
+pub mod api;
+pub mod background;
+pub mod builder;
+pub mod c2pa;
+pub mod cache;
+pub mod carrier;
+pub mod cbor;
+pub mod channels;
+pub mod charset;
+pub mod chunk;
+pub mod chunk_type;
+#[cfg(feature = "reference-decoder")]
+pub mod conformance;
+pub mod convert;
+pub mod envelope;
+pub mod explode;
+pub mod extensions;
+pub mod filter;
+pub mod geometry;
+pub mod git_filter;
+pub mod graph;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod guess;
+pub mod handshake;
+pub mod hash;
+pub mod hex;
+pub mod hexview;
+pub mod histogram;
+pub mod history;
+pub mod icc;
+pub mod image;
+pub mod index;
+pub mod inspect;
+pub mod legacy;
+pub mod lsb;
+pub mod metrics;
+pub mod mime;
+pub mod parallel;
+pub mod patch;
+pub mod plan;
+pub mod png;
+pub mod policy;
+pub mod polyglot;
+pub mod progress;
+pub mod provenance;
+pub mod registry;
+pub mod report;
+pub mod sarif;
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod seal;
+pub mod select;
+pub mod share;
+pub mod significant_bits;
+pub mod spread;
+pub mod steganalysis;
+pub mod strip;
+pub mod suggested_palette;
+pub mod template;
+#[cfg(test)]
+mod testing;
+pub mod thumbnail;
+pub mod tiles;
+pub mod transparency;
+pub mod validate;
+pub mod vectors;
+pub mod watermark;
+pub mod weigh;
+pub mod xmp;
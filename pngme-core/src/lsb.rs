@@ -0,0 +1,492 @@
+//! The lsb method: like spread, a message in the low bits of pseudo-randomly chosen samples,
+//! but in the channels and number of low bits the user picks. More channels and more bits
+//! hold more, and are easier for steganalysis to notice; one bit of blue, or of alpha in an
+//! image that has it, is the least conspicuous.
+//!
+//! The choice is recorded in a 32-bit header, masked with the password, in the lowest bit of
+//! colour samples picked by the password alone, so that reading the message back needs only
+//! the password. The message itself, behind a 32-bit length prefix as in spread, fills the
+//! chosen low bits of the chosen channels in a second password-seeded order that skips the
+//! header's samples.
+//!
+//! An adaptive layout keeps the message out of flat areas, where flipped low bits stand out
+//! against their neighbours, by using only the pixels whose neighbourhoods vary the most.
+//! The variance is worked out from the bits above the ones the message changes, so decoding
+//! finds the same pixels; the order among them is still the password's.
+
+use zeroize::Zeroizing;
+
+use crate::channels;
+use crate::image::{ColorType, ImageData, ImageHeader};
+use crate::spread;
+
+const HEADER_BITS: usize = 32;
+const LENGTH_BITS: usize = 32;
+/// The first two header bytes, which tell a right password from a wrong one.
+const MAGIC: [u8; 2] = *b"LS";
+/// The most low bits of a sample that may carry the message.
+pub const MAX_BITS: u8 = 4;
+/// The flag in the header's bits byte for an adaptive layout.
+const ADAPTIVE: u8 = 0x80;
+
+/// Which channels carry the message, and how many low bits of each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// A bit for each channel, in sample order: bit 0 for red or grey.
+    pub channels: u8,
+    /// From 1 to [`MAX_BITS`].
+    pub bits: u8,
+    /// Only the more textured half of the pixels, skipping flat areas.
+    pub adaptive: bool,
+}
+
+impl Layout {
+    /// The layout for `names`, each a channel of `color_type` as [`channels::names`] gives
+    /// them or its initial, or every colour channel if there are none.
+    pub fn parse(color_type: ColorType, names: &[String], bits: u8) -> Result<Self, String> {
+        let all = match color_type {
+            ColorType::Indexed => {
+                return Err(String::from(
+                    "the lsb method does not support palette images",
+                ))
+            }
+            _ => channels::names(color_type),
+        };
+        let mut mask = 0;
+        for name in names {
+            let name = name.trim().to_ascii_lowercase();
+            let index = all
+                .iter()
+                .position(|channel| {
+                    *channel == name || (name.len() == 1 && channel.starts_with(&name))
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "`{}` is not a channel of this image, which has {}",
+                        name,
+                        all.join(", ")
+                    )
+                })?;
+            mask |= 1 << index;
+        }
+        if mask == 0 {
+            // The colour channels, leaving out alpha.
+            mask = match color_type {
+                ColorType::Rgb | ColorType::Rgba => 0b111,
+                _ => 0b1,
+            };
+        }
+        let layout = Self {
+            channels: mask,
+            bits,
+            adaptive: false,
+        };
+        layout.check(color_type, 16)?;
+        Ok(layout)
+    }
+
+    fn check(self, color_type: ColorType, bit_depth: u8) -> Result<(), String> {
+        if !(1..=MAX_BITS.min(bit_depth)).contains(&self.bits) {
+            return Err(format!(
+                "--bits must be from 1 to {}, not {}",
+                MAX_BITS.min(bit_depth),
+                self.bits
+            ));
+        }
+        if self.channels == 0 || self.channels >> color_type.channels() != 0 {
+            return Err(String::from("the image doesn't have those channels"));
+        }
+        Ok(())
+    }
+
+    /// The names of the channels, in sample order.
+    pub fn channel_names(self, color_type: ColorType) -> Vec<&'static str> {
+        channels::names(color_type)
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| self.channels >> index & 1 == 1)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    fn selected(self) -> Vec<usize> {
+        (0..8)
+            .filter(|index| self.channels >> index & 1 == 1)
+            .collect()
+    }
+}
+
+/// The pixels an adaptive layout uses: those whose 3x3 neighbourhood varies at least as much
+/// as the median pixel's and at all, judged by the bits above the lowest `bits` of every
+/// colour sample, which embedding doesn't change.
+fn textured_pixels(image: &ImageData, bits: u8) -> Vec<usize> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let channels = image.channels();
+    let colour = match image.header().color_type {
+        ColorType::Rgb | ColorType::Rgba => 3,
+        _ => 1,
+    };
+    let levels: Vec<f64> = image
+        .samples()
+        .chunks(channels)
+        .map(|pixel| {
+            pixel[..colour]
+                .iter()
+                .map(|&sample| (sample >> bits) as f64)
+                .sum()
+        })
+        .collect();
+    let variances: Vec<f64> = (0..width * height)
+        .map(|pixel| {
+            let (x, y) = (pixel % width, pixel / width);
+            let (mut count, mut sum, mut squares) = (0.0, 0.0, 0.0);
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let level = levels[ny * width + nx];
+                    count += 1.0;
+                    sum += level;
+                    squares += level * level;
+                }
+            }
+            squares / count - (sum / count) * (sum / count)
+        })
+        .collect();
+    let mut sorted = variances.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted.get(sorted.len() / 2).copied().unwrap_or(0.0);
+    (0..variances.len())
+        .filter(|&pixel| variances[pixel] > 0.0 && variances[pixel] >= median)
+        .collect()
+}
+
+/// The samples in the channels `selected` of `pixels`, or of every pixel of an image with
+/// `header`, in a password-seeded order drawn from `domain`.
+fn slots(
+    header: &ImageHeader,
+    pixels: Option<Vec<usize>>,
+    selected: Vec<usize>,
+    domain: &str,
+    password: &str,
+) -> impl Iterator<Item = usize> {
+    let channels = header.color_type.channels();
+    let count = match &pixels {
+        Some(pixels) => pixels.len(),
+        None => header.width as usize * header.height as usize,
+    };
+    spread::permutation(domain, password, count * selected.len()).map(move |slot| {
+        let pixel = slot / selected.len();
+        let pixel = pixels.as_ref().map_or(pixel, |pixels| pixels[pixel]);
+        pixel * channels + selected[slot % selected.len()]
+    })
+}
+
+/// The pixels `layout` may use in `image`, or `None` for all of them.
+fn pixels(image: &ImageData, layout: Layout) -> Option<Vec<usize>> {
+    layout.adaptive.then(|| textured_pixels(image, layout.bits))
+}
+
+/// The samples whose lowest bits hold the header, whatever the layout.
+fn header_positions(header: &ImageHeader, password: &str) -> Result<Vec<usize>, ()> {
+    let colour = Layout::parse(header.color_type, &[], 1).map_err(|_| ())?;
+    let positions: Vec<usize> = slots(
+        header,
+        None,
+        colour.selected(),
+        "pngme lsb header",
+        password,
+    )
+    .take(HEADER_BITS)
+    .collect();
+    match positions.len() {
+        HEADER_BITS => Ok(positions),
+        _ => Err(()),
+    }
+}
+
+/// The samples that carry the message for `layout`, in order.
+fn body_positions<'a>(
+    header: &ImageHeader,
+    pixels: Option<Vec<usize>>,
+    layout: Layout,
+    password: &str,
+    reserved: &'a [usize],
+) -> impl Iterator<Item = usize> + 'a {
+    slots(
+        header,
+        pixels,
+        layout.selected(),
+        "pngme lsb positions",
+        password,
+    )
+    .filter(move |position| !reserved.contains(position))
+}
+
+fn mask_bits(password: &str) -> impl Iterator<Item = u8> {
+    spread::key_bytes("pngme lsb mask", password).map(|byte| byte & 1)
+}
+
+/// Payload bytes that fit in the image with `layout`, after the length prefix.
+pub fn capacity(image: &ImageData, layout: Layout) -> Result<usize, String> {
+    let count = match pixels(image, layout) {
+        Some(pixels) => pixels.len(),
+        None => image.width() as usize * image.height() as usize,
+    };
+    capacity_of(image.header(), layout, count)
+}
+
+/// [`capacity`] with `count` pixels to use.
+fn capacity_of(header: &ImageHeader, layout: Layout, count: usize) -> Result<usize, String> {
+    layout.check(header.color_type, header.bit_depth)?;
+    let samples = count * layout.selected().len();
+    // As many header samples as there are may be in the chosen channels, unless they are
+    // all alpha, and can't carry message bits too.
+    let colour = Layout::parse(header.color_type, &[], 1)?;
+    let reserved = match layout.channels & colour.channels {
+        0 => 0,
+        _ => HEADER_BITS,
+    };
+    let bits = samples.saturating_sub(reserved) * layout.bits as usize;
+    Ok(bits.saturating_sub(LENGTH_BITS) / 8)
+}
+
+/// Writes `message` into the low bits of the samples `layout` picks, XORed with a
+/// password-derived mask, and the layout into the header.
+pub fn embed(
+    image: &mut ImageData,
+    password: &str,
+    layout: Layout,
+    message: &[u8],
+) -> Result<(), String> {
+    let pixels = pixels(image, layout);
+    let count = pixels
+        .as_ref()
+        .map_or(image.width() as usize * image.height() as usize, Vec::len);
+    let capacity = capacity_of(image.header(), layout, count)?;
+    if message.len() > capacity || message.len() > u32::MAX as usize {
+        return Err(format!(
+            "message is {} bytes but the image can hold only {} in {} with {} bits each",
+            message.len(),
+            capacity,
+            layout.channel_names(image.header().color_type).join(", "),
+            layout.bits
+        ));
+    }
+    let header = *image.header();
+    let reserved = header_positions(&header, password)
+        .map_err(|()| "the image is too small to hold a message")?;
+    let flags = match layout.adaptive {
+        true => ADAPTIVE,
+        false => 0,
+    };
+    let record = [MAGIC[0], MAGIC[1], layout.channels, layout.bits | flags];
+    let record: Vec<u8> = record
+        .iter()
+        .zip(spread::key_bytes("pngme lsb header mask", password))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    let length = (message.len() as u32).to_be_bytes();
+    let mut payload = spread::bits(&length)
+        .chain(spread::bits(message))
+        .zip(mask_bits(password))
+        .map(|(bit, mask)| bit ^ mask)
+        .peekable();
+    let bits = layout.bits as u16;
+    let samples = image.samples_mut();
+    for (&position, bit) in reserved.iter().zip(spread::bits(&record)) {
+        samples[position] = (samples[position] & !1) | bit as u16;
+    }
+    for position in body_positions(&header, pixels, layout, password, &reserved) {
+        if payload.peek().is_none() {
+            break;
+        }
+        // The highest of the low bits first.
+        for plane in (0..bits).rev() {
+            let Some(bit) = payload.next() else { break };
+            samples[position] = (samples[position] & !(1 << plane)) | (bit as u16) << plane;
+        }
+    }
+    Ok(())
+}
+
+/// Reads back the layout and message written by `embed`; the buffer is wiped when dropped.
+pub fn extract(image: &ImageData, password: &str) -> Result<(Layout, Zeroizing<Vec<u8>>), ()> {
+    let header = *image.header();
+    let samples = image.samples();
+    let reserved = header_positions(&header, password)?;
+    let mut record = [0u8; 4];
+    for (bit, &position) in reserved.iter().enumerate() {
+        record[bit / 8] |= ((samples[position] & 1) as u8) << (7 - bit % 8);
+    }
+    for (byte, mask) in record
+        .iter_mut()
+        .zip(spread::key_bytes("pngme lsb header mask", password))
+    {
+        *byte ^= mask;
+    }
+    if record[..2] != MAGIC {
+        return Err(());
+    }
+    let layout = Layout {
+        channels: record[2],
+        bits: record[3] & !ADAPTIVE,
+        adaptive: record[3] & ADAPTIVE != 0,
+    };
+    layout
+        .check(header.color_type, header.bit_depth)
+        .map_err(|_| ())?;
+    let pixels = pixels(image, layout);
+    let count = pixels
+        .as_ref()
+        .map_or(image.width() as usize * image.height() as usize, Vec::len);
+
+    let bits = layout.bits as u16;
+    let mut read = body_positions(&header, pixels, layout, password, &reserved)
+        .flat_map(|position| {
+            (0..bits)
+                .rev()
+                .map(move |plane| (samples[position] >> plane) & 1)
+        })
+        .zip(mask_bits(password))
+        .map(|(bit, mask)| bit as u8 ^ mask);
+    let mut read_bytes = |count: usize| -> Result<Vec<u8>, ()> {
+        let mut bytes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut byte = 0;
+            for _ in 0..8 {
+                byte = byte << 1 | read.next().ok_or(())?;
+            }
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    };
+    let length = u32::from_be_bytes(read_bytes(4)?.try_into().unwrap()) as usize;
+    if length > capacity_of(&header, layout, count).map_err(|_| ())? {
+        return Err(());
+    }
+    Ok((layout, Zeroizing::new(read_bytes(length)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carrier::{Carrier, Pattern};
+
+    fn image(grayscale: bool) -> ImageData {
+        let carrier = Carrier {
+            pattern: Pattern::Noise,
+            width: 32,
+            height: 24,
+            seed: 3,
+            entropy: 0.5,
+            grayscale,
+        };
+        ImageData::from_png(&carrier.generate().unwrap()).unwrap()
+    }
+
+    fn names(names: &str) -> Vec<String> {
+        names.split(',').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = image(false);
+        let mut marked = image(false);
+        let layout = Layout::parse(ColorType::Rgb, &names("b,green"), 2).unwrap();
+        assert_eq!(layout.channel_names(ColorType::Rgb), ["green", "blue"]);
+        embed(&mut marked, "hunter2", layout, b"two bits of two channels").unwrap();
+        let (found, message) = extract(&marked, "hunter2").unwrap();
+        assert_eq!(found, layout);
+        assert_eq!(*message, b"two bits of two channels");
+        assert!(extract(&marked, "hunter3").is_err());
+
+        // Red changes only in its lowest bit, where the header is; the others in their two.
+        for (index, (before, after)) in original.samples().iter().zip(marked.samples()).enumerate()
+        {
+            let allowed = match index % 3 {
+                0 => 1,
+                _ => 3,
+            };
+            assert_eq!((before ^ after) & !allowed, 0);
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        let image = image(true);
+        let one = Layout::parse(ColorType::Grayscale, &[], 1).unwrap();
+        let four = Layout { bits: 4, ..one };
+        assert_eq!(capacity(&image, one).unwrap(), (32 * 24 - 32 - 32) / 8);
+        assert_eq!(
+            capacity(&image, four).unwrap(),
+            ((32 * 24 - 32) * 4 - 32) / 8
+        );
+        let mut image = image;
+        assert!(embed(&mut image, "pw", one, &[0; 89]).is_err());
+        embed(&mut image, "pw", four, &[7; 364]).unwrap();
+        assert_eq!(*extract(&image, "pw").unwrap().1, [7; 364]);
+    }
+
+    #[test]
+    fn test_adaptive() {
+        // Flat grey on the left, noise on the right; the last flat column borders the noise
+        // and so isn't flat itself.
+        let image = || {
+            let carrier = Carrier {
+                pattern: Pattern::Noise,
+                width: 64,
+                height: 32,
+                seed: 5,
+                entropy: 1.0,
+                grayscale: true,
+            };
+            let mut image = ImageData::from_png(&carrier.generate().unwrap()).unwrap();
+            for (index, sample) in image.samples_mut().iter_mut().enumerate() {
+                if index % 64 < 32 {
+                    *sample = 128;
+                }
+            }
+            image
+        };
+        let original = image();
+        let flat_changes = |marked: &ImageData| {
+            let changed: Vec<u16> = original
+                .samples()
+                .iter()
+                .zip(marked.samples())
+                .enumerate()
+                .filter(|&(index, (before, after))| index % 64 < 31 && before != after)
+                .map(|(_, (before, after))| before ^ after)
+                .collect();
+            changed
+        };
+        let plain = Layout::parse(ColorType::Grayscale, &[], 2).unwrap();
+        let adaptive = Layout {
+            adaptive: true,
+            ..plain
+        };
+        assert!(capacity(&original, adaptive).unwrap() < capacity(&original, plain).unwrap());
+
+        let mut marked = image();
+        embed(&mut marked, "pw", plain, &[0xa5; 100]).unwrap();
+        assert!(flat_changes(&marked).len() > 100);
+
+        let mut marked = image();
+        embed(&mut marked, "pw", adaptive, &[0xa5; 100]).unwrap();
+        // Only the header, one bit of each of at most 32 samples, lands in the flat half.
+        let changes = flat_changes(&marked);
+        assert!(changes.len() <= HEADER_BITS);
+        assert!(changes.iter().all(|&change| change == 1));
+        let (layout, message) = extract(&marked, "pw").unwrap();
+        assert_eq!(layout, adaptive);
+        assert_eq!(*message, [0xa5; 100]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(Layout::parse(ColorType::Rgb, &names("a"), 1).is_err());
+        assert!(Layout::parse(ColorType::Rgba, &names("a"), 5).is_err());
+        assert!(Layout::parse(ColorType::Indexed, &[], 1).is_err());
+        let alpha = Layout::parse(ColorType::GrayscaleAlpha, &names("alpha"), 1).unwrap();
+        assert_eq!(alpha.channels, 0b10);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::image::RgbaImage;
+
+const SSIM_WINDOW: usize = 8;
+const SSIM_C1: f64 = 0.01 * 0.01;
+const SSIM_C2: f64 = 0.03 * 0.03;
+
+fn check_dimensions(a: &RgbaImage, b: &RgbaImage) -> Result<(), ()> {
+    if a.width == b.width && a.height == b.height {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+fn squared_error(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    a.pixels
+        .iter()
+        .zip(&b.pixels)
+        .flat_map(|(x, y)| x.iter().zip(y))
+        .map(|(&x, &y)| (x as f64 - y as f64).powi(2))
+        .sum()
+}
+
+fn psnr_of(squared_error: f64, components: usize) -> f64 {
+    let mean = squared_error / components as f64;
+    if mean == 0.0 {
+        f64::INFINITY
+    } else {
+        -10.0 * mean.log10()
+    }
+}
+
+/// Peak signal-to-noise ratio in dB over the RGBA components; infinite for identical images.
+pub fn psnr(a: &RgbaImage, b: &RgbaImage) -> Result<f64, ()> {
+    check_dimensions(a, b)?;
+    Ok(psnr_of(squared_error(a, b), a.pixels.len() * 4))
+}
+
+fn luma(pixel: &[f32; 4]) -> f64 {
+    0.2126 * pixel[0] as f64 + 0.7152 * pixel[1] as f64 + 0.0722 * pixel[2] as f64
+}
+
+/// Mean structural similarity of the luma channel over 8x8 windows.
+pub fn ssim(a: &RgbaImage, b: &RgbaImage) -> Result<f64, ()> {
+    check_dimensions(a, b)?;
+    let (total, windows) = ssim_windows(a, b, SSIM_WINDOW.min(a.height as usize));
+    Ok(total / windows as f64)
+}
+
+/// The SSIM of each whole `window_height`-row window in `a` and `b`, summed, and how many
+/// there were.
+fn ssim_windows(a: &RgbaImage, b: &RgbaImage, window_height: usize) -> (f64, usize) {
+    let (width, height) = (a.width as usize, a.height as usize);
+    let window_width = SSIM_WINDOW.min(width);
+    let mut total = 0.0;
+    let mut windows = 0;
+    if height < window_height {
+        return (total, windows);
+    }
+    for top in (0..=height - window_height).step_by(window_height) {
+        for left in (0..=width - window_width).step_by(window_width) {
+            let mut x = Vec::with_capacity(window_width * window_height);
+            let mut y = Vec::with_capacity(window_width * window_height);
+            for row in top..top + window_height {
+                for column in left..left + window_width {
+                    x.push(luma(&a.pixels[row * width + column]));
+                    y.push(luma(&b.pixels[row * width + column]));
+                }
+            }
+            total += window_ssim(&x, &y);
+            windows += 1;
+        }
+    }
+    (total, windows)
+}
+
+/// PSNR and SSIM of two images given a band of rows at a time, as [`crate::tiles`] reads
+/// them. The results match [`psnr`] and [`ssim`] on the whole images as long as every band
+/// but the last is a multiple of 8 rows tall, so no SSIM window straddles two bands.
+pub struct Comparison {
+    window_height: usize,
+    squared_error: f64,
+    components: usize,
+    ssim_total: f64,
+    windows: usize,
+}
+
+impl Comparison {
+    /// For images `height` rows tall.
+    pub fn new(height: u32) -> Self {
+        Self {
+            window_height: SSIM_WINDOW.min(height as usize),
+            squared_error: 0.0,
+            components: 0,
+            ssim_total: 0.0,
+            windows: 0,
+        }
+    }
+
+    /// Adds the next band of each image, which must be the same size.
+    pub fn add(&mut self, a: &RgbaImage, b: &RgbaImage) -> Result<(), ()> {
+        check_dimensions(a, b)?;
+        self.squared_error += squared_error(a, b);
+        self.components += a.pixels.len() * 4;
+        let (total, windows) = ssim_windows(a, b, self.window_height);
+        self.ssim_total += total;
+        self.windows += windows;
+        Ok(())
+    }
+
+    pub fn psnr(&self) -> f64 {
+        psnr_of(self.squared_error, self.components)
+    }
+
+    pub fn ssim(&self) -> f64 {
+        self.ssim_total / self.windows as f64
+    }
+}
+
+fn window_ssim(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let (mut variance_x, mut variance_y, mut covariance) = (0.0, 0.0, 0.0);
+    for (&x, &y) in x.iter().zip(y) {
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+        covariance += (x - mean_x) * (y - mean_y);
+    }
+    let (variance_x, variance_y, covariance) = (variance_x / n, variance_y / n, covariance / n);
+    ((2.0 * mean_x * mean_y + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+        / ((mean_x.powi(2) + mean_y.powi(2) + SSIM_C1) * (variance_x + variance_y + SSIM_C2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        let pixels = (0..width * height)
+            .map(|i| {
+                let value = i as f32 / (width * height) as f32;
+                [value, 1.0 - value, value / 2.0, 1.0]
+            })
+            .collect();
+        RgbaImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let image = gradient(16, 16);
+        assert_eq!(psnr(&image, &image).unwrap(), f64::INFINITY);
+        assert!((ssim(&image, &image).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_small_perturbation() {
+        let a = gradient(16, 16);
+        let mut b = a.clone();
+        for pixel in b.pixels.iter_mut().step_by(3) {
+            pixel[0] = (pixel[0] + 1.0 / 255.0).min(1.0);
+        }
+        let psnr = psnr(&a, &b).unwrap();
+        assert!(psnr > 50.0 && psnr < 70.0);
+        let ssim = ssim(&a, &b).unwrap();
+        assert!(ssim > 0.9 && ssim < 1.0);
+    }
+
+    #[test]
+    fn test_images_smaller_than_window() {
+        let image = gradient(3, 2);
+        assert!((ssim(&image, &image).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_comparison_in_bands() {
+        let a = gradient(16, 20);
+        let mut b = a.clone();
+        for pixel in b.pixels.iter_mut().step_by(5) {
+            pixel[1] = (pixel[1] + 2.0 / 255.0).min(1.0);
+        }
+        let band = |image: &RgbaImage, top: usize, rows: usize| RgbaImage {
+            width: 16,
+            height: rows as u32,
+            pixels: image.pixels[top * 16..(top + rows) * 16].to_vec(),
+        };
+        let mut comparison = Comparison::new(20);
+        for (top, rows) in [(0, 8), (8, 8), (16, 4)] {
+            comparison
+                .add(&band(&a, top, rows), &band(&b, top, rows))
+                .unwrap();
+        }
+        assert!((comparison.psnr() - psnr(&a, &b).unwrap()).abs() < 1e-9);
+        assert!((comparison.ssim() - ssim(&a, &b).unwrap()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        assert!(psnr(&gradient(4, 4), &gradient(4, 5)).is_err());
+        assert!(ssim(&gradient(4, 4), &gradient(5, 4)).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! What kind of file a payload is, told from its first bytes, so that a decoded message can
+//! be labelled and saved with a fitting extension.
+
+/// Bytes a file of each type has at the given offsets, with the type's usual extension.
+struct Signature {
+    parts: &'static [(usize, &'static [u8])],
+    mime: &'static str,
+    extension: &'static str,
+}
+
+const SIGNATURES: [Signature; 17] = [
+    Signature {
+        parts: &[(0, b"\x89PNG\r\n\x1a\n")],
+        mime: "image/png",
+        extension: "png",
+    },
+    Signature {
+        parts: &[(0, b"\xff\xd8\xff")],
+        mime: "image/jpeg",
+        extension: "jpg",
+    },
+    Signature {
+        parts: &[(0, b"GIF8")],
+        mime: "image/gif",
+        extension: "gif",
+    },
+    Signature {
+        parts: &[(0, b"RIFF"), (8, b"WEBP")],
+        mime: "image/webp",
+        extension: "webp",
+    },
+    Signature {
+        parts: &[(0, b"%PDF-")],
+        mime: "application/pdf",
+        extension: "pdf",
+    },
+    Signature {
+        parts: &[(0, b"PK\x03\x04")],
+        mime: "application/zip",
+        extension: "zip",
+    },
+    Signature {
+        parts: &[(0, b"\x1f\x8b")],
+        mime: "application/gzip",
+        extension: "gz",
+    },
+    Signature {
+        parts: &[(0, b"BZh")],
+        mime: "application/x-bzip2",
+        extension: "bz2",
+    },
+    Signature {
+        parts: &[(0, b"\xfd7zXZ\0")],
+        mime: "application/x-xz",
+        extension: "xz",
+    },
+    Signature {
+        parts: &[(0, b"\x28\xb5\x2f\xfd")],
+        mime: "application/zstd",
+        extension: "zst",
+    },
+    Signature {
+        parts: &[(0, b"7z\xbc\xaf\x27\x1c")],
+        mime: "application/x-7z-compressed",
+        extension: "7z",
+    },
+    Signature {
+        parts: &[(257, b"ustar")],
+        mime: "application/x-tar",
+        extension: "tar",
+    },
+    Signature {
+        parts: &[(0, b"\x7fELF")],
+        mime: "application/x-elf",
+        extension: "elf",
+    },
+    Signature {
+        parts: &[(0, b"\0asm")],
+        mime: "application/wasm",
+        extension: "wasm",
+    },
+    Signature {
+        parts: &[(0, b"ID3")],
+        mime: "audio/mpeg",
+        extension: "mp3",
+    },
+    Signature {
+        parts: &[(0, b"OggS")],
+        mime: "audio/ogg",
+        extension: "ogg",
+    },
+    Signature {
+        parts: &[(0, b"fLaC")],
+        mime: "audio/flac",
+        extension: "flac",
+    },
+];
+
+/// Text types, told apart by content rather than by magic numbers.
+const TEXT_TYPES: [(&str, &str); 3] = [
+    ("text/plain", "txt"),
+    ("application/json", "json"),
+    ("application/xml", "xml"),
+];
+
+/// What [`sniff`] calls data it can't place.
+pub const UNKNOWN: &str = "application/octet-stream";
+
+/// The MIME type of `data`: one of the formats with a known signature, text (plain, JSON or
+/// XML) if it is UTF-8 without control characters, and otherwise
+/// `application/octet-stream`.
+pub fn sniff(data: &[u8]) -> &'static str {
+    let signature = SIGNATURES.iter().find(|signature| {
+        signature
+            .parts
+            .iter()
+            .all(|&(offset, magic)| data.get(offset..offset + magic.len()) == Some(magic))
+    });
+    if let Some(signature) = signature {
+        return signature.mime;
+    }
+    let Ok(text) = std::str::from_utf8(data) else {
+        return UNKNOWN;
+    };
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+    {
+        return UNKNOWN;
+    }
+    let trimmed = text.trim_start();
+    if trimmed.starts_with(['{', '[']) && serde_json::from_str::<serde_json::Value>(text).is_ok() {
+        "application/json"
+    } else if trimmed.starts_with("<?xml") {
+        "application/xml"
+    } else {
+        "text/plain"
+    }
+}
+
+/// The usual extension for files of `mime`, without the dot; `bin` for types not listed
+/// here.
+pub fn extension(mime: &str) -> &'static str {
+    let essence = essence(mime);
+    SIGNATURES
+        .iter()
+        .map(|signature| (signature.mime, signature.extension))
+        .chain(TEXT_TYPES)
+        .find(|(known, _)| known.eq_ignore_ascii_case(essence))
+        .map_or("bin", |(_, extension)| extension)
+}
+
+/// `mime` without any parameters, like `text/plain` for `text/plain; charset=utf-8`.
+fn essence(mime: &str) -> &str {
+    mime.split(';').next().unwrap_or_default().trim()
+}
+
+pub fn is_text(mime: &str) -> bool {
+    let essence = essence(mime);
+    TEXT_TYPES
+        .iter()
+        .any(|(text, _)| text.eq_ignore_ascii_case(essence))
+        || essence.starts_with("text/")
+}
+
+/// Checks that `mime` looks like `type/subtype`, optionally with parameters, for storing as a
+/// payload's declared type.
+pub fn validate(mime: &str) -> Result<(), String> {
+    let valid_token = |token: &str| {
+        !token.is_empty()
+            && token
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&byte))
+    };
+    match essence(mime).split_once('/') {
+        Some((kind, subtype))
+            if valid_token(kind) && valid_token(subtype) && !mime.contains(['\n', '\r']) =>
+        {
+            Ok(())
+        }
+        _ => Err(format!("`{}` is not a MIME type like image/png", mime)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing;
+
+    #[test]
+    fn test_sniff() {
+        let png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        assert_eq!(sniff(&png.as_bytes()), "image/png");
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff(b"RIFF\0\0\0\0WAVEfmt "), UNKNOWN);
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar), "application/x-tar");
+        assert_eq!(sniff(b"hello\n"), "text/plain");
+        assert_eq!(sniff(b" {\"a\": [1, 2]}"), "application/json");
+        assert_eq!(sniff(b"{not json"), "text/plain");
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><a/>"), "application/xml");
+        assert_eq!(sniff(b"\x01\x02\x03"), UNKNOWN);
+        assert_eq!(sniff(b""), "text/plain");
+
+        assert_eq!(extension("image/JPEG"), "jpg");
+        assert_eq!(extension("application/json"), "json");
+        assert_eq!(extension("text/plain; charset=utf-8"), "txt");
+        assert!(is_text("text/csv") && !is_text("image/png"));
+        assert_eq!(extension("application/x-unheard-of"), "bin");
+        assert!(validate("text/plain; charset=utf-8").is_ok());
+        assert!(validate("text").is_err());
+        assert!(validate("text/plain\nx").is_err());
+    }
+}
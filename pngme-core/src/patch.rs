@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use crc::crc32::checksum_ieee;
+
+use crate::png::Png;
+
+/// Largest chunk, type and data together, that [`fix_bitflip`] searches.
+pub const MAX_BITFLIP_LEN: usize = 64 << 20;
+const CRC_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Where each chunk sits in `bytes`, found by following the length fields alone, so that a
+/// file with bad CRCs or chunk types can still be walked. Stops at the first chunk that would
+/// run past the end.
+pub fn chunk_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut offset = Png::STANDARD_HEADER.len();
+    while let Some(length) = bytes.get(offset..offset + 4) {
+        let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+        let Some(end) = (offset + 12)
+            .checked_add(length)
+            .filter(|&end| end <= bytes.len())
+        else {
+            break;
+        };
+        ranges.push(offset..end);
+        offset = end;
+    }
+    ranges
+}
+
+/// Overwrites `bytes` from `offset` with `data`, which must fit within the file.
+pub fn write(bytes: &mut [u8], offset: usize, data: &[u8]) -> Result<(), String> {
+    let end = offset
+        .checked_add(data.len())
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| {
+            format!(
+                "the patch would end at byte {:#x} but the file is only {:#x} bytes",
+                offset.saturating_add(data.len()),
+                bytes.len()
+            )
+        })?;
+    bytes[offset..end].copy_from_slice(data);
+    Ok(())
+}
+
+/// Recomputes the CRC of every chunk overlapping `range`, returning the offsets of the
+/// chunks whose CRC changed.
+pub fn fix_crcs(bytes: &mut [u8], range: Range<usize>) -> Vec<usize> {
+    let mut fixed = Vec::new();
+    for chunk in chunk_ranges(bytes) {
+        if chunk.start >= range.end || chunk.end <= range.start {
+            continue;
+        }
+        let crc_start = chunk.end - 4;
+        let crc = checksum_ieee(&bytes[chunk.start + 4..crc_start]).to_be_bytes();
+        if bytes[crc_start..chunk.end] != crc {
+            bytes[crc_start..chunk.end].copy_from_slice(&crc);
+            fixed.push(chunk.start);
+        }
+    }
+    fixed
+}
+
+/// The chunks, as found by [`chunk_ranges`], whose stored CRC doesn't match.
+pub fn crc_mismatches(bytes: &[u8]) -> Vec<Range<usize>> {
+    chunk_ranges(bytes)
+        .into_iter()
+        .filter(|chunk| {
+            let crc_start = chunk.end - 4;
+            bytes[crc_start..chunk.end]
+                != checksum_ieee(&bytes[chunk.start + 4..crc_start]).to_be_bytes()
+        })
+        .collect()
+}
+
+/// A single flipped bit found and put right by [`fix_bitflip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFlip {
+    /// Offset of the byte in the file, which is the CRC's own when that was the bit.
+    pub offset: usize,
+    /// From 0 for the least significant bit.
+    pub bit: u8,
+}
+
+/// Runs the CRC register through a zero byte.
+fn shift_byte(mut register: u32) -> u32 {
+    for _ in 0..8 {
+        register = (register >> 1) ^ if register & 1 == 1 { CRC_POLYNOMIAL } else { 0 };
+    }
+    register
+}
+
+/// Looks for the one bit of the chunk at `chunk` whose flip makes its CRC match, and flips
+/// it back in `bytes`. The CRC is linear, so flipping a bit changes it by an amount that
+/// depends only on where the bit is: each is compared with the mismatch in turn rather than
+/// recomputing the CRC, and no two positions in a chunk up to [`MAX_BITFLIP_LEN`] share one.
+/// Fails if the CRC already matches, the chunk is larger than that, or no one bit explains
+/// the mismatch.
+pub fn fix_bitflip(bytes: &mut [u8], chunk: Range<usize>) -> Option<BitFlip> {
+    let crc_start = chunk.end - 4;
+    let covered = chunk.start + 4..crc_start;
+    if covered.len() > MAX_BITFLIP_LEN {
+        return None;
+    }
+    let stored = u32::from_be_bytes(bytes[crc_start..chunk.end].try_into().unwrap());
+    let mismatch = stored ^ checksum_ieee(&bytes[covered.clone()]);
+    if mismatch == 0 {
+        return None;
+    }
+    let flip = if mismatch.is_power_of_two() {
+        // A bit of the stored CRC itself, which is big-endian.
+        let bit = mismatch.trailing_zeros();
+        Some(BitFlip {
+            offset: crc_start + 3 - bit as usize / 8,
+            bit: (bit % 8) as u8,
+        })
+    } else {
+        // How the final register differs for each bit of the byte at `offset`, from the
+        // last byte backwards, each further byte shifting the difference through once more.
+        let mut differences: [u32; 8] = std::array::from_fn(|bit| shift_byte(1 << bit));
+        covered.rev().find_map(|offset| {
+            let bit = differences
+                .iter()
+                .position(|&difference| difference == mismatch);
+            differences = differences.map(shift_byte);
+            bit.map(|bit| BitFlip {
+                offset,
+                bit: bit as u8,
+            })
+        })
+    }?;
+    bytes[flip.offset] ^= 1 << flip.bit;
+    Some(flip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn testing_bytes() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hello".to_vec()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        let bytes = testing_bytes();
+        assert_eq!(chunk_ranges(&bytes), [8..25, 25..37]);
+        assert_eq!(chunk_ranges(&bytes[..30]), vec![8..25]);
+    }
+
+    #[test]
+    fn test_write_and_fix_crcs() {
+        let mut bytes = testing_bytes();
+        write(&mut bytes, 16, b"J").unwrap();
+        assert!(Png::try_from(&bytes[..]).is_err());
+        assert_eq!(fix_crcs(&mut bytes, 16..17), [8]);
+        let png = Png::try_from(&bytes[..]).unwrap();
+        assert_eq!(png.chunks()[0].data(), b"Jello");
+        let length = bytes.len();
+        assert!(fix_crcs(&mut bytes, 0..length).is_empty());
+        assert!(write(&mut bytes, length - 1, b"ab").is_err());
+        assert!(write(&mut bytes, usize::MAX, b"a").is_err());
+    }
+
+    #[test]
+    fn test_fix_bitflip() {
+        let original = testing_bytes();
+        assert_eq!(fix_bitflip(&mut original.clone(), 8..25), None);
+        // Every bit of the type, data and CRC of the first chunk.
+        for offset in 12..25 {
+            for bit in 0..8 {
+                let mut bytes = original.clone();
+                bytes[offset] ^= 1 << bit;
+                assert!(Png::try_from(&bytes[..]).is_err());
+                assert_eq!(
+                    fix_bitflip(&mut bytes, 8..25),
+                    Some(BitFlip { offset, bit })
+                );
+                assert_eq!(bytes, original);
+            }
+        }
+        let mut bytes = original.clone();
+        bytes[16] ^= 0b11;
+        assert_eq!(crc_mismatches(&bytes), vec![8..25]);
+        assert_eq!(fix_bitflip(&mut bytes, 8..25), None);
+    }
+}
@@ -0,0 +1,340 @@
+//! Batch edit plans for `pngme apply`: an ordered list of operations in a TOML file, run
+//! against each file in turn.
+//!
+//! ```toml
+//! [[operation]]
+//! op = "remove"
+//! types = ["tIME"]
+//! select = ["text,after-idat"]
+//!
+//! [[operation]]
+//! op = "insert"
+//! type = "ruSt"
+//! file = "payload.bin"
+//!
+//! [[operation]]
+//! op = "set-text"
+//! key = "Author"
+//! value = "Jane Doe"
+//!
+//! [[operation]]
+//! op = "optimize"
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::cache::ChunkCache;
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::image;
+use crate::png::{Png, UNIQUE_ANCILLARY};
+use crate::select::{self, Selector};
+use crate::validate;
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case", deny_unknown_fields)]
+enum OperationEntry {
+    Remove {
+        #[serde(default)]
+        types: Vec<String>,
+        #[serde(default)]
+        select: Vec<String>,
+    },
+    Insert {
+        #[serde(rename = "type")]
+        chunk_type: String,
+        file: PathBuf,
+    },
+    SetText {
+        key: String,
+        value: String,
+    },
+    Optimize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlanFile {
+    #[serde(default)]
+    operation: Vec<OperationEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Removes the ancillary chunks of these types, and those matched by any of the selectors.
+    Remove {
+        types: Vec<ChunkType>,
+        selectors: Vec<Selector>,
+    },
+    /// Adds an ancillary chunk ahead of the image data, replacing any existing chunk of a
+    /// type that may only appear once.
+    Insert {
+        chunk_type: ChunkType,
+        data: Vec<u8>,
+    },
+    /// Replaces every text chunk with this keyword by one holding `value`, as tEXt when it is
+    /// Latin-1 and as uncompressed iTXt otherwise.
+    SetText { key: String, value: String },
+    /// Drops duplicate ancillary chunks, and recompresses the image data and any compressed
+    /// iCCP, zTXt or iTXt payloads where that makes them smaller.
+    Optimize,
+}
+
+/// Recompressed payloads from `image::recompress_chunk`, shared across the files a plan runs
+/// on so that a profile or text repeated in each is only recompressed once.
+pub type RecompressCache = ChunkCache<Result<Option<Vec<u8>>, ()>>;
+
+const COMPRESSED_TYPES: [ChunkType; 3] = [ChunkType::ICCP, ChunkType::ZTXT, ChunkType::ITXT];
+
+/// The operations of a plan, in the order they run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub operations: Vec<Operation>,
+}
+
+fn ancillary_type(name: &str) -> Result<ChunkType, String> {
+    let chunk_type = ChunkType::from_str(name)?;
+    if chunk_type.is_critical() {
+        return Err(format!(
+            "{} is a critical chunk and cannot be changed",
+            name
+        ));
+    }
+    Ok(chunk_type)
+}
+
+impl Operation {
+    fn from_entry(
+        entry: OperationEntry,
+        read_file: &mut impl FnMut(&Path) -> Result<Vec<u8>, String>,
+    ) -> Result<Self, String> {
+        match entry {
+            OperationEntry::Remove { types, select } => {
+                if types.is_empty() && select.is_empty() {
+                    return Err("remove needs `types` or `select`".to_string());
+                }
+                Ok(Operation::Remove {
+                    types: types
+                        .iter()
+                        .map(|name| ancillary_type(name))
+                        .collect::<Result<_, _>>()?,
+                    selectors: select
+                        .iter()
+                        .map(|expression| expression.parse())
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+            OperationEntry::Insert { chunk_type, file } => {
+                let chunk_type = ancillary_type(&chunk_type)?;
+                Ok(Operation::Insert {
+                    chunk_type,
+                    data: read_file(&file)?,
+                })
+            }
+            OperationEntry::SetText { key, value } => {
+                chunk::keyword_bytes(&key)?;
+                Ok(Operation::SetText { key, value })
+            }
+            OperationEntry::Optimize => Ok(Operation::Optimize),
+        }
+    }
+
+    pub fn apply(&self, png: &mut Png, cache: &mut RecompressCache) -> Result<(), String> {
+        match self {
+            Operation::Remove { types, selectors } => {
+                let mut selected = select::selected(png, selectors).into_iter();
+                png.retain_chunks(|chunk| {
+                    let selected = selected.next().unwrap() && !chunk.chunk_type().is_critical();
+                    !selected && !types.contains(chunk.chunk_type())
+                });
+            }
+            Operation::Insert { chunk_type, data } => {
+                if UNIQUE_ANCILLARY.contains(chunk_type) {
+                    png.retain_chunks(|chunk| chunk.chunk_type() != chunk_type);
+                }
+                png.insert_ancillary(Chunk::new(*chunk_type, data.clone()));
+            }
+            Operation::SetText { key, value } => {
+                png.retain_chunks(|chunk| chunk::text_keyword(chunk) != Some(key.as_bytes()));
+                png.insert_ancillary(text_chunk(key, value));
+            }
+            Operation::Optimize => {
+                validate::remove_duplicates(png);
+                image::recompress(png).map_err(|()| "the image data is corrupt".to_string())?;
+                for index in 0..png.chunks().len() {
+                    let chunk = &png.chunks()[index];
+                    if !COMPRESSED_TYPES.contains(chunk.chunk_type()) {
+                        continue;
+                    }
+                    let recompressed = cache
+                        .get_or_compute(chunk, image::recompress_chunk)
+                        .map_err(|()| format!("the {} chunk is corrupt", chunk.chunk_type()))?;
+                    if let Some(data) = recompressed {
+                        let chunk_type = *chunk.chunk_type();
+                        png.replace_chunk(index, Chunk::new(chunk_type, data));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn text_chunk(key: &str, value: &str) -> Chunk {
+    // Validated when the plan was loaded.
+    chunk::text_chunk(key, value).unwrap()
+}
+
+impl Plan {
+    /// Parses a plan, reading the payload of each `insert` through `read_file`.
+    pub fn from_toml(
+        text: &str,
+        mut read_file: impl FnMut(&Path) -> Result<Vec<u8>, String>,
+    ) -> Result<Self, String> {
+        let file: PlanFile = toml::from_str(text).map_err(|error| error.to_string())?;
+        let operations = file
+            .operation
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                Operation::from_entry(entry, &mut read_file)
+                    .map_err(|error| format!("operation {}: {}", index + 1, error))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { operations })
+    }
+
+    /// Runs every operation in order, stopping at the first that fails.
+    pub fn apply(&self, png: &mut Png, cache: &mut RecompressCache) -> Result<(), String> {
+        for (index, operation) in self.operations.iter().enumerate() {
+            operation
+                .apply(png, cache)
+                .map_err(|error| format!("operation {}: {}", index + 1, error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    const PLAN: &str = r#"
+        [[operation]]
+        op = "remove"
+        types = ["tIME"]
+        select = ["private"]
+
+        [[operation]]
+        op = "insert"
+        type = "ruSt"
+        file = "payload.bin"
+
+        [[operation]]
+        op = "set-text"
+        key = "Author"
+        value = "Zoë"
+
+        [[operation]]
+        op = "set-text"
+        key = "Title"
+        value = "日本"
+
+        [[operation]]
+        op = "optimize"
+    "#;
+
+    fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+        match path.to_str() {
+            Some("payload.bin") => Ok(b"hello".to_vec()),
+            _ => Err(format!("cannot read {}", path.display())),
+        }
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::none());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let plan = Plan::from_toml(PLAN, read_file).unwrap();
+        assert_eq!(plan.operations.len(), 5);
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        png.replace_chunk(1, Chunk::new(ChunkType::IDAT, zlib(&[0; 4])));
+        png.insert_ancillary(Chunk::new(ChunkType::TIME, vec![0; 7]));
+        png.insert_ancillary(Chunk::new(ChunkType::from_str("prVt").unwrap(), vec![1]));
+        png.insert_ancillary(text_chunk("Author", "someone else"));
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Comment\0kept".to_vec()));
+        plan.apply(&mut png, &mut RecompressCache::default())
+            .unwrap();
+        assert_eq!(
+            types(&png),
+            ["IHDR", "tEXt", "ruSt", "tEXt", "iTXt", "IDAT", "IEND"]
+        );
+        assert_eq!(png.chunks()[3].data(), b"Author\0Zo\xeb");
+        assert!(png.chunks()[4].data().ends_with("日本".as_bytes()));
+    }
+
+    #[test]
+    fn test_invalid_plans() {
+        let plan = |text: &str| Plan::from_toml(text, read_file);
+        assert!(plan("").unwrap().operations.is_empty());
+        assert!(plan("[[operation]]\nop = \"remove\"").is_err());
+        assert!(plan("[[operation]]\nop = \"remove\"\ntypes = [\"IDAT\"]").is_err());
+        assert!(plan("[[operation]]\nop = \"remove\"\nselect = [\"big\"]").is_err());
+        assert!(plan("[[operation]]\nop = \"insert\"\ntype = \"ruSt\"\nfile = \"x\"").is_err());
+        assert!(plan("[[operation]]\nop = \"set-text\"\nkey = \" x\"\nvalue = \"\"").is_err());
+        assert!(plan("[[operation]]\nop = \"paint\"").is_err());
+        let error =
+            plan("[[operation]]\nop = \"optimize\"\n[[operation]]\nop = \"remove\"").unwrap_err();
+        assert!(error.starts_with("operation 2:"));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let testing_png = || {
+            let data = zlib(&[0; 1000]);
+            let mut png = png_with_header(ColorType::Grayscale, 8, 0);
+            png.replace_chunk(1, Chunk::new(ChunkType::IDAT, data[..500].to_vec()));
+            png.insert_chunk(2, Chunk::new(ChunkType::IDAT, data[500..].to_vec()));
+            let mut text = b"Comment\0\0".to_vec();
+            text.extend(zlib(&[b'a'; 1000]));
+            png.insert_ancillary(Chunk::new(ChunkType::ZTXT, text.clone()));
+            png.insert_ancillary(Chunk::new(ChunkType::ZTXT, text));
+            png
+        };
+        let mut cache = RecompressCache::default();
+        let mut png = testing_png();
+        Operation::Optimize.apply(&mut png, &mut cache).unwrap();
+        assert_eq!(types(&png), ["IHDR", "zTXt", "IDAT", "IEND"]);
+        assert!(png.chunks()[1].length() < 100);
+        assert!(png.chunks()[2].length() < 100);
+        assert_eq!((cache.len(), cache.hits()), (1, 0));
+        Operation::Optimize
+            .apply(&mut testing_png(), &mut cache)
+            .unwrap();
+        assert_eq!((cache.len(), cache.hits()), (1, 1));
+        png.replace_chunk(2, Chunk::new(ChunkType::IDAT, vec![1, 2, 3]));
+        assert!(Operation::Optimize
+            .apply(&mut png, &mut RecompressCache::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Repository policies for `pngme hook run`, read from `.pngme-policy.toml`:
+//!
+//! ```toml
+//! allow-gps = false
+//! forbidden-text-keys = ["Author", "Copyright"]
+//! max-file-size = 1048576
+//! max-chunk-size = 65536
+//! ```
+
+use serde::Deserialize;
+
+use crate::chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::thumbnail::Tiff;
+use crate::validate::{chunk_offsets, Finding};
+use crate::xmp;
+
+/// EXIF tag in IFD0 pointing at the GPS IFD.
+const TAG_GPS_IFD: u16 = 0x8825;
+const XMP_GPS_PROPERTIES: [&str; 2] = ["exif:GPSLatitude", "exif:GPSLongitude"];
+
+/// What staged images may contain. Anything left out of the file takes its default: no GPS
+/// location, no `Author` text entry and no size limits.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Policy {
+    pub allow_gps: bool,
+    /// tEXt, zTXt and iTXt keywords that must not appear.
+    pub forbidden_text_keys: Vec<String>,
+    pub max_file_size: Option<u64>,
+    /// Largest payload allowed in any one chunk, in bytes.
+    pub max_chunk_size: Option<u32>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allow_gps: false,
+            forbidden_text_keys: vec!["Author".to_string()],
+            max_file_size: None,
+            max_chunk_size: None,
+        }
+    }
+}
+
+/// Whether the EXIF data links a GPS IFD from IFD0.
+fn exif_has_gps(exif: &[u8]) -> Result<bool, ()> {
+    let tiff = Tiff::new(exif)?;
+    let ifd0 = tiff.u32_at(4)? as usize;
+    for entry in 0..tiff.u16_at(ifd0)? as usize {
+        let entry = ifd0 + 2 + 12 * entry;
+        if tiff.u16_at(entry)? == TAG_GPS_IFD {
+            return Ok(tiff.u32_at(entry + 8)? != 0);
+        }
+    }
+    Ok(false)
+}
+
+impl Policy {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|error| error.to_string())
+    }
+
+    /// Everything about `png`, `file_size` bytes on disk, that the policy forbids.
+    pub fn check(&self, png: &Png, file_size: u64) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if let Some(limit) = self.max_file_size.filter(|&limit| file_size > limit) {
+            findings.push(Finding {
+                rule_id: "file-size",
+                rule_description: "files must not exceed the policy's size limit",
+                message: format!(
+                    "the file is {} bytes, over the limit of {}",
+                    file_size, limit
+                ),
+                offset: None,
+            });
+        }
+        for (chunk, offset) in png.chunks().iter().zip(chunk_offsets(png)) {
+            let chunk_type = chunk.chunk_type();
+            if let Some(limit) = self.max_chunk_size.filter(|&limit| chunk.length() > limit) {
+                findings.push(Finding {
+                    rule_id: "chunk-size",
+                    rule_description: "chunks must not exceed the policy's size limit",
+                    message: format!(
+                        "{} chunk at offset {} is {} bytes, over the limit of {}",
+                        chunk_type,
+                        offset,
+                        chunk.length(),
+                        limit
+                    ),
+                    offset: Some(offset),
+                });
+            }
+            if let Some(keyword) = chunk::text_keyword(chunk) {
+                if let Some(key) = self
+                    .forbidden_text_keys
+                    .iter()
+                    .find(|key| key.as_bytes() == keyword)
+                {
+                    findings.push(Finding {
+                        rule_id: "text-key",
+                        rule_description: "text chunks must not use the policy's forbidden keys",
+                        message: format!(
+                            "{} chunk at offset {} has the forbidden key `{}`",
+                            chunk_type, offset, key
+                        ),
+                        offset: Some(offset),
+                    });
+                }
+            }
+            let gps = if self.allow_gps {
+                false
+            } else if *chunk_type == ChunkType::EXIF {
+                exif_has_gps(chunk.data()).unwrap_or(false)
+            } else {
+                xmp::is_xmp_chunk(chunk) && {
+                    let text = String::from_utf8_lossy(chunk.data());
+                    XMP_GPS_PROPERTIES
+                        .iter()
+                        .any(|property| text.contains(property))
+                }
+            };
+            if gps {
+                findings.push(Finding {
+                    rule_id: "gps-location",
+                    rule_description: "metadata must not record where the image was taken",
+                    message: format!(
+                        "{} chunk at offset {} holds a GPS location",
+                        chunk_type, offset
+                    ),
+                    offset: Some(offset),
+                });
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    // Little-endian TIFF whose IFD0 has a single GPSInfo entry.
+    fn exif_with_gps(pointer: u32) -> Vec<u8> {
+        let mut exif = b"II*\0".to_vec();
+        exif.extend(8u32.to_le_bytes());
+        exif.extend(1u16.to_le_bytes());
+        exif.extend(TAG_GPS_IFD.to_le_bytes());
+        exif.extend(4u16.to_le_bytes());
+        exif.extend(1u32.to_le_bytes());
+        exif.extend(pointer.to_le_bytes());
+        exif.extend(0u32.to_le_bytes());
+        exif
+    }
+
+    fn rules(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|finding| finding.rule_id).collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        let policy = Policy::default();
+        assert!(policy.check(&png, 100).is_empty());
+
+        png.insert_ancillary(Chunk::new(ChunkType::EXIF, exif_with_gps(26)));
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Author\0me".to_vec()));
+        png.insert_ancillary(xmp::xmp_chunk("<exif:GPSLatitude>1</exif:GPSLatitude>"));
+        assert_eq!(
+            rules(&policy.check(&png, 100)),
+            ["gps-location", "text-key", "gps-location"]
+        );
+
+        let policy = Policy::from_toml(
+            "allow-gps = true\nforbidden-text-keys = []\nmax-file-size = 50\nmax-chunk-size = 20",
+        )
+        .unwrap();
+        let findings = policy.check(&png, 100);
+        assert_eq!(rules(&findings), ["file-size", "chunk-size", "chunk-size"]);
+        assert_eq!(findings[1].offset, Some(33));
+        assert!(Policy::from_toml("allow-gsp = true").is_err());
+    }
+
+    #[test]
+    fn test_exif_has_gps() {
+        assert_eq!(exif_has_gps(&exif_with_gps(26)), Ok(true));
+        assert_eq!(exif_has_gps(&exif_with_gps(0)), Ok(false));
+        assert!(exif_has_gps(b"JFIF").is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Private, ancillary, safe-to-copy chunk holding a ZIP archive embedded by `embed_zip`.
+pub const ZIP_CHUNK_TYPE: &str = "ziPp";
+
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+const ZIP_END_OF_DIRECTORY: &[u8] = b"PK\x05\x06";
+const ZIP_END_LEN: usize = 22;
+const ZIP_CENTRAL_HEADER_LEN: usize = 46;
+/// What follows the archive once it is embedded: the chunk's CRC and the IEND chunk.
+const TRAILER_LEN: usize = 4 + 12;
+/// The end-of-central-directory record is 22 bytes plus a comment of up to 65535.
+const ZIP_END_SEARCH: usize = 22 + 0xffff;
+const PDF_HEADER: &[u8] = b"%PDF-";
+/// Readers such as Acrobat accept a PDF header anywhere in the first kilobyte.
+const PDF_HEADER_SEARCH: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Png,
+    Zip,
+    Pdf,
+    /// Bytes after IEND that aren't any of the above.
+    Unknown,
+}
+
+impl Kind {
+    fn of(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&Png::STANDARD_HEADER) {
+            Kind::Png
+        } else if bytes.starts_with(ZIP_LOCAL_HEADER) {
+            Kind::Zip
+        } else if bytes.starts_with(PDF_HEADER) {
+            Kind::Pdf
+        } else {
+            Kind::Unknown
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Kind::Png => "png",
+            Kind::Zip => "zip",
+            Kind::Pdf => "pdf",
+            Kind::Unknown => "bin",
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Kind::Png => write!(fmt, "PNG file"),
+            Kind::Zip => write!(fmt, "ZIP archive"),
+            Kind::Pdf => write!(fmt, "PDF document"),
+            Kind::Unknown => write!(fmt, "unrecognised data"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub kind: Kind,
+    pub range: Range<usize>,
+}
+
+/// The end of the PNG file starting at `start`: just past its IEND chunk, or where its
+/// chunks stop making sense.
+fn png_end(bytes: &[u8], start: usize) -> usize {
+    let mut offset = start + Png::STANDARD_HEADER.len();
+    while let Some(header) = bytes.get(offset..offset + 8) {
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let Some(end) = (offset + 12)
+            .checked_add(length)
+            .filter(|&end| end <= bytes.len())
+        else {
+            break;
+        };
+        offset = end;
+        if &header[4..] == b"IEND" {
+            break;
+        }
+    }
+    offset.min(bytes.len())
+}
+
+/// The files laid end to end in `bytes`: one or more PNG files, then whatever follows the
+/// last IEND as a single part. An empty list if `bytes` doesn't start with a PNG.
+pub fn split(bytes: &[u8]) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let kind = Kind::of(&bytes[offset..]);
+        if kind != Kind::Png {
+            if !parts.is_empty() {
+                parts.push(Part {
+                    kind,
+                    range: offset..bytes.len(),
+                });
+            }
+            break;
+        }
+        let end = png_end(bytes, offset);
+        parts.push(Part {
+            kind,
+            range: offset..end,
+        });
+        offset = end;
+    }
+    parts
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub kind: Kind,
+    pub offset: usize,
+    /// Whether the file was appended after IEND, rather than interleaved with the PNG so
+    /// that one set of bytes is valid as both.
+    pub appended: bool,
+}
+
+impl Display for Detection {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        if self.appended {
+            write!(
+                fmt,
+                "{} appended after IEND at offset {}",
+                self.kind, self.offset
+            )
+        } else {
+            write!(
+                fmt,
+                "the file is also a {} (from offset {})",
+                self.kind, self.offset
+            )
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Files appended after the PNG, and ZIP archives or PDF documents that readers would find
+/// inside it.
+pub fn detect(bytes: &[u8]) -> Vec<Detection> {
+    let mut detections: Vec<Detection> = split(bytes)
+        .into_iter()
+        .skip(1)
+        .map(|part| Detection {
+            kind: part.kind,
+            offset: part.range.start,
+            appended: true,
+        })
+        .collect();
+    let appended =
+        |detections: &[Detection], kind| detections.iter().any(|detection| detection.kind == kind);
+    // ZIP readers work back from the end-of-central-directory record.
+    let tail = bytes.len().saturating_sub(ZIP_END_SEARCH);
+    if !appended(&detections, Kind::Zip) && find(&bytes[tail..], ZIP_END_OF_DIRECTORY).is_some() {
+        if let Some(offset) = find(bytes, ZIP_LOCAL_HEADER) {
+            detections.push(Detection {
+                kind: Kind::Zip,
+                offset,
+                appended: false,
+            });
+        }
+    }
+    let head = &bytes[..bytes.len().min(PDF_HEADER_SEARCH + PDF_HEADER.len())];
+    if let Some(offset) = find(head, PDF_HEADER).filter(|_| !appended(&detections, Kind::Pdf)) {
+        detections.push(Detection {
+            kind: Kind::Pdf,
+            offset,
+            appended: false,
+        });
+    }
+    detections
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> usize {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap()) as usize
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+/// Adds `shift` to the little-endian offset at `offset`, failing for ZIP64 archives, whose
+/// real offsets live in extra fields instead.
+fn shift_u32(bytes: &mut [u8], offset: usize, shift: usize) -> Result<(), String> {
+    let value = read_u32(bytes, offset);
+    if value == u32::MAX as usize {
+        return Err("ZIP64 archives are not supported".into());
+    }
+    let shifted = u32::try_from(value + shift).map_err(|_| "the archive is too large")?;
+    bytes[offset..offset + 4].copy_from_slice(&shifted.to_le_bytes());
+    Ok(())
+}
+
+/// Where the end-of-central-directory record starts: the last signature whose comment
+/// length reaches exactly to the end of the archive.
+fn end_of_directory(zip: &[u8]) -> Option<usize> {
+    let tail = zip.len().saturating_sub(ZIP_END_SEARCH);
+    (tail..=zip.len().checked_sub(ZIP_END_LEN)?)
+        .rev()
+        .find(|&offset| {
+            zip[offset..].starts_with(ZIP_END_OF_DIRECTORY)
+                && offset + ZIP_END_LEN + read_u16(zip, offset + 20) == zip.len()
+        })
+}
+
+/// A copy of `png` that is also a valid ZIP archive: `zip` goes in a chunk just before
+/// IEND, with its central directory offsets moved to where the archive now starts and its
+/// comment lengthened to cover the CRC and IEND that follow it, so that ZIP readers find
+/// the end-of-central-directory record exactly where they expect it.
+pub fn embed_zip(png: &Png, zip: &[u8]) -> Result<Vec<u8>, String> {
+    let end = end_of_directory(zip).ok_or("the payload is not a ZIP archive")?;
+    if read_u16(zip, end + 4) != 0 || read_u16(zip, end + 6) != 0 {
+        return Err("multi-disk ZIP archives are not supported".into());
+    }
+    let entries = read_u16(zip, end + 10);
+    let directory_len = read_u32(zip, end + 12);
+    let directory = read_u32(zip, end + 16);
+    if directory.checked_add(directory_len) != Some(end) {
+        return Err("the ZIP archive has data before its first entry".into());
+    }
+
+    let mut png = Png::from_chunks(
+        png.chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string() != ZIP_CHUNK_TYPE)
+            .filter(|chunk| *chunk.chunk_type() != ChunkType::IEND)
+            .map(|chunk| Chunk::new(*chunk.chunk_type(), chunk.data().to_vec()))
+            .collect(),
+    );
+    // The archive starts after its chunk's length and type.
+    let start = png.as_bytes().len() + 8;
+
+    let mut data = zip.to_vec();
+    let mut offset = directory;
+    for _ in 0..entries {
+        if offset + ZIP_CENTRAL_HEADER_LEN > end || !data[offset..].starts_with(ZIP_CENTRAL_HEADER)
+        {
+            return Err(format!("no central directory entry at offset {}", offset));
+        }
+        shift_u32(&mut data, offset + 42, start)?;
+        offset += ZIP_CENTRAL_HEADER_LEN
+            + read_u16(&data, offset + 28)
+            + read_u16(&data, offset + 30)
+            + read_u16(&data, offset + 32);
+    }
+    shift_u32(&mut data, end + 16, start)?;
+    let comment_len = u16::try_from(read_u16(&data, end + 20) + TRAILER_LEN)
+        .map_err(|_| "the ZIP archive's comment is too long")?;
+    data[end + 20..end + 22].copy_from_slice(&comment_len.to_le_bytes());
+    if data.len() > i32::MAX as usize {
+        return Err("the ZIP archive is too large for a chunk".into());
+    }
+
+    png.append_chunk(Chunk::new(
+        ChunkType::from_str(ZIP_CHUNK_TYPE).unwrap(),
+        data,
+    ));
+    png.append_chunk(Chunk::new(ChunkType::IEND, vec![]));
+    Ok(png.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crc::crc32::checksum_ieee;
+
+    fn png_with(payload: &[u8]) -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), payload.to_vec()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_split() {
+        let first = png_with(b"one");
+        let second = png_with(b"two");
+        let mut bytes = [first.clone(), second.clone()].concat();
+        bytes.extend_from_slice(b"PK\x03\x04rest");
+        let parts = split(&bytes);
+        let kinds: Vec<Kind> = parts.iter().map(|part| part.kind).collect();
+        assert_eq!(kinds, [Kind::Png, Kind::Png, Kind::Zip]);
+        assert_eq!(parts[1].range, first.len()..first.len() + second.len());
+        assert_eq!(parts[2].range.end, bytes.len());
+        assert_eq!(split(&first).len(), 1);
+        assert!(split(b"GIF89a").is_empty());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert!(detect(&png_with(b"plain")).is_empty());
+
+        let mut appended = png_with(b"x");
+        appended.extend_from_slice(b"%PDF-1.7 ...");
+        assert_eq!(
+            detect(&appended),
+            [Detection {
+                kind: Kind::Pdf,
+                offset: appended.len() - 12,
+                appended: true,
+            }]
+        );
+
+        let mut zip = b"PK\x03\x04local file".to_vec();
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 18]);
+        let interleaved = png_with(&zip);
+        let detections = detect(&interleaved);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].kind, Kind::Zip);
+        assert_eq!(detections[0].offset, 16);
+        assert!(!detections[0].appended);
+        assert_eq!(
+            detections[0].to_string(),
+            "the file is also a ZIP archive (from offset 16)"
+        );
+    }
+
+    /// A ZIP archive holding one stored file, `name`, with an optional comment.
+    fn stored_zip(name: &str, contents: &[u8], comment: &[u8]) -> Vec<u8> {
+        let crc = checksum_ieee(contents).to_le_bytes();
+        let size = (contents.len() as u32).to_le_bytes();
+        let name_len = (name.len() as u16).to_le_bytes();
+        let fields = [
+            &[0; 2][..],
+            &[0; 2],
+            &[0; 4],
+            &crc,
+            &size,
+            &size,
+            &name_len,
+            &[0; 2],
+        ]
+        .concat();
+        let mut zip = [
+            ZIP_LOCAL_HEADER,
+            &[20, 0],
+            &fields,
+            name.as_bytes(),
+            contents,
+        ]
+        .concat();
+        let directory = zip.len() as u32;
+        let central = [
+            ZIP_CENTRAL_HEADER,
+            &[20, 0, 20, 0],
+            &fields,
+            &[0; 12],
+            &0u32.to_le_bytes(),
+            name.as_bytes(),
+        ]
+        .concat();
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(ZIP_END_OF_DIRECTORY);
+        zip.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&directory.to_le_bytes());
+        zip.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+        zip.extend_from_slice(comment);
+        zip
+    }
+
+    #[test]
+    fn test_embed_zip() {
+        let png = Png::try_from(&png_with(b"image")[..]).unwrap();
+        let zip = stored_zip("flag.txt", b"CTF{both}", b"hi");
+        let bytes = embed_zip(&png, &zip).unwrap();
+
+        let embedded = Png::try_from(&bytes[..]).unwrap();
+        assert_eq!(embedded.chunks().len(), 3);
+        assert_eq!(*embedded.chunks()[2].chunk_type(), ChunkType::IEND);
+        assert_eq!(
+            embedded.chunk_by_type(ZIP_CHUNK_TYPE).unwrap().length() as usize,
+            zip.len()
+        );
+
+        let end = end_of_directory(&bytes).unwrap();
+        assert_eq!(&bytes[end + 22..end + 24], b"hi");
+        let directory = read_u32(&bytes, end + 16);
+        assert!(bytes[directory..].starts_with(ZIP_CENTRAL_HEADER));
+        let local = read_u32(&bytes, directory + 42);
+        assert!(bytes[local..].starts_with(ZIP_LOCAL_HEADER));
+        assert_eq!(&bytes[local + 30..local + 38], b"flag.txt");
+        assert!(detect(&bytes)
+            .iter()
+            .any(|detection| detection.kind == Kind::Zip));
+
+        // Embedding again replaces the archive rather than adding a second one.
+        let again = embed_zip(&embedded, &zip).unwrap();
+        assert_eq!(again, bytes);
+        assert!(embed_zip(&png, b"not a zip").is_err());
+    }
+}
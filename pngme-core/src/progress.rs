@@ -0,0 +1,79 @@
+//! Progress reports and cancellation for the operations that can take seconds on a large
+//! file: parsing it, recompressing its image data and embedding a message in its pixels.
+//! A GUI can drive a progress bar from [`Progress`] and a server can abort a request past
+//! its deadline with [`CancelToken`]; either way the input is left as it was.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Told how far an operation has got, in whatever units it counts: bytes for parsing and
+/// compression, message bits for embedding. May be called from several threads at once.
+pub trait Progress: Sync {
+    fn update(&self, done: u64, total: u64);
+}
+
+impl<F: Fn(u64, u64) + Sync> Progress for F {
+    fn update(&self, done: u64, total: u64) {
+        self(done, total)
+    }
+}
+
+/// Reports to nothing; what the plain versions of the operations use.
+pub const NO_PROGRESS: &dyn Progress = &|_, _| {};
+
+/// Shared between the caller and an operation, which stops at its next check once
+/// [`cancel`](CancelToken::cancel) has been called on any clone.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Interrupted::Cancelled)` once cancelled, for `?` at each check.
+    pub fn check(&self) -> Result<(), Interrupted> {
+        if self.is_cancelled() {
+            Err(Interrupted::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Why an operation with progress and cancellation didn't finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupted {
+    Cancelled,
+    /// The operation failed as its plain version would have.
+    Failed,
+}
+
+impl From<()> for Interrupted {
+    fn from((): ()) -> Self {
+        Interrupted::Failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert_eq!(token.check(), Ok(()));
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(Interrupted::Cancelled));
+    }
+}
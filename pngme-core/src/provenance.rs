@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use crate::cbor::Value;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Private, ancillary, safe-to-copy chunk holding build provenance as a CBOR map.
+pub const PROVENANCE_CHUNK_TYPE: &str = "prVn";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    entries: Vec<(String, String)>,
+}
+
+impl Provenance {
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn set(&mut self, key: &str, value: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|(k, _)| k == key) {
+            entry.1 = value.to_string();
+        } else {
+            self.entries.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, ()>> {
+        png.chunk_by_type(PROVENANCE_CHUNK_TYPE)
+            .map(|chunk| Self::from_cbor(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::from_str(PROVENANCE_CHUNK_TYPE).unwrap();
+        Chunk::new(chunk_type, self.to_cbor())
+    }
+
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(key, value)| (Value::Text(key.clone()), Value::Text(value.clone())))
+            .collect();
+        Value::Map(entries).encode()
+    }
+
+    /// Expects a single map whose keys and values are all text strings.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ()> {
+        let mut provenance = Self::default();
+        for (key, value) in Value::decode(bytes)?.as_map().ok_or(())? {
+            provenance.set(key.as_text().ok_or(())?, value.as_text().ok_or(())?);
+        }
+        Ok(provenance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_provenance() -> Provenance {
+        let mut provenance = Provenance::default();
+        provenance.set("version", "1.2.3");
+        provenance.set("commit", "abc123");
+        provenance
+    }
+
+    #[test]
+    fn test_cbor_encoding() {
+        let provenance = testing_provenance();
+        #[rustfmt::skip]
+        let expected = vec![
+            0xa2,                                           // map(2)
+            0x67, b'v', b'e', b'r', b's', b'i', b'o', b'n', // text(7)
+            0x65, b'1', b'.', b'2', b'.', b'3',             // text(5)
+            0x66, b'c', b'o', b'm', b'm', b'i', b't',       // text(6)
+            0x66, b'a', b'b', b'c', b'1', b'2', b'3',       // text(6)
+        ];
+        assert_eq!(provenance.to_cbor(), expected);
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let mut provenance = testing_provenance();
+        provenance.set("pipeline", &"x".repeat(300));
+        let decoded = Provenance::from_cbor(&provenance.to_cbor()).unwrap();
+        assert_eq!(decoded, provenance);
+    }
+
+    #[test]
+    fn test_set_replaces_existing_key() {
+        let mut provenance = testing_provenance();
+        provenance.set("version", "2.0.0");
+        assert_eq!(provenance.get("version"), Some("2.0.0"));
+        assert_eq!(provenance.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_cbor() {
+        assert!(Provenance::from_cbor(&[0x67, b'v']).is_err());
+        assert!(Provenance::from_cbor(&[0xa1, 0x61, b'k']).is_err());
+        assert!(Provenance::from_cbor(&[0xa0, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let mut png = Png::from_chunks(Vec::new());
+        png.append_chunk(testing_provenance().to_chunk());
+        let bytes = png.as_bytes();
+        let png = Png::try_from(&bytes[..]).unwrap();
+        let provenance = Provenance::from_png(&png).unwrap().unwrap();
+        assert_eq!(provenance, testing_provenance());
+    }
+}
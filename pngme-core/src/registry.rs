@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::chunk_type::ChunkType;
+use crate::schema::Schema;
+
+/// A user-registered chunk type and the layout of its payload.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChunkDescriptor {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub schema: Schema,
+}
+
+#[derive(Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    chunk: Vec<ChunkDescriptor>,
+}
+
+/// Project-specific chunk types loaded from a TOML file of `[[chunk]]` tables.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Registry {
+    descriptors: Vec<ChunkDescriptor>,
+}
+
+impl TryFrom<RegistryFile> for Registry {
+    type Error = String;
+    fn try_from(file: RegistryFile) -> Result<Self, String> {
+        for (index, descriptor) in file.chunk.iter().enumerate() {
+            if let Err(error) = ChunkType::from_str(&descriptor.chunk_type) {
+                return Err(format!(
+                    "`{}` is not a valid chunk type: {}",
+                    descriptor.chunk_type, error
+                ));
+            }
+            if file.chunk[..index]
+                .iter()
+                .any(|earlier| earlier.chunk_type == descriptor.chunk_type)
+            {
+                return Err(format!("{} is registered twice", descriptor.chunk_type));
+            }
+        }
+        Ok(Self {
+            descriptors: file.chunk,
+        })
+    }
+}
+
+impl Registry {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: RegistryFile = toml::from_str(text).map_err(|error| error.to_string())?;
+        Self::try_from(file)
+    }
+    pub fn descriptors(&self) -> &[ChunkDescriptor] {
+        &self.descriptors
+    }
+    pub fn descriptor(&self, chunk_type: &str) -> Option<&ChunkDescriptor> {
+        self.descriptors
+            .iter()
+            .find(|descriptor| descriptor.chunk_type == chunk_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Endianness, FieldKind};
+
+    const REGISTRY: &str = r#"
+        [[chunk]]
+        type = "lvGr"
+        name = "Level grid"
+        endianness = "little"
+        fields = [
+            { name = "width", type = "u16" },
+            { name = "flags", type = "bytes[2]" },
+        ]
+    "#;
+
+    #[test]
+    fn test_load_registry() {
+        let registry = Registry::from_toml(REGISTRY).unwrap();
+        let descriptor = registry.descriptor("lvGr").unwrap();
+        assert_eq!(descriptor.name, "Level grid");
+        assert_eq!(descriptor.schema.endianness, Endianness::Little);
+        assert_eq!(descriptor.schema.fields[1].kind, FieldKind::Bytes(2));
+        assert!(registry.descriptor("lvGR").is_none());
+    }
+
+    #[test]
+    fn test_invalid_registry() {
+        assert!(Registry::from_toml(
+            "[[chunk]]\ntype = \"lvGr\"\nname = \"x\"\nfields = [{ name = \"a\", type = \"f32\" }]"
+        )
+        .is_err());
+        assert!(
+            Registry::from_toml("[[chunk]]\ntype = \"l1Gr\"\nname = \"x\"\nfields = []").is_err()
+        );
+        let twice = "[[chunk]]\ntype = \"lvGr\"\nname = \"x\"\nfields = []\n".repeat(2);
+        assert!(Registry::from_toml(&twice).is_err());
+        assert_eq!(Registry::from_toml("").unwrap(), Registry::default());
+    }
+}
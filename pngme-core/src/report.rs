@@ -0,0 +1,207 @@
+use std::fmt::Write;
+
+use crate::hex;
+use crate::image::ImageHeader;
+use crate::png::Png;
+
+/// Bytes of each chunk's payload shown in the hex preview.
+const PREVIEW_BYTES: usize = 48;
+/// Bars in the whole-file entropy chart.
+const CHART_BLOCKS: usize = 64;
+const CHART_HEIGHT: usize = 80;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.2em .5em;text-align:left}\
+td.num{text-align:right}code{font-size:.85em;word-break:break-all}\
+.bar{background:#4a7;height:.8em}.chart rect{fill:#4a7}";
+
+/// Shannon entropy in bits per byte, from 0 for constant data to 8 for uniformly random data.
+pub fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    let total = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn header_section(html: &mut String, png: &Png) {
+    html.push_str("<h2>Header</h2>\n");
+    let Ok(header) = ImageHeader::from_png(png) else {
+        html.push_str("<p>No valid IHDR chunk.</p>\n");
+        return;
+    };
+    let rows = [
+        ("Width", header.width.to_string()),
+        ("Height", header.height.to_string()),
+        ("Bit depth", header.bit_depth.to_string()),
+        ("Colour type", format!("{:?}", header.color_type)),
+        ("Interlaced", header.interlaced.to_string()),
+    ];
+    html.push_str("<table>\n");
+    for (name, value) in rows {
+        writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", name, value).unwrap();
+    }
+    html.push_str("</table>\n");
+}
+
+fn chunk_section(html: &mut String, png: &Png, offsets: &[u64]) {
+    html.push_str("<h2>Chunks</h2>\n<table>\n<tr><th>#</th><th>Offset</th><th>Type</th>");
+    html.push_str("<th>Length</th><th>CRC</th><th>Entropy</th><th>Preview</th></tr>\n");
+    for (index, (chunk, offset)) in png.chunks().iter().zip(offsets).enumerate() {
+        let entropy = entropy(chunk.data());
+        let preview = &chunk.data()[..chunk.data().len().min(PREVIEW_BYTES)];
+        let elided = if preview.len() < chunk.data().len() {
+            "&hellip;"
+        } else {
+            ""
+        };
+        writeln!(
+            html,
+            "<tr><td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td>\
+             <td class=\"num\">{}</td><td><code>{:08x}</code></td>\
+             <td>{:.2}<div class=\"bar\" style=\"width:{:.0}px\"></div></td>\
+             <td><code>{}{}</code></td></tr>",
+            index,
+            offset,
+            escape(&chunk.chunk_type().to_string()),
+            chunk.length(),
+            chunk.crc(),
+            entropy,
+            entropy * 10.0,
+            hex::encode(preview),
+            elided
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n");
+}
+
+fn entropy_chart(html: &mut String, bytes: &[u8]) {
+    html.push_str("<h2>Entropy across the file</h2>\n");
+    if bytes.is_empty() {
+        html.push_str("<p>The file is empty.</p>\n");
+        return;
+    }
+    let block = bytes.len().div_ceil(CHART_BLOCKS);
+    let blocks: Vec<&[u8]> = bytes.chunks(block).collect();
+    writeln!(
+        html,
+        "<svg class=\"chart\" width=\"{}\" height=\"{}\" role=\"img\">",
+        blocks.len() * 8,
+        CHART_HEIGHT
+    )
+    .unwrap();
+    for (index, block_bytes) in blocks.iter().enumerate() {
+        let entropy = entropy(block_bytes);
+        let height = entropy / 8.0 * CHART_HEIGHT as f64;
+        writeln!(
+            html,
+            "<rect x=\"{}\" y=\"{:.1}\" width=\"7\" height=\"{:.1}\">\
+             <title>bytes {}..{}: {:.2} bits/byte</title></rect>",
+            index * 8,
+            CHART_HEIGHT as f64 - height,
+            height,
+            index * block,
+            index * block + block_bytes.len(),
+            entropy
+        )
+        .unwrap();
+    }
+    writeln!(
+        html,
+        "</svg>\n<p>Each bar is {} bytes; full height is 8 bits per byte.</p>",
+        block
+    )
+    .unwrap();
+}
+
+/// A self-contained HTML page describing `png`, which was parsed from `bytes` with each chunk
+/// at the matching entry of `offsets`, along with whatever findings the caller's checks produced.
+pub fn html(name: &str, bytes: &[u8], png: &Png, offsets: &[u64], findings: &[String]) -> String {
+    let mut html = String::new();
+    let title = escape(name);
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>pngme report: {}</title>\n<style>{}</style>\n</head>\n<body>\n\
+         <h1>{}</h1>\n<p>{} bytes, {} chunks.</p>",
+        title,
+        STYLE,
+        title,
+        bytes.len(),
+        png.chunks().len()
+    )
+    .unwrap();
+    header_section(&mut html, png);
+    chunk_section(&mut html, png, offsets);
+    entropy_chart(&mut html, bytes);
+    html.push_str("<h2>Findings</h2>\n");
+    if findings.is_empty() {
+        html.push_str("<p>None.</p>\n");
+    } else {
+        html.push_str("<ul>\n");
+        for finding in findings {
+            writeln!(html, "<li>{}</li>", escape(finding)).unwrap();
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::validate;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7; 100]), 0.0);
+        assert_eq!(entropy(&[0, 1]), 1.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&all), 8.0);
+    }
+
+    #[test]
+    fn test_html() {
+        let chunk = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"<script>".to_vec());
+        let png = Png::from_chunks(vec![chunk, Chunk::new(ChunkType::IEND, vec![])]);
+        let bytes = png.as_bytes();
+        let findings = ["IHDR must be <first>".to_string()];
+        let offsets = validate::chunk_offsets(&png);
+        let html = html("a&b.png", &bytes, &png, &offsets, &findings);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>a&amp;b.png</h1>"));
+        assert!(html.contains("No valid IHDR chunk."));
+        assert!(html.contains(&hex::encode(b"<script>")));
+        assert!(html.contains("<li>IHDR must be &lt;first&gt;</li>"));
+        assert!(!html.contains("<script>"));
+    }
+}
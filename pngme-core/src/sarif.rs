@@ -0,0 +1,169 @@
+use serde::Serialize;
+
+use crate::validate::Finding;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const VERSION: &str = "2.1.0";
+
+#[derive(Debug, Serialize)]
+pub struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: &'static str,
+    short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    byte_offset: u64,
+}
+
+/// A SARIF 2.1.0 log of `findings` in the file at `uri`, one warning-level result each.
+pub fn log(uri: &str, findings: &[Finding]) -> Log {
+    let mut rules: Vec<ReportingDescriptor> = Vec::new();
+    let results = findings
+        .iter()
+        .map(|finding| {
+            let rule_index = match rules.iter().position(|rule| rule.id == finding.rule_id) {
+                Some(index) => index,
+                None => {
+                    rules.push(ReportingDescriptor {
+                        id: finding.rule_id,
+                        short_description: Message {
+                            text: finding.rule_description.to_string(),
+                        },
+                    });
+                    rules.len() - 1
+                }
+            };
+            SarifResult {
+                rule_id: finding.rule_id,
+                rule_index,
+                level: "warning",
+                message: Message {
+                    text: finding.message.clone(),
+                },
+                locations: vec![Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location: ArtifactLocation {
+                            uri: uri.to_string(),
+                        },
+                        region: finding.offset.map(|byte_offset| Region { byte_offset }),
+                    },
+                }],
+            }
+        })
+        .collect();
+    Log {
+        schema: SCHEMA,
+        version: VERSION,
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "pngme",
+                    version: env!("CARGO_PKG_VERSION"),
+                    rules,
+                },
+            },
+            results,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::Png;
+    use crate::validate;
+
+    #[test]
+    fn test_log() {
+        let chunk = |chunk_type| Chunk::new(chunk_type, vec![]);
+        let png = Png::from_chunks(vec![
+            chunk(ChunkType::IDAT),
+            chunk(ChunkType::IHDR),
+            chunk(ChunkType::IEND),
+            chunk(ChunkType::IHDR),
+        ]);
+        let findings = validate::findings(&png, &png.as_bytes());
+        let log = serde_json::to_value(log("image.png", &findings)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "pngme");
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), findings.len());
+        assert_eq!(results[0]["ruleId"], "ihdr-first");
+        assert_eq!(results[0]["level"], "warning");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "image.png");
+        assert_eq!(location["region"]["byteOffset"], 8);
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        for result in results {
+            let rule = &rules[result["ruleIndex"].as_u64().unwrap() as usize];
+            assert_eq!(rule["id"], result["ruleId"]);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::hex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum FieldKind {
+    U8,
+    U16,
+    U32,
+    /// NUL-terminated Latin-1 text, as in `tEXt` keywords.
+    Strz,
+    Bytes(usize),
+}
+
+impl FromStr for FieldKind {
+    type Err = String;
+    fn from_str(kind: &str) -> Result<Self, String> {
+        let bytes = kind
+            .strip_prefix("bytes[")
+            .and_then(|rest| rest.strip_suffix(']'));
+        match (kind, bytes) {
+            ("u8", _) => Ok(FieldKind::U8),
+            ("u16", _) => Ok(FieldKind::U16),
+            ("u32", _) => Ok(FieldKind::U32),
+            ("strz", _) => Ok(FieldKind::Strz),
+            (_, Some(length)) => length
+                .parse()
+                .map(FieldKind::Bytes)
+                .map_err(|_| format!("invalid length in field type `{}`", kind)),
+            _ => Err(format!(
+                "unknown field type `{}`; expected u8, u16, u32, strz or bytes[n]",
+                kind
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for FieldKind {
+    type Error = String;
+    fn try_from(kind: String) -> Result<Self, String> {
+        kind.parse()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    Unsigned(u32),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            FieldValue::Unsigned(value) => write!(fmt, "{}", value),
+            FieldValue::Text(text) => write!(fmt, "{}", text),
+            FieldValue::Bytes(bytes) => write!(fmt, "{}", hex::encode(bytes)),
+        }
+    }
+}
+
+impl FieldKind {
+    /// Parses a value in the form `Display` prints it: decimal, plain text, or hex.
+    pub fn parse_value(self, text: &str) -> Result<FieldValue, ()> {
+        let value = match self {
+            FieldKind::U8 => FieldValue::Unsigned(text.parse::<u8>().map_err(|_| ())? as u32),
+            FieldKind::U16 => FieldValue::Unsigned(text.parse::<u16>().map_err(|_| ())? as u32),
+            FieldKind::U32 => FieldValue::Unsigned(text.parse().map_err(|_| ())?),
+            FieldKind::Strz => FieldValue::Text(text.to_string()),
+            FieldKind::Bytes(_) => FieldValue::Bytes(hex::decode(text)?),
+        };
+        if self.accepts(&value) {
+            Ok(value)
+        } else {
+            Err(())
+        }
+    }
+
+    fn accepts(self, value: &FieldValue) -> bool {
+        match (self, value) {
+            (FieldKind::U8, FieldValue::Unsigned(value)) => *value <= u8::MAX as u32,
+            (FieldKind::U16, FieldValue::Unsigned(value)) => *value <= u16::MAX as u32,
+            (FieldKind::U32, FieldValue::Unsigned(_)) => true,
+            (FieldKind::Strz, FieldValue::Text(text)) => {
+                text.chars().all(|c| c != '\0' && (c as u32) <= 0xff)
+            }
+            (FieldKind::Bytes(length), FieldValue::Bytes(bytes)) => bytes.len() == length,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+}
+
+/// A declarative payload layout: fields packed back to back with no padding.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Schema {
+    #[serde(default)]
+    pub endianness: Endianness,
+    pub fields: Vec<Field>,
+}
+
+pub type Fields = Vec<(String, FieldValue)>;
+
+impl Schema {
+    fn unsigned(&self, bytes: &[u8]) -> u32 {
+        let fold = |value: u32, byte: &u8| value << 8 | *byte as u32;
+        match self.endianness {
+            Endianness::Big => bytes.iter().fold(0, fold),
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+        }
+    }
+
+    /// Decodes a payload, which must be exactly as long as the layout describes.
+    pub fn decode(&self, data: &[u8]) -> Result<Fields, ()> {
+        let mut rest = data;
+        let mut fields = Vec::new();
+        for field in &self.fields {
+            let take = match field.kind {
+                FieldKind::U8 => 1,
+                FieldKind::U16 => 2,
+                FieldKind::U32 => 4,
+                FieldKind::Strz => rest.iter().position(|&byte| byte == 0).ok_or(())? + 1,
+                FieldKind::Bytes(length) => length,
+            };
+            if take > rest.len() {
+                return Err(());
+            }
+            let (bytes, remaining) = rest.split_at(take);
+            rest = remaining;
+            let value = match field.kind {
+                FieldKind::U8 | FieldKind::U16 | FieldKind::U32 => {
+                    FieldValue::Unsigned(self.unsigned(bytes))
+                }
+                FieldKind::Strz => {
+                    FieldValue::Text(bytes[..take - 1].iter().map(|&b| b as char).collect())
+                }
+                FieldKind::Bytes(_) => FieldValue::Bytes(bytes.to_vec()),
+            };
+            fields.push((field.name.clone(), value));
+        }
+        if rest.is_empty() {
+            Ok(fields)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Encodes a value for every field in the layout, looked up by name.
+    pub fn encode(&self, values: &[(String, FieldValue)]) -> Result<Vec<u8>, ()> {
+        let mut data = Vec::new();
+        for field in &self.fields {
+            let value = values
+                .iter()
+                .find(|(name, _)| *name == field.name)
+                .map(|(_, value)| value)
+                .ok_or(())?;
+            if !field.kind.accepts(value) {
+                return Err(());
+            }
+            let width = match field.kind {
+                FieldKind::U8 => 1,
+                FieldKind::U16 => 2,
+                _ => 4,
+            };
+            match value {
+                FieldValue::Unsigned(value) => match self.endianness {
+                    Endianness::Big => data.extend_from_slice(&value.to_be_bytes()[4 - width..]),
+                    Endianness::Little => data.extend_from_slice(&value.to_le_bytes()[..width]),
+                },
+                FieldValue::Text(text) => {
+                    data.extend(text.chars().map(|c| c as u8));
+                    data.push(0);
+                }
+                FieldValue::Bytes(bytes) => data.extend_from_slice(bytes),
+            }
+        }
+        Ok(data)
+    }
+
+    fn field(&self, name: &str) -> Result<&Field, String> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| format!("no field named `{}`", name))
+    }
+
+    pub fn get(&self, data: &[u8], name: &str) -> Result<FieldValue, String> {
+        self.field(name)?;
+        let fields = self
+            .decode(data)
+            .map_err(|()| String::from("payload does not match the layout"))?;
+        Ok(fields
+            .into_iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+            .unwrap())
+    }
+
+    /// Re-encodes `data` with one field replaced by `value`, given in `Display` form.
+    pub fn set(&self, data: &[u8], name: &str, value: &str) -> Result<Vec<u8>, String> {
+        let field = self.field(name)?;
+        let value = field
+            .kind
+            .parse_value(value)
+            .map_err(|()| format!("`{}` is not a valid value for {}", value, name))?;
+        let mut fields = self
+            .decode(data)
+            .map_err(|()| String::from("payload does not match the layout"))?;
+        for (field, old) in fields.iter_mut() {
+            if field == name {
+                *old = value.clone();
+            }
+        }
+        Ok(self.encode(&fields).unwrap())
+    }
+
+    /// Parses the `name = value` lines written by `format_fields`, skipping blanks and `#` comments.
+    pub fn parse_fields(&self, text: &str) -> Result<Fields, String> {
+        let mut values = Vec::new();
+        for line in text.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("expected `name = value`, got `{}`", line))?;
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            let field = self.field(name.trim())?;
+            let value = field
+                .kind
+                .parse_value(value)
+                .map_err(|()| format!("`{}` is not a valid value for {}", value, field.name))?;
+            values.push((field.name.clone(), value));
+        }
+        Ok(values)
+    }
+}
+
+pub fn format_fields(fields: &[(String, FieldValue)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{} = {}\n", name, value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        endianness = "little"
+        fields = [
+            { name = "width", type = "u16" },
+            { name = "height", type = "u16" },
+            { name = "title", type = "strz" },
+            { name = "seed", type = "u32" },
+            { name = "flags", type = "bytes[2]" },
+        ]
+    "#;
+    const DATA: [u8; 13] = [3, 0, 2, 0, b'h', b'i', 0, 1, 0, 0, 0, 0xab, 0xcd];
+
+    fn schema() -> Schema {
+        toml::from_str(SCHEMA).unwrap()
+    }
+
+    #[test]
+    fn test_field_kinds() {
+        assert_eq!("bytes[16]".parse(), Ok(FieldKind::Bytes(16)));
+        assert!("bytes[]".parse::<FieldKind>().is_err());
+        assert!("f32".parse::<FieldKind>().is_err());
+        assert_eq!(schema().fields[4].kind, FieldKind::Bytes(2));
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        let schema = schema();
+        let fields = schema.decode(&DATA).unwrap();
+        assert_eq!(fields[0], ("width".to_string(), FieldValue::Unsigned(3)));
+        assert_eq!(
+            fields[2],
+            ("title".to_string(), FieldValue::Text("hi".into()))
+        );
+        assert_eq!(fields[3], ("seed".to_string(), FieldValue::Unsigned(1)));
+        assert_eq!(schema.encode(&fields).unwrap(), DATA);
+
+        assert!(schema.decode(&DATA[..12]).is_err());
+        assert!(schema.decode(&[&DATA[..], &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let schema: Schema = toml::from_str("fields = [{ name = \"n\", type = \"u32\" }]").unwrap();
+        let fields = schema.decode(&[0, 0, 1, 2]).unwrap();
+        assert_eq!(fields[0].1, FieldValue::Unsigned(258));
+        assert_eq!(schema.encode(&fields).unwrap(), [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_get_set() {
+        let schema = schema();
+        assert_eq!(schema.get(&DATA, "seed").unwrap(), FieldValue::Unsigned(1));
+        let data = schema.set(&DATA, "title", "longer title").unwrap();
+        assert_eq!(data.len(), DATA.len() + 10);
+        assert_eq!(
+            schema.get(&data, "title").unwrap(),
+            FieldValue::Text("longer title".into())
+        );
+        assert_eq!(
+            schema.get(&data, "flags").unwrap(),
+            FieldValue::Bytes(vec![0xab, 0xcd])
+        );
+        assert!(schema.set(&DATA, "width", "-1").is_err());
+        assert!(schema.set(&DATA, "flags", "00").is_err());
+        assert!(schema.get(&DATA, "depth").is_err());
+        assert!(schema.get(&DATA[..3], "width").is_err());
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let schema = schema();
+        let text =
+            "width = 640\nheight = 480\n# comment\ntitle = two words\nseed = 7\nflags = 0001\n";
+        let fields = schema.parse_fields(text).unwrap();
+        assert_eq!(format_fields(&fields), text.replace("# comment\n", ""));
+        assert!(schema.parse_fields("width = 70000").is_err());
+        assert!(schema.parse_fields("depth = 1").is_err());
+        assert!(schema.encode(&fields[..2]).is_err());
+    }
+}
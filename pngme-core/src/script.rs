@@ -0,0 +1,250 @@
+//! Rhai scripts for `pngme script`, for transformations the other commands don't cover.
+//!
+//! A script sees the file as the variable `png`, a list of chunks it can read, replace and
+//! rearrange in place:
+//!
+//! ```rhai
+//! for index in png.select("text,after-idat") {
+//!     print(png[index].data.as_string());
+//! }
+//! png.remove_type("tIME");
+//! png.insert_ancillary(chunk("ruSt", "hello".to_blob()));
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use rhai::{Array, Blob, Engine, EvalAltResult, Scope, INT};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::select::{self, Selector};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// A chunk as scripts see it: a copy, so changes only land when it is stored back in `png`.
+#[derive(Debug, Clone)]
+struct ScriptChunk {
+    chunk_type: ChunkType,
+    data: Blob,
+}
+
+impl ScriptChunk {
+    fn of(chunk: &Chunk) -> Self {
+        Self {
+            chunk_type: *chunk.chunk_type(),
+            data: chunk.data().to_vec(),
+        }
+    }
+    fn to_chunk(&self) -> Chunk {
+        Chunk::new(self.chunk_type, self.data.clone())
+    }
+}
+
+/// Shared with the script's scope, so the edits can be taken back once it finishes.
+#[derive(Clone)]
+struct ScriptPng(Rc<RefCell<Png>>);
+
+impl ScriptPng {
+    fn index(&self, index: INT, allow_end: bool) -> ScriptResult<usize> {
+        let len = self.0.borrow().chunks().len();
+        let limit = if allow_end { len + 1 } else { len };
+        usize::try_from(index)
+            .ok()
+            .filter(|&index| index < limit)
+            .ok_or_else(|| {
+                format!("chunk index {} is out of range for {} chunks", index, len).into()
+            })
+    }
+    fn len(&mut self) -> INT {
+        self.0.borrow().chunks().len() as INT
+    }
+    fn get(&mut self, index: INT) -> ScriptResult<ScriptChunk> {
+        let index = self.index(index, false)?;
+        Ok(ScriptChunk::of(&self.0.borrow().chunks()[index]))
+    }
+    fn set(&mut self, index: INT, chunk: ScriptChunk) -> ScriptResult<()> {
+        let index = self.index(index, false)?;
+        self.0.borrow_mut().replace_chunk(index, chunk.to_chunk());
+        Ok(())
+    }
+    fn insert(&mut self, index: INT, chunk: ScriptChunk) -> ScriptResult<()> {
+        let index = self.index(index, true)?;
+        self.0.borrow_mut().insert_chunk(index, chunk.to_chunk());
+        Ok(())
+    }
+    fn insert_ancillary(&mut self, chunk: ScriptChunk) {
+        self.0.borrow_mut().insert_ancillary(chunk.to_chunk());
+    }
+    fn remove(&mut self, index: INT) -> ScriptResult<ScriptChunk> {
+        let index = self.index(index, false)?;
+        let mut removed = None;
+        let mut position = 0;
+        self.0.borrow_mut().retain_chunks(|chunk| {
+            position += 1;
+            if position - 1 == index {
+                removed = Some(ScriptChunk::of(chunk));
+            }
+            position - 1 != index
+        });
+        Ok(removed.unwrap())
+    }
+    fn remove_type(&mut self, chunk_type: &str) -> ScriptResult<INT> {
+        let chunk_type = parse_chunk_type(chunk_type)?;
+        let mut png = self.0.borrow_mut();
+        let before = png.chunks().len();
+        png.retain_chunks(|chunk| *chunk.chunk_type() != chunk_type);
+        Ok((before - png.chunks().len()) as INT)
+    }
+    fn types(&mut self) -> Array {
+        self.0
+            .borrow()
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string().into())
+            .collect()
+    }
+    fn indices(&mut self, chunk_type: &str) -> ScriptResult<Array> {
+        let chunk_type = parse_chunk_type(chunk_type)?;
+        Ok(self
+            .0
+            .borrow()
+            .chunks()
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| *chunk.chunk_type() == chunk_type)
+            .map(|(index, _)| (index as INT).into())
+            .collect())
+    }
+    /// Indices of the chunks matching a `--select` expression.
+    fn select(&mut self, expression: &str) -> ScriptResult<Array> {
+        let selector = Selector::from_str(expression)?;
+        Ok(select::selected(&self.0.borrow(), &[selector])
+            .into_iter()
+            .enumerate()
+            .filter(|(_, selected)| *selected)
+            .map(|(index, _)| (index as INT).into())
+            .collect())
+    }
+}
+
+fn parse_chunk_type(chunk_type: &str) -> ScriptResult<ChunkType> {
+    ChunkType::from_str(chunk_type)
+        .map_err(|error| format!("`{}` is not a valid chunk type: {}", chunk_type, error).into())
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register_type_with_name::<ScriptChunk>("Chunk")
+        .register_fn("chunk", |chunk_type: &str, data: Blob| {
+            Ok(ScriptChunk {
+                chunk_type: parse_chunk_type(chunk_type)?,
+                data,
+            }) as ScriptResult<_>
+        })
+        .register_get_set(
+            "type",
+            |chunk: &mut ScriptChunk| chunk.chunk_type.to_string(),
+            |chunk: &mut ScriptChunk, chunk_type: String| {
+                chunk.chunk_type = parse_chunk_type(&chunk_type)?;
+                Ok(()) as ScriptResult<_>
+            },
+        )
+        .register_get_set(
+            "data",
+            |chunk: &mut ScriptChunk| chunk.data.clone(),
+            |chunk: &mut ScriptChunk, data: Blob| chunk.data = data,
+        )
+        .register_get("length", |chunk: &mut ScriptChunk| chunk.data.len() as INT)
+        .register_get("critical", |chunk: &mut ScriptChunk| {
+            chunk.chunk_type.is_critical()
+        })
+        .register_type_with_name::<ScriptPng>("Png")
+        .register_fn("len", ScriptPng::len)
+        .register_indexer_get(ScriptPng::get)
+        .register_indexer_set(ScriptPng::set)
+        .register_fn("insert", ScriptPng::insert)
+        .register_fn("insert_ancillary", ScriptPng::insert_ancillary)
+        .register_fn("remove", ScriptPng::remove)
+        .register_fn("remove_type", ScriptPng::remove_type)
+        .register_fn("types", ScriptPng::types)
+        .register_fn("indices", ScriptPng::indices)
+        .register_fn("select", ScriptPng::select);
+    engine
+}
+
+/// Runs `source` against `png`, returning the file as the script left it.
+pub fn run(source: &str, png: Png) -> Result<Png, String> {
+    let handle = ScriptPng(Rc::new(RefCell::new(png)));
+    let mut scope = Scope::new();
+    scope.push("png", handle.clone());
+    engine()
+        .run_with_scope(&mut scope, source)
+        .map_err(|error| error.to_string())?;
+    drop(scope);
+    let png = handle.0.replace(Png::from_chunks(vec![]));
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::TIME, vec![0; 7]),
+            Chunk::new(ChunkType::IDAT, vec![]),
+            Chunk::new(ChunkType::TEXT, b"Comment\0late".to_vec()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    fn types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_run() {
+        let script = r#"
+            let late = png.select("text,after-idat");
+            if late != [3] { throw "select"; }
+            let text = png.remove(3);
+            png.insert_ancillary(text);
+            if png.remove_type("tIME") != 1 { throw "remove_type"; }
+            png.insert(1, chunk("ruSt", "hi".to_blob()));
+            let header = png[0];
+            header.data[0] = 1;
+            png[0] = header;
+            if png.indices("tEXt") != [2] || png[1].length != 2 || !png[0].critical {
+                throw "accessors";
+            }
+        "#;
+        let png = run(script, testing_png()).unwrap();
+        assert_eq!(types(&png), ["IHDR", "ruSt", "tEXt", "IDAT", "IEND"]);
+        assert_eq!(png.chunks()[0].data()[0], 1);
+        assert_eq!(png.chunks()[1].data(), b"hi");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(run("png[9]", testing_png()).is_err());
+        assert!(run("png.remove(-1)", testing_png()).is_err());
+        assert!(run("chunk(\"no\", blob())", testing_png()).is_err());
+        assert!(run("png.select(\"bogus\")", testing_png()).is_err());
+        assert!(run("let x = ;", testing_png()).is_err());
+        assert_eq!(
+            run("png.insert(5, png[0])", testing_png())
+                .unwrap()
+                .chunks()
+                .len(),
+            6
+        );
+    }
+}
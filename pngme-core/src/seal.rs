@@ -0,0 +1,372 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::validate::chunk_offsets;
+
+/// Private, ancillary, unsafe-to-copy chunk holding a hash tree of the image data, so an
+/// editor that rewrites IDAT also drops the seal.
+pub const SEAL_CHUNK_TYPE: &str = "seAL";
+pub const DEFAULT_SEGMENT_SIZE: u32 = 1 << 16;
+
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 1 + 4 + 8 + 32;
+
+type Hash = [u8; 32];
+
+/// One IDAT payload, and where it starts in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece<'a> {
+    pub offset: u64,
+    pub data: &'a [u8],
+}
+
+/// The IDAT payloads of `png`, at the offsets they would have when written.
+pub fn pieces(png: &Png) -> Vec<Piece<'_>> {
+    png.chunks()
+        .iter()
+        .zip(chunk_offsets(png))
+        .filter(|(chunk, _)| *chunk.chunk_type() == ChunkType::IDAT)
+        .map(|(chunk, offset)| Piece {
+            offset: offset + 8,
+            data: chunk.data(),
+        })
+        .collect()
+}
+
+/// The IDAT payloads and the seal chunk's payload in a whole PNG file, found by walking the
+/// chunk headers without copying or CRC-checking anything, so `bytes` can be a memory map.
+pub fn scan(bytes: &[u8]) -> Result<(Vec<Piece<'_>>, Option<&[u8]>), ()> {
+    if bytes.get(..8) != Some(&Png::STANDARD_HEADER[..]) {
+        return Err(());
+    }
+    let mut pieces = Vec::new();
+    let mut seal = None;
+    let mut offset = 8;
+    while offset < bytes.len() {
+        let header = bytes.get(offset..offset + 8).ok_or(())?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let data_start = offset + 8;
+        let data = bytes.get(data_start..data_start + length).ok_or(())?;
+        match &header[4..] {
+            b"IDAT" => pieces.push(Piece {
+                offset: data_start as u64,
+                data,
+            }),
+            b"IEND" => break,
+            chunk_type if chunk_type == SEAL_CHUNK_TYPE.as_bytes() => seal = Some(data),
+            _ => {}
+        }
+        offset = data_start + length + 4;
+    }
+    Ok((pieces, seal))
+}
+
+fn leaf_hash(parts: impl Iterator<Item = impl AsRef<[u8]>>) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Root of the tree over `leaves`, where a node without a sibling moves up unchanged.
+fn root_of(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return Sha256::digest([]).into();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [only] => *only,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// The image data as one stream split across pieces, for hashing any segment of it.
+struct Stream<'a> {
+    pieces: &'a [Piece<'a>],
+    /// Where each piece starts in the stream.
+    starts: Vec<u64>,
+    len: u64,
+}
+
+impl<'a> Stream<'a> {
+    fn new(pieces: &'a [Piece<'a>]) -> Self {
+        let mut len = 0;
+        let starts = pieces
+            .iter()
+            .map(|piece| {
+                let start = len;
+                len += piece.data.len() as u64;
+                start
+            })
+            .collect();
+        Self {
+            pieces,
+            starts,
+            len,
+        }
+    }
+
+    fn parts(&self, range: Range<u64>) -> impl Iterator<Item = &'a [u8]> + '_ {
+        let first = self.starts.partition_point(|&start| start <= range.start);
+        self.pieces[first.saturating_sub(1)..]
+            .iter()
+            .zip(&self.starts[first.saturating_sub(1)..])
+            .take_while(move |(_, &start)| start < range.end)
+            .map(move |(piece, &start)| {
+                let from = range.start.saturating_sub(start) as usize;
+                let to = ((range.end - start) as usize).min(piece.data.len());
+                &piece.data[from..to]
+            })
+    }
+
+    /// File offset of a position in the stream.
+    fn file_offset(&self, position: u64) -> Option<u64> {
+        if position >= self.len {
+            return None;
+        }
+        let index = self.starts.partition_point(|&start| start <= position);
+        let index = index.checked_sub(1)?;
+        Some(self.pieces[index].offset + position - self.starts[index])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seal {
+    pub segment_size: u32,
+    /// Total length of the sealed IDAT payloads.
+    pub stream_len: u64,
+    leaves: Vec<Hash>,
+}
+
+/// A sealed segment whose data no longer matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modified {
+    pub segment: usize,
+    /// Position of the segment within the concatenated IDAT payloads.
+    pub stream_range: Range<u64>,
+    /// Where the segment now starts in the file, if it still exists.
+    pub file_offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub checked: usize,
+    /// Length of the image data now, when it differs from the sealed length.
+    pub stream_len_changed: Option<u64>,
+    pub modified: Vec<Modified>,
+}
+
+impl Verification {
+    pub fn is_intact(&self) -> bool {
+        self.stream_len_changed.is_none() && self.modified.is_empty()
+    }
+}
+
+impl Seal {
+    pub fn new(pieces: &[Piece], segment_size: u32) -> Result<Self, ()> {
+        if segment_size == 0 {
+            return Err(());
+        }
+        let stream = Stream::new(pieces);
+        let mut seal = Self {
+            segment_size,
+            stream_len: stream.len,
+            leaves: Vec::new(),
+        };
+        seal.leaves = (0..seal.segment_count())
+            .map(|segment| leaf_hash(stream.parts(seal.segment_range(segment))))
+            .collect();
+        Ok(seal)
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.stream_len.div_ceil(self.segment_size as u64) as usize
+    }
+
+    pub fn segment_range(&self, segment: usize) -> Range<u64> {
+        let start = segment as u64 * self.segment_size as u64;
+        start..(start + self.segment_size as u64).min(self.stream_len)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        root_of(&self.leaves)
+    }
+
+    /// Re-hashes the given segments of `pieces`, or all of them, against the seal. Nothing
+    /// outside those segments is read, and segment numbers past the end are ignored.
+    pub fn verify(&self, pieces: &[Piece], segments: Option<&[usize]>) -> Verification {
+        let stream = Stream::new(pieces);
+        let segments: Vec<usize> = match segments {
+            Some(segments) => segments
+                .iter()
+                .copied()
+                .filter(|&segment| segment < self.segment_count())
+                .collect(),
+            None => (0..self.segment_count()).collect(),
+        };
+        let modified = segments
+            .iter()
+            .filter_map(|&segment| {
+                let range = self.segment_range(segment);
+                let intact = range.end <= stream.len
+                    && leaf_hash(stream.parts(range.clone())) == self.leaves[segment];
+                (!intact).then(|| Modified {
+                    segment,
+                    file_offset: stream.file_offset(range.start),
+                    stream_range: range,
+                })
+            })
+            .collect();
+        Verification {
+            checked: segments.len(),
+            stream_len_changed: (stream.len != self.stream_len).then_some(stream.len),
+            modified,
+        }
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, ()>> {
+        png.chunk_by_type(SEAL_CHUNK_TYPE)
+            .map(|chunk| Self::from_bytes(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::from_str(SEAL_CHUNK_TYPE).unwrap();
+        Chunk::new(chunk_type, self.to_bytes())
+    }
+
+    /// Version, segment size, stream length and root, then every leaf hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 32 * self.leaves.len());
+        bytes.push(VERSION);
+        bytes.extend(self.segment_size.to_be_bytes());
+        bytes.extend(self.stream_len.to_be_bytes());
+        bytes.extend(self.root());
+        for leaf in &self.leaves {
+            bytes.extend(leaf);
+        }
+        bytes
+    }
+
+    /// Rejects a seal whose stored root does not match its leaves.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() < HEADER_LEN || bytes[0] != VERSION {
+            return Err(());
+        }
+        let segment_size = u32::from_be_bytes(bytes[1..5].try_into().unwrap());
+        let stream_len = u64::from_be_bytes(bytes[5..13].try_into().unwrap());
+        let root = &bytes[13..HEADER_LEN];
+        let leaves = &bytes[HEADER_LEN..];
+        if segment_size == 0 || !leaves.len().is_multiple_of(32) {
+            return Err(());
+        }
+        let seal = Self {
+            segment_size,
+            stream_len,
+            leaves: leaves
+                .chunks(32)
+                .map(|leaf| leaf.try_into().unwrap())
+                .collect(),
+        };
+        if seal.leaves.len() != seal.segment_count() || seal.root() != root {
+            return Err(());
+        }
+        Ok(seal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png(idat: &[&[u8]]) -> Png {
+        let mut chunks = vec![Chunk::new(ChunkType::IHDR, vec![0; 13])];
+        chunks.extend(
+            idat.iter()
+                .map(|data| Chunk::new(ChunkType::IDAT, data.to_vec())),
+        );
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let png = testing_png(&[&[1; 10], &[2; 7]]);
+        let seal = Seal::new(&pieces(&png), 4).unwrap();
+        assert_eq!(seal.stream_len, 17);
+        assert_eq!(seal.segment_count(), 5);
+        assert_eq!(seal.segment_range(4), 16..17);
+        assert_eq!(Seal::from_bytes(&seal.to_bytes()).unwrap(), seal);
+
+        let mut tampered = seal.to_bytes();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(Seal::from_bytes(&tampered).is_err());
+        assert!(Seal::new(&pieces(&png), 0).is_err());
+    }
+
+    #[test]
+    fn test_segments_ignore_idat_boundaries() {
+        let split = Seal::new(&pieces(&testing_png(&[&[1; 10], &[2; 7]])), 4).unwrap();
+        let mut joined = vec![1; 10];
+        joined.extend([2; 7]);
+        let whole = Seal::new(&pieces(&testing_png(&[&joined])), 4).unwrap();
+        assert_eq!(split.root(), whole.root());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut png = testing_png(&[&[1; 10], &[2; 7]]);
+        let seal = Seal::new(&pieces(&png), 4).unwrap();
+        assert!(seal.verify(&pieces(&png), None).is_intact());
+
+        png.replace_chunk(2, Chunk::new(ChunkType::IDAT, vec![2, 2, 2, 9, 2, 2, 2]));
+        let verification = seal.verify(&pieces(&png), None);
+        assert_eq!(verification.checked, 5);
+        let modified: Vec<usize> = verification.modified.iter().map(|m| m.segment).collect();
+        assert_eq!(modified, [3]);
+        // Segment 3 starts two bytes into the second IDAT, whose payload is at 8 + 25 + 22 + 8.
+        assert_eq!(verification.modified[0].file_offset, Some(65));
+
+        let partial = seal.verify(&pieces(&png), Some(&[0, 1]));
+        assert!(partial.is_intact());
+        assert_eq!(partial.checked, 2);
+
+        let truncated = seal.verify(&pieces(&testing_png(&[&[1; 10]])), None);
+        assert_eq!(truncated.stream_len_changed, Some(10));
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut png = testing_png(&[&[1; 10], &[2; 7]]);
+        let seal = Seal::new(&pieces(&png), 4).unwrap();
+        png.insert_ancillary(seal.to_chunk());
+        let bytes = png.as_bytes();
+        let (scanned, stored) = scan(&bytes).unwrap();
+        assert_eq!(scanned, pieces(&png));
+        assert_eq!(Seal::from_bytes(stored.unwrap()).unwrap(), seal);
+        assert!(seal.verify(&scanned, None).is_intact());
+        assert!(scan(&bytes[..bytes.len() - 20]).is_err());
+        assert!(scan(&bytes[1..]).is_err());
+    }
+}
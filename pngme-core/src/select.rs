@@ -0,0 +1,233 @@
+//! Chunk selection expressions shared by `print`, `strip`, `explode` and `copy`.
+//!
+//! An expression is a comma-separated list of terms that must all hold, such as
+//! `ancillary,size>1000` or `text,!after-idat`. Passing several expressions selects the
+//! chunks matched by any of them.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::strip::Category;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 5] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+        (">", Comparison::Greater),
+    ];
+
+    fn holds(self, left: u32, right: u32) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        Self::OPERATORS
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .unwrap()
+            .0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Predicate {
+    Critical,
+    Ancillary,
+    /// Any of the groups `pngme strip --category` knows, named the same way.
+    Category(Category),
+    /// Comes after the last IDAT chunk.
+    AfterIdat,
+    Type(ChunkType),
+    /// Compares the payload length, in bytes.
+    Size(Comparison, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    negated: bool,
+    predicate: Predicate,
+}
+
+/// One selection expression: every term must match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    terms: Vec<Term>,
+}
+
+impl Selector {
+    /// Whether `chunk` matches, given whether it comes after the last IDAT chunk.
+    pub fn matches(&self, chunk: &Chunk, after_idat: bool) -> bool {
+        let chunk_type = chunk.chunk_type();
+        self.terms.iter().all(|term| {
+            let holds = match &term.predicate {
+                Predicate::Critical => chunk_type.is_critical(),
+                Predicate::Ancillary => !chunk_type.is_critical(),
+                Predicate::Category(category) => category.contains(chunk),
+                Predicate::AfterIdat => after_idat,
+                Predicate::Type(wanted) => chunk_type == wanted,
+                Predicate::Size(comparison, size) => comparison.holds(chunk.length(), *size),
+            };
+            holds != term.negated
+        })
+    }
+}
+
+/// For each chunk of `png`, whether any of `selectors` matches it.
+pub fn selected(png: &Png, selectors: &[Selector]) -> Vec<bool> {
+    let last_data = png
+        .chunks()
+        .iter()
+        .rposition(|chunk| *chunk.chunk_type() == ChunkType::IDAT);
+    png.chunks()
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let after_idat = last_data.is_some_and(|last| index > last);
+            selectors
+                .iter()
+                .any(|selector| selector.matches(chunk, after_idat))
+        })
+        .collect()
+}
+
+impl FromStr for Term {
+    type Err = String;
+    fn from_str(term: &str) -> Result<Self, String> {
+        let (negated, name) = match term.strip_prefix('!') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, term),
+        };
+        let predicate = match name {
+            "critical" => Predicate::Critical,
+            "ancillary" => Predicate::Ancillary,
+            "after-idat" => Predicate::AfterIdat,
+            _ if name.starts_with("type=") => Predicate::Type(ChunkType::from_str(&name[5..])?),
+            _ if name.starts_with("size") => {
+                let rest = &name[4..];
+                let (symbol, comparison) = Comparison::OPERATORS
+                    .iter()
+                    .find(|(symbol, _)| rest.starts_with(symbol))
+                    .ok_or_else(|| format!("`{}` needs one of <, <=, =, >= or >", name))?;
+                let size = rest[symbol.len()..]
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a size in bytes", &rest[symbol.len()..]))?;
+                Predicate::Size(*comparison, size)
+            }
+            _ => Predicate::Category(Category::from_str(name).map_err(|_| {
+                format!(
+                    "unknown term `{}`; expected critical, ancillary, legacy, text, metadata, \
+                     private, after-idat, type=XXXX or size>N",
+                    name
+                )
+            })?),
+        };
+        Ok(Self { negated, predicate })
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+    fn from_str(expression: &str) -> Result<Self, String> {
+        let terms = expression
+            .split(',')
+            .map(|term| term.trim().parse())
+            .collect::<Result<Vec<Term>, String>>()?;
+        Ok(Self { terms })
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            if index > 0 {
+                write!(fmt, ",")?;
+            }
+            if term.negated {
+                write!(fmt, "!")?;
+            }
+            match &term.predicate {
+                Predicate::Critical => write!(fmt, "critical")?,
+                Predicate::Ancillary => write!(fmt, "ancillary")?,
+                Predicate::Category(category) => write!(fmt, "{}", category)?,
+                Predicate::AfterIdat => write!(fmt, "after-idat")?,
+                Predicate::Type(chunk_type) => write!(fmt, "type={}", chunk_type)?,
+                Predicate::Size(comparison, size) => {
+                    write!(fmt, "size{}{}", comparison.symbol(), size)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::TEXT, b"Title\0before".to_vec()),
+            Chunk::new(ChunkType::IDAT, vec![0; 2000]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 5]),
+            Chunk::new(ChunkType::TEXT, b"Title\0after".to_vec()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    fn select(expressions: &[&str]) -> Vec<usize> {
+        let selectors: Vec<Selector> = expressions
+            .iter()
+            .map(|expression| expression.parse().unwrap())
+            .collect();
+        selected(&testing_png(), &selectors)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, selected)| selected.then_some(index))
+            .collect()
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select(&["critical"]), [0, 2, 5]);
+        assert_eq!(select(&["ancillary"]), [1, 3, 4]);
+        assert_eq!(select(&["private"]), [3]);
+        assert_eq!(select(&["text,after-idat"]), [4]);
+        assert_eq!(select(&["text, !after-idat"]), [1]);
+        assert_eq!(select(&["type=IHDR", "size>1000"]), [0, 2]);
+        assert_eq!(select(&["size<=5,ancillary"]), [3]);
+        assert_eq!(select(&["size=0"]), [5]);
+        assert!(select(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let selector: Selector = "ancillary, !type=tEXt,size>=10".parse().unwrap();
+        assert_eq!(selector.to_string(), "ancillary,!type=tEXt,size>=10");
+        assert!("".parse::<Selector>().is_err());
+        assert!("type=tEX".parse::<Selector>().is_err());
+        assert!("size~10".parse::<Selector>().is_err());
+        assert!("size>big".parse::<Selector>().is_err());
+        assert!("everything".parse::<Selector>().is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use zeroize::Zeroizing;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Private, ancillary, safe-to-copy chunk holding one Shamir share of a secret.
+pub const SHARE_CHUNK_TYPE: &str = "shRe";
+
+const VERSION: u8 = 1;
+/// Version, set id, threshold and x coordinate.
+const HEADER_LEN: usize = 1 + 8 + 1 + 1;
+
+/// Multiplication in GF(2^8) modulo the AES polynomial x^8 + x^4 + x^3 + x + 1.
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// The multiplicative inverse of a non-zero element, as `a^254`.
+fn inverse(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            result = mul(result, power);
+        }
+        power = mul(power, power);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// One point on each of the per-byte polynomials that hide a secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// Random, and the same in every share of one secret, so shares of different secrets
+    /// aren't combined by mistake.
+    pub set_id: [u8; 8],
+    /// How many shares are needed to reconstruct the secret.
+    pub threshold: u8,
+    /// Where the polynomials were evaluated; never zero, since that would be the secret.
+    pub x: u8,
+    pub data: Vec<u8>,
+}
+
+impl Share {
+    pub fn from_png(png: &Png) -> Option<Result<Self, ()>> {
+        png.chunk_by_type(SHARE_CHUNK_TYPE)
+            .map(|chunk| Self::from_bytes(chunk.data()))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let chunk_type = ChunkType::from_str(SHARE_CHUNK_TYPE).unwrap();
+        Chunk::new(chunk_type, self.to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.data.len());
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.set_id);
+        bytes.push(self.threshold);
+        bytes.push(self.x);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() < HEADER_LEN || bytes[0] != VERSION || bytes[9] == 0 || bytes[10] == 0 {
+            return Err(());
+        }
+        Ok(Self {
+            set_id: bytes[1..9].try_into().unwrap(),
+            threshold: bytes[9],
+            x: bytes[10],
+            data: bytes[HEADER_LEN..].to_vec(),
+        })
+    }
+}
+
+/// Splits `secret` into `shares` shares, any `threshold` of which reconstruct it with
+/// `combine`; fewer reveal nothing about it but its length.
+pub fn split(secret: &[u8], threshold: u8, shares: u8) -> Result<Vec<Share>, String> {
+    if threshold == 0 || threshold > shares {
+        return Err(format!(
+            "the threshold must be between 1 and the number of shares ({})",
+            shares
+        ));
+    }
+    let random_error = |error| format!("cannot get random bytes: {}", error);
+    let mut set_id = [0; 8];
+    getrandom::fill(&mut set_id).map_err(random_error)?;
+    // Each byte of the secret is the constant term of its own polynomial of degree
+    // threshold - 1, with the other coefficients chosen at random.
+    let degree = threshold as usize - 1;
+    let mut coefficients = Zeroizing::new(vec![0; secret.len() * degree]);
+    getrandom::fill(&mut coefficients).map_err(random_error)?;
+    Ok((1..=shares)
+        .map(|x| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(index, &constant)| {
+                    // Horner's rule, from the highest coefficient down to the secret byte.
+                    let higher = &coefficients[index * degree..(index + 1) * degree];
+                    higher
+                        .iter()
+                        .rev()
+                        .chain(std::iter::once(&constant))
+                        .fold(0, |acc, &coefficient| mul(acc, x) ^ coefficient)
+                })
+                .collect();
+            Share {
+                set_id,
+                threshold,
+                x,
+                data,
+            }
+        })
+        .collect())
+}
+
+/// Reconstructs the secret from at least `threshold` shares of it, by Lagrange interpolation
+/// at zero.
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let first = shares.first().ok_or("no shares given")?;
+    for share in shares {
+        if share.set_id != first.set_id {
+            return Err("the shares are from different secrets".into());
+        }
+        if share.threshold != first.threshold || share.data.len() != first.data.len() {
+            return Err("the shares disagree about the secret; one may be damaged".into());
+        }
+    }
+    let mut used: Vec<&Share> = Vec::new();
+    for share in shares {
+        if used.iter().all(|earlier| earlier.x != share.x) {
+            used.push(share);
+        }
+    }
+    if used.len() < first.threshold as usize {
+        return Err(format!(
+            "only {} of the {} shares needed were given",
+            used.len(),
+            first.threshold
+        ));
+    }
+    let used = &used[..first.threshold as usize];
+    // The Lagrange basis polynomials evaluated at zero; addition and subtraction are both XOR.
+    let weights: Vec<u8> = used
+        .iter()
+        .map(|share| {
+            used.iter()
+                .filter(|other| other.x != share.x)
+                .fold(1, |weight, other| {
+                    mul(weight, mul(other.x, inverse(other.x ^ share.x)))
+                })
+        })
+        .collect();
+    let mut secret = Zeroizing::new(vec![0; first.data.len()]);
+    for (share, &weight) in used.iter().zip(&weights) {
+        for (byte, &y) in secret.iter_mut().zip(&share.data) {
+            *byte ^= mul(weight, y);
+        }
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field() {
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+        for a in 1..=255 {
+            assert_eq!(mul(a, inverse(a)), 1, "{}", a);
+        }
+    }
+
+    #[test]
+    fn test_split_and_combine() {
+        let secret = b"the vault code is 1234";
+        let shares = split(secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.data.len() == secret.len()));
+        for picked in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<Share> = picked.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(&combine(&subset).unwrap()[..], secret);
+        }
+        assert!(combine(&shares[..2]).is_err());
+        let repeated = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(combine(&repeated).is_err());
+        let other = split(secret, 3, 5).unwrap();
+        let mixed = [shares[0].clone(), shares[1].clone(), other[2].clone()];
+        assert!(combine(&mixed).is_err());
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let shares = split(b"x", 1, 2).unwrap();
+        assert_eq!(&combine(&shares[1..]).unwrap()[..], b"x");
+        assert!(split(b"x", 0, 2).is_err());
+        assert!(split(b"x", 3, 2).is_err());
+        assert_eq!(split(b"x", 255, 255).unwrap().len(), 255);
+    }
+
+    #[test]
+    fn test_share_round_trip() {
+        let share = split(b"secret", 2, 3).unwrap().remove(1);
+        let mut png = Png::from_chunks(vec![Chunk::new(ChunkType::IEND, vec![])]);
+        png.append_chunk(share.to_chunk());
+        assert_eq!(Share::from_png(&png), Some(Ok(share)));
+        assert!(Share::from_bytes(&[1; 10]).is_err());
+        assert!(Share::from_bytes(&[2; 11]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::{ColorType, ImageHeader};
+use crate::png::Png;
+
+/// The channels sBIT gives a value for, in order. Palette images describe the palette's
+/// red, green and blue.
+pub fn channel_names(color_type: ColorType) -> &'static [&'static str] {
+    match color_type {
+        ColorType::Grayscale => &["grey"],
+        ColorType::Rgb | ColorType::Indexed => &["red", "green", "blue"],
+        ColorType::GrayscaleAlpha => &["grey", "alpha"],
+        ColorType::Rgba => &["red", "green", "blue", "alpha"],
+    }
+}
+
+/// sBIT: how many bits of each channel were significant in the source data, before the
+/// samples were scaled up to the image's bit depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignificantBits {
+    color_type: ColorType,
+    bits: Vec<u8>,
+}
+
+impl SignificantBits {
+    /// Checks `bits` against the colour type, and each value against the sample depth, which
+    /// for palette images is 8.
+    pub fn new(header: &ImageHeader, bits: Vec<u8>) -> Result<Self, String> {
+        let channels = channel_names(header.color_type);
+        if bits.len() != channels.len() {
+            return Err(format!(
+                "sBIT has {} values but the colour type needs {} ({})",
+                bits.len(),
+                channels.len(),
+                channels.join(", ")
+            ));
+        }
+        let depth = match header.color_type {
+            ColorType::Indexed => 8,
+            _ => header.bit_depth,
+        };
+        if let Some((channel, &value)) = channels
+            .iter()
+            .zip(&bits)
+            .find(|(_, &value)| value == 0 || value > depth)
+        {
+            return Err(format!(
+                "sBIT gives {} significant bits for {}, outside 1 to {}",
+                value, channel, depth
+            ));
+        }
+        Ok(Self {
+            color_type: header.color_type,
+            bits,
+        })
+    }
+
+    pub fn from_png(png: &Png) -> Option<Result<Self, String>> {
+        let chunk = png.chunk_by_type(ChunkType::SBIT)?;
+        Some(
+            ImageHeader::from_png(png)
+                .map_err(|()| String::from("the file has no valid IHDR chunk"))
+                .and_then(|header| Self::new(&header, chunk.data().to_vec())),
+        )
+    }
+
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SBIT, self.bits.clone())
+    }
+}
+
+impl Display for SignificantBits {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let channels: Vec<String> = channel_names(self.color_type)
+            .iter()
+            .zip(&self.bits)
+            .map(|(channel, bits)| format!("{} {}", channel, bits))
+            .collect();
+        write!(fmt, "{}", channels.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::png_with_header;
+
+    #[test]
+    fn test_significant_bits() {
+        let mut png = png_with_header(ColorType::Rgba, 8, 0);
+        assert!(SignificantBits::from_png(&png).is_none());
+        let header = ImageHeader::from_png(&png).unwrap();
+        let bits = SignificantBits::new(&header, vec![5, 6, 5, 1]).unwrap();
+        assert_eq!(bits.to_string(), "red 5, green 6, blue 5, alpha 1");
+        png.insert_ancillary(bits.to_chunk());
+        assert_eq!(SignificantBits::from_png(&png), Some(Ok(bits)));
+
+        assert!(SignificantBits::new(&header, vec![5, 6, 5])
+            .unwrap_err()
+            .contains("needs 4 (red, green, blue, alpha)"));
+        assert!(SignificantBits::new(&header, vec![5, 6, 9, 1]).is_err());
+        assert!(SignificantBits::new(&header, vec![0, 6, 5, 1]).is_err());
+
+        let header = ImageHeader::from_png(&png_with_header(ColorType::Indexed, 2, 4)).unwrap();
+        assert!(SignificantBits::new(&header, vec![8, 8, 8]).is_ok());
+        let header = ImageHeader::from_png(&png_with_header(ColorType::Grayscale, 2, 0)).unwrap();
+        assert!(SignificantBits::new(&header, vec![3]).is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::image::{ColorType, ImageData, ImageHeader};
+use crate::png::Png;
+use crate::progress::{self, CancelToken, Interrupted, Progress};
+use crate::tiles::{self, Bands};
+
+const LENGTH_BITS: usize = 32;
+/// Positions drawn between progress reports and cancellation checks.
+const PROGRESS_BITS: usize = 1 << 16;
+
+/// SHA-256 in counter mode, keyed by a password; deterministic so decoding can replay it.
+struct KeyStream {
+    key: [u8; 32],
+    counter: u64,
+    block: [u8; 32],
+    used: usize,
+}
+
+impl KeyStream {
+    fn new(domain: &str, password: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(domain.as_bytes());
+        hasher.update([0]);
+        hasher.update(password.as_bytes());
+        Self {
+            key: hasher.finalize().into(),
+            counter: 0,
+            block: [0; 32],
+            used: 32,
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        if self.used == self.block.len() {
+            let mut hasher = Sha256::new();
+            hasher.update(self.key);
+            hasher.update(self.counter.to_be_bytes());
+            self.block = hasher.finalize().into();
+            self.counter += 1;
+            self.used = 0;
+        }
+        self.used += 1;
+        self.block[self.used - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (0..8).fold(0, |value, _| value << 8 | self.next_byte() as u64)
+    }
+
+    /// Uniform value in `0..bound`, using rejection sampling to avoid modulo bias.
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+impl Drop for KeyStream {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.block.zeroize();
+    }
+}
+
+/// A password-seeded permutation of carrier positions, drawn lazily with a sparse
+/// Fisher-Yates shuffle so memory grows with the payload rather than the image.
+struct Positions {
+    stream: KeyStream,
+    swapped: HashMap<usize, usize>,
+    next: usize,
+    total: usize,
+}
+
+impl Iterator for Positions {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        if self.next == self.total {
+            return None;
+        }
+        let pick = self.next + self.stream.below((self.total - self.next) as u64) as usize;
+        let chosen = *self.swapped.get(&pick).unwrap_or(&pick);
+        let displaced = *self.swapped.get(&self.next).unwrap_or(&self.next);
+        self.swapped.insert(pick, displaced);
+        self.next += 1;
+        Some(chosen)
+    }
+}
+
+/// A password-seeded permutation of `0..total`, for other methods that pick positions the
+/// way this one does.
+pub(crate) fn permutation(
+    domain: &str,
+    password: &str,
+    total: usize,
+) -> impl Iterator<Item = usize> {
+    Positions {
+        stream: KeyStream::new(domain, password),
+        swapped: HashMap::new(),
+        next: 0,
+        total,
+    }
+}
+
+/// Password-derived bytes, for masking what other methods write.
+pub(crate) fn key_bytes(domain: &str, password: &str) -> impl Iterator<Item = u8> {
+    let mut stream = KeyStream::new(domain, password);
+    std::iter::repeat_with(move || stream.next_byte())
+}
+
+/// Samples that can carry a bit: every colour sample, skipping alpha and palette images.
+fn carriers(header: &ImageHeader) -> Result<(usize, usize), ()> {
+    let (channels, color_channels) = match header.color_type {
+        ColorType::Indexed => return Err(()),
+        ColorType::Grayscale => (1, 1),
+        ColorType::GrayscaleAlpha => (2, 1),
+        ColorType::Rgb => (3, 3),
+        ColorType::Rgba => (4, 3),
+    };
+    Ok((channels, color_channels))
+}
+
+fn carrier_count(header: &ImageHeader) -> Result<usize, ()> {
+    let (_, color_channels) = carriers(header)?;
+    Ok(header.width as usize * header.height as usize * color_channels)
+}
+
+fn positions(header: &ImageHeader, password: &str) -> Result<impl Iterator<Item = usize>, ()> {
+    let (channels, color_channels) = carriers(header)?;
+    let positions = permutation("pngme spread positions", password, carrier_count(header)?);
+    Ok(
+        positions
+            .map(move |carrier| carrier / color_channels * channels + carrier % color_channels),
+    )
+}
+
+fn mask_bits(password: &str) -> impl Iterator<Item = u8> {
+    key_bytes("pngme spread mask", password).map(|byte| byte & 1)
+}
+
+pub(crate) fn bits(bytes: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1))
+}
+
+/// Payload bytes that fit in the image, after the 32-bit length prefix.
+pub fn capacity(image: &ImageData) -> Result<usize, ()> {
+    header_capacity(image.header())
+}
+
+/// [`capacity`] for an image with `header`, without decoding it.
+pub fn header_capacity(header: &ImageHeader) -> Result<usize, ()> {
+    Ok(carrier_count(header)?.saturating_sub(LENGTH_BITS) / 8)
+}
+
+/// Writes `message` into the least significant bits of pseudo-randomly chosen samples,
+/// XORed with a password-derived mask.
+pub fn embed(image: &mut ImageData, password: &str, message: &[u8]) -> Result<(), ()> {
+    embed_with_progress(
+        image,
+        password,
+        message,
+        progress::NO_PROGRESS,
+        &CancelToken::new(),
+    )
+    .map_err(|_| ())
+}
+
+/// [`embed`], reporting the bits placed so far, length prefix included. The image is only
+/// changed once every position has been drawn, so a cancelled embed leaves it as it was.
+pub fn embed_with_progress(
+    image: &mut ImageData,
+    password: &str,
+    message: &[u8],
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> Result<(), Interrupted> {
+    if message.len() > capacity(image)? || message.len() > u32::MAX as usize {
+        return Err(Interrupted::Failed);
+    }
+    let length = (message.len() as u32).to_be_bytes();
+    let payload = bits(&length).chain(bits(message));
+    let total = LENGTH_BITS + message.len() * 8;
+    let mut positions = positions(image.header(), password)?;
+    let mut drawn = Vec::with_capacity(total);
+    while drawn.len() < total {
+        cancel.check()?;
+        let piece = PROGRESS_BITS.min(total - drawn.len());
+        drawn.extend(positions.by_ref().take(piece));
+        progress.update(drawn.len() as u64, total as u64);
+    }
+    let samples = image.samples_mut();
+    for ((bit, mask), position) in payload.zip(mask_bits(password)).zip(drawn) {
+        samples[position] = (samples[position] & !1) | (bit ^ mask) as u16;
+    }
+    Ok(())
+}
+
+/// Reads back a message written by `embed`; the buffer is wiped when dropped.
+pub fn extract(image: &ImageData, password: &str) -> Result<Zeroizing<Vec<u8>>, ()> {
+    let samples = image.samples();
+    let mut positions = positions(image.header(), password)?;
+    let mut masks = mask_bits(password);
+    let mut read_bytes = |count: usize| -> Result<Vec<u8>, ()> {
+        let mut bytes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut byte = 0;
+            for _ in 0..8 {
+                let position = positions.next().ok_or(())?;
+                let bit = (samples[position] & 1) as u8 ^ masks.next().unwrap();
+                byte = byte << 1 | bit;
+            }
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    };
+    let length = u32::from_be_bytes(read_bytes(4)?.try_into().unwrap()) as usize;
+    if length > capacity(image)? {
+        return Err(());
+    }
+    read_bytes(length).map(Zeroizing::new)
+}
+
+/// [`embed`] on the image data of `png`, decoded and re-encoded `band_height` rows at a
+/// time, so memory grows with the message and the band rather than the image.
+pub fn embed_in_bands(
+    png: &mut Png,
+    password: &str,
+    message: &[u8],
+    band_height: u32,
+) -> Result<(), ()> {
+    let header = ImageHeader::from_png(png)?;
+    if message.len() > header_capacity(&header)? || message.len() > u32::MAX as usize {
+        return Err(());
+    }
+    let length = (message.len() as u32).to_be_bytes();
+    let payload = bits(&length).chain(bits(message));
+    let mut writes: Vec<(usize, u8)> = positions(&header, password)?
+        .zip(
+            payload
+                .zip(mask_bits(password))
+                .map(|(bit, mask)| bit ^ mask),
+        )
+        .collect();
+    writes.sort_unstable_by_key(|&(position, _)| position);
+
+    let row_samples = header.width as usize * header.color_type.channels();
+    let mut writes = writes.into_iter().peekable();
+    tiles::edit_bands(png, band_height, |band| {
+        let start = band.top as usize * row_samples;
+        let samples = band.image.samples_mut();
+        while let Some((position, bit)) =
+            writes.next_if(|&(position, _)| position < start + samples.len())
+        {
+            let sample = &mut samples[position - start];
+            *sample = (*sample & !1) | bit as u16;
+        }
+        Ok(())
+    })
+}
+
+/// The least significant bits of the samples at `positions`, in that order, read from
+/// `png` a band at a time.
+fn read_lsbs(png: &Png, positions: &[usize], band_height: u32) -> Result<Vec<u8>, ()> {
+    let mut order: Vec<usize> = (0..positions.len()).collect();
+    order.sort_unstable_by_key(|&index| positions[index]);
+    let mut order = order.into_iter().peekable();
+    let mut bits = vec![0; positions.len()];
+    let mut start = 0;
+    for band in Bands::new(png, band_height)? {
+        let band = band?;
+        let samples = band.image.samples();
+        while let Some(index) = order.next_if(|&index| positions[index] < start + samples.len()) {
+            bits[index] = (samples[positions[index] - start] & 1) as u8;
+        }
+        if order.peek().is_none() {
+            break;
+        }
+        start += samples.len();
+    }
+    Ok(bits)
+}
+
+/// [`extract`] from the image data of `png`, decoded `band_height` rows at a time. The
+/// image is decoded twice: once for the length, then again for the message.
+pub fn extract_from_bands(
+    png: &Png,
+    password: &str,
+    band_height: u32,
+) -> Result<Zeroizing<Vec<u8>>, ()> {
+    let header = ImageHeader::from_png(png)?;
+    let mut positions = positions(&header, password)?;
+    let mut masks = mask_bits(password);
+    let mut read_bytes = |count: usize| -> Result<Vec<u8>, ()> {
+        let wanted: Vec<usize> = positions.by_ref().take(count * 8).collect();
+        if wanted.len() != count * 8 {
+            return Err(());
+        }
+        let bits = read_lsbs(png, &wanted, band_height)?;
+        Ok(bits
+            .chunks(8)
+            .map(|byte| {
+                byte.iter()
+                    .fold(0, |value, &bit| value << 1 | (bit ^ masks.next().unwrap()))
+            })
+            .collect())
+    };
+    let length = u32::from_be_bytes(read_bytes(4)?.try_into().unwrap()) as usize;
+    if length > header_capacity(&header)? {
+        return Err(());
+    }
+    read_bytes(length).map(Zeroizing::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use flate2::{write::ZlibEncoder, Compression};
+    use proptest::prelude::*;
+    use std::io::Write;
+    use std::str::FromStr;
+
+    fn testing_png(color_type: ColorType) -> Png {
+        let header = ImageHeader {
+            width: 16,
+            height: 16,
+            bit_depth: 8,
+            color_type,
+            interlaced: false,
+        };
+        let row_bytes = header.row_bytes(16);
+        let mut raw = Vec::new();
+        for row in 0..16 {
+            raw.push(0);
+            raw.extend((0..row_bytes).map(|i| (row * 16 + i) as u8));
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let chunk = |chunk_type: &str, data: Vec<u8>| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        let mut chunks = vec![chunk("IHDR", header.to_bytes().to_vec())];
+        if color_type == ColorType::Indexed {
+            chunks.push(chunk("PLTE", vec![0; 768]));
+        }
+        chunks.push(chunk("IDAT", encoder.finish().unwrap()));
+        Png::from_chunks(chunks)
+    }
+
+    fn testing_image(color_type: ColorType) -> ImageData {
+        ImageData::from_png(&testing_png(color_type)).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut image = testing_image(ColorType::Rgb);
+        embed(&mut image, "hunter2", b"spread me thin").unwrap();
+        assert_eq!(*extract(&image, "hunter2").unwrap(), b"spread me thin");
+    }
+
+    #[test]
+    fn test_only_least_significant_bits_change() {
+        let original = testing_image(ColorType::Rgba);
+        let mut image = testing_image(ColorType::Rgba);
+        embed(&mut image, "pw", &[0xff; 40]).unwrap();
+        let changes: Vec<(usize, u16)> = original
+            .samples()
+            .iter()
+            .zip(image.samples())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(index, (a, b))| (index, a ^ b))
+            .collect();
+        assert!(!changes.is_empty());
+        assert!(changes
+            .iter()
+            .all(|&(index, diff)| diff == 1 && index % 4 != 3));
+    }
+
+    #[test]
+    fn test_positions_are_a_permutation() {
+        let image = testing_image(ColorType::Grayscale);
+        let mut all: Vec<usize> = positions(image.header(), "pw").unwrap().collect();
+        all.sort();
+        assert_eq!(all, (0..256).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let mut image = testing_image(ColorType::Rgb);
+        embed(&mut image, "right", b"secret").unwrap();
+        assert_ne!(
+            extract(&image, "wrong").as_deref().ok(),
+            Some(&b"secret".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut image = testing_image(ColorType::Grayscale);
+        assert_eq!(capacity(&image).unwrap(), 28);
+        assert!(embed(&mut image, "pw", &[0; 29]).is_err());
+        assert!(embed(&mut image, "pw", &[0; 28]).is_ok());
+    }
+
+    #[test]
+    fn test_progress_and_cancel() {
+        let mut image = testing_image(ColorType::Rgb);
+        let last = std::sync::Mutex::new((0, 0));
+        let progress = |done, total| *last.lock().unwrap() = (done, total);
+        embed_with_progress(&mut image, "pw", b"tracked", &progress, &CancelToken::new()).unwrap();
+        assert_eq!(*last.lock().unwrap(), (88, 88));
+        assert_eq!(*extract(&image, "pw").unwrap(), b"tracked");
+
+        let original = image.samples().to_vec();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert_eq!(
+            embed_with_progress(&mut image, "pw", b"stopped", &progress, &cancel),
+            Err(Interrupted::Cancelled)
+        );
+        assert_eq!(image.samples(), original);
+    }
+
+    #[test]
+    fn test_bands() {
+        let mut png = testing_png(ColorType::Rgba);
+        embed_in_bands(&mut png, "pw", b"one band at a time", 3).unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(*extract(&image, "pw").unwrap(), b"one band at a time");
+
+        let mut image = testing_image(ColorType::Rgb);
+        embed(&mut image, "pw", b"whole").unwrap();
+        image.write_to(&mut png).unwrap();
+        assert_eq!(*extract_from_bands(&png, "pw", 5).unwrap(), b"whole");
+        assert!(embed_in_bands(&mut png, "pw", &[0; 1000], 5).is_err());
+    }
+
+    #[test]
+    fn test_indexed_images_are_rejected() {
+        let mut image = testing_image(ColorType::Indexed);
+        assert!(capacity(&image).is_err());
+        assert!(embed(&mut image, "pw", b"x").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(
+            password in ".{0,16}",
+            message in prop::collection::vec(any::<u8>(), 0..=92),
+        ) {
+            let mut image = testing_image(ColorType::Rgb);
+            embed(&mut image, &password, &message).unwrap();
+            prop_assert_eq!(&*extract(&image, &password).unwrap(), &message);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use crate::image::{ColorType, ImageData};
+
+/// Pairs of values expected to occur less often than this are left out of the
+/// chi-square test, as the statistic is unreliable for sparse cells.
+const MIN_EXPECTED: f64 = 5.0;
+/// Above either threshold, [`is_likely`] considers LSB embedding likely.
+pub const CHI_SQUARE_THRESHOLD: f64 = 0.99;
+pub const RS_THRESHOLD: f64 = 0.1;
+const RS_MASK: [bool; 4] = [false, true, true, false];
+const RS_SATURATION: f64 = 0.02;
+/// Below this gap between R-m and S-m the cover is too noisy for flipping to make groups
+/// any less regular, so neither test can tell its LSBs from embedded bits.
+const RS_MIN_CONTRAST: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: usize,
+    /// Probability that the pairs of values were equalised by LSB embedding.
+    pub probability: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsAnalysis {
+    pub regular: f64,
+    pub singular: f64,
+    pub regular_negative: f64,
+    pub singular_negative: f64,
+    /// Estimated fraction of colour samples carrying embedded bits, from 0 to 1.
+    pub estimate: f64,
+}
+
+/// The colour planes of an image, one `Vec` per row per channel, leaving out alpha.
+fn color_rows(image: &ImageData) -> Result<Vec<Vec<i32>>, ()> {
+    let (channels, color_channels) = match image.header().color_type {
+        ColorType::Indexed => return Err(()),
+        ColorType::Grayscale => (1, 1),
+        ColorType::GrayscaleAlpha => (2, 1),
+        ColorType::Rgb => (3, 3),
+        ColorType::Rgba => (4, 3),
+    };
+    let row_samples = image.width() as usize * channels;
+    let mut rows = Vec::new();
+    for row in image.samples().chunks(row_samples) {
+        for channel in 0..color_channels {
+            rows.push(
+                row.iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .map(|&sample| sample as i32)
+                    .collect(),
+            );
+        }
+    }
+    Ok(rows)
+}
+
+/// Westfeld and Pfitzmann's pairs-of-values test over the whole image.
+pub fn chi_square(image: &ImageData) -> Result<ChiSquare, ()> {
+    let mut histogram = vec![0u64; 1 << image.header().bit_depth];
+    for row in color_rows(image)? {
+        for sample in row {
+            histogram[sample as usize] += 1;
+        }
+    }
+
+    let mut statistic = 0.0;
+    let mut cells = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected < MIN_EXPECTED {
+            continue;
+        }
+        statistic += (pair[0] as f64 - expected).powi(2) / expected;
+        cells += 1;
+    }
+    if cells < 2 {
+        return Err(());
+    }
+    let degrees_of_freedom = cells - 1;
+    Ok(ChiSquare {
+        statistic,
+        degrees_of_freedom,
+        probability: 1.0 - regularized_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0),
+    })
+}
+
+fn flip(value: i32) -> i32 {
+    value ^ 1
+}
+
+fn flip_negative(value: i32) -> i32 {
+    flip(value + 1) - 1
+}
+
+fn smoothness(group: &[i32]) -> i32 {
+    group.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum()
+}
+
+/// Fractions of regular and singular groups under the mask and its negation.
+fn rs_fractions(rows: &[Vec<i32>]) -> [f64; 4] {
+    let mut counts = [0u64; 4];
+    let mut groups = 0;
+    for group in rows.iter().flat_map(|row| row.chunks_exact(RS_MASK.len())) {
+        let original = smoothness(group);
+        for (index, function) in [flip as fn(i32) -> i32, flip_negative]
+            .into_iter()
+            .enumerate()
+        {
+            let flipped: Vec<i32> = group
+                .iter()
+                .zip(RS_MASK)
+                .map(|(&value, masked)| if masked { function(value) } else { value })
+                .collect();
+            match smoothness(&flipped).cmp(&original) {
+                std::cmp::Ordering::Greater => counts[index * 2] += 1,
+                std::cmp::Ordering::Less => counts[index * 2 + 1] += 1,
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+        groups += 1;
+    }
+    counts.map(|count| count as f64 / groups.max(1) as f64)
+}
+
+/// Fridrich, Goljan and Du's RS analysis, estimating the embedded fraction from how
+/// regular and singular groups respond to flipping every LSB.
+pub fn rs(image: &ImageData) -> Result<RsAnalysis, ()> {
+    let rows = color_rows(image)?;
+    if rows.iter().all(|row| row.len() < RS_MASK.len()) {
+        return Err(());
+    }
+    let [regular, singular, regular_negative, singular_negative] = rs_fractions(&rows);
+    let flipped: Vec<Vec<i32>> = rows
+        .iter()
+        .map(|row| row.iter().map(|&value| flip(value)).collect())
+        .collect();
+    let [flipped_regular, flipped_singular, flipped_regular_negative, flipped_singular_negative] =
+        rs_fractions(&flipped);
+
+    let d0 = regular - singular;
+    let d1 = flipped_regular - flipped_singular;
+    let d0_negative = regular_negative - singular_negative;
+    let d1_negative = flipped_regular_negative - flipped_singular_negative;
+    // A cover too noisy to measure gets no estimate at all. Otherwise, with every LSB
+    // randomised, Rm and Sm meet and the quadratic's roots run off to infinity, so treat a
+    // near-equal pair as full embedding.
+    let estimate = if d0_negative.abs() < RS_MIN_CONTRAST {
+        0.0
+    } else if d0.abs() <= RS_SATURATION * d0_negative.abs() {
+        1.0
+    } else {
+        let a = 2.0 * (d1 + d0);
+        let b = d0_negative - d1_negative - d1 - 3.0 * d0;
+        let c = d0 - d0_negative;
+        let x = if a.abs() < f64::EPSILON {
+            -c / b
+        } else {
+            let discriminant = (b * b - 4.0 * a * c).max(0.0).sqrt();
+            let roots = [
+                (-b + discriminant) / (2.0 * a),
+                (-b - discriminant) / (2.0 * a),
+            ];
+            if roots[0].abs() < roots[1].abs() {
+                roots[0]
+            } else {
+                roots[1]
+            }
+        };
+        x / (x - 0.5)
+    };
+    Ok(RsAnalysis {
+        regular,
+        singular,
+        regular_negative,
+        singular_negative,
+        estimate: if estimate.is_finite() {
+            estimate.clamp(0.0, 1.0)
+        } else {
+            0.0
+        },
+    })
+}
+
+impl RsAnalysis {
+    /// Whether the cover is smooth enough for either test to mean anything. Noise has
+    /// pairs of values as even as embedding leaves them, so a flat histogram alone is no
+    /// sign of a message.
+    pub fn is_conclusive(&self) -> bool {
+        (self.regular_negative - self.singular_negative).abs() >= RS_MIN_CONTRAST
+    }
+}
+
+/// Whether either test points to LSB embedding.
+pub fn is_likely(chi_square: &ChiSquare, rs: &RsAnalysis) -> bool {
+    rs.is_conclusive()
+        && (chi_square.probability > CHI_SQUARE_THRESHOLD || rs.estimate > RS_THRESHOLD)
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    // Lanczos approximation, g = 7.
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// The regularised lower incomplete gamma function P(a, x), which is the chi-square
+/// CDF at `2x` with `2a` degrees of freedom.
+pub(crate) fn regularized_gamma(a: f64, x: f64) -> f64 {
+    const ITERATIONS: usize = 500;
+    const EPSILON: f64 = 1e-12;
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let prefix = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (sum * prefix).min(1.0)
+    } else {
+        // Lentz's continued fraction for the upper function Q(a, x).
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (1.0 - prefix * h).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carrier::{Carrier, Pattern};
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::image::ImageHeader;
+    use crate::png::Png;
+    use crate::spread;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+    use std::str::FromStr;
+
+    // A smooth RGB gradient with a little deterministic noise, like a photograph. A
+    // combed histogram, as left by stretching levels, gives the chi-square test a
+    // pairs-of-values imbalance to find.
+    fn testing_image(combed: bool) -> ImageData {
+        let header = ImageHeader {
+            width: 64,
+            height: 64,
+            bit_depth: 8,
+            color_type: ColorType::Rgb,
+            interlaced: false,
+        };
+        let mut state = 0x1234_5678u32;
+        let mut raw = Vec::new();
+        for y in 0..64u32 {
+            raw.push(0);
+            for x in 0..64u32 {
+                for channel in 0..3 {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    let value = if combed {
+                        60 + (x + y) / 3 * 2 + channel * 30 + [0, 0, 1][(state >> 16) as usize % 3]
+                    } else {
+                        40 + x * 2 + y + channel * 20 + (state >> 16) % 5
+                    };
+                    raw.push(value as u8);
+                }
+            }
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let chunk = |chunk_type: &str, data: Vec<u8>| {
+            Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+        };
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", header.to_bytes().to_vec()),
+            chunk("IDAT", encoder.finish().unwrap()),
+        ]);
+        ImageData::from_png(&png).unwrap()
+    }
+
+    fn embed(mut image: ImageData, fraction: f64) -> ImageData {
+        let length = (spread::capacity(&image).unwrap() as f64 * fraction) as usize;
+        let message: Vec<u8> = (0..length).map(|i| (i * 151 % 256) as u8).collect();
+        spread::embed(&mut image, "pw", &message).unwrap();
+        image
+    }
+
+    #[test]
+    fn test_regularized_gamma() {
+        // The chi-square CDF with 2 degrees of freedom is 1 - e^(-x/2).
+        for x in [0.5f64, 2.0, 7.0] {
+            let expected = 1.0 - (-x / 2.0).exp();
+            assert!((regularized_gamma(1.0, x / 2.0) - expected).abs() < 1e-9);
+        }
+        assert!((regularized_gamma(5.0, 5.0) - 0.559_506_714_934_788).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_chi_square() {
+        let image = testing_image(true);
+        assert!(chi_square(&image).unwrap().probability < 0.01);
+        assert!(chi_square(&embed(image, 1.0)).unwrap().probability > 0.5);
+    }
+
+    #[test]
+    fn test_rs() {
+        let image = testing_image(false);
+        assert!(rs(&image).unwrap().estimate < 0.1);
+        let estimate = rs(&embed(image, 0.5)).unwrap().estimate;
+        assert!((estimate - 0.5).abs() < 0.15);
+        assert!(rs(&embed(testing_image(false), 1.0)).unwrap().estimate > 0.9);
+    }
+
+    #[test]
+    fn test_clean_noise_is_not_flagged() {
+        for (seed, entropy) in [(1, 0.25), (3, 0.25), (5, 0.25), (0, 1.0), (7, 1.0)] {
+            let carrier = Carrier {
+                pattern: Pattern::Noise,
+                width: 512,
+                height: 512,
+                seed,
+                entropy,
+                grayscale: false,
+            };
+            let image = ImageData::from_png(&carrier.generate().unwrap()).unwrap();
+            let (chi_square, rs) = (chi_square(&image).unwrap(), rs(&image).unwrap());
+            assert!(!is_likely(&chi_square, &rs), "{:?} {:?}", chi_square, rs);
+        }
+        let embedded = embed(testing_image(false), 1.0);
+        assert!(is_likely(
+            &chi_square(&embedded).unwrap(),
+            &rs(&embedded).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::legacy::LEGACY_CHUNK_TYPES;
+use crate::png::Png;
+use crate::select::{self, Selector};
+
+/// Groups of ancillary chunks `pngme strip` can remove together. Critical chunks are never
+/// in any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// gIFg, gIFx and gIFt, left behind by GIF converters.
+    Legacy,
+    /// tEXt, zTXt and iTXt, including XMP.
+    Text,
+    /// eXIf and tIME.
+    Metadata,
+    /// Private chunk types, such as the ones pngme hides messages in.
+    Private,
+}
+
+impl Category {
+    pub fn contains(self, chunk: &Chunk) -> bool {
+        let chunk_type = chunk.chunk_type();
+        if chunk_type.is_critical() {
+            return false;
+        }
+        match self {
+            Category::Legacy => LEGACY_CHUNK_TYPES.contains(chunk_type),
+            Category::Text => {
+                [ChunkType::TEXT, ChunkType::ZTXT, ChunkType::ITXT].contains(chunk_type)
+            }
+            Category::Metadata => [ChunkType::EXIF, ChunkType::TIME].contains(chunk_type),
+            Category::Private => !chunk_type.is_public(),
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+    fn from_str(category: &str) -> Result<Self, String> {
+        match category {
+            "legacy" => Ok(Category::Legacy),
+            "text" => Ok(Category::Text),
+            "metadata" => Ok(Category::Metadata),
+            "private" => Ok(Category::Private),
+            other => Err(format!(
+                "unknown category `{}`; expected legacy, text, metadata or private",
+                other
+            )),
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        let name = match self {
+            Category::Legacy => "legacy",
+            Category::Text => "text",
+            Category::Metadata => "metadata",
+            Category::Private => "private",
+        };
+        write!(fmt, "{}", name)
+    }
+}
+
+/// Removes every chunk in any of `categories`, returning how many went.
+pub fn strip(png: &mut Png, categories: &[Category]) -> usize {
+    let before = png.chunks().len();
+    png.retain_chunks(|chunk| !categories.iter().any(|category| category.contains(chunk)));
+    before - png.chunks().len()
+}
+
+/// Removes every ancillary chunk matched by any of `selectors`, returning how many went.
+/// Critical chunks are kept even when selected.
+pub fn strip_selected(png: &mut Png, selectors: &[Selector]) -> usize {
+    let before = png.chunks().len();
+    let mut selected = select::selected(png, selectors).into_iter();
+    png.retain_chunks(|chunk| !selected.next().unwrap() || chunk.chunk_type().is_critical());
+    before - png.chunks().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_of(types: &[&str]) -> Png {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), vec![]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_strip() {
+        let mut png = png_of(&["IHDR", "gIFg", "tEXt", "gIFx", "ruSt", "IDAT", "IEND"]);
+        assert_eq!(strip(&mut png, &[Category::Legacy]), 2);
+        assert_eq!(strip(&mut png, &[Category::Legacy]), 0);
+        assert_eq!(strip(&mut png, &[Category::Text, Category::Private]), 2);
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        let selectors = ["size=0".parse().unwrap()];
+        let mut png = png_of(&["IHDR", "tEXt", "IDAT", "ruSt", "IEND"]);
+        assert_eq!(strip_selected(&mut png, &selectors), 2);
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(Category::from_str("legacy"), Ok(Category::Legacy));
+        assert!(Category::from_str("gif").is_err());
+    }
+}
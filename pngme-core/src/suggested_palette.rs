@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::image::RgbaImage;
+use crate::png::Png;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entry {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub alpha: u16,
+    /// How often the colour appears, relative to the palette's other entries.
+    pub frequency: u16,
+}
+
+/// sPLT: a palette a viewer limited to fewer colours can use in place of the full image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestedPalette {
+    name: String,
+    sample_depth: u8,
+    entries: Vec<Entry>,
+}
+
+impl SuggestedPalette {
+    /// Checks the name, that the sample depth is 8 or 16, and that every sample fits it.
+    pub fn new(name: &str, sample_depth: u8, entries: Vec<Entry>) -> Result<Self, String> {
+        chunk::keyword_bytes(name)?;
+        let max = match sample_depth {
+            8 => u8::MAX as u16,
+            16 => u16::MAX,
+            _ => {
+                return Err(format!(
+                    "the sample depth must be 8 or 16, not {}",
+                    sample_depth
+                ))
+            }
+        };
+        if let Some(entry) = entries.iter().find(|entry| {
+            [entry.red, entry.green, entry.blue, entry.alpha]
+                .iter()
+                .any(|&sample| sample > max)
+        }) {
+            return Err(format!(
+                "{},{},{},{} does not fit in {}-bit samples",
+                entry.red, entry.green, entry.blue, entry.alpha, sample_depth
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            sample_depth,
+            entries,
+        })
+    }
+
+    /// Up to `max_entries` of the image's most common colours, quantised to `sample_depth`,
+    /// with frequencies scaled so that the most common is 65535.
+    pub fn from_image(
+        name: &str,
+        sample_depth: u8,
+        image: &RgbaImage,
+        max_entries: usize,
+    ) -> Result<Self, String> {
+        let max = ((1u32 << sample_depth.min(16)) - 1) as f32;
+        let mut counts: HashMap<[u16; 4], u64> = HashMap::new();
+        for pixel in &image.pixels {
+            *counts
+                .entry(pixel.map(|value| (value * max).round() as u16))
+                .or_default() += 1;
+        }
+        let mut colors: Vec<([u16; 4], u64)> = counts.into_iter().collect();
+        // Most common first, then by value so that the order doesn't depend on hashing.
+        colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        colors.truncate(max_entries);
+        let most = colors.first().map_or(1, |&(_, count)| count);
+        let entries = colors
+            .into_iter()
+            .map(|([red, green, blue, alpha], count)| Entry {
+                red,
+                green,
+                blue,
+                alpha,
+                frequency: (count * u16::MAX as u64 / most) as u16,
+            })
+            .collect();
+        Self::new(name, sample_depth, entries)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sample_depth(&self) -> u8 {
+        self.sample_depth
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The name, a null separator and the sample depth, then each entry's red, green, blue and
+    /// alpha in one or two bytes each, and its frequency in two.
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = chunk::keyword_bytes(&self.name).unwrap();
+        data.push(0);
+        data.push(self.sample_depth);
+        for entry in &self.entries {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                if self.sample_depth == 8 {
+                    data.push(sample as u8);
+                } else {
+                    data.extend_from_slice(&sample.to_be_bytes());
+                }
+            }
+            data.extend_from_slice(&entry.frequency.to_be_bytes());
+        }
+        Chunk::new(ChunkType::SPLT, data)
+    }
+
+    pub fn from_chunk(chunk: &Chunk) -> Result<Self, String> {
+        let data = chunk.data();
+        let separator = data
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("sPLT has no null separator after its name")?;
+        let name: String = data[..separator].iter().map(|&byte| byte as char).collect();
+        let sample_depth = *data
+            .get(separator + 1)
+            .ok_or("sPLT ends before its sample depth")?;
+        let entry_len = match sample_depth {
+            8 => 6,
+            16 => 10,
+            _ => {
+                return Err(format!(
+                    "sPLT `{}` has a sample depth of {}, not 8 or 16",
+                    name, sample_depth
+                ))
+            }
+        };
+        let entries = &data[separator + 2..];
+        if !entries.len().is_multiple_of(entry_len) {
+            return Err(format!(
+                "sPLT `{}` has {} bytes of entries, not a whole number of {}-byte entries",
+                name,
+                entries.len(),
+                entry_len
+            ));
+        }
+        let entries = entries
+            .chunks_exact(entry_len)
+            .map(|entry| {
+                let sample = |index: usize| match sample_depth {
+                    8 => entry[index] as u16,
+                    _ => u16::from_be_bytes([entry[2 * index], entry[2 * index + 1]]),
+                };
+                Entry {
+                    red: sample(0),
+                    green: sample(1),
+                    blue: sample(2),
+                    alpha: sample(3),
+                    frequency: u16::from_be_bytes([entry[entry_len - 2], entry[entry_len - 1]]),
+                }
+            })
+            .collect();
+        Self::new(&name, sample_depth, entries)
+    }
+}
+
+impl Display for SuggestedPalette {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{}: {} entries, {}-bit samples",
+            self.name,
+            self.entries.len(),
+            self.sample_depth
+        )
+    }
+}
+
+/// Every sPLT chunk in the file, in order.
+pub fn palettes(png: &Png) -> Vec<Result<SuggestedPalette, String>> {
+    png.chunks()
+        .iter()
+        .filter(|chunk| *chunk.chunk_type() == ChunkType::SPLT)
+        .map(SuggestedPalette::from_chunk)
+        .collect()
+}
+
+/// Problems with the file's sPLT chunks as a whole: malformed chunks and repeated names,
+/// which the specification forbids.
+pub fn problems(png: &Png) -> Vec<String> {
+    let mut names = HashSet::new();
+    palettes(png)
+        .into_iter()
+        .filter_map(|palette| match palette {
+            Err(error) => Some(error),
+            Ok(palette) => (!names.insert(palette.name.clone()))
+                .then(|| format!("more than one sPLT chunk is named `{}`", palette.name)),
+        })
+        .collect()
+}
+
+/// Adds `palette` ahead of the image data, unless a palette with the same name exists.
+pub fn add(png: &mut Png, palette: &SuggestedPalette) -> Result<(), String> {
+    if palettes(png)
+        .iter()
+        .flatten()
+        .any(|existing| existing.name == palette.name)
+    {
+        return Err(format!(
+            "the file already has a suggested palette named `{}`",
+            palette.name
+        ));
+    }
+    png.insert_ancillary(palette.to_chunk());
+    Ok(())
+}
+
+pub fn remove(png: &mut Png, name: &str) -> bool {
+    let before = png.chunks().len();
+    png.retain_chunks(|chunk| {
+        *chunk.chunk_type() != ChunkType::SPLT
+            || SuggestedPalette::from_chunk(chunk).map_or(true, |palette| palette.name != name)
+    });
+    png.chunks().len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+
+    fn entry(red: u16, alpha: u16, frequency: u16) -> Entry {
+        Entry {
+            red,
+            green: 2,
+            blue: 3,
+            alpha,
+            frequency,
+        }
+    }
+
+    #[test]
+    fn test_chunk_round_trip() {
+        for sample_depth in [8, 16] {
+            let palette = SuggestedPalette::new(
+                "web safe",
+                sample_depth,
+                vec![entry(1, 255, 10), entry(0, 0, 0)],
+            )
+            .unwrap();
+            let chunk = palette.to_chunk();
+            let entry_len = if sample_depth == 8 { 6 } else { 10 };
+            assert_eq!(
+                chunk.length() as usize,
+                "web safe".len() + 2 + 2 * entry_len
+            );
+            assert_eq!(SuggestedPalette::from_chunk(&chunk), Ok(palette));
+        }
+        assert_eq!(
+            SuggestedPalette::new("big", 16, vec![entry(65535, 256, 0)])
+                .unwrap()
+                .to_chunk()
+                .data()[5..9],
+            [255, 255, 0, 2]
+        );
+        assert!(SuggestedPalette::new("x", 8, vec![entry(256, 0, 0)]).is_err());
+        assert!(SuggestedPalette::new("x", 4, vec![]).is_err());
+        assert!(SuggestedPalette::new(" x", 8, vec![]).is_err());
+        let truncated = Chunk::new(ChunkType::SPLT, b"x\0\x08\x01\x02".to_vec());
+        assert!(SuggestedPalette::from_chunk(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_from_image() {
+        let pixels = [
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0, 1.0],
+            [1.0, 0.0, 0.0, 1.0],
+        ];
+        let image = RgbaImage {
+            width: 3,
+            height: 1,
+            pixels: pixels.to_vec(),
+        };
+        let palette = SuggestedPalette::from_image("common", 8, &image, 1).unwrap();
+        assert_eq!(
+            palette.entries(),
+            [Entry {
+                red: 255,
+                green: 0,
+                blue: 0,
+                alpha: 255,
+                frequency: 65535
+            }]
+        );
+        let palette = SuggestedPalette::from_image("all", 16, &image, 256).unwrap();
+        assert_eq!(palette.entries()[1].blue, 65535);
+        assert_eq!(palette.entries()[1].frequency, 32767);
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        let palette = SuggestedPalette::new("one", 8, vec![entry(1, 1, 1)]).unwrap();
+        add(&mut png, &palette).unwrap();
+        assert!(add(&mut png, &palette).is_err());
+        add(&mut png, &SuggestedPalette::new("two", 16, vec![]).unwrap()).unwrap();
+        assert_eq!(palettes(&png).len(), 2);
+        assert!(problems(&png).is_empty());
+
+        png.insert_ancillary(palette.to_chunk());
+        assert_eq!(problems(&png), ["more than one sPLT chunk is named `one`"]);
+        assert!(remove(&mut png, "one"));
+        assert!(!remove(&mut png, "one"));
+        assert_eq!(
+            palettes(&png),
+            [Ok(SuggestedPalette::new("two", 16, vec![]).unwrap())]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! A file's structure as other reverse-engineering tools describe binaries: a Kaitai Struct
+//! spec or an 010 Editor binary template, laid out as [`hexview::sections`] reads the file.
+//! Each chunk gets a field of its own, named by its position and type and documented with
+//! what pngme found, so that the other tool shows the file the way pngme does.
+
+use std::fmt::Write;
+
+use crate::hexview::{self, Section};
+use crate::png::Png;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// A `.ksy` spec for Kaitai Struct.
+    Kaitai,
+    /// A `.bt` template for 010 Editor.
+    BinaryTemplate,
+}
+
+/// `text` as an identifier of lowercase letters, digits and underscores that starts with a
+/// letter, as Kaitai wants.
+fn identifier(text: &str) -> String {
+    let mut id: String = text
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_lowercase()) {
+        id.insert_str(0, "png_");
+    }
+    id
+}
+
+/// The field names of the sections, `chunk_<index>_<type>` in lowercase for chunks.
+fn field_names(sections: &[Section]) -> Vec<String> {
+    let mut chunks = 0;
+    sections
+        .iter()
+        .map(|section| match section.chunk_type {
+            Some(chunk_type) => {
+                chunks += 1;
+                identifier(&format!("chunk_{}_{}", chunks - 1, chunk_type))
+            }
+            None if section.range.start == 0 => String::from("signature"),
+            None => String::from("trailing_data"),
+        })
+        .collect()
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn kaitai(name: &str, bytes: &[u8], sections: &[Section]) -> String {
+    let stem = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let stem = stem.split('.').next().unwrap_or(stem);
+    let mut ksy = String::new();
+    writeln!(
+        ksy,
+        "meta:\n  id: {}\n  title: {}\n  file-extension: png\n  endian: be\nseq:",
+        identifier(stem),
+        quoted(&format!("{} as read by pngme", name))
+    )
+    .unwrap();
+    for (section, field) in sections.iter().zip(field_names(sections)) {
+        writeln!(ksy, "  - id: {}", field).unwrap();
+        match section.chunk_type {
+            Some(_) => ksy.push_str("    type: chunk\n"),
+            None if section.range.start == 0 => {
+                let signature: Vec<String> = bytes[section.range.clone()]
+                    .iter()
+                    .map(|byte| format!("0x{:02x}", byte))
+                    .collect();
+                writeln!(ksy, "    contents: [{}]", signature.join(", ")).unwrap();
+            }
+            None => writeln!(ksy, "    size: {}", section.range.len()).unwrap(),
+        }
+        writeln!(
+            ksy,
+            "    doc: {}",
+            quoted(&format!(
+                "{} at offset {}",
+                section.label, section.range.start
+            ))
+        )
+        .unwrap();
+    }
+    ksy.push_str(
+        "types:\n  chunk:\n    seq:\n      - id: len\n        type: u4\n      - id: type\n        \
+         type: str\n        size: 4\n        encoding: ASCII\n      - id: body\n        \
+         size: len\n      - id: crc\n        type: u4\n",
+    );
+    ksy
+}
+
+fn binary_template(name: &str, sections: &[Section]) -> String {
+    let mut bt = String::new();
+    writeln!(
+        bt,
+        "// {} as read by pngme\nBigEndian();\n\ntypedef struct {{\n    uint32 length;\n    \
+         char type[4];\n    if (length > 0)\n        uchar data[length];\n    \
+         uint32 crc <format=hex>;\n}} CHUNK;\n",
+        name.replace(['\n', '\r'], " ")
+    )
+    .unwrap();
+    for (section, field) in sections.iter().zip(field_names(sections)) {
+        let declaration = match section.chunk_type {
+            Some(_) => format!("CHUNK {}", field),
+            None => format!("uchar {}[{}]", field, section.range.len()),
+        };
+        writeln!(
+            bt,
+            "{} <comment={}>;",
+            declaration,
+            quoted(&format!(
+                "{} at offset {}",
+                section.label, section.range.start
+            ))
+        )
+        .unwrap();
+    }
+    bt
+}
+
+/// The structure of `bytes`, the contents of the file called `name`, as `template`.
+pub fn render(template: Template, name: &str, bytes: &[u8]) -> Result<String, String> {
+    if !bytes.starts_with(&Png::STANDARD_HEADER) {
+        return Err(String::from("not a PNG file"));
+    }
+    let sections = hexview::sections(bytes);
+    Ok(match template {
+        Template::Kaitai => kaitai(name, bytes, &sections),
+        Template::BinaryTemplate => binary_template(name, &sections),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::image::ColorType;
+    use crate::testing;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_render() {
+        let mut png = testing::png_with_header(ColorType::Rgb, 8, 0);
+        png.append_chunk(Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![1; 3]));
+        let mut bytes = png.as_bytes();
+        bytes.extend(b"tail");
+
+        let ksy = render(Template::Kaitai, "dir/2 \"x\".png", &bytes).unwrap();
+        assert!(ksy.starts_with("meta:\n  id: png_2__x_\n"));
+        assert!(ksy.contains("  title: \"dir/2 \\\"x\\\".png as read by pngme\"\n"));
+        assert!(ksy.contains("    contents: [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]"));
+        assert!(ksy.contains("  - id: chunk_2_rust\n    type: chunk\n"));
+        assert!(ksy.contains("  - id: trailing_data\n    size: 4\n"));
+        assert!(ksy.contains("      - id: body\n        size: len\n"));
+
+        let bt = render(Template::BinaryTemplate, "x.png", &bytes).unwrap();
+        assert!(bt.contains("BigEndian();"));
+        assert!(bt.contains("uchar signature[8] <comment=\"signature at offset 0\">;"));
+        assert!(bt.contains("CHUNK chunk_2_rust <comment=\"chunk #2 RuSt, 3 bytes of data"));
+        assert!(bt.contains("uchar trailing_data[4]"));
+
+        assert!(render(Template::Kaitai, "x.gif", b"GIF89a").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Proptest strategies for chunks and whole files, and fixtures shared by the unit tests.
+use std::str::FromStr;
+
+use proptest::prelude::*;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::{ColorType, ImageHeader};
+use crate::png::Png;
+
+/// Any valid chunk type except IEND, which would end the file early.
+pub fn chunk_type() -> impl Strategy<Value = ChunkType> {
+    "[a-zA-Z]{2}[A-Z][a-zA-Z]"
+        .prop_map(|chunk_type| ChunkType::from_str(&chunk_type).unwrap())
+        .prop_filter("IEND ends the file", |chunk_type| {
+            *chunk_type != ChunkType::IEND
+        })
+}
+
+pub fn chunk() -> impl Strategy<Value = Chunk> {
+    (chunk_type(), prop::collection::vec(any::<u8>(), 0..256))
+        .prop_map(|(chunk_type, data)| Chunk::new(chunk_type, data))
+}
+
+/// Up to eight arbitrary chunks, closed by IEND.
+pub fn png() -> impl Strategy<Value = Png> {
+    prop::collection::vec(chunk(), 0..8).prop_map(|mut chunks| {
+        chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+        Png::from_chunks(chunks)
+    })
+}
+
+/// A 1x1 image with an empty IDAT, and a palette of `palette` black entries if non-zero.
+pub fn png_with_header(color_type: ColorType, bit_depth: u8, palette: usize) -> Png {
+    let header = ImageHeader {
+        width: 1,
+        height: 1,
+        bit_depth,
+        color_type,
+        interlaced: false,
+    };
+    let mut chunks = vec![Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec())];
+    if palette > 0 {
+        chunks.push(Chunk::new(ChunkType::PLTE, vec![0; 3 * palette]));
+    }
+    chunks.push(Chunk::new(ChunkType::IDAT, vec![]));
+    chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+    Png::from_chunks(chunks)
+}
@@ -0,0 +1,258 @@
+use std::ops::Range;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::xmp;
+
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const XMP_IMAGE_TAG: &str = "xmpGImg:image";
+const XMP_THUMBNAILS_TAG: &str = "xmp:Thumbnails";
+
+/// A TIFF stream, as EXIF data is laid out.
+pub(crate) struct Tiff<'a> {
+    bytes: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Result<Self, ()> {
+        let little_endian = match bytes.get(0..4) {
+            Some(b"II*\0") => true,
+            Some(b"MM\0*") => false,
+            _ => return Err(()),
+        };
+        Ok(Self {
+            bytes,
+            little_endian,
+        })
+    }
+    pub(crate) fn u16_at(&self, offset: usize) -> Result<u16, ()> {
+        let bytes: [u8; 2] = self
+            .bytes
+            .get(offset..offset + 2)
+            .ok_or(())?
+            .try_into()
+            .unwrap();
+        Ok(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+    pub(crate) fn u32_at(&self, offset: usize) -> Result<u32, ()> {
+        let bytes: [u8; 4] = self
+            .bytes
+            .get(offset..offset + 4)
+            .ok_or(())?
+            .try_into()
+            .unwrap();
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+}
+
+struct ExifThumbnail {
+    /// Position of IFD0's next-IFD pointer, which links to the thumbnail's IFD1.
+    link: usize,
+    data: Range<usize>,
+}
+
+fn find_exif_thumbnail(exif: &[u8]) -> Result<Option<ExifThumbnail>, ()> {
+    let tiff = Tiff::new(exif)?;
+    let ifd0 = tiff.u32_at(4)? as usize;
+    let link = ifd0 + 2 + 12 * tiff.u16_at(ifd0)? as usize;
+    let ifd1 = tiff.u32_at(link)? as usize;
+    if ifd1 == 0 {
+        return Ok(None);
+    }
+
+    let (mut offset, mut length) = (None, None);
+    for entry in 0..tiff.u16_at(ifd1)? as usize {
+        let entry = ifd1 + 2 + 12 * entry;
+        match tiff.u16_at(entry)? {
+            TAG_JPEG_OFFSET => offset = Some(tiff.u32_at(entry + 8)? as usize),
+            TAG_JPEG_LENGTH => length = Some(tiff.u32_at(entry + 8)? as usize),
+            _ => {}
+        }
+    }
+    match (offset, length) {
+        (Some(offset), Some(length)) if offset + length <= exif.len() => Ok(Some(ExifThumbnail {
+            link,
+            data: offset..offset + length,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(()),
+    }
+}
+
+pub fn exif_thumbnail(exif: &[u8]) -> Result<Option<&[u8]>, ()> {
+    Ok(find_exif_thumbnail(exif)?.map(|thumbnail| &exif[thumbnail.data]))
+}
+
+/// Unlinks IFD1 and wipes the JPEG bytes so that no remnant of the thumbnail survives.
+pub fn strip_exif_thumbnail(exif: &[u8]) -> Result<Option<Vec<u8>>, ()> {
+    let thumbnail = match find_exif_thumbnail(exif)? {
+        Some(thumbnail) => thumbnail,
+        None => return Ok(None),
+    };
+    let mut stripped = exif.to_vec();
+    stripped[thumbnail.link..thumbnail.link + 4].fill(0);
+    if thumbnail.data.end == stripped.len() {
+        stripped.truncate(thumbnail.data.start);
+    } else {
+        stripped[thumbnail.data].fill(0);
+    }
+    Ok(Some(stripped))
+}
+
+fn xmp_image_value(xmp: &str) -> Option<&str> {
+    let element_start = format!("<{}>", XMP_IMAGE_TAG);
+    let element_end = format!("</{}>", XMP_IMAGE_TAG);
+    let attribute = format!("{}=\"", XMP_IMAGE_TAG);
+    if let Some(start) = xmp.find(&element_start) {
+        let value = &xmp[start + element_start.len()..];
+        return value.find(&element_end).map(|end| &value[..end]);
+    }
+    let start = xmp.find(&attribute)?;
+    let value = &xmp[start + attribute.len()..];
+    value.find('"').map(|end| &value[..end])
+}
+
+/// Decodes the first base64 thumbnail in an XMP packet.
+pub fn xmp_thumbnail(xmp: &str) -> Option<Result<Vec<u8>, ()>> {
+    let value = xmp_image_value(xmp)?;
+    let encoded: String = value
+        .replace("&#xA;", "")
+        .replace("&#xD;", "")
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    Some(STANDARD.decode(encoded).map_err(|_| ()))
+}
+
+/// Removes the whole `xmp:Thumbnails` property, returning `None` if there was none.
+pub fn strip_xmp_thumbnail(xmp: &str) -> Option<String> {
+    let start = xmp.find(&format!("<{}", XMP_THUMBNAILS_TAG))?;
+    let element_end = format!("</{}>", XMP_THUMBNAILS_TAG);
+    let end = start + xmp[start..].find(&element_end)? + element_end.len();
+    Some(format!("{}{}", &xmp[..start], &xmp[end..]))
+}
+
+/// Returns the embedded thumbnail (normally a JPEG), preferring EXIF over XMP.
+pub fn extract(png: &Png) -> Option<Result<Vec<u8>, ()>> {
+    if let Some(exif) = png.chunk_by_type(ChunkType::EXIF) {
+        match exif_thumbnail(exif.data()) {
+            Ok(Some(thumbnail)) => return Some(Ok(thumbnail.to_vec())),
+            Ok(None) => {}
+            Err(()) => return Some(Err(())),
+        }
+    }
+    match xmp::xmp(png)? {
+        Ok(packet) => xmp_thumbnail(&packet),
+        Err(()) => None,
+    }
+}
+
+/// Removes thumbnails from both EXIF and XMP, returning whether anything changed.
+pub fn strip(png: &mut Png) -> Result<bool, ()> {
+    let mut changed = false;
+    let exif_index = png
+        .chunks()
+        .iter()
+        .position(|chunk| *chunk.chunk_type() == ChunkType::EXIF);
+    if let Some(index) = exif_index {
+        if let Some(stripped) = strip_exif_thumbnail(png.chunks()[index].data())? {
+            png.replace_chunk(index, Chunk::new(ChunkType::EXIF, stripped));
+            changed = true;
+        }
+    }
+    if let Some(Ok(packet)) = xmp::xmp(png) {
+        if let Some(stripped) = strip_xmp_thumbnail(&packet) {
+            xmp::set_xmp(png, &stripped);
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPEG: &[u8] = &[0xff, 0xd8, 0xff, 0xe0, 1, 2, 3, 0xff, 0xd9];
+
+    // Big-endian TIFF with an empty IFD0 linking to an IFD1 that points at `JPEG`.
+    fn testing_exif() -> Vec<u8> {
+        let mut exif = b"MM\0*".to_vec();
+        exif.extend(8u32.to_be_bytes());
+        exif.extend(0u16.to_be_bytes());
+        exif.extend(14u32.to_be_bytes());
+        let jpeg_offset = 14 + 2 + 2 * 12 + 4;
+        exif.extend(2u16.to_be_bytes());
+        for (tag, value) in [
+            (TAG_JPEG_OFFSET, jpeg_offset),
+            (TAG_JPEG_LENGTH, JPEG.len() as u32),
+        ] {
+            exif.extend(tag.to_be_bytes());
+            exif.extend(4u16.to_be_bytes());
+            exif.extend(1u32.to_be_bytes());
+            exif.extend(value.to_be_bytes());
+        }
+        exif.extend(0u32.to_be_bytes());
+        exif.extend_from_slice(JPEG);
+        exif
+    }
+
+    #[test]
+    fn test_exif_thumbnail() {
+        let exif = testing_exif();
+        assert_eq!(exif_thumbnail(&exif).unwrap(), Some(JPEG));
+    }
+
+    #[test]
+    fn test_strip_exif_thumbnail() {
+        let exif = testing_exif();
+        let stripped = strip_exif_thumbnail(&exif).unwrap().unwrap();
+        assert_eq!(stripped.len(), exif.len() - JPEG.len());
+        assert_eq!(exif_thumbnail(&stripped).unwrap(), None);
+        assert_eq!(strip_exif_thumbnail(&stripped).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_exif() {
+        assert!(exif_thumbnail(b"JUNK").is_err());
+        assert!(exif_thumbnail(b"MM\0*\0\0\0\xff").is_err());
+    }
+
+    #[test]
+    fn test_xmp_thumbnail() {
+        let encoded = STANDARD.encode(JPEG);
+        let packet = format!(
+            "<rdf:Description><xmp:Thumbnails><rdf:Alt><rdf:li><xmpGImg:image>{}&#xA;{}</xmpGImg:image></rdf:li></rdf:Alt></xmp:Thumbnails></rdf:Description>",
+            &encoded[..4],
+            &encoded[4..]
+        );
+        assert_eq!(xmp_thumbnail(&packet).unwrap().unwrap(), JPEG);
+
+        let stripped = strip_xmp_thumbnail(&packet).unwrap();
+        assert_eq!(stripped, "<rdf:Description></rdf:Description>");
+        assert!(xmp_thumbnail(&stripped).is_none());
+    }
+
+    #[test]
+    fn test_png_extract_and_strip() {
+        let chunk = Chunk::new(ChunkType::EXIF, testing_exif());
+        let mut png = Png::from_chunks(vec![chunk]);
+        assert_eq!(extract(&png).unwrap().unwrap(), JPEG);
+        assert!(strip(&mut png).unwrap());
+        assert!(extract(&png).is_none());
+        assert!(!strip(&mut png).unwrap());
+    }
+}
@@ -0,0 +1,260 @@
+//! Images read and written a band of rows at a time, so that memory grows with the band
+//! height rather than the image: a 30000 by 30000 RGBA map export decodes to several
+//! gigabytes, while a band of 256 of its rows is about 60 MB.
+//!
+//! Only the decoded pixels are bounded this way; the file's compressed data is still held
+//! whole. Interlaced images spread each row over all seven Adam7 passes, so they have to be
+//! decoded whole and aren't supported.
+
+use std::io::{self, Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::chunk_type::ChunkType;
+use crate::image::{self, ImageData, ImageHeader};
+use crate::png::Png;
+
+/// The payloads of the IDAT chunks as one stream.
+struct ImageDataReader<'a> {
+    chunks: std::vec::IntoIter<&'a [u8]>,
+    current: &'a [u8],
+}
+
+impl Read for ImageDataReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.chunks.next() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        self.current.read(buffer)
+    }
+}
+
+/// Rows `top..top + image.height()` of the image.
+pub struct Band {
+    pub top: u32,
+    pub image: ImageData,
+}
+
+/// The bands of a non-interlaced image, top to bottom, each `band_height` rows but the last.
+pub struct Bands<'a> {
+    metadata: ImageData,
+    decoder: ZlibDecoder<ImageDataReader<'a>>,
+    /// The last row decoded, unfiltered, which the next row's filter may refer to.
+    previous: Vec<u8>,
+    top: u32,
+    band_height: u32,
+}
+
+impl<'a> Bands<'a> {
+    pub fn new(png: &'a Png, band_height: u32) -> Result<Self, ()> {
+        let metadata = ImageData::metadata(png)?;
+        if metadata.header().interlaced || band_height == 0 {
+            return Err(());
+        }
+        let chunks: Vec<&[u8]> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+            .map(|chunk| chunk.data())
+            .collect();
+        let row_bytes = metadata.header().row_bytes(metadata.width());
+        Ok(Self {
+            metadata,
+            decoder: ZlibDecoder::new(ImageDataReader {
+                chunks: chunks.into_iter(),
+                current: &[],
+            }),
+            previous: vec![0; row_bytes],
+            top: 0,
+            band_height,
+        })
+    }
+
+    pub fn header(&self) -> &ImageHeader {
+        self.metadata.header()
+    }
+
+    fn read_band(&mut self) -> Result<Band, ()> {
+        let header = *self.metadata.header();
+        let rows = self.band_height.min(header.height - self.top);
+        let row_bytes = header.row_bytes(header.width);
+        // The previous row goes first, unfiltered and marked so, for the filters of this
+        // band's first row to refer to.
+        let mut filtered = vec![0; (row_bytes + 1) * (rows as usize + 1)];
+        filtered[1..row_bytes + 1].copy_from_slice(&self.previous);
+        self.decoder
+            .read_exact(&mut filtered[row_bytes + 1..])
+            .map_err(|_| ())?;
+        let unfiltered = image::unfilter(&filtered, row_bytes, header.bits_per_pixel())?;
+        let unfiltered = &unfiltered[row_bytes..];
+        self.previous
+            .copy_from_slice(&unfiltered[unfiltered.len() - row_bytes..]);
+
+        let row_samples = header.width as usize * header.color_type.channels();
+        let samples: Vec<u16> = unfiltered
+            .chunks(row_bytes)
+            .flat_map(|row| image::unpack(row, header.bit_depth, row_samples))
+            .collect();
+        self.metadata.check_indices(&samples)?;
+        let band = Band {
+            top: self.top,
+            image: self.metadata.band(rows, samples),
+        };
+        self.top += rows;
+        Ok(band)
+    }
+}
+
+impl Iterator for Bands<'_> {
+    type Item = Result<Band, ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.top == self.metadata.height() {
+            return None;
+        }
+        let band = self.read_band();
+        if band.is_err() {
+            // Nothing after a corrupt band can be trusted.
+            self.top = self.metadata.height();
+        }
+        Some(band)
+    }
+}
+
+/// Compresses bands of rows, top to bottom, into new image data for `header`.
+pub struct BandWriter {
+    header: ImageHeader,
+    encoder: ZlibEncoder<Vec<u8>>,
+    rows: u32,
+}
+
+impl BandWriter {
+    pub fn new(header: &ImageHeader) -> Self {
+        Self {
+            header: ImageHeader {
+                interlaced: false,
+                ..*header
+            },
+            encoder: ZlibEncoder::new(Vec::new(), Compression::default()),
+            rows: 0,
+        }
+    }
+
+    /// Appends the rows of `band`, written like [`ImageData::write_to`] with no filtering.
+    pub fn write(&mut self, band: &ImageData) -> Result<(), ()> {
+        let header = band.header();
+        if (header.width, header.color_type, header.bit_depth)
+            != (
+                self.header.width,
+                self.header.color_type,
+                self.header.bit_depth,
+            )
+            || self.rows + header.height > self.header.height
+        {
+            return Err(());
+        }
+        for row in band
+            .samples()
+            .chunks(header.width as usize * band.channels())
+        {
+            self.encoder.write_all(&[0]).map_err(|_| ())?;
+            self.encoder
+                .write_all(&image::pack(row, header.bit_depth))
+                .map_err(|_| ())?;
+        }
+        self.rows += header.height;
+        Ok(())
+    }
+
+    /// Replaces the IHDR and IDAT chunks of `png` with what has been written, once every
+    /// row has.
+    pub fn finish(self, png: &mut Png) -> Result<(), ()> {
+        if self.rows != self.header.height {
+            return Err(());
+        }
+        let compressed = self.encoder.finish().map_err(|_| ())?;
+        image::replace_image_data(png, &self.header, compressed)
+    }
+}
+
+/// Runs `edit` on each band of `png` in turn and writes the results back as its image data.
+pub fn edit_bands(
+    png: &mut Png,
+    band_height: u32,
+    mut edit: impl FnMut(&mut Band) -> Result<(), ()>,
+) -> Result<(), ()> {
+    let bands = Bands::new(png, band_height)?;
+    let mut writer = BandWriter::new(bands.header());
+    for band in bands {
+        let mut band = band?;
+        edit(&mut band)?;
+        writer.write(&band.image)?;
+    }
+    writer.finish(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::image::ColorType;
+
+    // 3 rows of 2-pixel 8-bit greyscale, each filtered a different way.
+    fn filtered() -> Png {
+        let header = ImageHeader {
+            width: 2,
+            height: 3,
+            bit_depth: 8,
+            color_type: ColorType::Grayscale,
+            interlaced: false,
+        };
+        // Rows 10 20, 30 40, 35 50: None, Up, then Sub.
+        let raw = [0, 10, 20, 2, 20, 20, 1, 35, 15];
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()),
+            Chunk::new(ChunkType::IDAT, encoder.finish().unwrap()),
+            Chunk::new(ChunkType::IEND, vec![]),
+        ])
+    }
+
+    #[test]
+    fn test_bands() {
+        let png = filtered();
+        let bands: Vec<Band> = Bands::new(&png, 2)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(bands.len(), 2);
+        assert_eq!((bands[1].top, bands[1].image.height()), (2, 1));
+        let samples: Vec<u16> = bands
+            .iter()
+            .flat_map(|band| band.image.samples().iter().copied())
+            .collect();
+        assert_eq!(samples, ImageData::from_png(&png).unwrap().samples());
+    }
+
+    #[test]
+    fn test_edit_bands() {
+        let mut png = filtered();
+        edit_bands(&mut png, 1, |band| {
+            for sample in band.image.samples_mut() {
+                *sample += band.top as u16;
+            }
+            Ok(())
+        })
+        .unwrap();
+        let image = ImageData::from_png(&png).unwrap();
+        assert_eq!(image.samples(), &[10, 20, 31, 41, 37, 52]);
+
+        let mut interlaced = filtered();
+        let mut header = ImageHeader::from_png(&interlaced).unwrap();
+        header.interlaced = true;
+        interlaced.replace_chunk(0, Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec()));
+        assert!(Bands::new(&interlaced, 1).is_err());
+    }
+}
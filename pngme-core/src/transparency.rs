@@ -0,0 +1,115 @@
+use std::fmt::{Display, Formatter};
+
+use crate::background::palette_len;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::image::{Color, ColorType, ImageHeader};
+use crate::png::Png;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// An alpha value for each palette entry in turn; entries past the end are opaque.
+    Palette(Vec<u8>),
+    /// The one greyscale or RGB colour that is fully transparent.
+    Key(Color),
+}
+
+impl Display for Transparency {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Transparency::Palette(alpha) => {
+                let alpha: Vec<String> = alpha.iter().map(u8::to_string).collect();
+                write!(fmt, "palette alpha {}", alpha.join(","))
+            }
+            Transparency::Key(color) => write!(fmt, "transparent colour {}", color),
+        }
+    }
+}
+
+pub fn transparency(png: &Png) -> Option<Result<Transparency, ()>> {
+    let chunk = png.chunk_by_type(ChunkType::TRNS)?;
+    Some(
+        ImageHeader::from_png(png).and_then(|header| match header.color_type {
+            ColorType::Indexed if chunk.length() as usize <= palette_len(png) => {
+                Ok(Transparency::Palette(chunk.data().to_vec()))
+            }
+            ColorType::Grayscale | ColorType::Rgb => {
+                Color::from_bytes(chunk.data(), header.color_type).map(Transparency::Key)
+            }
+            _ => Err(()),
+        }),
+    )
+}
+
+/// Makes `color` fully transparent. For palette images this sets that entry's alpha to zero,
+/// keeping the alpha of any others; greyscale and RGB images get it as their single
+/// transparent colour. Images with an alpha channel can't have tRNS.
+pub fn set_transparent_color(png: &mut Png, color: Color) -> Result<(), String> {
+    let header = ImageHeader::from_png(png).map_err(|()| "the file has no valid IHDR chunk")?;
+    if matches!(
+        header.color_type,
+        ColorType::GrayscaleAlpha | ColorType::Rgba
+    ) {
+        return Err("the image has an alpha channel, so it cannot have tRNS".into());
+    }
+    color.check(&header, palette_len(png))?;
+    let data = match (color, transparency(png)) {
+        (Color::Level(index), Some(Ok(Transparency::Palette(mut alpha))))
+            if header.color_type == ColorType::Indexed =>
+        {
+            alpha.resize(alpha.len().max(index as usize + 1), 255);
+            alpha[index as usize] = 0;
+            alpha
+        }
+        (Color::Level(index), _) if header.color_type == ColorType::Indexed => {
+            let mut alpha = vec![255; index as usize + 1];
+            alpha[index as usize] = 0;
+            alpha
+        }
+        _ => color.to_bytes(header.color_type),
+    };
+    remove_transparency(png);
+    png.insert_ancillary(Chunk::new(ChunkType::TRNS, data));
+    Ok(())
+}
+
+pub fn remove_transparency(png: &mut Png) -> bool {
+    let before = png.chunks().len();
+    png.retain_chunks(|chunk| *chunk.chunk_type() != ChunkType::TRNS);
+    png.chunks().len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::png_with_header;
+
+    #[test]
+    fn test_set_transparent_color() {
+        let mut png = png_with_header(ColorType::Indexed, 8, 4);
+        set_transparent_color(&mut png, Color::Level(2)).unwrap();
+        assert_eq!(
+            transparency(&png),
+            Some(Ok(Transparency::Palette(vec![255, 255, 0])))
+        );
+        set_transparent_color(&mut png, Color::Level(0)).unwrap();
+        assert_eq!(
+            transparency(&png),
+            Some(Ok(Transparency::Palette(vec![0, 255, 0])))
+        );
+        assert!(set_transparent_color(&mut png, Color::Level(4)).is_err());
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "tRNS");
+
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        set_transparent_color(&mut png, Color::Rgb([0, 255, 0])).unwrap();
+        assert_eq!(
+            transparency(&png).unwrap().unwrap().to_string(),
+            "transparent colour 0,255,0"
+        );
+        assert!(set_transparent_color(&mut png, Color::Rgb([0, 256, 0])).is_err());
+        assert!(remove_transparency(&mut png));
+
+        let mut png = png_with_header(ColorType::Rgba, 8, 0);
+        assert!(set_transparent_color(&mut png, Color::Rgb([0, 0, 0])).is_err());
+    }
+}
@@ -0,0 +1,737 @@
+use std::fmt::{Display, Formatter};
+
+use crate::background;
+use crate::c2pa::{self, BindingStatus};
+use crate::chunk_type::ChunkType;
+use crate::envelope::{self, Date};
+use crate::extensions::{ImageOffset, PhysicalScale, PixelCalibration};
+use crate::histogram::Histogram;
+use crate::image::{Color, ColorType, ImageHeader};
+use crate::png::{Png, BEFORE_PALETTE};
+use crate::polyglot::{self, Kind};
+use crate::significant_bits::SignificantBits;
+use crate::suggested_palette;
+
+/// Ancillary chunks that must come before the first IDAT.
+const BEFORE_DATA: [ChunkType; 8] = [
+    ChunkType::BKGD,
+    ChunkType::HIST,
+    ChunkType::TRNS,
+    ChunkType::PHYS,
+    ChunkType::SPLT,
+    ChunkType::OFFS,
+    ChunkType::PCAL,
+    ChunkType::SCAL,
+];
+/// Ancillary chunks that must come after PLTE when there is one.
+const AFTER_PALETTE: [ChunkType; 3] = [ChunkType::BKGD, ChunkType::HIST, ChunkType::TRNS];
+const UNIQUE_CRITICAL: [ChunkType; 3] = [ChunkType::IHDR, ChunkType::PLTE, ChunkType::IEND];
+
+/// Chunk ordering rules from the PNG specification, §5.6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    IhdrFirst,
+    IendLast,
+    SingleCritical,
+    PlteBeforeIdat,
+    BeforePlte,
+    AfterPlte,
+    BeforeIdat,
+    ContiguousIdat,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::IhdrFirst => "ihdr-first",
+            Rule::IendLast => "iend-last",
+            Rule::SingleCritical => "single-critical",
+            Rule::PlteBeforeIdat => "plte-before-idat",
+            Rule::BeforePlte => "before-plte",
+            Rule::AfterPlte => "after-plte",
+            Rule::BeforeIdat => "before-idat",
+            Rule::ContiguousIdat => "contiguous-idat",
+        }
+    }
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::IhdrFirst => "IHDR must be the first chunk",
+            Rule::IendLast => "IEND must be the last chunk",
+            Rule::SingleCritical => "IHDR, PLTE and IEND may appear only once",
+            Rule::PlteBeforeIdat => "PLTE must come before the image data",
+            Rule::BeforePlte => "colour-space chunks must come before PLTE",
+            Rule::AfterPlte => "tRNS, bKGD and hIST must come after PLTE",
+            Rule::BeforeIdat => "this chunk must come before the image data",
+            Rule::ContiguousIdat => "IDAT chunks must be consecutive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub chunk_index: usize,
+    pub chunk_type: String,
+    /// Byte offset of the chunk's length field from the start of the file.
+    pub offset: u64,
+}
+
+impl Display for Violation {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{}: {} chunk at offset {}: {}",
+            self.rule.name(),
+            self.chunk_type,
+            self.offset,
+            self.rule.description()
+        )
+    }
+}
+
+/// Byte offset of each chunk, assuming the chunks are laid out as they would be written.
+pub fn chunk_offsets(png: &Png) -> Vec<u64> {
+    let mut offset = Png::STANDARD_HEADER.len() as u64;
+    png.chunks()
+        .iter()
+        .map(|chunk| {
+            let start = offset;
+            offset += 12 + chunk.length() as u64;
+            start
+        })
+        .collect()
+}
+
+pub fn check_ordering(png: &Png) -> Vec<Violation> {
+    let types: Vec<ChunkType> = png
+        .chunks()
+        .iter()
+        .map(|chunk| *chunk.chunk_type())
+        .collect();
+    let offsets = chunk_offsets(png);
+    let first = |chunk_type: ChunkType| types.iter().position(|t| *t == chunk_type);
+    let first_idat = first(ChunkType::IDAT);
+    let last_idat = types.iter().rposition(|t| *t == ChunkType::IDAT);
+    let palette = first(ChunkType::PLTE);
+
+    let mut violations = Vec::new();
+    for (index, &chunk_type) in types.iter().enumerate() {
+        let after_data = first_idat.is_some_and(|idat| index > idat);
+        let mut broken = Vec::new();
+        if index == 0 && chunk_type != ChunkType::IHDR {
+            broken.push(Rule::IhdrFirst);
+        }
+        if chunk_type == ChunkType::IEND && index != types.len() - 1 {
+            broken.push(Rule::IendLast);
+        }
+        if UNIQUE_CRITICAL.contains(&chunk_type) && first(chunk_type) != Some(index) {
+            broken.push(Rule::SingleCritical);
+        }
+        if chunk_type == ChunkType::PLTE && after_data {
+            broken.push(Rule::PlteBeforeIdat);
+        }
+        if BEFORE_PALETTE.contains(&chunk_type) && palette.is_some_and(|plte| index > plte) {
+            broken.push(Rule::BeforePlte);
+        }
+        if AFTER_PALETTE.contains(&chunk_type) && palette.is_some_and(|plte| index < plte) {
+            broken.push(Rule::AfterPlte);
+        }
+        if (BEFORE_PALETTE.contains(&chunk_type) || BEFORE_DATA.contains(&chunk_type)) && after_data
+        {
+            broken.push(Rule::BeforeIdat);
+        }
+        if chunk_type != ChunkType::IDAT && after_data && last_idat.is_some_and(|idat| index < idat)
+        {
+            broken.push(Rule::ContiguousIdat);
+        }
+        violations.extend(broken.into_iter().map(|rule| Violation {
+            rule,
+            chunk_index: index,
+            chunk_type: chunk_type.to_string(),
+            offset: offsets[index],
+        }));
+    }
+    violations
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub chunk_index: usize,
+    /// Index of the earlier chunk with the same type and payload.
+    pub original_index: usize,
+    pub chunk_type: String,
+    pub offset: u64,
+}
+
+impl Display for Duplicate {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "duplicate-chunk: {} chunk at offset {} repeats chunk {}",
+            self.chunk_type, self.offset, self.original_index
+        )
+    }
+}
+
+/// Ancillary chunks whose type and payload exactly match an earlier chunk.
+pub fn find_duplicates(png: &Png) -> Vec<Duplicate> {
+    let chunks = png.chunks();
+    let offsets = chunk_offsets(png);
+    chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !chunk.chunk_type().is_critical())
+        .filter_map(|(index, chunk)| {
+            let original_index = chunks[..index].iter().position(|earlier| {
+                earlier.chunk_type() == chunk.chunk_type() && earlier.data() == chunk.data()
+            })?;
+            Some(Duplicate {
+                chunk_index: index,
+                original_index,
+                chunk_type: chunk.chunk_type().to_string(),
+                offset: offsets[index],
+            })
+        })
+        .collect()
+}
+
+/// Drops the chunks reported by `find_duplicates`, returning how many were removed.
+pub fn remove_duplicates(png: &mut Png) -> usize {
+    let duplicates: Vec<usize> = find_duplicates(png)
+        .iter()
+        .map(|duplicate| duplicate.chunk_index)
+        .collect();
+    let mut index = 0;
+    png.retain_chunks(|_| {
+        index += 1;
+        !duplicates.contains(&(index - 1))
+    });
+    duplicates.len()
+}
+
+/// One problem found by `findings`, with enough structure for machine-readable reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Stable identifier of the check that failed, e.g. `ihdr-first`.
+    pub rule_id: &'static str,
+    /// What the check looks for, the same for every finding of a rule.
+    pub rule_description: &'static str,
+    pub message: String,
+    /// Byte offset of the chunk concerned, when there is one.
+    pub offset: Option<u64>,
+}
+
+impl Display for Finding {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.message)
+    }
+}
+
+const C2PA_DESCRIPTION: &str = "a C2PA manifest must match the file it is embedded in";
+
+/// Chunks holding a message written with `pngme encode --expires` whose date is before `today`.
+fn expired_payloads(png: &Png, today: Date) -> Vec<Finding> {
+    png.chunks()
+        .iter()
+        .zip(chunk_offsets(png))
+        .filter_map(|(chunk, offset)| {
+            let (expires, _) = envelope::split_expiry(chunk.data());
+            let expires = expires.filter(|&expires| envelope::is_expired(expires, today))?;
+            Some(Finding {
+                rule_id: "expired-payload",
+                rule_description: "embedded messages should be removed once they expire",
+                message: format!(
+                    "{} chunk at offset {} holds a message that expired on {}",
+                    chunk.chunk_type(),
+                    offset,
+                    expires
+                ),
+                offset: Some(offset),
+            })
+        })
+        .collect()
+}
+
+/// Ancillary chunks whose contents don't fit the image: sBIT, bKGD and tRNS values against
+/// the colour type and bit depth, hIST and tRNS lengths against the palette, and malformed
+/// extension chunks.
+fn ancillary_findings(png: &Png) -> Vec<Finding> {
+    let Ok(header) = ImageHeader::from_png(png) else {
+        return Vec::new();
+    };
+    let palette_len = background::palette_len(png);
+    png.chunks()
+        .iter()
+        .zip(chunk_offsets(png))
+        .filter_map(|(chunk, offset)| {
+            let (rule_id, rule_description, problem) = match *chunk.chunk_type() {
+                ChunkType::SBIT => (
+                    "significant-bits",
+                    "sBIT must give 1 to the sample depth bits for each channel",
+                    SignificantBits::new(&header, chunk.data().to_vec()).err(),
+                ),
+                ChunkType::HIST => (
+                    "histogram-length",
+                    "hIST must have one entry for each palette entry",
+                    Histogram::from_bytes(chunk.data(), palette_len).err(),
+                ),
+                ChunkType::BKGD => (
+                    "background-color",
+                    "bKGD must be a colour the image could contain",
+                    match Color::from_bytes(chunk.data(), header.color_type) {
+                        Ok(color) => color.check(&header, palette_len).err(),
+                        Err(()) => Some(format!(
+                            "bKGD is {} bytes, the wrong size for the colour type",
+                            chunk.length()
+                        )),
+                    },
+                ),
+                ChunkType::TRNS => (
+                    "transparency",
+                    "tRNS must suit the colour type, bit depth and palette",
+                    transparency_problem(&header, chunk.data(), palette_len),
+                ),
+                ChunkType::OFFS | ChunkType::SCAL | ChunkType::PCAL => (
+                    "extension-chunk",
+                    "oFFs, sCAL and pCAL must follow the layouts in the PNG extensions",
+                    match *chunk.chunk_type() {
+                        ChunkType::OFFS => ImageOffset::from_bytes(chunk.data()).err(),
+                        ChunkType::SCAL => PhysicalScale::from_bytes(chunk.data()).err(),
+                        _ => PixelCalibration::from_bytes(chunk.data()).err(),
+                    },
+                ),
+                _ => return None,
+            };
+            Some(Finding {
+                rule_id,
+                rule_description,
+                message: format!(
+                    "{} chunk at offset {}: {}",
+                    chunk.chunk_type(),
+                    offset,
+                    problem?
+                ),
+                offset: Some(offset),
+            })
+        })
+        .collect()
+}
+
+fn transparency_problem(header: &ImageHeader, data: &[u8], palette_len: usize) -> Option<String> {
+    match header.color_type {
+        ColorType::GrayscaleAlpha | ColorType::Rgba => {
+            Some("images with an alpha channel cannot have tRNS".into())
+        }
+        ColorType::Indexed if data.len() > palette_len => Some(format!(
+            "tRNS has {} entries but PLTE has {}",
+            data.len(),
+            palette_len
+        )),
+        ColorType::Indexed => None,
+        ColorType::Grayscale | ColorType::Rgb => match Color::from_bytes(data, header.color_type) {
+            Ok(color) => color.check(header, palette_len).err(),
+            Err(()) => Some(format!(
+                "tRNS is {} bytes, the wrong size for the colour type",
+                data.len()
+            )),
+        },
+    }
+}
+
+/// Other files appended to `bytes` or readable from inside it.
+fn polyglot_findings(bytes: &[u8]) -> Vec<Finding> {
+    polyglot::detect(bytes)
+        .into_iter()
+        .map(|detection| {
+            let (rule_id, rule_description) = match detection.kind {
+                Kind::Png => (
+                    "concatenated-png",
+                    "a PNG file should not have another appended to it",
+                ),
+                Kind::Unknown => ("data-after-iend", "nothing should follow the IEND chunk"),
+                Kind::Zip | Kind::Pdf => (
+                    "polyglot",
+                    "a PNG file should not also be readable as another format",
+                ),
+            };
+            Finding {
+                rule_id,
+                rule_description,
+                message: detection.to_string(),
+                offset: Some(detection.offset as u64),
+            }
+        })
+        .collect()
+}
+
+/// Extended attributes that `pngme encode --method xattr` writes are named this followed by
+/// the chunk type, e.g. `pngme.ruSt`.
+pub const PAYLOAD_ATTRIBUTE_PREFIX: &str = "pngme.";
+
+/// Warnings about a file's extended attributes, given as names and value sizes: macOS
+/// metadata such as `com.apple.metadata:kMDItemWhereFroms`, which can record where the
+/// image was downloaded from, and messages hidden with `--method xattr`. Neither is part of
+/// the PNG, but both travel with the file when it is copied on macOS or archived with them.
+pub fn attribute_findings(attributes: &[(String, usize)]) -> Vec<Finding> {
+    attributes
+        .iter()
+        .filter_map(|(name, size)| {
+            let (rule_id, rule_description, what) = if name.starts_with("com.apple.") {
+                (
+                    "apple-metadata",
+                    "macOS metadata should not accompany a published image",
+                    "macOS metadata",
+                )
+            } else if name.starts_with(PAYLOAD_ATTRIBUTE_PREFIX) {
+                (
+                    "attribute-payload",
+                    "messages should not be hidden in extended attributes",
+                    "a pngme message",
+                )
+            } else {
+                return None;
+            };
+            Some(Finding {
+                rule_id,
+                rule_description,
+                message: format!(
+                    "extended attribute {} ({} bytes) holds {}",
+                    name, size, what
+                ),
+                offset: None,
+            })
+        })
+        .collect()
+}
+
+/// Non-fatal problems noticed while a file is parsed, collected by
+/// [`Png::parse_with_diagnostics`] rather than failing it: unknown critical chunks,
+/// deprecated chunks, chunks out of order and a missing IEND, and in permissive mode the
+/// damage it tolerates.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    findings: Vec<Finding>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    pub fn into_findings(self) -> Vec<Finding> {
+        self.findings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl Extend<Finding> for Diagnostics {
+    fn extend<I: IntoIterator<Item = Finding>>(&mut self, findings: I) {
+        self.findings.extend(findings);
+    }
+}
+
+/// What parsing notes about a chunk of `chunk_type` read at `offset`. Unknown ancillary
+/// chunks, pngme's own among them, are ordinary; an unknown critical chunk makes decoders
+/// reject the file.
+pub(crate) fn chunk_finding(chunk_type: &ChunkType, offset: u64) -> Option<Finding> {
+    let (rule_id, rule_description, problem) = if *chunk_type == ChunkType::GIFT {
+        (
+            "deprecated-chunk",
+            "deprecated chunks should not be written",
+            "is deprecated",
+        )
+    } else if chunk_type.is_critical() && chunk_type.known().is_none() {
+        (
+            "unknown-critical-chunk",
+            "decoders must reject a file with a critical chunk they don't know",
+            "is an unknown critical chunk",
+        )
+    } else {
+        return None;
+    };
+    Some(Finding {
+        rule_id,
+        rule_description,
+        message: format!("{} chunk at offset {} {}", chunk_type, offset, problem),
+        offset: Some(offset),
+    })
+}
+
+pub(crate) fn ordering_findings(png: &Png) -> Vec<Finding> {
+    check_ordering(png)
+        .iter()
+        .map(|violation| Finding {
+            rule_id: violation.rule.name(),
+            rule_description: violation.rule.description(),
+            message: violation.to_string(),
+            offset: Some(violation.offset),
+        })
+        .collect()
+}
+
+/// Everything `pngme check` warns about: ordering violations, duplicate chunks, ancillary
+/// chunks that don't fit the image, expired messages, appended or polyglot files and a C2PA manifest that no longer matches `bytes`, the
+/// file `png` was parsed from.
+pub fn findings(png: &Png, bytes: &[u8]) -> Vec<Finding> {
+    let mut findings = ordering_findings(png);
+    findings.extend(content_findings(png, bytes));
+    findings
+}
+
+/// [`findings`] less the ordering violations, which [`Diagnostics`] already has for a file
+/// parsed with them.
+pub fn content_findings(png: &Png, bytes: &[u8]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = find_duplicates(png)
+        .iter()
+        .map(|duplicate| Finding {
+            rule_id: "duplicate-chunk",
+            rule_description: "ancillary chunks should not repeat an earlier chunk exactly",
+            message: duplicate.to_string(),
+            offset: Some(duplicate.offset),
+        })
+        .collect();
+    findings.extend(ancillary_findings(png));
+    findings.extend(
+        suggested_palette::problems(png)
+            .into_iter()
+            .map(|message| Finding {
+                rule_id: "suggested-palette",
+                rule_description: "sPLT chunks must be well formed and have unique names",
+                message,
+                offset: None,
+            }),
+    );
+    findings.extend(expired_payloads(png, Date::today()));
+    findings.extend(polyglot_findings(bytes));
+    if let Some(store) = c2pa::manifest_store(png) {
+        let message = match c2pa::check_binding(bytes, store) {
+            BindingStatus::Valid => None,
+            BindingStatus::Invalid => Some(String::from(
+                "C2PA manifest hash does not match; the file was modified after signing",
+            )),
+            BindingStatus::Unverifiable(reason) => {
+                Some(format!("C2PA manifest could not be verified: {}", reason))
+            }
+        };
+        findings.extend(message.map(|message| Finding {
+            rule_id: "c2pa-binding",
+            rule_description: C2PA_DESCRIPTION,
+            message,
+            offset: None,
+        }));
+    }
+    findings
+}
+
+/// The messages of `findings`, one per problem.
+pub fn problems(png: &Png, bytes: &[u8]) -> Vec<String> {
+    findings(png, bytes)
+        .into_iter()
+        .map(|finding| finding.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::testing::png_with_header;
+    use std::str::FromStr;
+
+    fn png_of(types: &[&str]) -> Png {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), vec![0; 4]))
+                .collect(),
+        )
+    }
+
+    fn rules(png: &Png) -> Vec<(Rule, usize)> {
+        check_ordering(png)
+            .into_iter()
+            .map(|violation| (violation.rule, violation.chunk_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let bytes = png_of(&["IHDR", "ABCD", "IDAT", "gIFt", "gAMA", "IEND"]).as_bytes();
+        let mut diagnostics = Diagnostics::new();
+        Png::parse_with_diagnostics(&bytes, &Default::default(), &mut diagnostics).unwrap();
+        let found: Vec<(&str, Option<u64>)> = diagnostics
+            .findings()
+            .iter()
+            .map(|finding| (finding.rule_id, finding.offset))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("unknown-critical-chunk", Some(24)),
+                ("deprecated-chunk", Some(56)),
+                ("before-idat", Some(72)),
+            ]
+        );
+        let png = Png::try_from(&bytes[..]).unwrap();
+        assert_eq!(
+            findings(&png, &bytes).len(),
+            1 + content_findings(&png, &bytes).len()
+        );
+    }
+
+    #[test]
+    fn test_valid_ordering() {
+        let png = png_of(&[
+            "IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND",
+        ]);
+        assert!(check_ordering(&png).is_empty());
+    }
+
+    #[test]
+    fn test_critical_chunk_rules() {
+        let png = png_of(&["gAMA", "IHDR", "IDAT", "PLTE", "IEND", "IHDR"]);
+        assert_eq!(
+            rules(&png),
+            [
+                (Rule::IhdrFirst, 0),
+                (Rule::PlteBeforeIdat, 3),
+                (Rule::IendLast, 4),
+                (Rule::SingleCritical, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ancillary_rules() {
+        let png = png_of(&[
+            "IHDR", "tRNS", "PLTE", "sRGB", "IDAT", "tIME", "IDAT", "pHYs", "IEND",
+        ]);
+        assert_eq!(
+            rules(&png),
+            [
+                (Rule::AfterPlte, 1),
+                (Rule::BeforePlte, 3),
+                (Rule::ContiguousIdat, 5),
+                (Rule::BeforeIdat, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let text =
+            |payload: &[u8]| Chunk::new(ChunkType::from_str("tEXt").unwrap(), payload.to_vec());
+        let mut png = png_of(&["IHDR", "IDAT", "IDAT", "IEND"]);
+        for (index, payload) in [(1, b"a"), (2, b"b"), (3, b"a"), (4, b"a")] {
+            png.insert_chunk(index, text(payload));
+        }
+        let duplicates = find_duplicates(&png);
+        assert_eq!(
+            duplicates
+                .iter()
+                .map(|d| (d.chunk_index, d.original_index))
+                .collect::<Vec<_>>(),
+            [(3, 1), (4, 1)]
+        );
+        assert_eq!(remove_duplicates(&mut png), 2);
+        assert!(find_duplicates(&png).is_empty());
+        assert_eq!(png.chunks().len(), 6);
+    }
+
+    #[test]
+    fn test_offsets() {
+        let png = png_of(&["IHDR", "IDAT", "IEND"]);
+        assert_eq!(chunk_offsets(&png), [8, 24, 40]);
+        let violation = &check_ordering(&png_of(&["IDAT", "IHDR"]))[0];
+        assert_eq!(violation.offset, 8);
+        assert_eq!(
+            violation.to_string(),
+            "ihdr-first: IDAT chunk at offset 8: IHDR must be the first chunk"
+        );
+    }
+
+    #[test]
+    fn test_expired_payloads() {
+        let mut png = png_of(&["IHDR", "IDAT", "IEND"]);
+        for (index, expires) in [(1, "2025-12-31"), (3, "2026-06-30")] {
+            let payload = envelope::with_expiry(expires.parse().unwrap(), b"drop");
+            png.insert_chunk(
+                index,
+                Chunk::new(ChunkType::from_str("ruSt").unwrap(), payload.to_vec()),
+            );
+        }
+        let findings = expired_payloads(&png, "2026-01-01".parse().unwrap());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule_id, "expired-payload");
+        assert_eq!(
+            findings[0].message,
+            "ruSt chunk at offset 24 holds a message that expired on 2025-12-31"
+        );
+        assert!(expired_payloads(&png, "2025-12-31".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_ancillary_findings() {
+        let mut png = png_with_header(ColorType::Indexed, 2, 3);
+        assert!(ancillary_findings(&png).is_empty());
+        png.insert_ancillary(Histogram::new(vec![1, 2, 3], 3).unwrap().to_chunk());
+        png.insert_ancillary(Chunk::new(ChunkType::TRNS, vec![0; 3]));
+        png.insert_ancillary(Chunk::new(ChunkType::SBIT, vec![8, 8, 8]));
+        assert!(ancillary_findings(&png).is_empty());
+
+        png.insert_ancillary(Chunk::new(ChunkType::BKGD, vec![3]));
+        png.insert_ancillary(Chunk::new(ChunkType::SBIT, vec![8, 9]));
+        let palette = png
+            .chunks()
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == ChunkType::PLTE)
+            .unwrap();
+        png.replace_chunk(palette, Chunk::new(ChunkType::PLTE, vec![0; 6]));
+        let findings = ancillary_findings(&png);
+        let rules: Vec<&str> = findings.iter().map(|finding| finding.rule_id).collect();
+        assert_eq!(
+            rules,
+            [
+                "significant-bits",
+                "histogram-length",
+                "transparency",
+                "background-color"
+            ]
+        );
+        png.insert_ancillary(Chunk::new(ChunkType::SCAL, b"\x01-1\x001".to_vec()));
+        assert_eq!(ancillary_findings(&png)[4].rule_id, "extension-chunk");
+        // After the signature, IHDR, both sBIT chunks and the shortened PLTE.
+        assert_eq!(
+            findings[1].message,
+            "hIST chunk at offset 80: hIST has 3 entries but PLTE has 2"
+        );
+    }
+
+    #[test]
+    fn test_attribute_findings() {
+        let attributes = [
+            ("com.apple.quarantine".to_string(), 57),
+            ("security.selinux".to_string(), 30),
+            ("pngme.ruSt".to_string(), 5),
+        ];
+        let findings = attribute_findings(&attributes);
+        let rules: Vec<&str> = findings.iter().map(|finding| finding.rule_id).collect();
+        assert_eq!(rules, ["apple-metadata", "attribute-payload"]);
+        assert_eq!(
+            findings[1].message,
+            "extended attribute pngme.ruSt (5 bytes) holds a pngme message"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use crate::chunk::{self, Chunk};
+use crate::chunk_type::ChunkType;
+use crate::image::{self, ColorType, ImageData, ImageHeader};
+use crate::png::Png;
+use crate::validate;
+
+/// Size of the payload in `large-chunk.png`. A chunk of `chunk::MAX_LENGTH` bytes would make a
+/// 2 GiB file, so that limit is covered by a header that claims it instead.
+const LARGE_CHUNK_LEN: usize = 1 << 20;
+
+/// What a conforming decoder should make of a test vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    /// Parses, decodes and breaks no ordering rule.
+    Valid,
+    /// Parses and decodes, but breaks a chunk ordering rule.
+    Misordered,
+    /// Must be rejected while parsing.
+    Malformed,
+}
+
+impl Display for Expect {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Expect::Valid => write!(fmt, "valid"),
+            Expect::Misordered => write!(fmt, "misordered"),
+            Expect::Malformed => write!(fmt, "malformed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Vector {
+    /// A file name, ending in `.png`.
+    pub name: String,
+    pub description: String,
+    pub expect: Expect,
+    pub bytes: Vec<u8>,
+}
+
+impl Vector {
+    fn new(name: &str, description: &str, expect: Expect, bytes: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            expect,
+            bytes,
+        }
+    }
+
+    /// Whether this crate treats the vector as `expect` says it should.
+    pub fn check(&self) -> Result<(), String> {
+        let parsed = Png::try_from(&self.bytes[..]);
+        let png = match (self.expect, parsed) {
+            (Expect::Malformed, Err(())) => return Ok(()),
+            (Expect::Malformed, Ok(_)) => return Err("parsed but should be rejected".into()),
+            (_, Err(())) => return Err("did not parse".into()),
+            (_, Ok(png)) => png,
+        };
+        if png.as_bytes() != self.bytes {
+            return Err("did not round-trip".into());
+        }
+        ImageData::from_png(&png).map_err(|()| "image data did not decode")?;
+        let violations = validate::check_ordering(&png);
+        match (self.expect, violations.is_empty()) {
+            (Expect::Valid, false) => Err(format!("unexpected violation: {}", violations[0])),
+            (Expect::Misordered, true) => Err("no ordering violation reported".into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+fn compress(raw: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(raw).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// A deterministic pattern that exercises every sample value the bit depth allows.
+fn sample(x: usize, y: usize, channel: usize, bit_depth: u8) -> u16 {
+    let max = (1u32 << bit_depth) - 1;
+    ((x * 37 + y * 91 + channel * 53) as u32 % (max + 1)) as u16
+}
+
+/// The zlib-compressed scanlines of a patterned image, laid out in Adam7 passes if the
+/// header says so.
+fn image_data(header: &ImageHeader) -> Vec<u8> {
+    let passes = if header.interlaced {
+        image::ADAM7.to_vec()
+    } else {
+        vec![(0, 0, 1, 1)]
+    };
+    let (width, height) = (header.width as usize, header.height as usize);
+    let channels = header.color_type.channels();
+    let mut raw = Vec::new();
+    for (x0, y0, dx, dy) in passes {
+        let xs: Vec<usize> = (x0..width).step_by(dx).collect();
+        if xs.is_empty() {
+            continue;
+        }
+        for y in (y0..height).step_by(dy) {
+            let samples: Vec<u16> = xs
+                .iter()
+                .flat_map(|&x| (0..channels).map(move |c| sample(x, y, c, header.bit_depth)))
+                .collect();
+            raw.push(0);
+            raw.extend(image::pack(&samples, header.bit_depth));
+        }
+    }
+    compress(&raw)
+}
+
+fn header(width: u32, height: u32, bit_depth: u8, color_type: ColorType) -> ImageHeader {
+    ImageHeader {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        interlaced: false,
+    }
+}
+
+/// IHDR, a palette for indexed images, then `extra` and the image data in one IDAT.
+fn image_chunks(header: &ImageHeader, extra: Vec<Chunk>) -> Vec<Chunk> {
+    let mut chunks = vec![Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec())];
+    if header.color_type == ColorType::Indexed {
+        let entries = 1usize << header.bit_depth;
+        let palette = (0..entries)
+            .flat_map(|i| [i as u8, (i * 7) as u8, 255 - i as u8])
+            .collect();
+        chunks.push(Chunk::new(ChunkType::PLTE, palette));
+    }
+    chunks.extend(extra);
+    chunks.push(Chunk::new(ChunkType::IDAT, image_data(header)));
+    chunks
+}
+
+fn file(mut chunks: Vec<Chunk>) -> Vec<u8> {
+    chunks.push(Chunk::new(ChunkType::IEND, vec![]));
+    Png::from_chunks(chunks).as_bytes()
+}
+
+fn color_types() -> Vec<Vector> {
+    let combinations = [
+        (ColorType::Grayscale, "gray", &[1, 2, 4, 8, 16][..]),
+        (ColorType::Rgb, "rgb", &[8, 16]),
+        (ColorType::Indexed, "indexed", &[1, 2, 4, 8]),
+        (ColorType::GrayscaleAlpha, "gray-alpha", &[8, 16]),
+        (ColorType::Rgba, "rgba", &[8, 16]),
+    ];
+    let mut vectors = Vec::new();
+    for (color_type, label, depths) in combinations {
+        for &bit_depth in depths {
+            let mut header = header(7, 5, bit_depth, color_type);
+            for interlaced in [false, true] {
+                header.interlaced = interlaced;
+                let suffix = if interlaced { "-interlaced" } else { "" };
+                vectors.push(Vector::new(
+                    &format!("color-{}-{}{}.png", label, bit_depth, suffix),
+                    &format!(
+                        "7x5 {} image, {}-bit samples{}",
+                        label,
+                        bit_depth,
+                        if interlaced { ", Adam7 interlaced" } else { "" }
+                    ),
+                    Expect::Valid,
+                    file(image_chunks(&header, vec![])),
+                ));
+            }
+        }
+    }
+    vectors
+}
+
+fn sizes() -> Vec<Vector> {
+    let gray = |width, height| header(width, height, 8, ColorType::Grayscale);
+    let mut interlaced = gray(1, 1);
+    interlaced.interlaced = true;
+    vec![
+        Vector::new(
+            "1x1.png",
+            "a single grayscale pixel",
+            Expect::Valid,
+            file(image_chunks(&gray(1, 1), vec![])),
+        ),
+        Vector::new(
+            "1x1-interlaced.png",
+            "a single pixel, interlaced, so six of the seven passes are empty",
+            Expect::Valid,
+            file(image_chunks(&interlaced, vec![])),
+        ),
+        Vector::new(
+            "wide.png",
+            "4096x1 grayscale",
+            Expect::Valid,
+            file(image_chunks(&gray(4096, 1), vec![])),
+        ),
+        Vector::new(
+            "tall.png",
+            "1x4096 grayscale",
+            Expect::Valid,
+            file(image_chunks(&gray(1, 4096), vec![])),
+        ),
+    ]
+}
+
+fn chunk_layouts() -> Vec<Vector> {
+    let header = header(4, 4, 8, ColorType::Rgb);
+    let data = image_data(&header);
+    let ihdr = || Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec());
+    let idat = |bytes: &[u8]| Chunk::new(ChunkType::IDAT, bytes.to_vec());
+
+    let mut split = vec![ihdr()];
+    split.extend(data.iter().map(|&byte| idat(&[byte])));
+
+    let mut with_empty = vec![ihdr(), idat(&[])];
+    let (first, second) = data.split_at(data.len() / 2);
+    with_empty.extend([idat(first), idat(&[]), idat(second), idat(&[])]);
+
+    vec![
+        Vector::new(
+            "zero-length-chunk.png",
+            "a private ancillary chunk with no payload",
+            Expect::Valid,
+            file(image_chunks(&header, vec![chunk("zeRo", vec![])])),
+        ),
+        Vector::new(
+            "zero-length-idat.png",
+            "the image data split around empty IDAT chunks",
+            Expect::Valid,
+            file(with_empty),
+        ),
+        Vector::new(
+            "one-byte-idat.png",
+            "the image data split into one IDAT chunk per byte",
+            Expect::Valid,
+            file(split),
+        ),
+        Vector::new(
+            "large-chunk.png",
+            "a private ancillary chunk with a 1 MiB payload",
+            Expect::Valid,
+            file(image_chunks(
+                &header,
+                vec![chunk("laRg", vec![0xa5; LARGE_CHUNK_LEN])],
+            )),
+        ),
+    ]
+}
+
+fn orderings() -> Vec<Vector> {
+    let rgb = header(4, 4, 8, ColorType::Rgb);
+    let indexed = header(4, 4, 2, ColorType::Indexed);
+    let ihdr = |header: &ImageHeader| Chunk::new(ChunkType::IHDR, header.to_bytes().to_vec());
+    let idat = |header: &ImageHeader| Chunk::new(ChunkType::IDAT, image_data(header));
+    let palette = || Chunk::new(ChunkType::PLTE, vec![0; 12]);
+    let (first, second) = {
+        let data = image_data(&rgb);
+        let (first, second) = data.split_at(data.len() / 2);
+        (first.to_vec(), second.to_vec())
+    };
+    vec![
+        Vector::new(
+            "ihdr-not-first.png",
+            "a tEXt chunk ahead of IHDR",
+            Expect::Misordered,
+            file(vec![
+                chunk("tEXt", b"Comment\0early".to_vec()),
+                ihdr(&rgb),
+                idat(&rgb),
+            ]),
+        ),
+        Vector::new(
+            "plte-after-idat.png",
+            "an indexed image whose palette follows the image data",
+            Expect::Misordered,
+            file(vec![ihdr(&indexed), idat(&indexed), palette()]),
+        ),
+        Vector::new(
+            "gama-after-plte.png",
+            "a gAMA chunk after the palette it must precede",
+            Expect::Misordered,
+            file(vec![
+                ihdr(&indexed),
+                palette(),
+                chunk("gAMA", 45455u32.to_be_bytes().to_vec()),
+                idat(&indexed),
+            ]),
+        ),
+        Vector::new(
+            "split-idat.png",
+            "a tEXt chunk between two IDAT chunks",
+            Expect::Misordered,
+            file(vec![
+                ihdr(&rgb),
+                Chunk::new(ChunkType::IDAT, first),
+                chunk("tEXt", b"Comment\0between".to_vec()),
+                Chunk::new(ChunkType::IDAT, second),
+            ]),
+        ),
+        Vector::new(
+            "ancillary-after-idat.png",
+            "a pHYs chunk after the image data it must precede",
+            Expect::Misordered,
+            file(vec![
+                ihdr(&rgb),
+                idat(&rgb),
+                chunk("pHYs", vec![0, 0, 0x0b, 0x13, 0, 0, 0x0b, 0x13, 1]),
+            ]),
+        ),
+    ]
+}
+
+fn malformed() -> Vec<Vector> {
+    let valid = file(image_chunks(&header(2, 2, 8, ColorType::Grayscale), vec![]));
+    let ihdr_crc = 8 + 8 + 13;
+
+    let mut bad_crc = valid.clone();
+    bad_crc[ihdr_crc] ^= 0xff;
+    let mut bad_signature = valid.clone();
+    bad_signature[1] = b'Q';
+    let truncated = valid[..valid.len() - 6].to_vec();
+    let mut bad_type = valid.clone();
+    bad_type[12 + 1] = b'4';
+
+    let claims_length = |length: u32| {
+        let mut bytes = valid[..valid.len() - 12].to_vec();
+        bytes.extend_from_slice(&length.to_be_bytes());
+        bytes.extend_from_slice(b"laRg");
+        bytes
+    };
+    vec![
+        Vector::new(
+            "bad-crc.png",
+            "the IHDR CRC is wrong",
+            Expect::Malformed,
+            bad_crc,
+        ),
+        Vector::new(
+            "bad-signature.png",
+            "the file signature is wrong",
+            Expect::Malformed,
+            bad_signature,
+        ),
+        Vector::new(
+            "truncated.png",
+            "the file ends part-way through IEND",
+            Expect::Malformed,
+            truncated,
+        ),
+        Vector::new(
+            "bad-chunk-type.png",
+            "a chunk type containing a digit",
+            Expect::Malformed,
+            bad_type,
+        ),
+        Vector::new(
+            "max-length-chunk.png",
+            "a chunk claiming the largest legal length, 2^31 - 1 bytes, with none present",
+            Expect::Malformed,
+            claims_length(chunk::MAX_LENGTH),
+        ),
+        Vector::new(
+            "over-max-length-chunk.png",
+            "a chunk claiming 2^31 bytes, one more than the format allows",
+            Expect::Malformed,
+            claims_length(chunk::MAX_LENGTH + 1),
+        ),
+    ]
+}
+
+/// Edge-case files for testing decoders, this crate's included.
+pub fn corpus() -> Vec<Vector> {
+    let mut vectors = color_types();
+    vectors.extend(sizes());
+    vectors.extend(chunk_layouts());
+    vectors.extend(orderings());
+    vectors.extend(malformed());
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corpus_names() {
+        let corpus = corpus();
+        let mut names: Vec<&str> = corpus.iter().map(|vector| vector.name.as_str()).collect();
+        assert!(names.iter().all(|name| name.ends_with(".png")));
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), corpus.len());
+    }
+
+    #[test]
+    fn test_check_catches_mislabelled_vectors() {
+        let mut vector = corpus().remove(0);
+        assert_eq!(vector.check(), Ok(()));
+        vector.expect = Expect::Malformed;
+        assert!(vector.check().is_err());
+        vector.expect = Expect::Misordered;
+        assert!(vector.check().is_err());
+    }
+}
@@ -0,0 +1,551 @@
+//! Watermarks, for `pngme watermark`: visible text drawn in a 5x7 bitmap font, or an
+//! invisible mark keyed by the same text.
+//!
+//! The invisible mark raises or lowers every 8x8 block of pixels by a few levels, in a
+//! pattern only the text reproduces. [`detect`] correlates the pattern with each block's
+//! difference from its neighbours, which smooth image content keeps close to zero, so the
+//! mark survives noise, small edits and re-encoding that leave the blocks' averages about
+//! where they were. Without the mark the score is roughly normally distributed around zero.
+//!
+//! The robust mark, from [`add_robust`], is built the same way for harsher treatment. Its
+//! cells are laid out as a fixed grid over the image whatever its size, so scaling leaves
+//! them where they were, and are large enough that JPEG's quantisation averages out. They
+//! carry a 64-bit payload derived from the text, each bit repeated in many cells, and
+//! [`detect_robust`] reads it back as well as scoring the whole: a mark is only found if
+//! nearly every bit agrees. Neither mark is hidden data in the sense of `pngme encode`,
+//! whose LSB embedding is gone after any lossy step; these carry no message, only evidence
+//! that a given text was stamped on the image.
+
+use std::ops::Range;
+
+use sha2::{Digest, Sha256};
+
+use crate::carrier::Rng;
+use crate::image::{ColorType, ImageData};
+use crate::steganalysis;
+
+/// Pixels along each side of the blocks the invisible mark raises or lowers.
+const BLOCK: usize = 8;
+
+/// Cells across and down the robust mark's grid, whatever the size of the image.
+const CELLS: usize = 32;
+
+/// Bits in the robust mark's payload, each carried by `CELLS * CELLS / PAYLOAD_BITS` cells.
+pub const PAYLOAD_BITS: usize = 64;
+
+/// Payload bits that may read back wrong in a robust mark that is found; 64 random bits
+/// match the payload this well about once in 10^10 tries.
+const MAX_BIT_ERRORS: usize = 8;
+
+/// The smallest width and height the robust mark is added to or read from, which gives its
+/// cells 4 pixels a side.
+const MIN_ROBUST: u32 = 4 * CELLS as u32;
+
+/// The [`detect`] score above which a mark is taken to be present; images without one
+/// score over this about once in 30000.
+pub const THRESHOLD: f64 = 4.0;
+
+/// Printable ASCII from space to `~`, five columns of seven bits each, the top row in the
+/// lowest bit.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5f, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14],
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00],
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08],
+    [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3e, 0x51, 0x49, 0x45, 0x3e],
+    [0x00, 0x42, 0x7f, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4b, 0x31],
+    [0x18, 0x14, 0x12, 0x7f, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3c, 0x4a, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1e],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3e],
+    [0x7e, 0x11, 0x11, 0x11, 0x7e],
+    [0x7f, 0x49, 0x49, 0x49, 0x36],
+    [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x22, 0x1c],
+    [0x7f, 0x49, 0x49, 0x49, 0x41],
+    [0x7f, 0x09, 0x09, 0x01, 0x01],
+    [0x3e, 0x41, 0x41, 0x51, 0x32],
+    [0x7f, 0x08, 0x08, 0x08, 0x7f],
+    [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01],
+    [0x7f, 0x08, 0x14, 0x22, 0x41],
+    [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x04, 0x02, 0x7f],
+    [0x7f, 0x04, 0x08, 0x10, 0x7f],
+    [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06],
+    [0x3e, 0x41, 0x51, 0x21, 0x5e],
+    [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7f, 0x01, 0x01],
+    [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f],
+    [0x7f, 0x20, 0x18, 0x20, 0x7f],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7f, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7f, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7f, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7f],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7e, 0x09, 0x01, 0x02],
+    [0x08, 0x14, 0x54, 0x54, 0x3c],
+    [0x7f, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7d, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3d, 0x00],
+    [0x00, 0x7f, 0x10, 0x28, 0x44],
+    [0x00, 0x41, 0x7f, 0x40, 0x00],
+    [0x7c, 0x04, 0x18, 0x04, 0x78],
+    [0x7c, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7c, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7c],
+    [0x7c, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3f, 0x44, 0x40, 0x20],
+    [0x3c, 0x40, 0x40, 0x20, 0x7c],
+    [0x1c, 0x20, 0x40, 0x20, 0x1c],
+    [0x3c, 0x40, 0x30, 0x40, 0x3c],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0c, 0x50, 0x50, 0x50, 0x3c],
+    [0x44, 0x64, 0x54, 0x4c, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7f, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02],
+];
+
+/// The copyright sign, which the font would otherwise lack.
+const COPYRIGHT: [u8; 5] = [0x3e, 0x5d, 0x55, 0x55, 0x3e];
+
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '©' => COPYRIGHT,
+        ' '..='~' => FONT[c as usize - ' ' as usize],
+        _ => FONT['?' as usize - ' ' as usize],
+    }
+}
+
+/// The colour samples of each pixel, leaving out alpha.
+fn color_channels(image: &ImageData) -> Result<usize, String> {
+    match image.header().color_type {
+        ColorType::Indexed => Err(String::from(
+            "watermarks need greyscale or truecolour images; convert palette images first",
+        )),
+        ColorType::Grayscale | ColorType::GrayscaleAlpha => Ok(1),
+        ColorType::Rgb | ColorType::Rgba => Ok(3),
+    }
+}
+
+/// Draws `text` in the bottom right corner, a pixel of the font being as many of the
+/// image's as fits the text in about two fifths of its width, in white over a dark shadow
+/// at `opacity` from 0 to 1.
+pub fn add_visible(image: &mut ImageData, text: &str, opacity: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("opacity must be from 0 to 1, not {}", opacity));
+    }
+    let color_channels = color_channels(image)?;
+    let glyphs: Vec<[u8; 5]> = text.chars().map(glyph).collect();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // Each glyph is 6 columns with its spacing, and 8 rows with the shadow.
+    let text_columns = (glyphs.len() * 6).max(1);
+    let scale = (width * 2 / 5 / text_columns).min(height / 10 / 8).max(1);
+    let margin = scale * 2;
+    let (text_width, text_height) = (text_columns * scale, 7 * scale);
+    if text_width + margin > width || text_height + margin > height {
+        return Err(format!("the image is too small for `{}`", text));
+    }
+    let (left, top) = (width - margin - text_width, height - margin - text_height);
+    let max = ((1u32 << image.header().bit_depth) - 1) as f64;
+    let channels = image.channels();
+    let samples = image.samples_mut();
+    let mut blend = |x: usize, y: usize, value: f64| {
+        if x >= width || y >= height {
+            return;
+        }
+        let pixel = (y * width + x) * channels;
+        for sample in &mut samples[pixel..pixel + color_channels] {
+            *sample = (*sample as f64 * (1.0 - opacity) + value * opacity).round() as u16;
+        }
+    };
+    let lit = |column: usize, row: usize| {
+        let (glyph, column) = (column / 6, column % 6);
+        column < 5 && glyphs[glyph][column] >> row & 1 == 1
+    };
+    // The shadow first, one font pixel down and to the right, then the text over it.
+    for (offset, value) in [(scale, 0.0), (0, max)] {
+        for y in 0..text_height {
+            for x in 0..text_width {
+                if lit(x / scale, y / scale) {
+                    blend(left + x + offset, top + y + offset, value);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The cells a mark shifts, as ranges of columns and rows of pixels.
+struct Grid {
+    columns: Vec<Range<usize>>,
+    rows: Vec<Range<usize>>,
+}
+
+impl Grid {
+    /// Aligned `BLOCK`-pixel squares, leaving out any partial ones at the edges.
+    fn blocks(image: &ImageData) -> Self {
+        let ranges = |size: u32| (0..size as usize / BLOCK).map(|i| i * BLOCK..(i + 1) * BLOCK);
+        Self {
+            columns: ranges(image.width()).collect(),
+            rows: ranges(image.height()).collect(),
+        }
+    }
+
+    /// `CELLS` cells across and down, whatever the size of the image, so that scaling it
+    /// moves their edges by less than a pixel. With `inset`, each cell loses a quarter of
+    /// its width and height at each edge, for reading a mark that may have shifted a little.
+    fn proportional(image: &ImageData, inset: bool) -> Self {
+        let ranges = |size: u32| {
+            let size = size as usize;
+            (0..CELLS).map(move |i| {
+                let (start, end) = (i * size / CELLS, (i + 1) * size / CELLS);
+                match inset {
+                    true => start + (end - start) / 4..end - (end - start) / 4,
+                    false => start..end,
+                }
+            })
+        };
+        Self {
+            columns: ranges(image.width()).collect(),
+            rows: ranges(image.height()).collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.columns.len() * self.rows.len()
+    }
+
+    /// The pixel ranges of each cell, in order across then down.
+    fn cells(&self) -> impl Iterator<Item = (&Range<usize>, &Range<usize>)> {
+        self.rows
+            .iter()
+            .flat_map(|rows| self.columns.iter().map(move |columns| (columns, rows)))
+    }
+
+    /// Raises the colour samples of each cell by `levels` of 255, or lowers them where the
+    /// cell's sign is false.
+    fn shift(&self, image: &mut ImageData, signs: &[bool], levels: f64) -> Result<(), String> {
+        let color_channels = color_channels(image)?;
+        let max = ((1u32 << image.header().bit_depth) - 1) as f64;
+        let delta = levels * max / 255.0;
+        let (width, channels) = (image.width() as usize, image.channels());
+        let samples = image.samples_mut();
+        for ((columns, rows), &raise) in self.cells().zip(signs) {
+            let delta = if raise { delta } else { -delta };
+            for y in rows.clone() {
+                for x in columns.clone() {
+                    let pixel = (y * width + x) * channels;
+                    for sample in &mut samples[pixel..pixel + color_channels] {
+                        *sample = (*sample as f64 + delta).round().clamp(0.0, max) as u16;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// How much brighter each cell is than the average of its neighbours, in levels of 255:
+    /// close to zero for smooth image content, and the shift where a mark was added.
+    fn residuals(&self, image: &ImageData) -> Result<Vec<f64>, String> {
+        let color_channels = color_channels(image)?;
+        let scale = 255.0 / ((1u32 << image.header().bit_depth) - 1) as f64;
+        let (width, channels) = (image.width() as usize, image.channels());
+        let samples = image.samples();
+        let means: Vec<f64> = self
+            .cells()
+            .map(|(columns, rows)| {
+                let mut total = 0.0;
+                for y in rows.clone() {
+                    let row = &samples[(y * width + columns.start) * channels..]
+                        [..columns.len() * channels];
+                    for pixel in row.chunks(channels) {
+                        total += pixel[..color_channels]
+                            .iter()
+                            .map(|&sample| sample as f64)
+                            .sum::<f64>();
+                    }
+                }
+                total * scale / (columns.len() * rows.len() * color_channels).max(1) as f64
+            })
+            .collect();
+        let (across, down) = (self.columns.len(), self.rows.len());
+        Ok((0..means.len())
+            .map(|cell| {
+                let (x, y) = (cell % across, cell / across);
+                let neighbours: Vec<f64> = [
+                    (x > 0).then(|| means[cell - 1]),
+                    (x + 1 < across).then(|| means[cell + 1]),
+                    (y > 0).then(|| means[cell - across]),
+                    (y + 1 < down).then(|| means[cell + across]),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                means[cell] - neighbours.iter().sum::<f64>() / neighbours.len() as f64
+            })
+            .collect())
+    }
+}
+
+/// How many standard deviations the residuals lean towards `signs`, which is roughly
+/// normally distributed around zero when they are unrelated.
+fn correlation(residuals: &[f64], signs: &[bool]) -> f64 {
+    let (mut correlation, mut energy) = (0.0, 0.0);
+    for (&residual, &raise) in residuals.iter().zip(signs) {
+        correlation += if raise { residual } else { -residual };
+        energy += residual * residual;
+    }
+    match energy {
+        0.0 => 0.0,
+        _ => correlation / energy.sqrt(),
+    }
+}
+
+fn rng(key: &str) -> Rng {
+    let digest = Sha256::digest(key);
+    Rng(u64::from_be_bytes(digest[..8].try_into().unwrap()))
+}
+
+/// Whether each of `cells` cells, in order, is raised or lowered by the mark for `text`.
+fn pattern(text: &str, cells: usize) -> Vec<bool> {
+    let mut rng = rng(&format!("pngme watermark\0{}", text));
+    (0..cells).map(|_| rng.next() & 1 == 1).collect()
+}
+
+/// Adds the invisible mark for `text`, raising or lowering each block by `strength` levels
+/// of 255 in every colour sample.
+pub fn add_invisible(image: &mut ImageData, text: &str, strength: f64) -> Result<(), String> {
+    let grid = Grid::blocks(image);
+    if grid.columns.len() < 2 || grid.rows.len() < 2 {
+        return Err(String::from("the image is too small to mark"));
+    }
+    grid.shift(image, &pattern(text, grid.len()), strength)
+}
+
+/// How strongly `image` carries the invisible mark for `text`, in standard deviations:
+/// above [`THRESHOLD`] means it does.
+pub fn detect(image: &ImageData, text: &str) -> Result<f64, String> {
+    let grid = Grid::blocks(image);
+    if grid.columns.len() < 2 || grid.rows.len() < 2 {
+        return Err(String::from("the image is too small to carry a mark"));
+    }
+    let residuals = grid.residuals(image)?;
+    Ok(correlation(&residuals, &pattern(text, grid.len())))
+}
+
+/// The robust mark's 64-bit payload for `text`, the start of a digest of it.
+fn payload(text: &str) -> [bool; PAYLOAD_BITS] {
+    let digest = Sha256::digest(format!("pngme robust watermark\0{}", text));
+    std::array::from_fn(|bit| digest[bit / 8] >> (7 - bit % 8) & 1 == 1)
+}
+
+/// Which payload bit each cell of the robust grid carries and the sign it is spread with,
+/// the same for every image so that the payload alone tells marks apart. Each bit gets the
+/// same number of cells, scattered over the image by a shuffle.
+fn spreading() -> Vec<(usize, bool)> {
+    let mut rng = rng("pngme robust watermark");
+    let mut cells: Vec<(usize, bool)> = (0..CELLS * CELLS)
+        .map(|cell| (cell % PAYLOAD_BITS, rng.next() & 1 == 1))
+        .collect();
+    for i in (1..cells.len()).rev() {
+        cells.swap(i, rng.below(i + 1));
+    }
+    cells
+}
+
+/// What [`detect_robust`] found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Standard deviations by which the image leans towards the mark, as from [`detect`].
+    pub score: f64,
+    /// The lesser of the probabilities that an image without the mark would score lower and
+    /// that a random payload would match fewer bits, from 0 to 1.
+    pub confidence: f64,
+    /// How many of the [`PAYLOAD_BITS`] payload bits read back as written.
+    pub bits: usize,
+}
+
+impl Detection {
+    /// Whether the score clears [`THRESHOLD`] and the payload reads back with no more than a
+    /// few bits wrong. Both are needed: a strong mark for one text also raises the score for
+    /// another, by as much as their payloads happen to share bits.
+    pub fn found(&self) -> bool {
+        self.score > THRESHOLD && self.bits >= PAYLOAD_BITS - MAX_BIT_ERRORS
+    }
+}
+
+/// Adds the robust mark for `text`: each bit of its payload is spread over
+/// `CELLS * CELLS / PAYLOAD_BITS` cells scattered over the image, each raised or lowered by
+/// `strength` levels of 255. The cells are large and stay put relative to the image, so the
+/// mark survives lossy re-encoding, which keeps their averages, and mild scaling, which
+/// keeps their layout.
+pub fn add_robust(image: &mut ImageData, text: &str, strength: f64) -> Result<(), String> {
+    if image.width() < MIN_ROBUST || image.height() < MIN_ROBUST {
+        return Err(format!(
+            "a robust mark needs an image of at least {}x{}",
+            MIN_ROBUST, MIN_ROBUST
+        ));
+    }
+    let payload = payload(text);
+    let signs: Vec<bool> = spreading()
+        .into_iter()
+        .map(|(bit, chip)| payload[bit] == chip)
+        .collect();
+    Grid::proportional(image, false).shift(image, &signs, strength)
+}
+
+/// Looks for the robust mark for `text`, reading the middle of each cell so that scaling
+/// and cropping by a pixel or two don't blur neighbouring cells together.
+pub fn detect_robust(image: &ImageData, text: &str) -> Result<Detection, String> {
+    if image.width() < MIN_ROBUST || image.height() < MIN_ROBUST {
+        return Err(format!(
+            "a robust mark needs an image of at least {}x{}",
+            MIN_ROBUST, MIN_ROBUST
+        ));
+    }
+    let residuals = Grid::proportional(image, true).residuals(image)?;
+    let payload = payload(text);
+    let spreading = spreading();
+    let signs: Vec<bool> = spreading
+        .iter()
+        .map(|&(bit, chip)| payload[bit] == chip)
+        .collect();
+    // Each bit is read by adding up the evidence of all its cells, so that a few cells
+    // spoiled by editing are outvoted by the rest.
+    let mut votes = [0.0; PAYLOAD_BITS];
+    for (&residual, &(bit, chip)) in residuals.iter().zip(&spreading) {
+        votes[bit] += if chip { residual } else { -residual };
+    }
+    let bits = votes
+        .iter()
+        .zip(payload)
+        .filter(|&(&vote, bit)| (vote > 0.0) == bit)
+        .count();
+    let score = correlation(&residuals, &signs);
+    // The upper tail of the standard normal distribution, by way of the chi-square one.
+    let tail = 0.5 * (1.0 - steganalysis::regularized_gamma(0.5, score * score / 2.0));
+    let by_score = match score > 0.0 {
+        true => 1.0 - tail,
+        false => tail,
+    };
+    // The binomial distribution of the bits a random payload matches, each with a half chance.
+    let mut ways = 1.0;
+    let mut by_bits = 0.0;
+    for matched in 0..bits {
+        by_bits += ways;
+        ways = ways * (PAYLOAD_BITS - matched) as f64 / (matched + 1) as f64;
+    }
+    let by_bits = by_bits / 2f64.powi(PAYLOAD_BITS as i32);
+    Ok(Detection {
+        score,
+        confidence: by_score.min(by_bits),
+        bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carrier::{Carrier, Pattern};
+
+    fn image(pattern: Pattern) -> ImageData {
+        let carrier = Carrier {
+            pattern,
+            width: 256,
+            height: 192,
+            seed: 1,
+            entropy: 0.1,
+            grayscale: false,
+        };
+        ImageData::from_png(&carrier.generate().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_invisible() {
+        let mut marked = image(Pattern::Perlin);
+        assert!(detect(&marked, "© 2024").unwrap().abs() < THRESHOLD);
+        add_invisible(&mut marked, "© 2024", 2.0).unwrap();
+        assert!(detect(&marked, "© 2024").unwrap() > THRESHOLD);
+        assert!(detect(&marked, "© 2025").unwrap() < THRESHOLD);
+
+        // Noise of several levels in every sample leaves the mark detectable.
+        let mut rng = Rng(9);
+        for sample in marked.samples_mut() {
+            let noise = (rng.next() % 9) as i32 - 4;
+            *sample = (*sample as i32 + noise).clamp(0, 255) as u16;
+        }
+        assert!(detect(&marked, "© 2024").unwrap() > THRESHOLD);
+    }
+
+    #[test]
+    fn test_robust() {
+        let mut marked = image(Pattern::Perlin);
+        assert!(!detect_robust(&marked, "© 2024").unwrap().found());
+        add_robust(&mut marked, "© 2024", 2.0).unwrap();
+        let detection = detect_robust(&marked, "© 2024").unwrap();
+        assert!(detection.found());
+        assert_eq!(detection.bits, PAYLOAD_BITS);
+        assert!(!detect_robust(&marked, "© 2025").unwrap().found());
+        let small = marked.cropped(0, 0, 100, 192).unwrap();
+        assert!(detect_robust(&small, "© 2024").is_err());
+    }
+
+    #[test]
+    fn test_visible() {
+        let mut marked = image(Pattern::Gradient);
+        let before = marked.samples().to_vec();
+        add_visible(&mut marked, "© 2024", 1.0).unwrap();
+        let changed = before
+            .iter()
+            .zip(marked.samples())
+            .filter(|(before, after)| before != after)
+            .count();
+        assert!(changed > 100);
+        // Only the bottom right corner is touched.
+        assert_eq!(&before[..256 * 100 * 3], &marked.samples()[..256 * 100 * 3]);
+        assert!(marked.samples().contains(&255));
+        assert_eq!(glyph('\u{1F600}'), glyph('?'));
+        assert!(add_visible(&mut marked, "far too long for the image to hold", 0.5).is_ok());
+        assert!(add_visible(&mut marked, "x", 1.5).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Where a file's bytes go, for `pngme weigh`, and what the other commands would save.
+
+use std::fmt::{Display, Formatter};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::icc::COLOUR_SPACE_CHUNKS;
+use crate::plan::{Operation, RecompressCache};
+use crate::png::Png;
+use crate::strip::Category;
+
+/// Length, type and CRC around every chunk's payload.
+const CHUNK_OVERHEAD: u64 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    /// IDAT payloads.
+    PixelData,
+    /// iCCP, sRGB, cICP, gAMA and cHRM payloads.
+    ColourManagement,
+    /// tEXt, zTXt and iTXt payloads, including XMP.
+    TextMetadata,
+    OtherAncillary,
+    /// The signature, every chunk's length, type and CRC, and the other critical chunks.
+    Structure,
+    /// Bytes after IEND.
+    Trailer,
+}
+
+impl Display for Part {
+    fn fmt(&self, fmt: &mut Formatter) -> std::fmt::Result {
+        fmt.write_str(match self {
+            Part::PixelData => "pixel data",
+            Part::ColourManagement => "colour management",
+            Part::TextMetadata => "text metadata",
+            Part::OtherAncillary => "other ancillary",
+            Part::Structure => "structural overhead",
+            Part::Trailer => "trailing data",
+        })
+    }
+}
+
+fn part_of(chunk: &Chunk) -> Part {
+    let chunk_type = chunk.chunk_type();
+    if *chunk_type == ChunkType::IDAT {
+        Part::PixelData
+    } else if chunk_type.is_critical() {
+        Part::Structure
+    } else if COLOUR_SPACE_CHUNKS.contains(chunk_type) {
+        Part::ColourManagement
+    } else if Category::Text.contains(chunk) {
+        Part::TextMetadata
+    } else {
+        Part::OtherAncillary
+    }
+}
+
+/// Bytes in each part of a `file_size`-byte file that parsed as `png`, in the order of
+/// `Part`. Every part is listed, even if empty.
+pub fn weigh(png: &Png, file_size: u64) -> Vec<(Part, u64)> {
+    let mut parts = vec![
+        (Part::PixelData, 0),
+        (Part::ColourManagement, 0),
+        (Part::TextMetadata, 0),
+        (Part::OtherAncillary, 0),
+        (Part::Structure, Png::STANDARD_HEADER.len() as u64),
+        (Part::Trailer, 0),
+    ];
+    let mut add = |part: Part, bytes: u64| {
+        if let Some((_, total)) = parts.iter_mut().find(|(found, _)| *found == part) {
+            *total += bytes;
+        }
+    };
+    let mut png_size = Png::STANDARD_HEADER.len() as u64;
+    for chunk in png.chunks() {
+        add(Part::Structure, CHUNK_OVERHEAD);
+        add(part_of(chunk), chunk.length() as u64);
+        png_size += CHUNK_OVERHEAD + chunk.length() as u64;
+    }
+    add(Part::Trailer, file_size.saturating_sub(png_size));
+    parts
+}
+
+/// A command and the bytes it would take off the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saving {
+    pub command: String,
+    pub bytes: u64,
+}
+
+/// What `strip`, `normalize` and an `optimize` plan operation would each save on their own;
+/// run together they save less than the sum, since some remove the same bytes.
+pub fn savings(png: &Png, file_size: u64) -> Vec<Saving> {
+    let mut savings = Vec::new();
+    for category in [
+        Category::Text,
+        Category::Metadata,
+        Category::Legacy,
+        Category::Private,
+    ] {
+        let bytes: u64 = png
+            .chunks()
+            .iter()
+            .filter(|chunk| category.contains(chunk))
+            .map(|chunk| CHUNK_OVERHEAD + chunk.length() as u64)
+            .sum();
+        if bytes > 0 {
+            savings.push(Saving {
+                command: format!("pngme strip --category {}", category),
+                bytes,
+            });
+        }
+    }
+    let png_bytes = png.as_bytes();
+    let trailer = file_size.saturating_sub(png_bytes.len() as u64);
+    if trailer > 0 {
+        savings.push(Saving {
+            command: String::from("pngme normalize"),
+            bytes: trailer,
+        });
+    }
+    if let Ok(mut optimized) = Png::try_from(&png_bytes[..]) {
+        let optimize = Operation::Optimize.apply(&mut optimized, &mut RecompressCache::default());
+        let bytes = (png_bytes.len() as u64).saturating_sub(optimized.as_bytes().len() as u64);
+        if optimize.is_ok() && bytes > 0 {
+            savings.push(Saving {
+                command: String::from("pngme apply, with an optimize operation"),
+                bytes,
+            });
+        }
+    }
+    savings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_weigh() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        png.insert_ancillary(Chunk::new(ChunkType::TEXT, b"Title\0abc".to_vec()));
+        png.insert_ancillary(Chunk::new(ChunkType::SRGB, vec![0]));
+        png.insert_ancillary(Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 5]));
+        let size = png.as_bytes().len() as u64;
+        let parts = weigh(&png, size + 4);
+        let bytes: Vec<u64> = parts.iter().map(|(_, bytes)| *bytes).collect();
+        // Signature, six chunk headers and the 13-byte IHDR.
+        assert_eq!(bytes, [0, 1, 9, 5, 8 + 6 * 12 + 13, 4]);
+        assert_eq!(bytes.iter().sum::<u64>(), size + 4);
+
+        let savings = savings(&png, size + 4);
+        let commands: Vec<&str> = savings
+            .iter()
+            .map(|saving| saving.command.as_str())
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                "pngme strip --category text",
+                "pngme strip --category private",
+                "pngme normalize"
+            ]
+        );
+        assert_eq!(savings[0].bytes, 21);
+    }
+}
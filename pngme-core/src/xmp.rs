@@ -0,0 +1,142 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// XMP is stored in an uncompressed `iTXt` chunk with this keyword (XMP spec part 3, §1.1.5).
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+const PACKET_HEADER: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>";
+const PACKET_TRAILER: &str = "<?xpacket end=\"w\"?>";
+const PADDING_LINES: usize = 20;
+const PADDING_LINE_WIDTH: usize = 100;
+
+struct InternationalText<'a> {
+    keyword: &'a [u8],
+    compressed: bool,
+    text: &'a [u8],
+}
+
+fn parse_itxt(data: &[u8]) -> Result<InternationalText<'_>, ()> {
+    let mut fields = data.splitn(2, |&byte| byte == 0);
+    let keyword = fields.next().ok_or(())?;
+    let rest = fields.next().ok_or(())?;
+    if rest.len() < 2 {
+        return Err(());
+    }
+    let compressed = rest[0] != 0;
+    let mut fields = rest[2..].splitn(3, |&byte| byte == 0);
+    let _language = fields.next().ok_or(())?;
+    let _translated_keyword = fields.next().ok_or(())?;
+    let text = fields.next().ok_or(())?;
+    Ok(InternationalText {
+        keyword,
+        compressed,
+        text,
+    })
+}
+
+pub fn is_xmp_chunk(chunk: &Chunk) -> bool {
+    *chunk.chunk_type() == ChunkType::ITXT
+        && parse_itxt(chunk.data()).is_ok_and(|itxt| itxt.keyword == XMP_KEYWORD.as_bytes())
+}
+
+/// Returns the XMP packet, or `Err` if the chunk is compressed or not UTF-8.
+pub fn xmp(png: &Png) -> Option<Result<String, ()>> {
+    let chunk = png.chunks().iter().find(|chunk| is_xmp_chunk(chunk))?;
+    Some(parse_itxt(chunk.data()).and_then(|itxt| {
+        if itxt.compressed {
+            return Err(());
+        }
+        String::from_utf8(itxt.text.to_vec()).map_err(|_| ())
+    }))
+}
+
+/// Wraps bare XMP in an `xpacket` with writable padding; complete packets pass through.
+pub fn wrap_packet(xmp: &str) -> String {
+    if xmp.trim_start().starts_with("<?xpacket begin") {
+        return xmp.to_string();
+    }
+    let padding_line = format!("{}\n", " ".repeat(PADDING_LINE_WIDTH - 1));
+    format!(
+        "{}\n{}\n{}{}",
+        PACKET_HEADER,
+        xmp.trim(),
+        padding_line.repeat(PADDING_LINES),
+        PACKET_TRAILER
+    )
+}
+
+pub fn xmp_chunk(packet: &str) -> Chunk {
+    let mut data = Vec::new();
+    data.extend_from_slice(XMP_KEYWORD.as_bytes());
+    // Null separator, compression flag and method, then empty language and translated keyword.
+    data.extend_from_slice(&[0, 0, 0, 0, 0]);
+    data.extend_from_slice(packet.as_bytes());
+    Chunk::new(ChunkType::ITXT, data)
+}
+
+pub fn remove_xmp(png: &mut Png) -> bool {
+    let before = png.chunks().len();
+    png.retain_chunks(|chunk| !is_xmp_chunk(chunk));
+    png.chunks().len() != before
+}
+
+/// Replaces any existing XMP, placing the new packet ahead of the image data.
+pub fn set_xmp(png: &mut Png, xmp: &str) {
+    remove_xmp(png);
+    png.insert_before_data(xmp_chunk(&wrap_packet(xmp)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ColorType;
+    use crate::testing::png_with_header;
+    use std::str::FromStr;
+
+    const XMP: &str = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"></x:xmpmeta>";
+
+    #[test]
+    fn test_wrap_packet() {
+        let packet = wrap_packet(XMP);
+        assert!(packet.starts_with(PACKET_HEADER));
+        assert!(packet.ends_with(PACKET_TRAILER));
+        assert!(packet.contains(XMP));
+        assert!(packet.len() > PADDING_LINES * PADDING_LINE_WIDTH);
+        assert_eq!(wrap_packet(&packet), packet);
+    }
+
+    #[test]
+    fn test_xmp_chunk_layout() {
+        let chunk = xmp_chunk(XMP);
+        let data = chunk.data();
+        assert_eq!(&data[..XMP_KEYWORD.len()], XMP_KEYWORD.as_bytes());
+        assert_eq!(&data[XMP_KEYWORD.len()..XMP_KEYWORD.len() + 5], &[0; 5]);
+        assert_eq!(&data[XMP_KEYWORD.len() + 5..], XMP.as_bytes());
+    }
+
+    #[test]
+    fn test_set_get_remove() {
+        let mut png = png_with_header(ColorType::Rgb, 8, 0);
+        assert!(xmp(&png).is_none());
+
+        set_xmp(&mut png, XMP);
+        set_xmp(&mut png, XMP);
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "iTXt");
+        assert_eq!(xmp(&png).unwrap().unwrap(), wrap_packet(XMP));
+
+        assert!(remove_xmp(&mut png));
+        assert!(xmp(&png).is_none());
+        assert!(!remove_xmp(&mut png));
+    }
+
+    #[test]
+    fn test_compressed_xmp_is_rejected() {
+        let mut data = XMP_KEYWORD.as_bytes().to_vec();
+        data.extend_from_slice(&[0, 1, 0, 0, 0, 0x78, 0x9c]);
+        let chunk = Chunk::new(ChunkType::from_str("iTXt").unwrap(), data);
+        let png = Png::from_chunks(vec![chunk]);
+        assert!(xmp(&png).unwrap().is_err());
+    }
+}
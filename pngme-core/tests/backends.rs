@@ -0,0 +1,105 @@
+//! What must not change with the deflate backend picked by the `zlib-ng` and `zlib-rs`
+//! features. Run once per backend, as CI does:
+//!
+//! ```text
+//! cargo test -p pngme-core --test backends
+//! cargo test -p pngme-core --test backends --features zlib-rs
+//! cargo test -p pngme-core --test backends --features zlib-ng
+//! ```
+//!
+//! Compressed bytes differ between backends, so the guarantee is on everything else: the
+//! decoded pixels, and what survives re-encoding.
+use std::fs;
+use std::path::PathBuf;
+
+use pngme_core::chunk_type::ChunkType;
+use pngme_core::hash::Algorithm;
+use pngme_core::image::{self, ImageData};
+use pngme_core::parallel;
+use pngme_core::png::Png;
+
+/// The SHA-256 of every valid PngSuite image's name, header and samples, as decoded with
+/// miniz_oxide.
+const DECODED_DIGEST: &str = "4221a95b0acbf218d4727c0e0025b399f7dc00f9c6232fcf4548f0da2333e89d";
+
+fn valid_suite() -> Vec<(String, Png)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/pngsuite");
+    let mut files: Vec<(String, Png)> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('x'))
+        .map(|name| {
+            let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/pngsuite")
+                .join(&name);
+            let png = Png::try_from(&fs::read(path).unwrap()[..]).unwrap();
+            (name, png)
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+fn fingerprint(image: &ImageData) -> Vec<u8> {
+    let mut bytes = image.header().to_bytes().to_vec();
+    bytes.extend(
+        image
+            .samples()
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes()),
+    );
+    bytes
+}
+
+#[test]
+fn decoded_pixels_match_the_reference_backend() {
+    let mut all = Vec::new();
+    for (name, png) in valid_suite() {
+        let image =
+            ImageData::from_png(&png).unwrap_or_else(|()| panic!("{} did not decode", name));
+        all.extend(name.as_bytes());
+        all.extend(fingerprint(&image));
+    }
+    println!("deflate backend: {}", parallel::DEFLATE_BACKEND);
+    assert_eq!(Algorithm::Sha256.digest(&all), DECODED_DIGEST);
+}
+
+#[test]
+fn re_encoding_keeps_pixels_and_other_chunks() {
+    for jobs in [1, 4] {
+        parallel::set_jobs(jobs);
+        for (name, png) in valid_suite() {
+            let image = ImageData::from_png(&png).unwrap();
+            let others = |png: &Png| -> Vec<Vec<u8>> {
+                png.chunks()
+                    .iter()
+                    .filter(|chunk| {
+                        ![ChunkType::IHDR, ChunkType::IDAT].contains(chunk.chunk_type())
+                    })
+                    .map(|chunk| chunk.as_bytes())
+                    .collect()
+            };
+
+            let mut rewritten = Png::try_from(&png.as_bytes()[..]).unwrap();
+            image.write_to(&mut rewritten).unwrap();
+            let decoded = ImageData::from_png(&rewritten).unwrap();
+            assert!(
+                decoded.samples() == image.samples(),
+                "{} changed when written",
+                name
+            );
+            assert!(others(&rewritten) == others(&png), "{} lost chunks", name);
+
+            let mut recompressed = Png::try_from(&png.as_bytes()[..]).unwrap();
+            image::recompress(&mut recompressed).unwrap();
+            let decoded = ImageData::from_png(&recompressed).unwrap();
+            assert!(
+                fingerprint(&decoded) == fingerprint(&image),
+                "{} changed when recompressed",
+                name
+            );
+        }
+    }
+}
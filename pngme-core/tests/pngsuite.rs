@@ -0,0 +1,48 @@
+//! Conformance against PngSuite (http://www.schaik.com/pngsuite/), vendored in
+//! tests/pngsuite. Files whose names start with `x` are deliberately corrupt.
+use std::fs;
+use std::path::PathBuf;
+
+use pngme_core::image::ImageData;
+use pngme_core::png::Png;
+
+fn suite() -> Vec<(String, Vec<u8>)> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/pngsuite");
+    let mut files: Vec<(String, Vec<u8>)> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn valid_files_round_trip() {
+    let valid: Vec<_> = suite()
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with('x'))
+        .collect();
+    assert!(valid.len() > 150);
+    for (name, bytes) in valid {
+        let png = Png::try_from(&bytes[..]).unwrap_or_else(|()| panic!("{} did not parse", name));
+        assert!(png.as_bytes() == bytes, "{} did not round-trip", name);
+    }
+}
+
+#[test]
+fn corrupt_files_are_rejected() {
+    let corrupt: Vec<_> = suite()
+        .into_iter()
+        .filter(|(name, _)| name.starts_with('x'))
+        .collect();
+    assert_eq!(corrupt.len(), 14);
+    for (name, bytes) in corrupt {
+        let decoded = Png::try_from(&bytes[..]).and_then(|png| ImageData::from_png(&png));
+        assert!(decoded.is_err(), "{} was accepted", name);
+    }
+}
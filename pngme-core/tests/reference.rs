@@ -0,0 +1,32 @@
+//! Decoded pixels against the `png` crate's, over PngSuite; needs the `reference-decoder`
+//! feature:
+//!
+//! ```text
+//! cargo test -p pngme-core --test reference --features reference-decoder
+//! ```
+#![cfg(feature = "reference-decoder")]
+
+use std::fs;
+use std::path::PathBuf;
+
+use pngme_core::conformance::{self, Outcome};
+
+#[test]
+fn pngsuite_matches_reference() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/pngsuite");
+    let mut disagreements = Vec::new();
+    let mut matched = 0;
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "png") {
+            continue;
+        }
+        match conformance::compare(&fs::read(&path).unwrap()) {
+            Outcome::Match => matched += 1,
+            outcome if outcome.agrees() => {}
+            outcome => disagreements.push(format!("{}: {}", path.display(), outcome)),
+        }
+    }
+    assert!(matched > 150);
+    assert!(disagreements.is_empty(), "{}", disagreements.join("\n"));
+}
@@ -0,0 +1,15 @@
+//! The edge-case corpus written by `pngme selftest --emit`, checked against this crate.
+use pngme_core::vectors::{self, Expect};
+
+#[test]
+fn every_vector_is_handled_as_labelled() {
+    let corpus = vectors::corpus();
+    for expect in [Expect::Valid, Expect::Misordered, Expect::Malformed] {
+        assert!(corpus.iter().any(|vector| vector.expect == expect));
+    }
+    for vector in corpus {
+        if let Err(error) = vector.check() {
+            panic!("{} ({}): {}", vector.name, vector.description, error);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! The robust watermark through what it is meant to survive: a round trip through JPEG at
+//! the quality photo sites re-encode uploads at, then scaling, encoded and decoded by
+//! the `image` crate rather than anything of pngme's.
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbImage};
+
+use pngme_core::builder::PngBuilder;
+use pngme_core::carrier::{Carrier, Pattern};
+use pngme_core::image::{ColorType, ImageData, ImageHeader};
+use pngme_core::watermark::{self, PAYLOAD_BITS};
+
+fn carrier() -> ImageData {
+    let carrier = Carrier {
+        pattern: Pattern::Perlin,
+        width: 384,
+        height: 288,
+        seed: 7,
+        entropy: 0.15,
+        grayscale: false,
+    };
+    ImageData::from_png(&carrier.generate().unwrap()).unwrap()
+}
+
+/// `image` as a JPEG at `quality`, decoded again and scaled by `scale`.
+fn reencoded(image: &ImageData, quality: u8, scale: f64) -> ImageData {
+    let samples = image.samples().iter().map(|&sample| sample as u8).collect();
+    let rgb = RgbImage::from_raw(image.width(), image.height(), samples).unwrap();
+    let mut jpeg = Vec::new();
+    rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, quality))
+        .unwrap();
+    let decoded = image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg)
+        .unwrap()
+        .to_rgb8();
+    let (width, height) = (
+        (image.width() as f64 * scale).round() as u32,
+        (image.height() as f64 * scale).round() as u32,
+    );
+    let scaled = imageops::resize(&decoded, width, height, FilterType::Triangle);
+    let header = ImageHeader {
+        width,
+        height,
+        bit_depth: 8,
+        color_type: ColorType::Rgb,
+        interlaced: false,
+    };
+    let rows: Vec<Vec<u16>> = scaled
+        .as_raw()
+        .chunks(width as usize * 3)
+        .map(|row| row.iter().map(|&sample| sample as u16).collect())
+        .collect();
+    ImageData::from_png(&PngBuilder::new(header).image_rows(rows).build().unwrap()).unwrap()
+}
+
+#[test]
+fn test_robust_survives_jpeg_and_scaling() {
+    let original = carrier();
+    let mut marked = carrier();
+    watermark::add_robust(&mut marked, "© 2024 Example", 2.0).unwrap();
+
+    for (quality, scale) in [(75, 1.0), (75, 0.9), (75, 0.75), (75, 1.2), (50, 1.0)] {
+        let copy = reencoded(&marked, quality, scale);
+        let detection = watermark::detect_robust(&copy, "© 2024 Example").unwrap();
+        assert!(detection.found(), "{:?} at {}", detection, scale);
+        assert!(detection.confidence > 0.9999);
+        assert!(detection.bits > PAYLOAD_BITS * 9 / 10, "{:?}", detection);
+        let other = watermark::detect_robust(&copy, "© 2025 Example").unwrap();
+        assert!(!other.found(), "{:?} at {}", other, scale);
+        assert!(other.confidence < 0.99);
+
+        let unmarked = reencoded(&original, quality, scale);
+        let detection = watermark::detect_robust(&unmarked, "© 2024 Example").unwrap();
+        assert!(!detection.found(), "{:?} at {}", detection, scale);
+    }
+}
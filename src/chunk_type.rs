@@ -1,163 +0,0 @@
-use core::str::FromStr;
-use std::fmt::{Display, Formatter};
-
-#[derive(Eq, PartialEq, Debug)]
-pub struct ChunkType {
-    bytes: [u8; 4],
-}
-
-impl ChunkType {
-    pub fn try_from(bytes: [u8; 4]) -> Result<Self, ()> {
-        Ok(Self { bytes })
-    }
-    pub fn bytes(&self) -> [u8; 4] {
-        self.bytes
-    }
-    pub fn is_critical(&self) -> bool {
-        self.bytes[0].is_ascii_uppercase()
-    }
-    pub fn is_public(&self) -> bool {
-        self.bytes[1].is_ascii_uppercase()
-    }
-    pub fn is_reserved_bit_valid(&self) -> bool {
-        self.bytes[2].is_ascii_uppercase()
-    }
-    pub fn is_safe_to_copy(&self) -> bool {
-        self.bytes[3].is_ascii_lowercase()
-    }
-    pub fn is_valid(&self) -> bool {
-        self.is_reserved_bit_valid()
-    }
-}
-
-impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = ();
-    fn try_from(value: [u8; 4]) -> Result<Self, ()> {
-        Ok(Self { bytes: value })
-    }
-}
-
-fn is_letter(char: char) -> bool {
-    ('a'..='z').contains(&char) || ('A'..='Z').contains(&char)
-}
-
-impl FromStr for ChunkType {
-    type Err = ();
-    fn from_str(str: &str) -> Result<Self, Self::Err> {
-        if str.chars().all(is_letter) {
-            if let Ok(bytes) = str.as_bytes().try_into() {
-                return Ok(Self { bytes });
-            }
-        }
-        Err(())
-    }
-}
-
-impl Display for ChunkType {
-    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
-        if let Ok(str) = std::str::from_utf8(&self.bytes) {
-            write!(fmt, "{}", str)
-        } else {
-            Err(std::fmt::Error)
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::convert::TryFrom;
-    use std::str::FromStr;
-
-    #[test]
-    pub fn test_chunk_type_from_bytes() {
-        let expected = [82, 117, 83, 116];
-        let actual = ChunkType::try_from([82, 117, 83, 116]).unwrap();
-
-        assert_eq!(expected, actual.bytes());
-    }
-
-    #[test]
-    pub fn test_chunk_type_from_str() {
-        let expected = ChunkType::try_from([82, 117, 83, 116]).unwrap();
-        let actual = ChunkType::from_str("RuSt").unwrap();
-        assert_eq!(expected, actual);
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_critical() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert!(chunk.is_critical());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_not_critical() {
-        let chunk = ChunkType::from_str("ruSt").unwrap();
-        assert!(!chunk.is_critical());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_public() {
-        let chunk = ChunkType::from_str("RUSt").unwrap();
-        assert!(chunk.is_public());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_not_public() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert!(!chunk.is_public());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_reserved_bit_valid() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert!(chunk.is_reserved_bit_valid());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_reserved_bit_invalid() {
-        let chunk = ChunkType::from_str("Rust").unwrap();
-        assert!(!chunk.is_reserved_bit_valid());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_safe_to_copy() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert!(chunk.is_safe_to_copy());
-    }
-
-    #[test]
-    pub fn test_chunk_type_is_unsafe_to_copy() {
-        let chunk = ChunkType::from_str("RuST").unwrap();
-        assert!(!chunk.is_safe_to_copy());
-    }
-
-    #[test]
-    pub fn test_valid_chunk_is_valid() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert!(chunk.is_valid());
-    }
-
-    #[test]
-    pub fn test_invalid_chunk_is_valid() {
-        let chunk = ChunkType::from_str("Rust").unwrap();
-        assert!(!chunk.is_valid());
-
-        let chunk = ChunkType::from_str("Ru1t");
-        assert!(chunk.is_err());
-    }
-
-    #[test]
-    pub fn test_chunk_type_string() {
-        let chunk = ChunkType::from_str("RuSt").unwrap();
-        assert_eq!(&chunk.to_string(), "RuSt");
-    }
-
-    #[test]
-    pub fn test_chunk_type_trait_impls() {
-        let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
-        let chunk_type_2: ChunkType = FromStr::from_str("RuSt").unwrap();
-        let _chunk_string = format!("{}", chunk_type_1);
-        let _are_chunks_equal = chunk_type_1 == chunk_type_2;
-    }
-}
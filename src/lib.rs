@@ -0,0 +1,3 @@
+//! The pngme library, now maintained as `pngme-core`; the command-line tool lives in
+//! `pngme-cli`.
+pub use pngme_core::*;
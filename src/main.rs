@@ -1,7 +0,0 @@
-mod args;
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
-
-fn main() {}